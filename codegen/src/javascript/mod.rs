@@ -1,3 +1,5 @@
+use super::utils::doc_comment;
+use super::utils::is_ident;
 use super::utils::Either;
 use super::utils::Either::*;
 use linked_hash_map::LinkedHashMap;
@@ -61,6 +63,12 @@ impl Default for GenContext {
 
 pub trait Gen {
     fn gen(&self, _ctx: &GenContext) -> String;
+
+    /// Writes the code into `w`, which nodes holding much code do piece by
+    /// piece rather than as a single string
+    fn gen_into(&self, ctx: &GenContext, w: &mut dyn fmt::Write) -> fmt::Result {
+        w.write_str(&self.gen(ctx))
+    }
 }
 
 #[derive(Debug)]
@@ -85,7 +93,17 @@ pub struct Ident(pub String);
 
 impl Gen for Ident {
     fn gen(&self, _ctx: &GenContext) -> String {
-        return self.0.to_string();
+        self.0.to_string()
+    }
+}
+
+/// The name of a property, method or getter, quoted unless it's an
+/// identifier, as in `"v1.2"`
+fn property_name(name: &str, ctx: &GenContext) -> String {
+    if is_ident(name) {
+        name.to_string()
+    } else {
+        Literal::String(name.to_string()).gen(ctx)
     }
 }
 
@@ -161,12 +179,47 @@ pub enum Stmt {
         incr: Option<Expr>,
         stmts: Vec<Stmt>,
     },
+    // for await (const a of b) { ... }
+    ForOf {
+        typ: DeclType,
+        ident: Ident,
+        iterable: Expr,
+        is_await: bool,
+        stmts: Vec<Stmt>,
+    },
+    // while (a) { ... }
+    // do { ... } while (a);
+    While {
+        cond: Expr,
+        stmts: Vec<Stmt>,
+        is_do: bool,
+    },
+    // if (a) { ... }
+    If {
+        cond: Expr,
+        stmts: Vec<Stmt>,
+    },
+    // yield;
+    // yield a;
+    // yield* a;
+    Yield {
+        expr: Option<Expr>,
+        is_delegate: bool,
+    },
+    // throw a;
+    Throw(Expr),
     Import(Import),
     Class(Class),
+    // function a(p1, p2) { ... }
+    Func(Method),
     Export {
         is_default: bool,
         stmt: Box<Stmt>,
     },
+    // Emitted as is, for helpers that are easier to write by hand
+    Verbatim(String),
+    // // a
+    LineComment(String),
 }
 
 impl Gen for Stmt {
@@ -191,12 +244,85 @@ impl Gen for Stmt {
 for ({inst}; {chk}; {incr}) {{
 {stmts}
 }}",
-                    inst = inst.as_ref().map_or(String::new(), |ref v| v.gen(ctx)),
+                    inst = inst.as_ref().map_or(String::new(), |v| v.gen(ctx)),
                     chk = chk.as_ref().map_or(String::new(), |v| v.gen(ctx)),
                     incr = incr.as_ref().map_or(String::new(), |v| v.gen(ctx)),
                     stmts = rendered_stmts
                 )
             }
+            Stmt::ForOf {
+                typ,
+                ident,
+                iterable,
+                is_await,
+                stmts,
+            } => {
+                let rendered_stmts = stmts
+                    .iter()
+                    .map(|v| indent(&v.gen(ctx), ctx))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                format!(
+                    "\
+for {await_}({typ} {ident} of {iterable}) {{
+{stmts}
+}}",
+                    await_ = if *is_await { "await " } else { "" },
+                    typ = typ,
+                    ident = ident.gen(ctx),
+                    iterable = iterable.gen(ctx),
+                    stmts = rendered_stmts
+                )
+            }
+            Stmt::While { cond, stmts, is_do } => {
+                let rendered_stmts = stmts
+                    .iter()
+                    .map(|v| indent(&v.gen(ctx), ctx))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                if *is_do {
+                    format!(
+                        "\
+do {{
+{stmts}
+}} while ({cond});",
+                        cond = cond.gen(ctx),
+                        stmts = rendered_stmts
+                    )
+                } else {
+                    format!(
+                        "\
+while ({cond}) {{
+{stmts}
+}}",
+                        cond = cond.gen(ctx),
+                        stmts = rendered_stmts
+                    )
+                }
+            }
+            Stmt::If { cond, stmts } => {
+                let rendered_stmts = stmts
+                    .iter()
+                    .map(|v| indent(&v.gen(ctx), ctx))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                format!(
+                    "\
+if ({cond}) {{
+{stmts}
+}}",
+                    cond = cond.gen(ctx),
+                    stmts = rendered_stmts
+                )
+            }
+            Stmt::Yield { expr, is_delegate } => {
+                let yield_ = if *is_delegate { "yield*" } else { "yield" };
+                match expr {
+                    Some(expr) => format!("{} {};", yield_, expr.gen(ctx)),
+                    None => format!("{};", yield_),
+                }
+            }
+            Stmt::Throw(expr) => format!("throw {};", expr.gen(ctx)),
             Stmt::Return(expr) => format!("return {};", expr.gen(ctx)),
             Stmt::Import(imp) => imp.gen(ctx),
             Stmt::Export { is_default, stmt } => {
@@ -207,6 +333,28 @@ for ({inst}; {chk}; {incr}) {{
                 }
             }
             Stmt::Class(kls) => kls.gen(ctx),
+            Stmt::Func(func) => {
+                let rendered_stmts = func
+                    .stmts
+                    .iter()
+                    .map(|v| indent(&v.gen(ctx), ctx))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                format!(
+                    "\
+{doc}{async_}function{star} {ident}({params}) {{
+{stmts}
+}}",
+                    doc = doc_comment(&func.doc),
+                    async_ = if func.is_async { "async " } else { "" },
+                    star = if func.is_generator { "*" } else { "" },
+                    ident = func.ident.gen(ctx),
+                    params = func.params.join(", "),
+                    stmts = rendered_stmts
+                )
+            }
+            Stmt::Verbatim(code) => code.to_string(),
+            Stmt::LineComment(text) => format!("// {}", text),
         }
     }
 }
@@ -217,7 +365,7 @@ pub struct CompOp(pub String);
 
 impl Gen for CompOp {
     fn gen(&self, _ctx: &GenContext) -> String {
-        return self.0.to_string();
+        self.0.to_string()
     }
 }
 
@@ -227,7 +375,7 @@ pub struct ArithOp(pub String);
 
 impl Gen for ArithOp {
     fn gen(&self, _ctx: &GenContext) -> String {
-        return self.0.to_string();
+        self.0.to_string()
     }
 }
 
@@ -239,12 +387,16 @@ pub enum Expr {
     Var(String),
     // object
     Object(LinkedHashMap<String, Expr>),
+    // [a, b]
+    Array(Vec<Expr>),
     // a > b
     Comp {
         op: CompOp,
         l: Box<Expr>,
         r: Box<Expr>,
     },
+    // !a
+    Not(Box<Expr>),
     // a + b
     Arith {
         op: ArithOp,
@@ -277,6 +429,13 @@ pub enum Expr {
         constructor: Box<Expr>,
         args: Vec<Expr>,
     },
+    // await a
+    Await(Box<Expr>),
+    // yield a, yield* a
+    Yield {
+        expr: Box<Expr>,
+        is_delegate: bool,
+    },
 }
 
 impl Gen for Expr {
@@ -287,12 +446,7 @@ impl Gen for Expr {
             Expr::Object(obj) => {
                 let kvpairs = obj
                     .iter()
-                    .map(|(k, v)| {
-                        (
-                            Expr::Literal(Literal::String(k.to_string())).gen(ctx),
-                            v.gen(ctx),
-                        )
-                    })
+                    .map(|(k, v)| (property_name(k, ctx), v.gen(ctx)))
                     .collect::<Vec<(String, String)>>();
                 format!(
                     "{{
@@ -305,11 +459,32 @@ impl Gen for Expr {
                         .join(",\n")
                 )
             }
+            Expr::Array(items) => {
+                if items.is_empty() {
+                    return "[]".to_string();
+                }
+                format!(
+                    "[\n{}\n]",
+                    items
+                        .iter()
+                        .map(|v| indent(&v.gen(ctx), ctx))
+                        .collect::<Vec<String>>()
+                        .join(",\n")
+                )
+            }
             Expr::Comp { op, l, r } => format!("({}) {} ({})", l.gen(ctx), op.gen(ctx), r.gen(ctx)),
+            Expr::Not(expr) => format!("!({})", expr.gen(ctx)),
             Expr::Arith { op, l, r } => {
                 format!("({}) {} ({})", l.gen(ctx), op.gen(ctx), r.gen(ctx))
             }
-            Expr::Member { base, member } => format!("({}).{}", base.gen(ctx), member.gen(ctx)),
+            Expr::Member { base, member } if is_ident(&member.0) => {
+                format!("({}).{}", base.gen(ctx), member.gen(ctx))
+            }
+            Expr::Member { base, member } => format!(
+                "({})[{}]",
+                base.gen(ctx),
+                Literal::String(member.0.to_string()).gen(ctx)
+            ),
             Expr::FuncCall { func, args } => {
                 let rendered_args = args
                     .iter()
@@ -326,7 +501,7 @@ impl Gen for Expr {
             } => {
                 let rendered_stmts = stmts
                     .iter()
-                    .map(|v| indent(&v.gen(ctx), &ctx))
+                    .map(|v| indent(&v.gen(ctx), ctx))
                     .collect::<Vec<String>>()
                     .join("\n");
                 format!(
@@ -350,12 +525,12 @@ impl Gen for Expr {
                     Left(stmts) => {
                         let rendered_stmts = stmts
                             .iter()
-                            .map(|v| indent(&v.gen(ctx), &ctx))
+                            .map(|v| indent(&v.gen(ctx), ctx))
                             .collect::<Vec<String>>()
                             .join("\n");
                         format!(
                             "\
-({async_}{params}) => {{
+{async_}({params}) => {{
 {stmts}
 }}",
                             async_ = async_,
@@ -380,6 +555,11 @@ impl Gen for Expr {
                     .join(", ");
                 format!("new ({})({})", constructor.gen(ctx), rendered_args)
             }
+            Expr::Await(expr) => format!("await {}", expr.gen(ctx)),
+            Expr::Yield { expr, is_delegate } => {
+                let yield_ = if *is_delegate { "yield*" } else { "yield" };
+                format!("{} {}", yield_, expr.gen(ctx))
+            }
         }
     }
 }
@@ -395,7 +575,7 @@ impl Gen for Constructor {
         let rendered_stmts = self
             .stmts
             .iter()
-            .map(|v| indent(&v.gen(ctx), &ctx))
+            .map(|v| indent(&v.gen(ctx), ctx))
             .collect::<Vec<String>>()
             .join("\n");
         format!(
@@ -416,10 +596,13 @@ constructor({params}) {{
 
 #[derive(Debug)]
 pub struct Method {
+    /// The lines of the JSDoc comment before the method
+    pub doc: Vec<String>,
     pub ident: Ident,
     pub params: Vec<String>,
     pub stmts: Vec<Stmt>,
     pub is_async: bool,
+    pub is_generator: bool,
 }
 
 impl Gen for Method {
@@ -427,16 +610,18 @@ impl Gen for Method {
         let rendered_stmts = self
             .stmts
             .iter()
-            .map(|v| indent(&v.gen(ctx), &ctx))
+            .map(|v| indent(&v.gen(ctx), ctx))
             .collect::<Vec<String>>()
             .join("\n");
         format!(
             "\
-{async_}{ident}({params}) {{
+{doc}{async_}{star}{ident}({params}) {{
 {stmts}
 }}",
+            doc = doc_comment(&self.doc),
             async_ = if self.is_async { "async " } else { "" },
-            ident = self.ident.gen(ctx),
+            star = if self.is_generator { "*" } else { "" },
+            ident = property_name(&self.ident.0, ctx),
             params = self.params.join(", "),
             stmts = rendered_stmts
         )
@@ -454,7 +639,7 @@ impl Gen for Getter {
         let rendered_stmts = self
             .stmts
             .iter()
-            .map(|v| indent(&v.gen(ctx), &ctx))
+            .map(|v| indent(&v.gen(ctx), ctx))
             .collect::<Vec<String>>()
             .join("\n");
         format!(
@@ -462,7 +647,7 @@ impl Gen for Getter {
 get {ident}() {{
 {stmts}
 }}",
-            ident = self.ident.gen(ctx),
+            ident = property_name(&self.ident.0, ctx),
             stmts = rendered_stmts
         )
     }
@@ -480,11 +665,8 @@ pub struct Class {
 impl Gen for Class {
     fn gen(&self, ctx: &GenContext) -> String {
         let mut rendered_decls: Vec<String> = Vec::new();
-        match &self.constructor {
-            Some(c) => {
-                rendered_decls.push(c.gen(ctx));
-            }
-            None => {}
+        if let Some(c) = &self.constructor {
+            rendered_decls.push(c.gen(ctx));
         };
         rendered_decls.extend(self.methods.iter().map(|v| v.gen(ctx)));
         rendered_decls.extend(self.getters.iter().map(|v| v.gen(ctx)));
@@ -499,7 +681,7 @@ class {ident} {extends}{{
             },
             decls = rendered_decls
                 .iter()
-                .map(|decl| format!("{}\n", indent(decl, &ctx)))
+                .map(|decl| format!("{}\n", indent(decl, ctx)))
                 .collect::<Vec<String>>()
                 .join(""),
         )
@@ -536,16 +718,15 @@ impl Gen for Import {
         if self.def.is_some() || self.imps.is_some() {
             let mut all_imps = Vec::new();
             let mut named_imps = Vec::new();
-            match &self.def {
-                Some(ident) => all_imps.push(ident.gen(ctx)),
-                None => {}
+            if let Some(ident) = &self.def {
+                all_imps.push(ident.gen(ctx))
             }
             match &self.imps {
                 Some(Left(_)) => all_imps.push("*".to_string()),
                 Some(Right(names)) => named_imps.extend(names.iter().map(|v| v.gen(ctx))),
                 None => {}
             }
-            if named_imps.len() > 0 {
+            if !named_imps.is_empty() {
                 let rendered_named_imps = format!("{{{imps}}}", imps = named_imps.join(", "));
                 all_imps.push(rendered_named_imps);
             }
@@ -567,10 +748,16 @@ pub struct Code {
 impl Gen for Code {
     fn gen(&self, ctx: &GenContext) -> String {
         let mut s = String::new();
+        self.gen_into(ctx, &mut s).unwrap();
+        s
+    }
+
+    fn gen_into(&self, ctx: &GenContext, w: &mut dyn fmt::Write) -> fmt::Result {
         for stmt in &self.stmts {
-            s.push_str(&format!("{}\n", stmt.gen(ctx)));
+            stmt.gen_into(ctx, w)?;
+            w.write_char('\n')?;
         }
-        s
+        Ok(())
     }
 }
 
@@ -596,10 +783,10 @@ mod tests {
     fn member_expr() {
         let ctx = GenContext::new();
         let member_expr = Expr::Member {
-            base: box Expr::Member {
-                base: box Expr::Var("this".to_string()),
+            base: Box::new(Expr::Member {
+                base: Box::new(Expr::Var("this".to_string())),
                 member: Ident("hello".to_string()),
-            },
+            }),
             member: Ident("world".to_string()),
         };
         assert_eq!(member_expr.gen(&ctx), "((this).hello).world");
@@ -610,7 +797,7 @@ mod tests {
         let ctx = GenContext::new();
         let func_call_expr = Expr::FuncCall {
             // TODO: Improper use of `Var`
-            func: box Expr::Var("console.log".to_string()),
+            func: Box::new(Expr::Var("console.log".to_string())),
             args: vec![Expr::Var("someVar".to_string())],
         };
         assert_eq!(func_call_expr.gen(&ctx), "console.log(someVar)");
@@ -621,7 +808,7 @@ mod tests {
         let ctx = GenContext::new();
         let stmt = Stmt::Expr(Expr::FuncCall {
             // TODO: Improper use of `Var`
-            func: box Expr::Var("console.log".to_string()),
+            func: Box::new(Expr::Var("console.log".to_string())),
             args: vec![Expr::Literal(Literal::String("OK".to_string()))],
         });
         let func_expr = Expr::Func {
@@ -644,12 +831,12 @@ console.log(\"OK\");
         let ctx = GenContext::new();
         let expr = Expr::FuncCall {
             // TODO: Improper use of `Var`
-            func: box Expr::Var("console.log".to_string()),
+            func: Box::new(Expr::Var("console.log".to_string())),
             args: vec![Expr::Literal(Literal::String("OK".to_string()))],
         };
         let arrow_func_expr = Expr::ArrowFunc {
             params: vec!["someVar".to_string()],
-            body: Either::Right(box expr),
+            body: Either::Right(Box::new(expr)),
             is_async: true,
         };
         assert_eq!(
@@ -660,18 +847,137 @@ console.log(\"OK\");
         );
     }
 
+    #[test]
+    fn arrow_func_block_expr() {
+        let ctx = Default::default();
+        let arrow_func_expr = Expr::ArrowFunc {
+            params: vec!["id".to_string()],
+            body: Either::Left(vec![Stmt::Return(Expr::Var("id".to_string()))]),
+            is_async: true,
+        };
+        assert_eq!(
+            arrow_func_expr.gen(&ctx),
+            "async (id) => {\n    return id;\n}"
+        );
+    }
+
     #[test]
     fn expr_stmt() {
         let ctx = GenContext::new();
         // TODO: Improper use of `Var` here
         let expr = Expr::FuncCall {
-            func: box Expr::Var("console.log".to_string()),
+            func: Box::new(Expr::Var("console.log".to_string())),
             args: vec![Expr::Literal(Literal::String("OK".to_string()))],
         };
         let expr_stmt = Stmt::Expr(expr);
         assert_eq!(expr_stmt.gen(&ctx), "console.log(\"OK\");")
     }
 
+    #[test]
+    fn array_expr() {
+        let ctx = Default::default();
+        let expr = Expr::Array(vec![
+            Expr::Literal(Literal::Number(1.0)),
+            Expr::Var("someVar".to_string()),
+        ]);
+        assert_eq!(expr.gen(&ctx), "[\n    1,\n    someVar\n]");
+        assert_eq!(Expr::Array(Vec::new()).gen(&ctx), "[]");
+    }
+
+    #[test]
+    fn object_expr() {
+        let ctx = Default::default();
+        let mut obj = LinkedHashMap::new();
+        obj.insert(
+            "method".to_string(),
+            Expr::Literal(Literal::String("get".to_string())),
+        );
+        obj.insert(
+            "Content-Type".to_string(),
+            Expr::Var("contentType".to_string()),
+        );
+        obj.insert("$ref".to_string(), Expr::Var("ref".to_string()));
+        obj.insert("2fa".to_string(), Expr::Var("code".to_string()));
+        assert_eq!(
+            Expr::Object(obj).gen(&ctx),
+            "{\n    method: \"get\",\n    \"Content-Type\": contentType,\n    $ref: ref,\n    \"2fa\": code\n}"
+        );
+    }
+
+    #[test]
+    fn if_stmt() {
+        let ctx = Default::default();
+        let stmt = Stmt::If {
+            cond: Expr::Not(Box::new(Expr::Var("ok".to_string()))),
+            stmts: vec![Stmt::Throw(Expr::Instantiate {
+                constructor: Box::new(Expr::Var("Error".to_string())),
+                args: vec![Expr::Literal(Literal::String("Not OK".to_string()))],
+            })],
+        };
+        assert_eq!(
+            stmt.gen(&ctx),
+            "if (!(ok)) {\n    throw new (Error)(\"Not OK\");\n}"
+        );
+    }
+
+    #[test]
+    fn line_comment_stmt() {
+        let ctx = Default::default();
+        let stmt = Stmt::LineComment("from schema: ratincren.get".to_string());
+        assert_eq!(stmt.gen(&ctx), "// from schema: ratincren.get");
+    }
+
+    #[test]
+    fn yield_stmt() {
+        let ctx = Default::default();
+        let stmt = Stmt::Yield {
+            expr: Some(Expr::Var("item".to_string())),
+            is_delegate: false,
+        };
+        assert_eq!(stmt.gen(&ctx), "yield item;");
+        let stmt = Stmt::Yield {
+            expr: None,
+            is_delegate: false,
+        };
+        assert_eq!(stmt.gen(&ctx), "yield;");
+    }
+
+    #[test]
+    fn yield_delegate_stmt() {
+        let ctx = Default::default();
+        let stmt = Stmt::Yield {
+            expr: Some(Expr::Member {
+                base: Box::new(Expr::Var("page".to_string())),
+                member: Ident("items".to_string()),
+            }),
+            is_delegate: true,
+        };
+        assert_eq!(stmt.gen(&ctx), "yield* (page).items;");
+    }
+
+    #[test]
+    fn while_stmt() {
+        let ctx = Default::default();
+        let stmts = || {
+            vec![Stmt::Yield {
+                expr: Some(Expr::Var("page".to_string())),
+                is_delegate: true,
+            }]
+        };
+        let stmt = Stmt::While {
+            cond: Expr::Var("cursor".to_string()),
+            stmts: stmts(),
+            is_do: false,
+        };
+        assert_eq!(stmt.gen(&ctx), "while (cursor) {\n    yield* page;\n}");
+        let stmt = Stmt::While {
+            cond: Expr::Var("cursor".to_string()),
+            stmts: stmts(),
+            is_do: true,
+        };
+        assert_eq!(stmt.gen(&ctx), "do {\n    yield* page;\n} while (cursor);");
+    }
+
     #[test]
     fn decl_stmt() {
         let ctx = GenContext::new();
@@ -687,7 +993,7 @@ console.log(\"OK\");
         let ctx = GenContext::new();
         let func_call = Expr::FuncCall {
             // TODO: Improper use of `Var` here
-            func: box Expr::Var("console.log".to_string()),
+            func: Box::new(Expr::Var("console.log".to_string())),
             args: vec![Expr::Literal(Literal::String("OK".to_string()))],
         };
         let global_assign_stmt = Stmt::Assign(Assign {
@@ -703,16 +1009,16 @@ console.log(\"OK\");
         let ctx = GenContext::new();
         let func_call_1 = Expr::FuncCall {
             // TODO: Improper use of `Var` here
-            func: box Expr::Var("console.log".to_string()),
+            func: Box::new(Expr::Var("console.log".to_string())),
             args: vec![Expr::Literal(Literal::String("OK".to_string()))],
         };
         let func_args_2 = vec![Expr::Arith {
             op: ArithOp("+".to_string()),
-            l: box Expr::Literal(Literal::Number(3.0)),
-            r: box Expr::Literal(Literal::Number(4.0)),
+            l: Box::new(Expr::Literal(Literal::Number(3.0))),
+            r: Box::new(Expr::Literal(Literal::Number(4.0))),
         }];
         let func_call_2 = Expr::FuncCall {
-            func: box Expr::Var("alert".to_string()),
+            func: Box::new(Expr::Var("alert".to_string())),
             args: func_args_2,
         };
         let stmts = vec![Stmt::Expr(func_call_1), Stmt::Expr(func_call_2)];
@@ -723,13 +1029,13 @@ console.log(\"OK\");
         });
         let chk = Some(Expr::Comp {
             op: CompOp("<".to_string()),
-            l: box Expr::Var("idx".to_string()),
-            r: box Expr::Literal(Literal::Number(10.0)),
+            l: Box::new(Expr::Var("idx".to_string())),
+            r: Box::new(Expr::Literal(Literal::Number(10.0))),
         });
         let incr = Some(Expr::Comp {
             op: CompOp("+=".to_string()),
-            l: box Expr::Var("idx".to_string()),
-            r: box Expr::Literal(Literal::Number(1.0)),
+            l: Box::new(Expr::Var("idx".to_string())),
+            r: Box::new(Expr::Literal(Literal::Number(1.0))),
         });
         let for_loop = Stmt::ForLoop {
             inst,
@@ -753,16 +1059,17 @@ alert((3) + (4));
         let stmt = Stmt::Expr(Expr::Var("xiaosi".to_string()));
         let export_stmt = Stmt::Export {
             is_default: false,
-            stmt: box stmt,
+            stmt: Box::new(stmt),
         };
         assert_eq!(export_stmt.gen(&GenContext::new()), "export xiaosi;")
     }
 
+    #[test]
     fn default_export() {
         let stmt = Stmt::Expr(Expr::Var("xiaosi".to_string()));
         let export_stmt = Stmt::Export {
             is_default: true,
-            stmt: box stmt,
+            stmt: Box::new(stmt),
         };
         assert_eq!(
             export_stmt.gen(&GenContext::new()),
@@ -770,21 +1077,44 @@ alert((3) + (4));
         )
     }
 
+    #[test]
+    fn default_export_func() {
+        let ctx = Default::default();
+        let func = Stmt::Func(Method {
+            doc: Vec::new(),
+            ident: Ident("createClient".to_string()),
+            params: vec!["baseURL".to_string()],
+            stmts: vec![Stmt::Return(Expr::Var("baseURL".to_string()))],
+            is_async: false,
+            is_generator: false,
+        });
+        let export_stmt = Stmt::Export {
+            is_default: true,
+            stmt: Box::new(func),
+        };
+        assert_eq!(
+            export_stmt.gen(&ctx),
+            "export default function createClient(baseURL) {\n    return baseURL;\n}"
+        );
+    }
+
     #[test]
     fn xiaosi_class() {
         let ident = Ident("XiaoSi".to_string());
         let constructor = Constructor {
             params: vec![Ident("url".to_string()), Ident("params".to_string())],
             stmts: vec![Stmt::Expr(Expr::FuncCall {
-                func: box Expr::Var("console.log".to_string()),
+                func: Box::new(Expr::Var("console.log".to_string())),
                 args: vec![Expr::Literal(Literal::String("Hello world!".to_string()))],
             })],
         };
         let methods = vec![Method {
+            doc: Vec::new(),
             ident: Ident("loves".to_string()),
             params: vec!["singoi".to_string()],
             stmts: vec![Stmt::Return(Expr::Literal(Literal::Boolean(true)))],
             is_async: true,
+            is_generator: false,
         }];
         let getters = vec![Getter {
             ident: Ident("age".to_string()),
@@ -815,6 +1145,30 @@ return 23;
         );
     }
 
+    #[test]
+    fn quoted_names() {
+        let ctx = GenContext::new();
+        let method = Method {
+            doc: Vec::new(),
+            ident: Ident("v1.2".to_string()),
+            params: Vec::new(),
+            stmts: Vec::new(),
+            is_async: true,
+            is_generator: false,
+        };
+        assert_eq!(method.gen(&ctx), "async \"v1.2\"() {\n\n}");
+        let getter = Getter {
+            ident: Ident("users.GET".to_string()),
+            stmts: Vec::new(),
+        };
+        assert_eq!(getter.gen(&ctx), "get \"users.GET\"() {\n\n}");
+        let member = Expr::Member {
+            base: Box::new(Expr::Var("this".to_string())),
+            member: Ident("users.GET".to_string()),
+        };
+        assert_eq!(member.gen(&ctx), "(this)[\"users.GET\"]");
+    }
+
     #[test]
     fn xiaosi_import_all() {
         let imp = Import {
@@ -876,7 +1230,70 @@ return 23;
                 getters: Vec::new(),
             }),
         ];
-        let code = Code { stmts: stmts };
+        let code = Code { stmts };
         println!("{}", code.gen(&GenContext::new()));
     }
+
+    #[test]
+    fn code_gen_into() {
+        let ctx = Default::default();
+        let code = Code {
+            stmts: vec![
+                Stmt::LineComment("XiaoSi".to_string()),
+                Stmt::Return(Expr::Var("xiaosi".to_string())),
+            ],
+        };
+        let mut s = String::new();
+        code.gen_into(&ctx, &mut s).unwrap();
+        assert_eq!(s, code.gen(&ctx));
+        assert_eq!(s, "// XiaoSi\nreturn xiaosi;\n");
+    }
+
+    #[test]
+    fn generator_method() {
+        let ctx = Default::default();
+        let method = Method {
+            doc: Vec::new(),
+            ident: Ident("ids".to_string()),
+            params: vec!["n".to_string()],
+            stmts: vec![Stmt::Yield {
+                expr: Some(Expr::Var("n".to_string())),
+                is_delegate: false,
+            }],
+            is_async: false,
+            is_generator: true,
+        };
+        assert_eq!(method.gen(&ctx), "*ids(n) {\n    yield n;\n}");
+    }
+
+    #[test]
+    fn async_generator_method() {
+        let ctx = GenContext::new();
+        let method = Method {
+            doc: Vec::new(),
+            ident: Ident("events".to_string()),
+            params: Vec::new(),
+            stmts: vec![Stmt::ForOf {
+                typ: DeclType::Const,
+                ident: Ident("chunk".to_string()),
+                iterable: Expr::Await(Box::new(Expr::Var("stream".to_string()))),
+                is_await: true,
+                stmts: vec![Stmt::Expr(Expr::Yield {
+                    expr: Box::new(Expr::Var("chunk".to_string())),
+                    is_delegate: false,
+                })],
+            }],
+            is_async: true,
+            is_generator: true,
+        };
+        assert_eq!(
+            method.gen(&ctx),
+            "\
+async *events() {
+for await (const chunk of await stream) {
+yield chunk;
+}
+}"
+        );
+    }
 }
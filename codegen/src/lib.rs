@@ -1,8 +1,10 @@
-#![feature(box_syntax)]
-
-#[macro_use]
-extern crate serde_derive;
+#[cfg(feature = "javascript")]
 pub mod javascript;
+#[cfg(feature = "php")]
+pub mod php;
+#[cfg(feature = "ruby")]
+pub mod ruby;
+#[cfg(feature = "typescript")]
 pub mod typescript;
 pub mod utils;
 
@@ -0,0 +1,394 @@
+use super::utils::indent;
+
+const INDENT: &str = "    ";
+
+pub trait Gen {
+    fn gen(&self) -> String;
+}
+
+fn escape_single_quoted(s: &str) -> String {
+    s.replace("\\", "\\\\").replace("'", "\\'")
+}
+
+fn escape_double_quoted(s: &str) -> String {
+    s.replace("\\", "\\\\")
+        .replace("\"", "\\\"")
+        .replace("$", "\\$")
+        .replace("{", "\\{")
+}
+
+fn gen_body(stmts: &[Stmt]) -> String {
+    stmts
+        .iter()
+        .map(|v| indent(&v.gen(), INDENT))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn gen_args(args: &[Expr]) -> String {
+    args.iter()
+        .map(|v| v.gen())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[derive(Debug)]
+pub enum Literal {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Boolean(bool),
+    Null,
+}
+
+impl Gen for Literal {
+    fn gen(&self) -> String {
+        match self {
+            Literal::Int(n) => n.to_string(),
+            Literal::Float(n) => n.to_string(),
+            Literal::String(s) => format!("'{}'", escape_single_quoted(s)),
+            Literal::Boolean(b) => b.to_string(),
+            Literal::Null => "null".to_string(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum StrPart {
+    Lit(String),
+    // Only variables and property fetches can be interpolated
+    Interp(Expr),
+}
+
+#[derive(Debug)]
+pub enum Expr {
+    Literal(Literal),
+    // $a
+    Var(String),
+    // Foo, Foo::class
+    Name(String),
+    // $a->b
+    Prop {
+        base: Box<Expr>,
+        name: String,
+    },
+    // $a->b(c)
+    MethodCall {
+        base: Box<Expr>,
+        name: String,
+        args: Vec<Expr>,
+    },
+    // f(a)
+    FuncCall {
+        name: String,
+        args: Vec<Expr>,
+    },
+    // new Foo(a)
+    New {
+        class: String,
+        args: Vec<Expr>,
+    },
+    // ['a' => b, c]
+    Array(Vec<(Option<Expr>, Expr)>),
+    // a . b
+    Concat(Vec<Expr>),
+    // "a{$b}"
+    Interp(Vec<StrPart>),
+    // a !== b
+    BinOp {
+        op: String,
+        l: Box<Expr>,
+        r: Box<Expr>,
+    },
+    // (string) a
+    Cast {
+        typ: String,
+        expr: Box<Expr>,
+    },
+    // static fn ($a) => b
+    ArrowFn {
+        params: Vec<String>,
+        body: Box<Expr>,
+    },
+}
+
+impl Gen for Expr {
+    fn gen(&self) -> String {
+        match self {
+            Expr::Literal(lit) => lit.gen(),
+            Expr::Var(name) => format!("${}", name),
+            Expr::Name(name) => name.to_string(),
+            Expr::Prop { base, name } => format!("{}->{}", base.gen(), name),
+            Expr::MethodCall { base, name, args } => {
+                format!("{}->{}({})", base.gen(), name, gen_args(args))
+            }
+            Expr::FuncCall { name, args } => format!("{}({})", name, gen_args(args)),
+            Expr::New { class, args } => format!("new {}({})", class, gen_args(args)),
+            Expr::Array(items) => {
+                let rendered = items
+                    .iter()
+                    .map(|(k, v)| match k {
+                        Some(k) => format!("{} => {}", k.gen(), v.gen()),
+                        None => v.gen(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("[{}]", rendered)
+            }
+            Expr::Concat(exprs) => exprs
+                .iter()
+                .map(|v| v.gen())
+                .collect::<Vec<String>>()
+                .join(" . "),
+            Expr::Interp(parts) => {
+                let rendered = parts
+                    .iter()
+                    .map(|part| match part {
+                        StrPart::Lit(s) => escape_double_quoted(s),
+                        StrPart::Interp(expr) => format!("{{{}}}", expr.gen()),
+                    })
+                    .collect::<Vec<String>>()
+                    .join("");
+                format!("\"{}\"", rendered)
+            }
+            Expr::BinOp { op, l, r } => format!("{} {} {}", l.gen(), op, r.gen()),
+            Expr::Cast { typ, expr } => format!("({}) {}", typ, expr.gen()),
+            Expr::ArrowFn { params, body } => format!(
+                "static fn ({}) => {}",
+                params
+                    .iter()
+                    .map(|p| format!("${}", p))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                body.gen()
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MethodParam {
+    pub typ: Option<String>,
+    pub name: String,
+    pub default: Option<Expr>,
+}
+
+impl Gen for MethodParam {
+    fn gen(&self) -> String {
+        let mut rendered = match &self.typ {
+            Some(typ) => format!("{} ${}", typ, self.name),
+            None => format!("${}", self.name),
+        };
+        if let Some(default) = &self.default {
+            rendered.push_str(&format!(" = {}", default.gen()));
+        }
+        rendered
+    }
+}
+
+#[derive(Debug)]
+pub struct Property {
+    pub typ: Option<String>,
+    pub name: String,
+}
+
+impl Gen for Property {
+    fn gen(&self) -> String {
+        match &self.typ {
+            Some(typ) => format!("public {} ${};", typ, self.name),
+            None => format!("public ${};", self.name),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Method {
+    pub name: String,
+    pub params: Vec<MethodParam>,
+    pub ret: Option<String>,
+    pub stmts: Vec<Stmt>,
+}
+
+impl Gen for Method {
+    fn gen(&self) -> String {
+        format!(
+            "\
+public function {name}({params}){ret}
+{{
+{stmts}
+}}",
+            name = self.name,
+            params = self
+                .params
+                .iter()
+                .map(|v| v.gen())
+                .collect::<Vec<String>>()
+                .join(", "),
+            ret = match &self.ret {
+                Some(ret) => format!(": {}", ret),
+                None => String::new(),
+            },
+            stmts = gen_body(&self.stmts)
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub ident: String,
+    pub is_final: bool,
+    pub props: Vec<Property>,
+    pub methods: Vec<Method>,
+}
+
+impl Gen for Class {
+    fn gen(&self) -> String {
+        let mut sections = Vec::new();
+        if !self.props.is_empty() {
+            sections.push(
+                self.props
+                    .iter()
+                    .map(|v| v.gen())
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            );
+        }
+        sections.extend(self.methods.iter().map(|v| v.gen()));
+        format!(
+            "\
+{final_}class {ident}
+{{
+{body}
+}}",
+            final_ = if self.is_final { "final " } else { "" },
+            ident = self.ident,
+            body = sections
+                .iter()
+                .map(|v| indent(v, INDENT))
+                .collect::<Vec<String>>()
+                .join("\n\n")
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum Stmt {
+    Expr(Expr),
+    Return(Expr),
+    Assign { assignee: Expr, expr: Expr },
+    // declare(strict_types=1);
+    DeclareStrictTypes,
+    Namespace(String),
+    Use(String),
+    Class(Class),
+    // // a
+    LineComment(String),
+}
+
+impl Gen for Stmt {
+    fn gen(&self) -> String {
+        match self {
+            Stmt::Expr(expr) => format!("{};", expr.gen()),
+            Stmt::Return(expr) => format!("return {};", expr.gen()),
+            Stmt::Assign { assignee, expr } => format!("{} = {};", assignee.gen(), expr.gen()),
+            Stmt::DeclareStrictTypes => "declare(strict_types=1);".to_string(),
+            Stmt::Namespace(ns) => format!("namespace {};", ns),
+            Stmt::Use(name) => format!("use {};", name),
+            Stmt::Class(kls) => kls.gen(),
+            Stmt::LineComment(text) => format!("// {}", text),
+        }
+    }
+}
+
+pub struct Code {
+    pub stmts: Vec<Stmt>,
+}
+
+impl Gen for Code {
+    fn gen(&self) -> String {
+        let mut s = "<?php\n".to_string();
+        let mut prev: Option<&Stmt> = None;
+        for stmt in &self.stmts {
+            match (prev, stmt) {
+                // Keep consecutive imports together
+                (Some(Stmt::Use(_)), Stmt::Use(_)) => {}
+                // Keep the lines of a comment, such as the banner, together
+                (Some(Stmt::LineComment(_)), Stmt::LineComment(_)) => {}
+                _ => s.push('\n'),
+            }
+            s.push_str(&format!("{}\n", stmt.gen()));
+            prev = Some(stmt);
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_literal_escape() {
+        let lit = Literal::String("it's \\ ok".to_string());
+        assert_eq!(lit.gen(), "'it\\'s \\\\ ok'");
+    }
+
+    #[test]
+    fn interp_expr() {
+        let expr = Expr::Interp(vec![
+            StrPart::Interp(Expr::Prop {
+                base: Box::new(Expr::Var("super".to_string())),
+                name: "url".to_string(),
+            }),
+            StrPart::Lit("/users/$id".to_string()),
+        ]);
+        assert_eq!(expr.gen(), "\"{$super->url}/users/\\$id\"");
+    }
+
+    #[test]
+    fn array_expr() {
+        let expr = Expr::Array(vec![
+            (
+                Some(Expr::Literal(Literal::String("query".to_string()))),
+                Expr::Var("query".to_string()),
+            ),
+            (None, Expr::Literal(Literal::Int(3))),
+        ]);
+        assert_eq!(expr.gen(), "['query' => $query, 3]");
+    }
+
+    #[test]
+    fn xiaosi_class() {
+        let kls = Class {
+            ident: "XiaoSi".to_string(),
+            is_final: true,
+            props: vec![Property {
+                typ: Some("int".to_string()),
+                name: "age".to_string(),
+            }],
+            methods: vec![Method {
+                name: "loves".to_string(),
+                params: vec![MethodParam {
+                    typ: Some("?string".to_string()),
+                    name: "singoi".to_string(),
+                    default: Some(Expr::Literal(Literal::Null)),
+                }],
+                ret: Some("bool".to_string()),
+                stmts: vec![Stmt::Return(Expr::Literal(Literal::Boolean(true)))],
+            }],
+        };
+        assert_eq!(
+            kls.gen(),
+            "\
+final class XiaoSi
+{
+    public int $age;
+
+    public function loves(?string $singoi = null): bool
+    {
+        return true;
+    }
+}"
+        );
+    }
+}
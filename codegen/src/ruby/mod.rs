@@ -0,0 +1,402 @@
+use super::utils::indent;
+use linked_hash_map::LinkedHashMap;
+
+const INDENT: &str = "  ";
+
+pub trait Gen {
+    fn gen(&self) -> String;
+}
+
+fn escape_str(s: &str) -> String {
+    let mut escaped = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '#' if chars.peek() == Some(&'{') => escaped.push_str("\\#"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn gen_body(stmts: &[Stmt]) -> String {
+    stmts
+        .iter()
+        .map(|v| indent(&v.gen(), INDENT))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[derive(Debug)]
+pub enum Literal {
+    Number(f64),
+    String(String),
+    Symbol(String),
+    Boolean(bool),
+    Nil,
+}
+
+impl Gen for Literal {
+    fn gen(&self) -> String {
+        match self {
+            Literal::Number(n) => n.to_string(),
+            Literal::String(s) => format!("\"{}\"", escape_str(s)),
+            Literal::Symbol(s) => format!(":{}", s),
+            Literal::Boolean(b) => b.to_string(),
+            Literal::Nil => "nil".to_string(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum StrPart {
+    Lit(String),
+    Interp(Expr),
+}
+
+#[derive(Debug)]
+pub struct Block {
+    pub params: Vec<String>,
+    pub stmts: Vec<Stmt>,
+}
+
+impl Gen for Block {
+    fn gen(&self) -> String {
+        let params = if !self.params.is_empty() {
+            format!(" |{}|", self.params.join(", "))
+        } else {
+            String::new()
+        };
+        format!(
+            "\
+do{params}
+{stmts}
+end",
+            params = params,
+            stmts = gen_body(&self.stmts)
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum Expr {
+    // nil
+    Literal(Literal),
+    // a
+    Var(String),
+    // @a
+    IVar(String),
+    // "a#{b}"
+    Interp(Vec<StrPart>),
+    // { "a" => b }
+    Hash(LinkedHashMap<String, Expr>),
+    // recv.method(args) do |p| ... end
+    Call {
+        recv: Option<Box<Expr>>,
+        method: String,
+        args: Vec<Expr>,
+        block: Option<Block>,
+    },
+}
+
+impl Gen for Expr {
+    fn gen(&self) -> String {
+        match self {
+            Expr::Literal(lit) => lit.gen(),
+            Expr::Var(ident) => ident.to_string(),
+            Expr::IVar(ident) => format!("@{}", ident),
+            Expr::Interp(parts) => {
+                let rendered = parts
+                    .iter()
+                    .map(|part| match part {
+                        StrPart::Lit(s) => escape_str(s),
+                        StrPart::Interp(expr) => format!("#{{{}}}", expr.gen()),
+                    })
+                    .collect::<Vec<String>>()
+                    .join("");
+                format!("\"{}\"", rendered)
+            }
+            Expr::Hash(pairs) => {
+                if pairs.is_empty() {
+                    "{}".to_string()
+                } else {
+                    let rendered = pairs
+                        .iter()
+                        .map(|(k, v)| {
+                            format!("{} => {}", Literal::String(k.to_string()).gen(), v.gen())
+                        })
+                        .collect::<Vec<String>>()
+                        .join(", ");
+                    format!("{{ {} }}", rendered)
+                }
+            }
+            Expr::Call {
+                recv,
+                method,
+                args,
+                block,
+            } => {
+                let mut rendered = match recv {
+                    Some(recv) => format!("{}.{}", recv.gen(), method),
+                    None => method.to_string(),
+                };
+                if !args.is_empty() {
+                    let rendered_args = args
+                        .iter()
+                        .map(|v| v.gen())
+                        .collect::<Vec<String>>()
+                        .join(", ");
+                    rendered.push_str(&format!("({})", rendered_args));
+                }
+                if let Some(block) = block {
+                    rendered.push_str(&format!(" {}", block.gen()));
+                }
+                rendered
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MethodParam {
+    // def m(a)
+    Positional(String),
+    // def m(a: nil)
+    Keyword { name: String, default: Option<Expr> },
+}
+
+impl Gen for MethodParam {
+    fn gen(&self) -> String {
+        match self {
+            MethodParam::Positional(name) => name.to_string(),
+            MethodParam::Keyword { name, default } => match default {
+                Some(expr) => format!("{}: {}", name, expr.gen()),
+                None => format!("{}:", name),
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Method {
+    pub ident: String,
+    pub params: Vec<MethodParam>,
+    pub stmts: Vec<Stmt>,
+}
+
+impl Gen for Method {
+    fn gen(&self) -> String {
+        let params = if !self.params.is_empty() {
+            format!(
+                "({})",
+                self.params
+                    .iter()
+                    .map(|v| v.gen())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )
+        } else {
+            String::new()
+        };
+        format!(
+            "\
+def {ident}{params}
+{stmts}
+end",
+            ident = self.ident,
+            params = params,
+            stmts = gen_body(&self.stmts)
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub ident: String,
+    pub body: Vec<Stmt>,
+}
+
+impl Gen for Class {
+    fn gen(&self) -> String {
+        format!(
+            "\
+class {ident}
+{body}
+end",
+            ident = self.ident,
+            body = self
+                .body
+                .iter()
+                .map(|v| indent(&v.gen(), INDENT))
+                .collect::<Vec<String>>()
+                .join("\n\n")
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Module {
+    pub ident: String,
+    pub body: Vec<Stmt>,
+}
+
+impl Gen for Module {
+    fn gen(&self) -> String {
+        format!(
+            "\
+module {ident}
+{body}
+end",
+            ident = self.ident,
+            body = self
+                .body
+                .iter()
+                .map(|v| indent(&v.gen(), INDENT))
+                .collect::<Vec<String>>()
+                .join("\n\n")
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum Stmt {
+    Expr(Expr),
+    // a = b
+    Assign { assignee: Expr, expr: Expr },
+    // require "faraday"
+    Require(String),
+    // attr_reader :a, :b
+    AttrReader(Vec<String>),
+    Method(Method),
+    Class(Class),
+    Module(Module),
+    // # a
+    LineComment(String),
+}
+
+impl Gen for Stmt {
+    fn gen(&self) -> String {
+        match self {
+            Stmt::Expr(expr) => expr.gen(),
+            Stmt::Assign { assignee, expr } => format!("{} = {}", assignee.gen(), expr.gen()),
+            Stmt::Require(path) => format!("require {}", Literal::String(path.to_string()).gen()),
+            Stmt::AttrReader(names) => format!(
+                "attr_reader {}",
+                names
+                    .iter()
+                    .map(|v| Literal::Symbol(v.to_string()).gen())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Stmt::Method(method) => method.gen(),
+            Stmt::Class(kls) => kls.gen(),
+            Stmt::Module(module) => module.gen(),
+            Stmt::LineComment(text) => format!("# {}", text),
+        }
+    }
+}
+
+pub struct Code {
+    pub stmts: Vec<Stmt>,
+}
+
+impl Gen for Code {
+    fn gen(&self) -> String {
+        let mut s = String::new();
+        let mut prev: Option<&Stmt> = None;
+        for stmt in &self.stmts {
+            match (prev, stmt) {
+                // Keep consecutive requires together
+                (None, _) | (Some(Stmt::Require(_)), Stmt::Require(_)) => {}
+                // Keep the lines of a comment, such as the banner, together
+                (Some(Stmt::LineComment(_)), Stmt::LineComment(_)) => {}
+                _ => s.push('\n'),
+            }
+            s.push_str(&format!("{}\n", stmt.gen()));
+            prev = Some(stmt);
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_escape() {
+        let lit = Literal::String("say \"#{hi}\"".to_string());
+        assert_eq!(lit.gen(), "\"say \\\"\\#{hi}\\\"\"");
+    }
+
+    #[test]
+    fn interp_expr() {
+        let expr = Expr::Interp(vec![
+            StrPart::Interp(Expr::Call {
+                recv: Some(Box::new(Expr::Var("self".to_string()))),
+                method: "url".to_string(),
+                args: Vec::new(),
+                block: None,
+            }),
+            StrPart::Lit("/users".to_string()),
+        ]);
+        assert_eq!(expr.gen(), "\"#{self.url}/users\"");
+    }
+
+    #[test]
+    fn call_with_block() {
+        let expr = Expr::Call {
+            recv: Some(Box::new(Expr::Var("conn".to_string()))),
+            method: "get".to_string(),
+            args: vec![Expr::Literal(Literal::String("/".to_string()))],
+            block: Some(Block {
+                params: vec!["req".to_string()],
+                stmts: vec![Stmt::Expr(Expr::Var("req".to_string()))],
+            }),
+        };
+        assert_eq!(
+            expr.gen(),
+            "\
+conn.get(\"/\") do |req|
+  req
+end"
+        );
+    }
+
+    #[test]
+    fn xiaosi_class() {
+        let kls = Class {
+            ident: "XiaoSi".to_string(),
+            body: vec![
+                Stmt::AttrReader(vec!["age".to_string()]),
+                Stmt::Method(Method {
+                    ident: "loves".to_string(),
+                    params: vec![
+                        MethodParam::Positional("singoi".to_string()),
+                        MethodParam::Keyword {
+                            name: "much".to_string(),
+                            default: Some(Expr::Literal(Literal::Nil)),
+                        },
+                    ],
+                    stmts: vec![Stmt::Assign {
+                        assignee: Expr::IVar("age".to_string()),
+                        expr: Expr::Literal(Literal::Number(23.0)),
+                    }],
+                }),
+            ],
+        };
+        assert_eq!(
+            kls.gen(),
+            "\
+class XiaoSi
+  attr_reader :age
+
+  def loves(singoi, much: nil)
+    @age = 23
+  end
+end"
+        );
+    }
+}
@@ -0,0 +1,453 @@
+use super::utils::{doc_comment, indent, is_ident};
+
+const INDENT: &str = "    ";
+
+pub trait Gen {
+    fn gen(&self) -> String;
+}
+
+fn escape_single_quoted(s: &str) -> String {
+    s.replace("\\", "\\\\")
+        .replace("'", "\\'")
+        .replace("\n", "\\n")
+}
+
+fn escape_template(s: &str) -> String {
+    s.replace("\\", "\\\\")
+        .replace("`", "\\`")
+        .replace("${", "\\${")
+}
+
+fn gen_body(stmts: &[Stmt]) -> String {
+    stmts
+        .iter()
+        .map(|v| indent(&v.gen(), INDENT))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn gen_list<T: Gen>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|v| v.gen())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn gen_type_args(types: &[String]) -> String {
+    if !types.is_empty() {
+        format!("<{}>", types.join(", "))
+    } else {
+        String::new()
+    }
+}
+
+#[derive(Debug)]
+pub enum Literal {
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    Null,
+    Undefined,
+}
+
+impl Gen for Literal {
+    fn gen(&self) -> String {
+        match self {
+            Literal::Number(n) => n.to_string(),
+            Literal::String(s) => format!("'{}'", escape_single_quoted(s)),
+            Literal::Boolean(b) => b.to_string(),
+            Literal::Null => "null".to_string(),
+            Literal::Undefined => "undefined".to_string(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TemplatePart {
+    Lit(String),
+    Interp(Expr),
+}
+
+#[derive(Debug)]
+pub enum Expr {
+    Literal(Literal),
+    Ident(String),
+    // a.b
+    Member {
+        base: Box<Expr>,
+        name: String,
+    },
+    // f<T>(a)
+    Call {
+        callee: Box<Expr>,
+        type_args: Vec<String>,
+        args: Vec<Expr>,
+    },
+    // new Foo<T>(a)
+    New {
+        class: String,
+        type_args: Vec<String>,
+        args: Vec<Expr>,
+    },
+    // { a, 'b-c': d }
+    Object(Vec<(String, Expr)>),
+    // `a${b}`
+    Template(Vec<TemplatePart>),
+    // () => a
+    Arrow {
+        params: Vec<String>,
+        body: Box<Expr>,
+    },
+}
+
+impl Gen for Expr {
+    fn gen(&self) -> String {
+        match self {
+            Expr::Literal(lit) => lit.gen(),
+            Expr::Ident(name) => name.to_string(),
+            Expr::Member { base, name } => format!("{}.{}", base.gen(), name),
+            Expr::Call {
+                callee,
+                type_args,
+                args,
+            } => format!(
+                "{}{}({})",
+                callee.gen(),
+                gen_type_args(type_args),
+                gen_list(args)
+            ),
+            Expr::New {
+                class,
+                type_args,
+                args,
+            } => format!(
+                "new {}{}({})",
+                class,
+                gen_type_args(type_args),
+                gen_list(args)
+            ),
+            Expr::Object(entries) => {
+                if entries.is_empty() {
+                    return "{}".to_string();
+                }
+                let rendered = entries
+                    .iter()
+                    .map(|(k, v)| match v {
+                        Expr::Ident(name) if name == k => name.to_string(),
+                        _ if is_ident(k) => format!("{}: {}", k, v.gen()),
+                        _ => format!("'{}': {}", escape_single_quoted(k), v.gen()),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("{{ {} }}", rendered)
+            }
+            Expr::Template(parts) => {
+                let rendered = parts
+                    .iter()
+                    .map(|part| match part {
+                        TemplatePart::Lit(s) => escape_template(s),
+                        TemplatePart::Interp(expr) => format!("${{{}}}", expr.gen()),
+                    })
+                    .collect::<Vec<String>>()
+                    .join("");
+                format!("`{}`", rendered)
+            }
+            Expr::Arrow { params, body } => {
+                format!("({}) => {}", params.join(", "), body.gen())
+            }
+        }
+    }
+}
+
+/// `@Injectable({ providedIn: 'root' })`
+#[derive(Debug)]
+pub struct Decorator {
+    pub name: String,
+    pub args: Vec<Expr>,
+}
+
+impl Gen for Decorator {
+    fn gen(&self) -> String {
+        format!("@{}({})", self.name, gen_list(&self.args))
+    }
+}
+
+#[derive(Debug)]
+pub struct MethodParam {
+    pub decorators: Vec<Decorator>,
+    // Turns a constructor param into a parameter property, e.g. `private`
+    pub modifier: Option<String>,
+    pub name: String,
+    pub typ: Option<String>,
+    pub is_optional: bool,
+}
+
+impl Gen for MethodParam {
+    fn gen(&self) -> String {
+        let mut rendered = String::new();
+        for decorator in &self.decorators {
+            rendered.push_str(&format!("{} ", decorator.gen()));
+        }
+        if let Some(modifier) = &self.modifier {
+            rendered.push_str(&format!("{} ", modifier));
+        }
+        rendered.push_str(&self.name);
+        if self.is_optional {
+            rendered.push('?');
+        }
+        if let Some(typ) = &self.typ {
+            rendered.push_str(&format!(": {}", typ));
+        }
+        rendered
+    }
+}
+
+#[derive(Debug)]
+pub struct Method {
+    /// The lines of the JSDoc comment before the method
+    pub doc: Vec<String>,
+    pub name: String,
+    pub type_params: Vec<String>,
+    pub params: Vec<MethodParam>,
+    pub ret: Option<String>,
+    pub stmts: Vec<Stmt>,
+}
+
+impl Gen for Method {
+    fn gen(&self) -> String {
+        let signature = format!(
+            "{}{}({}){}",
+            self.name,
+            gen_type_args(&self.type_params),
+            gen_list(&self.params),
+            match &self.ret {
+                Some(ret) => format!(": {}", ret),
+                None => String::new(),
+            }
+        );
+        let doc = doc_comment(&self.doc);
+        if self.stmts.is_empty() {
+            return format!("{}{} {{}}", doc, signature);
+        }
+        format!("{}{} {{\n{}\n}}", doc, signature, gen_body(&self.stmts))
+    }
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub decorators: Vec<Decorator>,
+    pub is_export: bool,
+    pub ident: String,
+    pub methods: Vec<Method>,
+}
+
+impl Gen for Class {
+    fn gen(&self) -> String {
+        let mut rendered = String::new();
+        for decorator in &self.decorators {
+            rendered.push_str(&format!("{}\n", decorator.gen()));
+        }
+        if self.is_export {
+            rendered.push_str("export ");
+        }
+        rendered.push_str(&format!(
+            "class {} {{\n{}\n}}",
+            self.ident,
+            self.methods
+                .iter()
+                .map(|v| indent(&v.gen(), INDENT))
+                .collect::<Vec<String>>()
+                .join("\n\n")
+        ));
+        rendered
+    }
+}
+
+#[derive(Debug)]
+pub enum Stmt {
+    Expr(Expr),
+    Return(Expr),
+    Const {
+        is_export: bool,
+        ident: String,
+        expr: Expr,
+    },
+    // import { a, b } from 'c';
+    Import {
+        names: Vec<String>,
+        from: String,
+    },
+    Class(Class),
+    // type Uuid = string;
+    Type {
+        is_export: bool,
+        ident: String,
+        typ: String,
+    },
+    // Hand-written code emitted as is
+    Verbatim(String),
+    // // a
+    LineComment(String),
+}
+
+impl Gen for Stmt {
+    fn gen(&self) -> String {
+        match self {
+            Stmt::Expr(expr) => format!("{};", expr.gen()),
+            Stmt::Return(expr) => format!("return {};", expr.gen()),
+            Stmt::Const {
+                is_export,
+                ident,
+                expr,
+            } => format!(
+                "{}const {} = {};",
+                if *is_export { "export " } else { "" },
+                ident,
+                expr.gen()
+            ),
+            Stmt::Import { names, from } => format!(
+                "import {{ {} }} from '{}';",
+                names.join(", "),
+                escape_single_quoted(from)
+            ),
+            Stmt::Class(kls) => kls.gen(),
+            Stmt::Type {
+                is_export,
+                ident,
+                typ,
+            } => format!(
+                "{}type {} = {};",
+                if *is_export { "export " } else { "" },
+                ident,
+                typ
+            ),
+            Stmt::Verbatim(code) => code.trim_end().to_string(),
+            Stmt::LineComment(text) => format!("// {}", text),
+        }
+    }
+}
+
+pub struct Code {
+    pub stmts: Vec<Stmt>,
+}
+
+impl Gen for Code {
+    fn gen(&self) -> String {
+        let mut s = String::new();
+        let mut prev: Option<&Stmt> = None;
+        for stmt in &self.stmts {
+            match (prev, stmt) {
+                (None, _) => {}
+                // Keep consecutive imports together
+                (Some(Stmt::Import { .. }), Stmt::Import { .. }) => {}
+                // Keep the lines of a comment, such as the banner, together
+                (Some(Stmt::LineComment(_)), Stmt::LineComment(_)) => {}
+                _ => s.push('\n'),
+            }
+            s.push_str(&format!("{}\n", stmt.gen()));
+            prev = Some(stmt);
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_expr() {
+        let expr = Expr::Template(vec![
+            TemplatePart::Interp(Expr::Member {
+                base: Box::new(Expr::Ident("this".to_string())),
+                name: "baseUrl".to_string(),
+            }),
+            TemplatePart::Lit("/users/`${id}`".to_string()),
+        ]);
+        assert_eq!(expr.gen(), "`${this.baseUrl}/users/\\`\\${id}\\``");
+    }
+
+    #[test]
+    fn object_expr() {
+        let expr = Expr::Object(vec![
+            ("detail".to_string(), Expr::Ident("detail".to_string())),
+            (
+                "x-token".to_string(),
+                Expr::Literal(Literal::String("it's".to_string())),
+            ),
+            ("limit".to_string(), Expr::Literal(Literal::Number(10.0))),
+        ]);
+        assert_eq!(expr.gen(), "{ detail, 'x-token': 'it\\'s', limit: 10 }");
+    }
+
+    #[test]
+    fn type_stmt() {
+        let stmt = Stmt::Type {
+            is_export: true,
+            ident: "Uuid".to_string(),
+            typ: "string & { readonly __brand: 'Uuid' }".to_string(),
+        };
+        assert_eq!(
+            stmt.gen(),
+            "export type Uuid = string & { readonly __brand: 'Uuid' };"
+        );
+    }
+
+    #[test]
+    fn xiaosi_class() {
+        let kls = Class {
+            decorators: vec![Decorator {
+                name: "Injectable".to_string(),
+                args: Vec::new(),
+            }],
+            is_export: true,
+            ident: "XiaoSi".to_string(),
+            methods: vec![
+                Method {
+                    doc: Vec::new(),
+                    name: "constructor".to_string(),
+                    type_params: Vec::new(),
+                    params: vec![MethodParam {
+                        decorators: vec![Decorator {
+                            name: "Inject".to_string(),
+                            args: vec![Expr::Ident("AGE".to_string())],
+                        }],
+                        modifier: Some("private".to_string()),
+                        name: "age".to_string(),
+                        typ: Some("number".to_string()),
+                        is_optional: false,
+                    }],
+                    ret: None,
+                    stmts: Vec::new(),
+                },
+                Method {
+                    doc: Vec::new(),
+                    name: "loves".to_string(),
+                    type_params: vec!["T".to_string()],
+                    params: vec![MethodParam {
+                        decorators: Vec::new(),
+                        modifier: None,
+                        name: "singoi".to_string(),
+                        typ: Some("T".to_string()),
+                        is_optional: true,
+                    }],
+                    ret: Some("boolean".to_string()),
+                    stmts: vec![Stmt::Return(Expr::Literal(Literal::Boolean(true)))],
+                },
+            ],
+        };
+        assert_eq!(
+            kls.gen(),
+            "\
+@Injectable()
+export class XiaoSi {
+    constructor(@Inject(AGE) private age: number) {}
+
+    loves<T>(singoi?: T): boolean {
+        return true;
+    }
+}"
+        );
+    }
+}
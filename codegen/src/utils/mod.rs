@@ -3,27 +3,125 @@ pub use self::Either::{Left, Right};
 #[derive(Debug)]
 pub enum Either<L, R> {
     Left(L),
-    Right(R)
+    Right(R),
 }
 
 impl<L, R> Either<L, R> {
     pub fn is_left(&self) -> bool {
-        match self {
-            Left(_) => true,
-            _ => false
-        }
+        matches!(self, Left(_))
     }
 
     pub fn is_right(&self) -> bool {
-        match self {
-            Right(_) => true,
-            _ => false
-        }
+        matches!(self, Right(_))
     }
 }
 
 pub fn indent(s: &str, by: &str) -> String {
-    s.split("\n").map(|line| format!("{}{}", by, line)).collect::<Vec<_>>().join("\n")
+    s.split("\n")
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", by, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Whether `s` can be written as a JavaScript or TypeScript identifier, and
+/// so as an object key without quotes
+pub fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(ch) if ch.is_alphabetic() || ch == '_' || ch == '$' => {}
+        _ => return false,
+    }
+    chars.all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '$')
+}
+
+fn words(s: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut curr = String::new();
+    let mut prev_lower = false;
+    for ch in s.chars() {
+        if ch == '_' || ch == '-' || ch == ' ' {
+            if !curr.is_empty() {
+                words.push(curr);
+                curr = String::new();
+            }
+            prev_lower = false;
+        } else {
+            if ch.is_uppercase() && prev_lower && !curr.is_empty() {
+                words.push(curr);
+                curr = String::new();
+            }
+            prev_lower = ch.is_lowercase() || ch.is_numeric();
+            curr.push(ch);
+        }
+    }
+    if !curr.is_empty() {
+        words.push(curr);
+    }
+    words
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(ch) => ch
+            .to_uppercase()
+            .chain(chars.flat_map(|c| c.to_lowercase()))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// `ahcroId` => `ahcro_id`
+pub fn to_snake_case(s: &str) -> String {
+    words(s)
+        .iter()
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// `ahcro_id` => `ahcroId`
+pub fn to_camel_case(s: &str) -> String {
+    let mut result = String::new();
+    for (idx, w) in words(s).iter().enumerate() {
+        if idx == 0 {
+            result.push_str(&w.to_lowercase());
+        } else {
+            result.push_str(&capitalize(w));
+        }
+    }
+    result
+}
+
+/// `ahcro_id` => `AhcroId`
+pub fn to_pascal_case(s: &str) -> String {
+    words(s).iter().map(|w| capitalize(w)).collect()
+}
+
+/// A JSDoc-style block comment of the lines, ending with a newline, or nothing
+/// if there are no lines
+pub fn doc_comment(lines: &[String]) -> String {
+    if lines.is_empty() {
+        return String::new();
+    }
+    let mut rendered = "/**\n".to_string();
+    for line in lines.iter().flat_map(|v| v.split('\n')) {
+        // Would end the comment otherwise
+        let line = line.replace("*/", "*\\/");
+        if line.is_empty() {
+            rendered.push_str(" *\n");
+        } else {
+            rendered.push_str(&format!(" * {}\n", line));
+        }
+    }
+    rendered.push_str(" */\n");
+    rendered
 }
 
 #[cfg(test)]
@@ -39,4 +137,28 @@ world";
     world";
         assert_eq!(indent(code, "    "), expected);
     }
+
+    #[test]
+    fn indent_should_skip_empty_lines() {
+        assert_eq!(indent("hello\n\nworld", "  "), "  hello\n\n  world");
+    }
+
+    #[test]
+    fn doc_comment_should_work() {
+        assert_eq!(doc_comment(&[]), "");
+        assert_eq!(
+            doc_comment(&["Finds */ them\n".to_string(), "@deprecated".to_string()]),
+            "/**\n * Finds *\\/ them\n *\n * @deprecated\n */\n"
+        );
+    }
+
+    #[test]
+    fn case_conversion_should_work() {
+        assert_eq!(to_snake_case("ahcroId"), "ahcro_id");
+        assert_eq!(to_snake_case("ahcro_id"), "ahcro_id");
+        assert_eq!(to_camel_case("ahcro_id"), "ahcroId");
+        assert_eq!(to_camel_case("ahcroId"), "ahcroId");
+        assert_eq!(to_pascal_case("ahcro_id"), "AhcroId");
+        assert_eq!(to_pascal_case("users"), "Users");
+    }
 }
@@ -0,0 +1,90 @@
+//! Lookups through a deep tree of contexts, the way references of a large
+//! schema are resolved. `lookup` shares the value with the context, and
+//! `lookup_cloned` copies it as lookups used to.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::cell::RefCell;
+use std::rc::Rc;
+use xsrc::se_parser::{Expr, Member};
+use xsrc::transformer::{Context, ContextValue};
+
+const DEPTH: usize = 32;
+const VALUES: usize = 8;
+
+/// A URL of `parts` literals and references, like a long `$url`
+fn url(parts: usize) -> Expr {
+    (0..parts).fold(Expr::Lit("http://ratina.org".to_string()), |expr, i| {
+        let part = if i % 2 == 0 {
+            Expr::Lit(format!("/ratincren{}", i))
+        } else {
+            Expr::Ref(vec![Member::Super, Member::Member(format!("url{}", i))])
+        };
+        Expr::Concat(Box::new(expr), Box::new(part))
+    })
+}
+
+/// A chain of `DEPTH` contexts below the root, each with `VALUES` URLs. The
+/// root and the deepest context are returned.
+fn prepare_context() -> (Rc<RefCell<Context>>, Rc<RefCell<Context>>) {
+    let root = Rc::new(RefCell::new(Context::new("root", None)));
+    let mut leaf = Rc::clone(&root);
+    for depth in 0..=DEPTH {
+        if depth > 0 {
+            leaf = Context::new_child(&leaf, &format!("ctx{}", depth));
+        }
+        for i in 0..VALUES {
+            leaf.borrow_mut()
+                .add_value(&format!("url{}", i), ContextValue::Expr(url(64)));
+        }
+    }
+    (root, leaf)
+}
+
+/// Keys from the deepest context up to each ancestor, and from the root down
+/// to each descendant
+fn keys() -> (Vec<Vec<Member>>, Vec<Vec<Member>>) {
+    let mut up = Vec::new();
+    let mut down = Vec::new();
+    for depth in 0..=DEPTH {
+        for i in 0..VALUES {
+            let mut key = vec![Member::Super; depth];
+            key.push(Member::Member(format!("url{}", i)));
+            up.push(key);
+            let mut key = (1..=depth)
+                .map(|d| Member::Member(format!("ctx{}", d)))
+                .collect::<Vec<_>>();
+            key.push(Member::Member(format!("url{}", i)));
+            down.push(key);
+        }
+    }
+    (up, down)
+}
+
+fn bench_lookup(c: &mut Criterion) {
+    let (root, leaf) = prepare_context();
+    let (up, down) = keys();
+    let mut group = c.benchmark_group("context_lookup");
+    group.bench_function("lookup", |b| {
+        b.iter(|| {
+            for key in &up {
+                black_box(leaf.borrow().lookup(key).unwrap());
+            }
+            for key in &down {
+                black_box(root.borrow().lookup(key).unwrap());
+            }
+        })
+    });
+    group.bench_function("lookup_cloned", |b| {
+        b.iter(|| {
+            for key in &up {
+                black_box((*leaf.borrow().lookup(key).unwrap()).clone());
+            }
+            for key in &down {
+                black_box((*root.borrow().lookup(key).unwrap()).clone());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_lookup);
+criterion_main!(benches);
@@ -0,0 +1,76 @@
+//! The stages of the pipeline on a schema as large as the largest ones in
+//! use, of 900 APIs in APISets two deep, to tell where the time goes.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::io;
+use xsrc::rewriter::{generate, generate_to, Registry};
+use xsrc::schema::parse_str;
+use xsrc::transformer::transform;
+
+const APISETS: usize = 30;
+const SUB_APISETS: usize = 3;
+const APIS: usize = 10;
+
+/// The YAML of the schema, with URL params at every level and params and
+/// data on every API
+fn large_schema() -> String {
+    let mut yaml =
+        String::from("$url: \"http://ratina.org/<tenant:string>\"\n$as: \"RatinaClient\"\n");
+    for i in 0..APISETS {
+        yaml.push_str(&format!(
+            "~ratincren{}:\n  $url: \"${{!super}}/ratincren{}/<group{}:int>\"\n",
+            i, i, i
+        ));
+        for j in 0..SUB_APISETS {
+            yaml.push_str(&format!(
+                "  ~budgets{}:\n    $url: \"${{!super}}/budgets{}/<name:string>\"\n",
+                j, j
+            ));
+            for k in 0..APIS {
+                yaml.push_str(&format!(
+                    "    \
+    api{}:
+      $url: \"${{!super}}/api{}/<id:int>?q=<q?:string=ahcro>\"
+      $method: \"POST\"
+      $params:
+        page: \"int\"
+        size: \"int\"
+      $data:
+        name: \"string\"
+        status: \"enum(open,closed)\"
+",
+                    k, k
+                ));
+            }
+        }
+    }
+    yaml
+}
+
+fn bench_pipeline(c: &mut Criterion) {
+    let yaml = large_schema();
+    let registry = Registry::default();
+    let root = transform(parse_str(&yaml).unwrap()).unwrap();
+    let mut group = c.benchmark_group("pipeline");
+    group.sample_size(20);
+    group.bench_function("parse", |b| b.iter(|| parse_str(black_box(&yaml)).unwrap()));
+    // The schema can't be cloned, so it's parsed each time
+    group.bench_function("parse_and_transform", |b| {
+        b.iter(|| transform(parse_str(black_box(&yaml)).unwrap()).unwrap())
+    });
+    group.bench_function("check", |b| b.iter(|| root.check().unwrap()));
+    for lang in &["javascript", "ruby", "angular"] {
+        let backend = registry.get(lang).unwrap();
+        group.bench_function(format!("generate/{}", lang), |b| {
+            b.iter(|| generate(backend, &root, &Default::default()).unwrap())
+        });
+    }
+    // The code streamed into a writer, without holding it whole
+    let backend = registry.get("javascript").unwrap();
+    group.bench_function("generate_to/javascript", |b| {
+        b.iter(|| generate_to(backend, &root, &Default::default(), &mut io::sink()).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_pipeline);
+criterion_main!(benches);
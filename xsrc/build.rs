@@ -0,0 +1,34 @@
+//! Sets `XSRC_VERSION`, the version of the crate followed by `git describe` of
+//! the tree when it is built from a git checkout, for `xsrc::version()`.
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+fn git_describe() -> Option<String> {
+    let output = Command::new("git")
+        .args(["describe", "--tags", "--always"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let describe = String::from_utf8(output.stdout).ok()?;
+    Some(describe.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+fn main() {
+    let version = env::var("CARGO_PKG_VERSION").unwrap();
+    let version = match git_describe() {
+        Some(describe) => format!("{} ({})", version, describe),
+        None => version,
+    };
+    println!("cargo:rustc-env=XSRC_VERSION={}", version);
+    println!("cargo:rerun-if-changed=build.rs");
+    // Described again when a commit is made or checked out. A path that
+    // doesn't exist would run the script on every build.
+    for path in &["../.git/HEAD", "../.git/logs/HEAD"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+/*
+ * The C interface of xsrc, in the cdylib built with the `ffi` feature.
+ * Strings are UTF-8 and NUL-terminated.
+ */
+#ifndef XSRC_H
+#define XSRC_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define XSRC_OK 0
+/* A pointer argument is null or its string isn't UTF-8 */
+#define XSRC_ERR_ARGUMENT 1
+/* options_json isn't a JSON object of valid options */
+#define XSRC_ERR_OPTIONS 2
+#define XSRC_ERR_PARSE 3
+#define XSRC_ERR_TRANSFORM 4
+/* No backend for the language, or the backend failed */
+#define XSRC_ERR_GENERATE 5
+#define XSRC_ERR_PANIC 6
+
+/*
+ * Generates the code of the YAML schema in lang, a backend name as taken by
+ * `xsrc --lang`. options_json is a JSON object of options keyed as in
+ * configs, e.g. {"param-case": "camel"}, or NULL or "" for the defaults.
+ *
+ * Returns XSRC_OK and stores the code in *out, to be freed with
+ * xsrc_free_string. Otherwise *out is set to NULL and an error code is
+ * returned, with the message from xsrc_last_error.
+ */
+int xsrc_generate(const char *schema, const char *lang, const char *options_json, char **out);
+
+/* Frees a string from xsrc_generate. NULL is ignored. */
+void xsrc_free_string(char *s);
+
+/*
+ * The message of the last error of xsrc_generate on this thread, or NULL if
+ * the last call succeeded. Owned by xsrc, and valid until the next call of
+ * xsrc_generate on the thread.
+ */
+const char *xsrc_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* XSRC_H */
@@ -1,103 +1,1851 @@
-#![feature(box_syntax)]
-
-use maplit::hashmap;
+use self::GenError::*;
+use clap::{load_yaml, App, Arg, ArgMatches, ErrorKind, Shell};
+use log::info;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
-use clap::{App, load_yaml};
-use std::collections::HashMap;
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
-use self::GenError::*;
-
-struct LangInfo<'a> {
-    ext: &'a str,
-}
+use std::sync::mpsc::channel;
+use std::time::Duration;
+use xsrc::config::Options;
+use xsrc::rewriter::{
+    generate, generate_to, output_file_name, Backend, ClientStyle, GenOptions, GenOptionsError,
+    Registry,
+};
+use xsrc::{SchemaSource, XsrcError};
 
-fn init_lang_infos() -> HashMap<&'static str, LangInfo<'static>> {
-    return hashmap![
-        "javascript" => LangInfo {
-            ext: ".js"
-        }
-    ];
+/// The errors of the library, and those of the command line
+#[derive(Debug)]
+enum GenError {
+    Xsrc(XsrcError),
+    DeniedWarnings(usize),
+    PatternError(glob::PatternError),
+    NoMatch(String),
+    /// An output file exists and has no banner of xsrc
+    NotGenerated(PathBuf),
+    /// The documents of a schema file would be written to the same file
+    SharedOutput(PathBuf),
+    /// A schema would be written to the file of one from an earlier source,
+    /// as two with the same class name are with `--out-dir`
+    TakenOutput(PathBuf, String),
+    /// Every error found in a schema, when there's more than one
+    Errors(Vec<GenError>),
 }
 
-enum GenError {
-    ParserError(xsrc::schema::ParserError),
-    TransformerError(xsrc::transformer::TransformerError),
-    UnsupportedLanguage(String),
-    IOError(std::io::Error),
+impl From<XsrcError> for GenError {
+    fn from(e: XsrcError) -> Self {
+        Xsrc(e)
+    }
 }
 
 impl From<xsrc::schema::ParserError> for GenError {
     fn from(e: xsrc::schema::ParserError) -> Self {
-        ParserError(e)
+        Xsrc(e.into())
     }
 }
 
 impl From<xsrc::transformer::TransformerError> for GenError {
     fn from(e: xsrc::transformer::TransformerError) -> Self {
-        TransformerError(e)
+        Xsrc(e.into())
+    }
+}
+
+impl From<xsrc::rewriter::GenError> for GenError {
+    fn from(e: xsrc::rewriter::GenError) -> Self {
+        Xsrc(e.into())
+    }
+}
+
+impl From<glob::PatternError> for GenError {
+    fn from(e: glob::PatternError) -> Self {
+        PatternError(e)
     }
 }
 
 impl From<std::io::Error> for GenError {
     fn from(e: std::io::Error) -> Self {
-        IOError(e)
+        Xsrc(e.into())
     }
 }
 
 impl fmt::Display for GenError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            ParserError(e) => write!(f, "Parser error: {}", e),
-            TransformerError(e) => write!(f, "Transformer error: {}", e),
-            UnsupportedLanguage(lang) => write!(f, "Unsupported language: {}", lang),
-            IOError(e) => write!(f, "IO error: {}", e)
+            Xsrc(e) => write!(f, "{}", e),
+            DeniedWarnings(n) => write!(f, "{} warning(s) denied", n),
+            PatternError(e) => write!(f, "Pattern error: {}", e),
+            NoMatch(pattern) => write!(f, "No schema file matches {}", pattern),
+            NotGenerated(path) => write!(
+                f,
+                "Refusing to overwrite {}, which wasn't generated by xsrc (use --force to overwrite it)",
+                path.display()
+            ),
+            SharedOutput(path) => write!(
+                f,
+                "The schemas of the file would all be written to {} (give each its own $as and no --output)",
+                path.display()
+            ),
+            TakenOutput(path, source) => write!(
+                f,
+                "{} is already generated from {} (give the schemas different $as)",
+                path.display(),
+                source
+            ),
+            Errors(errors) => {
+                let messages = errors.iter().map(|e| e.to_string());
+                write!(f, "{}", messages.collect::<Vec<String>>().join("\n"))
+            }
         }
     }
 }
 
-fn gen<P: AsRef<Path> + Clone, Q: AsRef<Path> + Clone>(
-    lang: &str,
-    schema_file: P,
+impl std::error::Error for GenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Xsrc(e) => e.source(),
+            PatternError(e) => Some(e),
+            DeniedWarnings(_) | NoMatch(_) | NotGenerated(_) | SharedOutput(_) | Errors(_) => None,
+            TakenOutput(..) => None,
+        }
+    }
+}
+
+impl GenError {
+    /// The error of `errors`, or all of them if there's more than one
+    fn all(errors: impl IntoIterator<Item = GenError>) -> Self {
+        let mut errors = errors.into_iter().collect::<Vec<GenError>>();
+        if errors.len() == 1 {
+            errors.remove(0)
+        } else {
+            Errors(errors)
+        }
+    }
+
+    /// The errors to print one by one
+    fn errors(&self) -> Vec<&GenError> {
+        match self {
+            Errors(errors) => errors.iter().collect(),
+            e => vec![e],
+        }
+    }
+
+    /// 1 if the code couldn't be generated, 2 if the arguments are wrong and 3
+    /// if the schema is invalid
+    fn exit_code(&self) -> i32 {
+        match self {
+            Xsrc(XsrcError::BackendError(_)) | Xsrc(XsrcError::IOError(_)) | NotGenerated(_) => 1,
+            Xsrc(XsrcError::UnsupportedLanguage(..)) | Xsrc(XsrcError::InvalidOptions(_)) => 2,
+            Xsrc(XsrcError::NotCompiledIn(_)) => 2,
+            PatternError(_) | NoMatch(_) | SharedOutput(_) | TakenOutput(..) => 2,
+            Xsrc(XsrcError::ParserError(_)) | Xsrc(XsrcError::TransformerError(_)) => 3,
+            DeniedWarnings(_) => 3,
+            Errors(errors) => errors.iter().map(|e| e.exit_code()).max().unwrap_or(3),
+        }
+    }
+
+    /// What went wrong, in a word or two for tools reading the JSON format
+    fn kind(&self) -> &'static str {
+        match self {
+            Xsrc(XsrcError::ParserError(_)) => "parse",
+            Xsrc(XsrcError::TransformerError(_)) => "transform",
+            Xsrc(XsrcError::BackendError(_)) => "generate",
+            Xsrc(XsrcError::InvalidOptions(_)) => "options",
+            Xsrc(XsrcError::UnsupportedLanguage(..)) => "unsupported-language",
+            Xsrc(XsrcError::NotCompiledIn(_)) => "not-compiled-in",
+            Xsrc(XsrcError::IOError(_)) => "io",
+            DeniedWarnings(_) => "denied-warnings",
+            PatternError(_) | NoMatch(_) => "input",
+            NotGenerated(_) | SharedOutput(_) | TakenOutput(..) => "output",
+            Errors(errors) => errors.first().map_or("transform", |e| e.kind()),
+        }
+    }
+
+    /// The path in the schema where the error is, if known
+    fn schema_path(&self) -> Option<&[String]> {
+        match self {
+            Xsrc(XsrcError::TransformerError(e)) => e.path(),
+            Xsrc(XsrcError::BackendError(xsrc::rewriter::GenError::ContextLookupError(e))) => {
+                e.context_path()
+            }
+            Xsrc(XsrcError::BackendError(xsrc::rewriter::GenError::NameClash {
+                paths, ..
+            })) => Some(&paths.1),
+            Xsrc(XsrcError::BackendError(xsrc::rewriter::GenError::UnsupportedParam {
+                path,
+                ..
+            })) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// The expression where the error is, and the index of the offending char
+    /// in it, if known
+    fn snippet(&self) -> Option<(&str, usize)> {
+        use xsrc::se_parser::ParserError as PE;
+        match self {
+            Xsrc(XsrcError::TransformerError(
+                xsrc::transformer::TransformerError::InvalidUrl { url, error, .. },
+            )) => match error {
+                PE::UnexpectedToken(_, pos) => Some((url, *pos)),
+                PE::UnexpectedEOF => Some((url, url.chars().count())),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The line and column in the schema file where the error is, if known.
+    /// That of an invalid `$url` is found in `file`, if it can be read.
+    fn position(&self, file: &str) -> Option<position::Position> {
+        match self {
+            Xsrc(XsrcError::ParserError(xsrc::schema::ParserError::SerdeError(e))) => {
+                e.location().map(|v| position::Position {
+                    line: v.line(),
+                    column: v.column(),
+                })
+            }
+            _ => {
+                let (url, offset) = self.snippet()?;
+                let source = std::fs::read_to_string(file).ok()?;
+                position::of_url(&source, self.schema_path()?, url, offset)
+            }
+        }
+    }
+}
+
+/// How errors and warnings are printed
+#[derive(Clone, Copy, PartialEq)]
+enum ErrorFormat {
+    Human,
+    /// A JSON object per line
+    Json,
+}
+
+/// An error or a warning about an input
+struct Diagnostic<'a> {
+    severity: &'static str,
+    /// What the error is about, or the rule of the warning
+    kind: &'static str,
+    message: String,
+    file: &'a str,
+    path: Option<&'a [String]>,
+    position: Option<position::Position>,
+    snippet: Option<(&'a str, usize)>,
+}
+
+impl<'a> Diagnostic<'a> {
+    fn error(file: &'a str, e: &'a GenError) -> Self {
+        Diagnostic {
+            severity: "error",
+            kind: e.kind(),
+            message: e.to_string(),
+            file,
+            path: e.schema_path(),
+            position: e.position(file),
+            snippet: e.snippet(),
+        }
+    }
+
+    fn warning(file: &'a str, w: &'a xsrc::transformer::TransformerWarning) -> Self {
+        Diagnostic {
+            severity: "warning",
+            // As the lint rules checking the same
+            kind: match w {
+                xsrc::transformer::TransformerWarning::EmptyAPISet { .. } => "empty-apiset",
+                xsrc::transformer::TransformerWarning::ShadowedParam { .. } => "shadowed-param",
+            },
+            message: w.to_string(),
+            file,
+            path: Some(w.path()),
+            position: None,
+            snippet: None,
+        }
+    }
+
+    fn finding(file: &'a str, finding: &'a xsrc::lint::Finding) -> Self {
+        Diagnostic {
+            severity: match finding.severity {
+                xsrc::lint::Severity::Warning => "warning",
+                xsrc::lint::Severity::Error => "error",
+            },
+            kind: finding.rule,
+            message: format!("{} [{}]", finding.message, finding.rule),
+            file,
+            path: Some(&finding.path),
+            position: None,
+            snippet: None,
+        }
+    }
+
+    /// Prints to stderr, after `prefix` in the human format, which is colored
+    /// if `color` is set
+    fn emit(&self, format: ErrorFormat, color: bool, prefix: &str) {
+        match format {
+            ErrorFormat::Human => eprint!("{}{}", prefix, snippet::render(self, color)),
+            ErrorFormat::Json => {
+                let diagnostic = serde_json::json!({
+                    "severity": self.severity,
+                    "kind": self.kind,
+                    "message": self.message,
+                    "file": self.file,
+                    "path": self.path,
+                    "position": self.position.map(|v| serde_json::json!({
+                        "line": v.line,
+                        "column": v.column,
+                    })),
+                });
+                eprintln!("{}", diagnostic);
+            }
+        }
+    }
+}
+
+/// Renders diagnostics in the human format, like:
+///
+/// ```text
+/// Error: ratina.yaml: Transformer error: Invalid $url of users.get: Unexpected EOF
+///   --> ratina.yaml:5:35: users.get
+///    |
+///    | ${!super}/users/<id:int
+///    |                        ^
+/// ```
+mod snippet {
+    use super::Diagnostic;
+
+    const RED: &str = "\x1b[1;31m";
+    const YELLOW: &str = "\x1b[1;33m";
+    const BLUE: &str = "\x1b[1;34m";
+    const RESET: &str = "\x1b[0m";
+
+    struct Paint {
+        color: bool,
+    }
+
+    impl Paint {
+        fn paint(&self, style: &str, s: &str) -> String {
+            if self.color {
+                format!("{}{}{}", style, s, RESET)
+            } else {
+                s.to_string()
+            }
+        }
+    }
+
+    /// The lines of a diagnostic, each ending with a newline. The expression
+    /// and the caret under its offending char are only shown when the
+    /// diagnostic has a snippet.
+    pub fn render(diagnostic: &Diagnostic, color: bool) -> String {
+        let p = Paint { color };
+        let severity = if diagnostic.severity == "error" {
+            p.paint(RED, "Error")
+        } else {
+            p.paint(YELLOW, "Warning")
+        };
+        let mut ret = format!(
+            "{}: {}: {}\n",
+            severity, diagnostic.file, diagnostic.message
+        );
+        let (expr, pos) = match diagnostic.snippet {
+            Some(v) => v,
+            None => return ret,
+        };
+        let path = match diagnostic.path {
+            Some(path) if !path.is_empty() => path.join("."),
+            _ => "(root)".to_string(),
+        };
+        let gutter = p.paint(BLUE, "|");
+        let file = match diagnostic.position {
+            Some(v) => format!("{}:{}:{}", diagnostic.file, v.line, v.column),
+            None => diagnostic.file.to_string(),
+        };
+        ret.push_str(&format!("  {} {}: {}\n", p.paint(BLUE, "-->"), file, path));
+        ret.push_str(&format!("   {}\n", gutter));
+        ret.push_str(&format!("   {} {}\n", gutter, expr));
+        ret.push_str(&format!(
+            "   {} {}{}\n",
+            gutter,
+            " ".repeat(pos),
+            p.paint(RED, "^")
+        ));
+        ret
+    }
+}
+
+/// Finds where a value is in the text of a schema file, which serde_yaml
+/// doesn't keep once the schema is parsed
+mod position {
+    use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+    use yaml_rust::scanner::{Marker, TScalarStyle};
+
+    /// A line and a column in a schema file, both counted from 1
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Position {
+        pub line: usize,
+        pub column: usize,
+    }
+
+    struct Events(Vec<(Event, Marker)>);
+
+    impl MarkedEventReceiver for Events {
+        fn on_event(&mut self, ev: Event, mark: Marker) {
+            self.0.push((ev, mark));
+        }
+    }
+
+    /// The index of the event after the node starting at `i`
+    fn skip(events: &[(Event, Marker)], mut i: usize) -> usize {
+        let mut depth = 0;
+        while let Some((ev, _)) = events.get(i) {
+            match ev {
+                Event::MappingStart(_) | Event::SequenceStart(_) => depth += 1,
+                Event::MappingEnd | Event::SequenceEnd => depth -= 1,
+                _ => {}
+            }
+            i += 1;
+            if depth == 0 {
+                break;
+            }
+        }
+        i
+    }
+
+    /// The index of the value of the first key that `is_key` accepts in the
+    /// mapping starting at `i`
+    fn value_of(
+        events: &[(Event, Marker)],
+        i: usize,
+        is_key: impl Fn(&str) -> bool,
+    ) -> Option<usize> {
+        if !matches!(events.get(i)?.0, Event::MappingStart(_)) {
+            return None;
+        }
+        let mut i = i + 1;
+        loop {
+            match &events.get(i)?.0 {
+                Event::MappingEnd => return None,
+                Event::Scalar(key, ..) if is_key(key) => return Some(i + 1),
+                _ => i = skip(events, skip(events, i)),
+            }
+        }
+    }
+
+    /// Whether `key` is that of the API or APISet named `name`, as `~users`
+    /// or `users.GET` are for `users`
+    fn is_named(key: &str, name: &str) -> bool {
+        key == name
+            || key.strip_prefix('~') == Some(name)
+            || key
+                .strip_prefix(name)
+                .and_then(|v| v.strip_prefix('.'))
+                .is_some_and(|v| v.chars().all(|ch| ch.is_ascii_alphabetic()))
+    }
+
+    /// Where the char at `offset` of `url`, the `$url` of the API or APISet
+    /// at `path`, is in `source`. It's the start of the value if the value
+    /// isn't written as it is, such as one with escapes.
+    pub fn of_url(source: &str, path: &[String], url: &str, offset: usize) -> Option<Position> {
+        let mut events = Events(Vec::new());
+        Parser::new(source.chars()).load(&mut events, false).ok()?;
+        let events = events.0;
+        // After the starts of the stream and of the document
+        let mut i = 2;
+        for name in path {
+            i = value_of(&events, i, |key| is_named(key, name))?;
+        }
+        i = value_of(&events, i, |key| key == "$url")?;
+        let (value, style, mark) = match &events[i] {
+            (Event::Scalar(value, style, ..), mark) => (value, style, mark),
+            _ => return None,
+        };
+        let quote = match style {
+            TScalarStyle::Plain => 0,
+            TScalarStyle::SingleQuoted | TScalarStyle::DoubleQuoted => 1,
+            _ => {
+                return Some(Position {
+                    line: mark.line(),
+                    column: mark.col() + 1,
+                })
+            }
+        };
+        let verbatim = value == url
+            && source
+                .chars()
+                .skip(mark.index() + quote)
+                .take(url.chars().count())
+                .eq(url.chars());
+        Some(Position {
+            line: mark.line(),
+            column: mark.col() + 1 + if verbatim { quote + offset } else { 0 },
+        })
+    }
+}
+
+/// Prints the backends in the order they were registered, as aligned columns
+/// of the name, the extension and the description, or as a JSON array
+fn print_langs(registry: &Registry, json: bool) {
+    let backends = registry.backends();
+    if json {
+        let langs = backends
+            .iter()
+            .map(|b| {
+                serde_json::json!({
+                    "name": b.name(),
+                    "extension": b.extension(),
+                    "description": b.description(),
+                })
+            })
+            .collect::<Vec<serde_json::Value>>();
+        println!("{}", serde_json::to_string_pretty(&langs).unwrap());
+        return;
+    }
+    let name_width = backends.iter().map(|b| b.name().len()).max().unwrap_or(0);
+    let ext_width = backends
+        .iter()
+        .map(|b| b.extension().len())
+        .max()
+        .unwrap_or(0);
+    for b in backends {
+        println!(
+            "{:name_width$}  {:ext_width$}  {}",
+            b.name(),
+            b.extension(),
+            b.description(),
+            name_width = name_width,
+            ext_width = ext_width
+        );
+    }
+}
+
+fn backend<'a>(registry: &'a Registry, lang: &str) -> Result<&'a dyn Backend, GenError> {
+    Ok(xsrc::backend(registry, lang)?)
+}
+
+enum Source {
+    File(PathBuf),
+    Dir(PathBuf),
+}
+
+impl Source {
+    fn path(&self) -> &Path {
+        match self {
+            Source::File(p) | Source::Dir(p) => p,
+        }
+    }
+
+    /// The name of the schema file or directory, without the extension
+    fn stem(&self) -> Option<&str> {
+        self.path().file_stem().and_then(|v| v.to_str())
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::File(p) | Source::Dir(p) => write!(f, "{}", p.display()),
+        }
+    }
+}
+
+/// Where the generated code goes
+enum Output {
+    File(PathBuf),
+    /// A file per schema, named after its client class, or after the schema
+    /// file if the class isn't named
+    Dir(PathBuf),
+    /// A file in the working directory, named as in `Dir`, but with no
+    /// manifest to clean up after
+    Default,
+}
+
+/// How far `--check` goes before discarding the result
+enum CheckMode {
+    Transform,
+    Full,
+}
+
+/// What is done with the generated code instead of writing it
+enum Preview {
+    /// Print it
+    DryRun,
+    /// Print how the output file differs from it
+    Diff,
+}
+
+/// What applies to every schema
+struct Settings<'a> {
+    registry: &'a Registry,
+    only: Vec<Vec<String>>,
+    check: Option<CheckMode>,
+    preview: Option<Preview>,
+    deny_warnings: bool,
+    emit_package_json: bool,
+    /// Overwrite output files even if they weren't generated by xsrc
+    force: bool,
+    /// Generate the APISet classes into files of their own, in a directory
+    /// named after the output file
+    split: bool,
+    /// Where the transformed schema is written as JSON, `-` being stdout
+    emit_ir: Option<PathBuf>,
+    /// Whether the code isn't generated, as only the JSON of the transformed
+    /// schema is asked for
+    ir_only: bool,
+    error_format: ErrorFormat,
+    /// Whether the human format is colored
+    color: bool,
+}
+
+/// A schema to generate, and how. The options come from the command line, the
+/// target in the config and the defaults in the config, in that order.
+struct Job {
+    source: Result<Source, (String, GenError)>,
+    output: Output,
+    /// The languages to generate the schema in, each from the same transform
+    langs: Vec<String>,
+    opts: GenOptions,
+}
+
+/// Expands an input that is a glob pattern rather than a file, which shells on
+/// some platforms leave as is. The matches are sorted.
+fn expand(input: &str) -> Result<Vec<PathBuf>, GenError> {
+    let path = PathBuf::from(input);
+    if path.exists() || !input.contains(['*', '?', '[']) {
+        return Ok(vec![path]);
+    }
+    let mut paths = glob::glob(input)?
+        .filter_map(|v| v.ok())
+        .collect::<Vec<PathBuf>>();
+    if paths.is_empty() {
+        return Err(NoMatch(input.to_string()));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Formats a schema file. With `write` the file is rewritten if it isn't
+/// formatted; with `check` the diff is printed instead. Returns whether the
+/// file was formatted already.
+fn fmt_file(path: &Path, write: bool, check: bool) -> Result<bool, GenError> {
+    let formatted = xsrc::schema::to_string(&xsrc::schema::parse_file(path)?)?;
+    if !write && !check {
+        print!("{}", formatted);
+        return Ok(true);
+    }
+    match diff(&formatted, path, "formatted")? {
+        None => Ok(true),
+        Some(diff) if check => {
+            print!("{}", diff);
+            Ok(false)
+        }
+        Some(_) => {
+            write_output(&formatted, path)?;
+            println!("Schema formatted: {}", path.display());
+            Ok(false)
+        }
+    }
+}
+
+/// The `fmt` subcommand. Returns the exit code: 1 if `--check` finds files
+/// that aren't formatted, or that of the worst error.
+fn fmt(matches: &ArgMatches) -> i32 {
+    let write = matches.is_present("write");
+    let check = matches.is_present("check");
+    let mut exit_code = 0;
+    for input in matches.values_of("schema").unwrap() {
+        let paths = match expand(input) {
+            Ok(paths) => paths,
+            Err(e) => {
+                eprintln!("Error: {}: {}", input, e);
+                exit_code = exit_code.max(e.exit_code());
+                continue;
+            }
+        };
+        for path in paths {
+            match fmt_file(&path, write, check) {
+                Ok(true) => {}
+                Ok(false) if check => exit_code = exit_code.max(1),
+                Ok(false) => {}
+                Err(e) => {
+                    eprintln!("Error: {}: {}", path.display(), e);
+                    exit_code = exit_code.max(e.exit_code());
+                }
+            }
+        }
+    }
+    exit_code
+}
+
+/// Prints the lint rules as aligned columns of the name, the severity and the
+/// description
+fn print_rules() {
+    let name_width = xsrc::lint::RULES
+        .iter()
+        .map(|v| v.name.len())
+        .max()
+        .unwrap_or(0);
+    for rule in xsrc::lint::RULES {
+        println!(
+            "{:name_width$}  {:7}  {}",
+            rule.name,
+            rule.severity.to_string(),
+            rule.description,
+            name_width = name_width
+        );
+    }
+}
+
+fn lint_file(path: &Path, allow: &[String]) -> Result<Vec<xsrc::lint::Finding>, GenError> {
+    let root = xsrc::transformer::transform(xsrc::schema::parse_file(path)?)?;
+    let findings =
+        xsrc::lint::lint(&root, allow).map_err(xsrc::transformer::TransformerError::from)?;
+    Ok(findings)
+}
+
+/// The `lint` subcommand, with the rules allowed in the config as well as on
+/// the command line. Returns the exit code: 1 if there are findings of the
+/// error severity, or that of the worst error.
+fn lint(matches: &ArgMatches, config_allow: &[String], format: ErrorFormat, color: bool) -> i32 {
+    if matches.is_present("list-rules") {
+        print_rules();
+        return 0;
+    }
+    let mut allow = config_allow.to_vec();
+    allow.extend(
+        matches
+            .values_of("allow")
+            .into_iter()
+            .flatten()
+            .map(String::from),
+    );
+    if let Some(name) = allow.iter().find(|v| xsrc::lint::rule(v).is_none()) {
+        eprintln!(
+            "Error: Unknown lint rule: {} (see `xsrc lint --list-rules`)",
+            name
+        );
+        return 2;
+    }
+    let mut exit_code = 0;
+    for input in matches.values_of("schema").unwrap() {
+        let paths = match expand(input) {
+            Ok(paths) => paths,
+            Err(e) => {
+                Diagnostic::error(input, &e).emit(format, color, "");
+                exit_code = exit_code.max(e.exit_code());
+                continue;
+            }
+        };
+        for path in paths {
+            let file = path.display().to_string();
+            match lint_file(&path, &allow) {
+                Ok(findings) => {
+                    for finding in &findings {
+                        Diagnostic::finding(&file, finding).emit(format, color, "");
+                        if finding.severity == xsrc::lint::Severity::Error {
+                            exit_code = exit_code.max(1);
+                        }
+                    }
+                }
+                Err(e) => {
+                    Diagnostic::error(&file, &e).emit(format, color, "");
+                    exit_code = exit_code.max(e.exit_code());
+                }
+            }
+        }
+    }
+    exit_code
+}
+
+/// The `diff` subcommand, printing the breaking changes before the others.
+/// Returns the exit code: 1 if there are breaking changes that aren't allowed,
+/// or that of the error.
+fn diff_schemas(matches: &ArgMatches, format: ErrorFormat, color: bool) -> i32 {
+    let load = |file: &str| -> Result<xsrc::transformer::ContextBoundedRoot, GenError> {
+        Ok(xsrc::transformer::transform(xsrc::schema::parse_file(
+            file,
+        )?)?)
+    };
+    let mut roots = Vec::new();
+    for file in &[
+        matches.value_of("old").unwrap(),
+        matches.value_of("new").unwrap(),
+    ] {
+        match load(file) {
+            Ok(root) => roots.push(root),
+            Err(e) => {
+                Diagnostic::error(file, &e).emit(format, color, "");
+                return e.exit_code();
+            }
+        }
+    }
+    let findings = match xsrc::diff::diff(&roots[0], &roots[1]) {
+        Ok(findings) => findings,
+        Err(e) => {
+            let e = GenError::from(xsrc::transformer::TransformerError::from(e));
+            Diagnostic::error(matches.value_of("new").unwrap(), &e).emit(format, color, "");
+            return e.exit_code();
+        }
+    };
+    let (breaking, others): (Vec<_>, Vec<_>) =
+        findings.iter().partition(|v| v.change.is_breaking());
+    for (title, findings) in &[("Breaking changes", &breaking), ("Other changes", &others)] {
+        if !findings.is_empty() {
+            println!("{}:", title);
+            for finding in findings.iter() {
+                println!("  {}", finding);
+            }
+        }
+    }
+    if !breaking.is_empty() && !matches.is_present("allow-breaking") {
+        1
+    } else {
+        0
+    }
+}
+
+/// The `explain` subcommand. Returns the exit code: 2 if there's no API at
+/// the path, or that of the error.
+fn explain(matches: &ArgMatches, format: ErrorFormat, color: bool) -> i32 {
+    let file = matches.value_of("schema").unwrap();
+    let path = matches
+        .value_of("path")
+        .unwrap()
+        .split('.')
+        .map(String::from)
+        .collect::<Vec<String>>();
+    let root = match xsrc::schema::parse_file(file)
+        .map_err(GenError::from)
+        .and_then(|schema| Ok(xsrc::transformer::transform(schema)?))
+    {
+        Ok(root) => root,
+        Err(e) => {
+            Diagnostic::error(file, &e).emit(format, color, "");
+            return e.exit_code();
+        }
+    };
+    match xsrc::explain::explain(&root, &path) {
+        Ok(explanation) => {
+            println!("{}", explanation);
+            0
+        }
+        Err(xsrc::explain::ExplainError::ContextLookupError(e)) => {
+            let e = GenError::from(xsrc::transformer::TransformerError::from(e));
+            Diagnostic::error(file, &e).emit(format, color, "");
+            e.exit_code()
+        }
+        Err(e) => {
+            eprintln!("Error: {}: {}", file, e);
+            2
+        }
+    }
+}
+
+/// The schemas of the `---`-separated documents of a file, or the one of a
+/// schema dir
+fn parse(source: &Source) -> Result<Vec<xsrc::schema::RootSchema>, GenError> {
+    match source {
+        Source::File(p) => Ok(xsrc::schema::parse_multi_file(p)?),
+        Source::Dir(p) => Ok(vec![SchemaSource::Path(p).parse()?]),
+    }
+}
+
+/// Transforms the schema and resolves every URL, printing the warnings about
+/// the schema.
+fn transform(
+    source: &Source,
+    root_schema: xsrc::schema::RootSchema,
+    settings: &Settings,
+) -> Result<xsrc::transformer::ContextBoundedRoot, GenError> {
+    let mut root = xsrc::transformer::transform_all(root_schema)
+        .map_err(|errors| GenError::all(errors.into_iter().map(GenError::from)))?;
+    if !settings.only.is_empty() {
+        root.prune(&settings.only)
+            .map_err(xsrc::transformer::TransformerError::from)?;
+    }
+    let warnings = root.check_all().map_err(|errors| {
+        let errors = errors
+            .into_iter()
+            .map(xsrc::transformer::TransformerError::from);
+        GenError::all(errors.map(GenError::from))
+    })?;
+    let file = source.to_string();
+    for warning in &warnings {
+        Diagnostic::warning(&file, warning).emit(settings.error_format, settings.color, "");
+    }
+    if settings.deny_warnings && !warnings.is_empty() {
+        return Err(DeniedWarnings(warnings.len()));
+    }
+    Ok(root)
+}
+
+/// The path of the main generated file, which takes the name of the client
+/// class unless given one
+fn output_file(source: &Source, klsname: &str, output: &Output, backend: &dyn Backend) -> PathBuf {
+    let file_name = || output_file_name(backend, klsname, source.stem());
+    match output {
+        Output::File(f) => f.to_path_buf(),
+        Output::Dir(d) => d.join(file_name()),
+        Output::Default => PathBuf::from(file_name()),
+    }
+}
+
+/// Whether a file can be overwritten without `--force`: it doesn't exist, or
+/// the banner of xsrc is in its leading comments
+fn is_generated(path: &Path) -> Result<bool, GenError> {
+    let existing = match std::fs::read(path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e.into()),
+    };
+    let existing = String::from_utf8_lossy(&existing);
+    Ok(xsrc::rewriter::has_banner(&existing))
+}
+
+/// Whether a file already has the content, so that writing it can be skipped
+fn is_unchanged(path: &Path, code: &str) -> Result<bool, GenError> {
+    match std::fs::read(path) {
+        Ok(existing) => Ok(existing == code.as_bytes()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// What came of writing a file, with its canonical path
+#[derive(Debug, PartialEq)]
+enum WriteOutcome {
+    Written(PathBuf),
+    /// The file already had the content and was left alone, so that its
+    /// mtime doesn't change and watchers aren't triggered for nothing
+    Unchanged(PathBuf),
+}
+
+impl WriteOutcome {
+    fn path(&self) -> &Path {
+        match self {
+            WriteOutcome::Written(p) | WriteOutcome::Unchanged(p) => p,
+        }
+    }
+}
+
+fn write_output<Q: AsRef<Path> + Clone>(
+    code: &str,
     output_file: Q,
-) -> Result<PathBuf, GenError> {
+) -> Result<WriteOutcome, GenError> {
+    if is_unchanged(output_file.as_ref(), code)? {
+        let p = output_file.as_ref().canonicalize()?;
+        info!("File up to date: {}", p.display());
+        return Ok(WriteOutcome::Unchanged(p));
+    }
+    if let Some(parent) = output_file.as_ref().parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut f = File::create(output_file.clone())?;
+    f.write_all(code.as_bytes())?;
+    let p = output_file.as_ref().canonicalize()?;
+    info!("File written: {} ({} bytes)", p.display(), code.len());
+    Ok(WriteOutcome::Written(p))
+}
+
+/// The npm packages that the generated code imports, for the languages that
+/// generate JavaScript
+fn npm_dependencies(lang: &str) -> Option<Vec<(&'static str, &'static str)>> {
     match lang {
-        "javascript" => {
-            let root_schema = xsrc::schema::parse_file(schema_file)?;
-            let root = xsrc::transformer::transform(root_schema)?;
-            let gen_ctx = Default::default();
-            let code = xsrc::rewriter::javascript::gen(&root, &gen_ctx);
-            let mut f = File::create(output_file.clone())?;
-            f.write_all(&code.as_bytes())?;
-            let p = output_file.as_ref().canonicalize()?;
-            Ok(p)
+        "javascript" | "node-cli" => Some(vec![("axios", "^1.6.0")]),
+        _ => None,
+    }
+}
+
+/// Writes a minimal `package.json` next to the generated code, with it as the
+/// main entry
+fn write_package_json(
+    klsname: &str,
+    output_file: &Path,
+    dependencies: &[(&str, &str)],
+) -> Result<WriteOutcome, GenError> {
+    let main = output_file
+        .file_name()
+        .and_then(|v| v.to_str())
+        .unwrap_or_default();
+    let dependencies = dependencies
+        .iter()
+        .map(|(k, v)| (k.to_string(), serde_json::Value::from(*v)))
+        .collect::<serde_json::Map<String, serde_json::Value>>();
+    let package = serde_json::json!({
+        "name": codegen::utils::to_snake_case(klsname).replace('_', "-"),
+        "version": "0.1.0",
+        "private": true,
+        "type": "module",
+        "main": main,
+        "dependencies": dependencies,
+    });
+    let package_file = output_file.with_file_name("package.json");
+    write_output(
+        &format!("{}\n", serde_json::to_string_pretty(&package).unwrap()),
+        &package_file,
+    )
+}
+
+/// A unified diff from the output file to the generated code, or `None` if
+/// they are the same. A missing output file is taken as empty. `label` tells
+/// the new side of the diff from the file.
+fn diff(code: &str, output_file: &Path, label: &str) -> Result<Option<String>, GenError> {
+    let (old, old_header) = match std::fs::read_to_string(output_file) {
+        Ok(old) => (old, output_file.display().to_string()),
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+            (String::new(), "/dev/null".to_string())
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if old == code {
+        return Ok(None);
+    }
+    let diff = similar::TextDiff::from_lines(old.as_str(), code)
+        .unified_diff()
+        .header(
+            &old_header,
+            &format!("{} ({})", output_file.display(), label),
+        )
+        .to_string();
+    Ok(Some(diff))
+}
+
+/// The files that `xsrc` generated into an output directory, by schema, so
+/// that the files a schema no longer generates can be removed
+const MANIFEST_FILE: &str = ".xsrc-manifest.json";
+
+type Manifest = BTreeMap<String, Vec<PathBuf>>;
+
+fn read_manifest(dir: &Path) -> Result<Manifest, GenError> {
+    match std::fs::read_to_string(dir.join(MANIFEST_FILE)) {
+        // A manifest that can't be read is no worse than none: nothing is
+        // removed
+        Ok(s) => Ok(serde_json::from_str(&s).unwrap_or_default()),
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Manifest::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Records the files generated from `source` into `dir`, relative to it, and
+/// removes those it generated the last time but not this time, along with the
+/// directories that this empties. Returns the removed files.
+fn update_manifest(
+    dir: &Path,
+    source: &Source,
+    files: Vec<PathBuf>,
+) -> Result<Vec<PathBuf>, GenError> {
+    let mut manifest = read_manifest(dir)?;
+    let key = source
+        .path()
+        .canonicalize()
+        .unwrap_or_else(|_| source.path().to_path_buf())
+        .display()
+        .to_string();
+    let previous = manifest.insert(key, files).unwrap_or_default();
+    let mut removed = Vec::new();
+    for file in previous {
+        if manifest.values().any(|v| v.contains(&file)) {
+            continue;
+        }
+        let path = dir.join(&file);
+        match std::fs::remove_file(&path) {
+            Ok(()) => removed.push(path.clone()),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        for parent in file.ancestors().skip(1) {
+            if parent.as_os_str().is_empty() || std::fs::remove_dir(dir.join(parent)).is_err() {
+                break;
+            }
+        }
+    }
+    write_output(
+        &format!("{}\n", serde_json::to_string_pretty(&manifest).unwrap()),
+        dir.join(MANIFEST_FILE),
+    )?;
+    Ok(removed)
+}
+
+/// What came of a schema that didn't fail
+enum Outcome {
+    Checked,
+    Written {
+        written: Vec<WriteOutcome>,
+        removed: Vec<PathBuf>,
+    },
+    Printed(String),
+    UpToDate(PathBuf),
+    /// The diff from the output file to the generated code
+    OutOfDate(String),
+}
+
+/// Generates the code of a schema in a language, and writes it unless it is
+/// only checked or previewed. The files written into the output directory are
+/// added to `generated`, relative to it.
+fn render(
+    settings: &Settings,
+    job: &Job,
+    source: &Source,
+    root: &xsrc::transformer::ContextBoundedRoot,
+    lang: &str,
+    generated: &mut Vec<PathBuf>,
+) -> Result<Outcome, GenError> {
+    let backend = backend(settings.registry, lang)?;
+    let output_file = output_file(source, root.klsname(), &job.output, backend);
+    let mut opts = job.opts.clone();
+    if settings.split {
+        opts.module_dir = output_file
+            .file_stem()
+            .and_then(|v| v.to_str())
+            .map(|v| v.to_string());
+    }
+    match settings.check {
+        Some(CheckMode::Transform) => return Ok(Outcome::Checked),
+        Some(CheckMode::Full) => {
+            generate(backend, root, &opts)?;
+            return Ok(Outcome::Checked);
+        }
+        None => {}
+    }
+    let preview = match &settings.preview {
+        Some(v) => v,
+        None => return write_generated(settings, job, root, lang, &opts, &output_file, generated),
+    };
+    let output = generate(backend, root, &opts)?;
+    let base_dir = output_file.parent().unwrap_or_else(|| Path::new(""));
+    let mut files = vec![(output_file.clone(), output.code)];
+    files.extend(
+        output
+            .files
+            .into_iter()
+            .map(|f| (base_dir.join(f.path), f.code)),
+    );
+    match preview {
+        Preview::DryRun if files.len() == 1 => Ok(Outcome::Printed(files.remove(0).1)),
+        Preview::DryRun => Ok(Outcome::Printed(
+            files
+                .iter()
+                .map(|(path, code)| format!("==> {} <==\n{}", path.display(), code))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        )),
+        Preview::Diff => {
+            let mut diffs = Vec::new();
+            for (path, code) in &files {
+                diffs.extend(diff(code, path, "generated")?);
+            }
+            if diffs.is_empty() {
+                Ok(Outcome::UpToDate(output_file))
+            } else {
+                Ok(Outcome::OutOfDate(diffs.concat()))
+            }
+        }
+    }
+}
+
+/// The file beside `output_file` that its code is streamed into, e.g.
+/// `.RatinaClient.js.xsrc` for `RatinaClient.js`
+fn staged_file(output_file: &Path) -> PathBuf {
+    let name = output_file
+        .file_name()
+        .and_then(|v| v.to_str())
+        .unwrap_or("");
+    output_file.with_file_name(format!(".{}.xsrc", name))
+}
+
+/// Streams the code of the main file into `staged`, and returns the other
+/// files
+fn stage(
+    backend: &dyn Backend,
+    root: &xsrc::transformer::ContextBoundedRoot,
+    opts: &GenOptions,
+    staged: &Path,
+) -> Result<Vec<xsrc::rewriter::GeneratedFile>, GenError> {
+    let mut w = BufWriter::new(File::create(staged)?);
+    let files = generate_to(backend, root, opts, &mut w)?;
+    w.flush()?;
+    Ok(files)
+}
+
+/// Whether the file at `b` has the content of that at `a`, compared a chunk
+/// at a time
+fn same_content(a: &Path, b: &Path) -> Result<bool, GenError> {
+    let b = match File::open(b) {
+        Ok(v) => v,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    let a = File::open(a)?;
+    let mut len = a.metadata()?.len();
+    if len != b.metadata()?.len() {
+        return Ok(false);
+    }
+    let (mut a, mut b) = (BufReader::new(a), BufReader::new(b));
+    let (mut chunk_a, mut chunk_b) = (vec![0; 1 << 16], vec![0; 1 << 16]);
+    while len > 0 {
+        let n = len.min(chunk_a.len() as u64) as usize;
+        a.read_exact(&mut chunk_a[..n])?;
+        b.read_exact(&mut chunk_b[..n])?;
+        if chunk_a[..n] != chunk_b[..n] {
+            return Ok(false);
+        }
+        len -= n as u64;
+    }
+    Ok(true)
+}
+
+/// Moves the staged code into the output file, unless the file already has
+/// it
+fn install(staged: &Path, output_file: &Path, unchanged: bool) -> Result<WriteOutcome, GenError> {
+    if unchanged {
+        std::fs::remove_file(staged)?;
+        let p = output_file.canonicalize()?;
+        info!("File up to date: {}", p.display());
+        return Ok(WriteOutcome::Unchanged(p));
+    }
+    let len = std::fs::metadata(staged)?.len();
+    std::fs::rename(staged, output_file)?;
+    let p = output_file.canonicalize()?;
+    info!("File written: {} ({} bytes)", p.display(), len);
+    Ok(WriteOutcome::Written(p))
+}
+
+/// Generates the code of a schema and writes it. The code of the main file
+/// is streamed into a file beside it rather than held whole, and replaces it
+/// once every file is known to be writable, so a failure leaves the old
+/// files in place.
+fn write_generated(
+    settings: &Settings,
+    job: &Job,
+    root: &xsrc::transformer::ContextBoundedRoot,
+    lang: &str,
+    opts: &GenOptions,
+    output_file: &Path,
+    generated: &mut Vec<PathBuf>,
+) -> Result<Outcome, GenError> {
+    let backend = backend(settings.registry, lang)?;
+    if let Some(parent) = output_file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let staged = staged_file(output_file);
+    let result = stage(backend, root, opts, &staged).and_then(|files| {
+        let unchanged = same_content(&staged, output_file)?;
+        let base_dir = output_file.parent().unwrap_or_else(|| Path::new(""));
+        let files: Vec<(PathBuf, String)> = files
+            .into_iter()
+            .map(|f| (base_dir.join(f.path), f.code))
+            .collect();
+        // Files that already have the code are left alone, so aren't
+        // overwritten even if they weren't generated by xsrc
+        if !settings.force {
+            if !unchanged && !is_generated(output_file)? {
+                return Err(NotGenerated(output_file.to_path_buf()));
+            }
+            for (path, code) in &files {
+                if !is_unchanged(path, code)? && !is_generated(path)? {
+                    return Err(NotGenerated(path.to_path_buf()));
+                }
+            }
+        }
+        let mut written = vec![install(&staged, output_file, unchanged)?];
+        for (path, code) in &files {
+            written.push(write_output(code, path)?);
+        }
+        Ok((written, files))
+    });
+    let (mut written, files) = match result {
+        Ok(v) => v,
+        Err(e) => {
+            // The staged file may not have been created
+            let _ = std::fs::remove_file(&staged);
+            return Err(e);
+        }
+    };
+    if let Output::Dir(d) = &job.output {
+        generated.extend(
+            std::iter::once(output_file)
+                .chain(files.iter().map(|(path, _)| path.as_path()))
+                .filter_map(|path| path.strip_prefix(d).ok().map(PathBuf::from)),
+        );
+    }
+    if settings.emit_package_json {
+        let dependencies = npm_dependencies(lang).unwrap_or_default();
+        let output_file = written[0].path().to_path_buf();
+        written.push(write_package_json(
+            root.klsname(),
+            &output_file,
+            &dependencies,
+        )?);
+    }
+    Ok(Outcome::Written {
+        written,
+        removed: Vec::new(),
+    })
+}
+
+/// Parses and transforms the schemas of a source, writing the IR if asked
+/// to. Returns none if there's nothing else to do.
+fn prepare(
+    settings: &Settings,
+    source: &Source,
+    outcomes: &mut Vec<(Option<&str>, Result<Outcome, GenError>)>,
+) -> Result<Vec<xsrc::transformer::ContextBoundedRoot>, GenError> {
+    let schemas = parse(source)?;
+    if let Some(ir_file) = &settings.emit_ir {
+        if schemas.len() > 1 && ir_file != Path::new("-") {
+            return Err(SharedOutput(ir_file.to_path_buf()));
+        }
+    }
+    let mut roots = Vec::new();
+    for schema in schemas {
+        roots.push(transform(source, schema, settings)?);
+    }
+    if let Some(ir_file) = &settings.emit_ir {
+        for root in &roots {
+            let ir =
+                xsrc::ir::to_string(root).map_err(xsrc::transformer::TransformerError::from)?;
+            let outcome = if ir_file == Path::new("-") {
+                Outcome::Printed(ir)
+            } else {
+                Outcome::Written {
+                    written: vec![write_output(&ir, ir_file)?],
+                    removed: Vec::new(),
+                }
+            };
+            outcomes.push((None, Ok(outcome)));
         }
-        _ => Err(GenError::UnsupportedLanguage(lang.to_string())),
+        if settings.ir_only {
+            return Ok(Vec::new());
+        }
+    }
+    Ok(roots)
+}
+
+/// Fails if two of the schemas of a source would be written to the same
+/// output file, or one of them to a file of an earlier source in `claimed`.
+/// Otherwise the files of the source are claimed.
+fn claim_outputs(
+    settings: &Settings,
+    job: &Job,
+    source: &Source,
+    roots: &[xsrc::transformer::ContextBoundedRoot],
+    claimed: &mut HashMap<PathBuf, String>,
+) -> Result<(), GenError> {
+    let mut files = HashSet::new();
+    for lang in &job.langs {
+        // An unknown language fails when rendered
+        if let Ok(backend) = backend(settings.registry, lang) {
+            for root in roots {
+                let file = output_file(source, root.klsname(), &job.output, backend);
+                if let Some(other) = claimed.get(&file) {
+                    return Err(TakenOutput(file, other.to_string()));
+                }
+                if !files.insert(file.clone()) {
+                    return Err(SharedOutput(file));
+                }
+            }
+        }
+    }
+    claimed.extend(files.into_iter().map(|file| (file, source.to_string())));
+    Ok(())
+}
+
+/// Generates the code of each schema of a source in each language of the
+/// job, from a single transform. A failure of a schema fails every schema of
+/// the source, while that of a language fails only the language. The outcomes
+/// of the languages are labelled with them. The output files already
+/// generated from other sources are in `claimed`.
+fn process<'a>(
+    settings: &Settings,
+    job: &'a Job,
+    source: &Source,
+    claimed: &mut HashMap<PathBuf, String>,
+) -> Vec<(Option<&'a str>, Result<Outcome, GenError>)> {
+    let mut outcomes = Vec::new();
+    let roots = match prepare(settings, source, &mut outcomes) {
+        Ok(roots) if roots.is_empty() => return outcomes,
+        Ok(roots) => roots,
+        Err(e) => {
+            outcomes.push((None, Err(e)));
+            return outcomes;
+        }
+    };
+    // Checks write nothing
+    if settings.check.is_none() {
+        if let Err(e) = claim_outputs(settings, job, source, &roots, claimed) {
+            outcomes.push((None, Err(e)));
+            return outcomes;
+        }
+    }
+    let mut generated = Vec::new();
+    let mut failed = false;
+    for root in &roots {
+        for lang in &job.langs {
+            let result = render(settings, job, source, root, lang, &mut generated);
+            failed |= result.is_err();
+            outcomes.push((Some(lang.as_str()), result));
+        }
+    }
+    // The files of a language that failed this time are kept
+    if let (Output::Dir(d), None, None, false) =
+        (&job.output, &settings.check, &settings.preview, failed)
+    {
+        let outcome = update_manifest(d, source, generated).map(|removed| Outcome::Written {
+            written: Vec::new(),
+            removed,
+        });
+        outcomes.push((None, outcome));
+    }
+    outcomes
+}
+
+/// Processes the schemas independently, so that one failure doesn't stop the
+/// others, and returns the exit code of the worst failure, or 0. An output
+/// file that differs from the generated code fails with 1. Each line of the
+/// summary is prefixed with the time if `timestamp` is set.
+fn process_all(settings: &Settings, jobs: &[Job], timestamp: bool) -> i32 {
+    let prefix = if timestamp {
+        format!("[{}] ", chrono::Local::now().format("%H:%M:%S"))
+    } else {
+        String::new()
+    };
+    // A schema in a language, or a schema that failed
+    let mut units = 0;
+    let mut failed = 0;
+    let mut exit_code = 0;
+    // The output files of the sources processed so far, with their source
+    let mut claimed = HashMap::new();
+    for job in jobs {
+        let (input, outcomes) = match &job.source {
+            Ok(source) => (
+                source.to_string(),
+                process(settings, job, source, &mut claimed),
+            ),
+            Err((input, e)) => {
+                units += 1;
+                failed += 1;
+                exit_code = exit_code.max(e.exit_code());
+                Diagnostic::error(input, e).emit(settings.error_format, settings.color, &prefix);
+                continue;
+            }
+        };
+        for (lang, result) in outcomes {
+            if lang.is_some() || result.is_err() {
+                units += 1;
+            }
+            match result {
+                Ok(Outcome::Checked) => println!("{}Schema checked: {}", prefix, input),
+                Ok(Outcome::Written { written, removed }) => {
+                    for outcome in written {
+                        match outcome {
+                            WriteOutcome::Written(path) => {
+                                let path_str = path.to_str().unwrap();
+                                println!("{}Code file generated at {}", prefix, path_str);
+                            }
+                            WriteOutcome::Unchanged(path) => {
+                                println!("{}Up to date: {}", prefix, path.display())
+                            }
+                        }
+                    }
+                    for path in removed {
+                        println!("{}Stale file removed: {}", prefix, path.display());
+                    }
+                }
+                Ok(Outcome::Printed(code)) => {
+                    print!("{}", code);
+                    if !code.ends_with('\n') {
+                        println!();
+                    }
+                }
+                Ok(Outcome::UpToDate(path)) => {
+                    println!("{}Up to date: {}", prefix, path.display())
+                }
+                Ok(Outcome::OutOfDate(diff)) => {
+                    failed += 1;
+                    exit_code = exit_code.max(1);
+                    print!("{}", diff);
+                }
+                Err(e) => {
+                    failed += 1;
+                    exit_code = exit_code.max(e.exit_code());
+                    let file = match lang {
+                        Some(lang) if job.langs.len() > 1 => format!("{} ({})", input, lang),
+                        _ => input.clone(),
+                    };
+                    for e in e.errors() {
+                        Diagnostic::error(&file, e).emit(
+                            settings.error_format,
+                            settings.color,
+                            &prefix,
+                        );
+                    }
+                }
+            }
+        }
+    }
+    if units > 1 {
+        println!("{}{} succeeded, {} failed", prefix, units - failed, failed);
     }
+    exit_code
+}
+
+/// Processes the schemas, then again whenever they change, until interrupted.
+/// Schema files are watched through their directories, so that editors that
+/// save by replacing the file are noticed as well.
+fn watch(settings: &Settings, jobs: &[Job]) -> notify::Result<()> {
+    use notify::{DebouncedEvent, RecursiveMode, Watcher};
+
+    let (tx, rx) = channel();
+    let mut watcher = notify::watcher(tx, Duration::from_millis(200))?;
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    for job in jobs {
+        match &job.source {
+            Ok(Source::File(f)) => {
+                let f = f.canonicalize()?;
+                if let Some(parent) = f.parent() {
+                    watcher.watch(parent, RecursiveMode::NonRecursive)?;
+                }
+                files.push(f);
+            }
+            Ok(Source::Dir(d)) => {
+                let d = d.canonicalize()?;
+                watcher.watch(&d, RecursiveMode::Recursive)?;
+                dirs.push(d);
+            }
+            Err(_) => {}
+        }
+    }
+    let is_relevant =
+        |p: &Path| files.iter().any(|f| f == p) || dirs.iter().any(|d| p.starts_with(d));
+    process_all(settings, jobs, true);
+    println!("Watching for changes, press Ctrl-C to stop");
+    loop {
+        let changed = match rx.recv() {
+            Ok(DebouncedEvent::Create(p))
+            | Ok(DebouncedEvent::Write(p))
+            | Ok(DebouncedEvent::Remove(p))
+            | Ok(DebouncedEvent::Rename(_, p)) => is_relevant(&p),
+            Ok(DebouncedEvent::Error(e, _)) => return Err(e),
+            Ok(_) => false,
+            Err(_) => return Ok(()),
+        };
+        if changed {
+            // Events of the same save can arrive apart
+            while rx.try_recv().is_ok() {}
+            process_all(settings, jobs, true);
+        }
+    }
+}
+
+/// Completes the app loaded from `cli.yaml` with the args that can't be
+/// declared there
+fn cli<'a>(app: App<'a, 'a>, registry: &'a Registry) -> App<'a, 'a> {
+    let mut langs = registry.names();
+    let missing = xsrc::Lang::ALL
+        .iter()
+        .map(|v| v.name())
+        .filter(|v| !langs.contains(v))
+        .collect::<Vec<&str>>();
+    langs.extend(missing);
+    // `require_equals` can't be set from YAML, and without it `--check` would
+    // take the schema as its value
+    app.arg(
+        Arg::with_name("check")
+            .long("check")
+            .value_name("stage")
+            .help("Validate the schema without writing any output, stopping after the transform (default) or after code generation (full)")
+            .takes_value(true)
+            .min_values(0)
+            .require_equals(true)
+            .possible_values(&["transform", "full"])
+            .conflicts_with_all(&["dry-run", "diff"]),
+    )
+    // Like `--check`, so that `--banner` doesn't take the schema as its text
+    .arg(
+        Arg::with_name("banner")
+            .long("banner")
+            .value_name("text")
+            .help("Put a comment above the banner of each generated file, by default \"AUTO-GENERATED by xsrc. Do not edit.\"")
+            .takes_value(true)
+            .min_values(0)
+            .require_equals(true),
+    )
+    // The languages are those of the registry, which the completion scripts
+    // offer as well. Built-in ones that aren't compiled in are taken too, to
+    // fail with why.
+    .arg(
+        Arg::with_name("lang")
+            .short("x")
+            .long("lang")
+            .value_name("lang")
+            .help("The languages of the rendered code, comma-separated or repeated, see `xsrc langs`. Each is generated from the same transform")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .use_delimiter(true)
+            .possible_values(&langs)
+            .hide_possible_values(true),
+    )
 }
 
 fn main() {
-    let lang_infos = init_lang_infos();
+    let registry = Registry::default();
     let yaml = load_yaml!("cli.yaml");
-    let matches = App::from_yaml(yaml).get_matches();
-    let schema_file = matches.value_of("schema").unwrap();
-    let lang = matches.value_of("lang").unwrap_or("javascript");
-    let output_file = match matches.value_of("output") {
-        Some(f) => f.to_string(),
-        None => {
-            let ext = match lang_infos.get(lang) {
-                Some(li) => li.ext,
-                _ => ".out",
-            };
-            format!("{}{}", "output", ext)
+    let matches = cli(App::from_yaml(yaml).version(xsrc::version()), &registry)
+        .get_matches_safe()
+        .unwrap_or_else(|e| match e.kind {
+            ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => e.exit(),
+            _ => {
+                eprintln!("{}", e.message);
+                std::process::exit(2);
+            }
+        });
+    // Only the logs of xsrc, unless RUST_LOG says otherwise
+    let level = match matches.occurrences_of("verbose") {
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or(format!("xsrc={}", level)),
+    )
+    .init();
+    if let Some(completions) = matches.subcommand_matches("completions") {
+        let shell = completions
+            .value_of("shell")
+            .unwrap()
+            .parse::<Shell>()
+            .unwrap();
+        cli(App::from_yaml(yaml), &registry).gen_completions_to(
+            "xsrc",
+            shell,
+            &mut std::io::stdout(),
+        );
+        return;
+    }
+    let error_format = match matches.value_of("error-format") {
+        Some("json") => ErrorFormat::Json,
+        _ => ErrorFormat::Human,
+    };
+    let color = match matches.value_of("color") {
+        Some("always") => true,
+        Some("never") => false,
+        _ => atty::is(atty::Stream::Stderr),
+    };
+    if let Some(fmt_matches) = matches.subcommand_matches("fmt") {
+        std::process::exit(fmt(fmt_matches));
+    }
+    if let Some(diff_matches) = matches.subcommand_matches("diff") {
+        std::process::exit(diff_schemas(diff_matches, error_format, color));
+    }
+    if let Some(explain_matches) = matches.subcommand_matches("explain") {
+        std::process::exit(explain(explain_matches, error_format, color));
+    }
+    if let Some(langs) = matches.subcommand_matches("langs") {
+        print_langs(&registry, langs.is_present("json"));
+        return;
+    }
+    if matches.is_present("list-langs") {
+        print_langs(&registry, matches.is_present("json"));
+        return;
+    }
+    let config = match matches.value_of("config").map(PathBuf::from).or_else(|| {
+        std::env::current_dir()
+            .ok()
+            .and_then(xsrc::config::discover)
+    }) {
+        Some(f) => match xsrc::config::parse_file(&f) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Error: {}: {}", f.display(), e);
+                std::process::exit(2);
+            }
+        },
+        None => Default::default(),
+    };
+    if let Some(lint_matches) = matches.subcommand_matches("lint") {
+        std::process::exit(lint(lint_matches, &config.lint.allow, error_format, color));
+    }
+    // The schemas on the command line take the defaults of the config, and the
+    // targets of the config are generated only without any
+    let targets = match (matches.value_of("schema-dir"), matches.values_of("schema")) {
+        (Some(d), _) => vec![(Ok(Source::Dir(PathBuf::from(d))), None, config.defaults)],
+        (None, Some(inputs)) => inputs
+            .flat_map(|input| match expand(input) {
+                Ok(paths) => paths.into_iter().map(|p| Ok(Source::File(p))).collect(),
+                Err(e) => vec![Err((input.to_string(), e))],
+            })
+            .map(|source| (source, None, config.defaults.clone()))
+            .collect(),
+        (None, None) if !config.targets.is_empty() => {
+            let defaults = config.defaults;
+            config
+                .targets
+                .into_iter()
+                .map(|target| {
+                    let options = target.options.or(&defaults);
+                    (Ok(Source::File(target.schema)), target.output, options)
+                })
+                .collect()
+        }
+        (None, None) => {
+            eprintln!(
+                "Error: No schema given, and no {} with targets found",
+                xsrc::config::FILE_NAME
+            );
+            std::process::exit(2);
         }
     };
-    match gen(lang, &schema_file, &output_file) {
-        Ok(path) => {
-            let path_str = path.to_str().unwrap();
-            println!("Code file generated at {}", path_str);
+    let multiple = targets.len() > 1;
+    if matches.is_present("output") && multiple {
+        eprintln!("Error: --output takes a single schema, use --out-dir for more");
+        std::process::exit(2);
+    }
+    if matches.is_present("output") && matches.occurrences_of("lang") > 0 {
+        let langs = matches.values_of("lang").unwrap().collect::<Vec<&str>>();
+        if langs.iter().any(|lang| lang != &langs[0]) {
+            eprintln!("Error: --output takes a single language, use --out-dir for more");
+            std::process::exit(2);
+        }
+    }
+    // The generation settings of the flags, over those of the config
+    let flag = |name| Some(true).filter(|_| matches.is_present(name));
+    let cli_options = Options {
+        param_case: matches.value_of("param-case").map(|s| s.parse().unwrap()),
+        query_encoding: matches
+            .value_of("query-encoding")
+            .map(|s| s.parse().unwrap()),
+        annotate: flag("annotate"),
+        branded_types: flag("branded-types"),
+        commonjs_interop: flag("commonjs-interop"),
+        no_import: flag("no-import"),
+        minify: flag("minify"),
+        node_keep_alive: flag("node-keep-alive"),
+        cancellation: flag("cancellation"),
+        return_style: matches.value_of("return-style").map(|s| s.parse().unwrap()),
+        style: matches.value_of("style").map(|s| s.parse().unwrap()),
+        banner: if matches.is_present("banner") {
+            let banner = matches.value_of("banner");
+            Some(banner.unwrap_or(xsrc::rewriter::DEFAULT_BANNER).to_string())
+        } else {
+            None
         },
-        Err(e) => eprintln!("Error: {}", e)
+        ..Default::default()
+    };
+    let mut jobs = Vec::new();
+    for (source, target_output, options) in targets {
+        let opts = match cli_options.or(&options).gen_options().build() {
+            // The module directory is only known per output file, --split
+            // is checked against the style here
+            Ok(opts) if matches.is_present("split") && opts.style != ClientStyle::Class => {
+                eprintln!("Error: {}", GenOptionsError::Conflict("split", "style"));
+                std::process::exit(2);
+            }
+            Ok(opts) => opts,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(2);
+            }
+        };
+        let langs = match matches.values_of("lang") {
+            Some(vs) => vs.fold(Vec::new(), |mut langs, v| {
+                if !langs.iter().any(|lang| lang == v) {
+                    langs.push(v.to_string());
+                }
+                langs
+            }),
+            None => vec![options.lang.unwrap_or_else(|| "javascript".to_string())],
+        };
+        let output = match (
+            matches.value_of("output"),
+            matches.value_of("out-dir"),
+            target_output,
+            options.out_dir,
+        ) {
+            (Some(f), _, _, _) => Output::File(PathBuf::from(f)),
+            (_, Some(d), _, _) => Output::Dir(PathBuf::from(d)),
+            (_, _, Some(f), _) => Output::File(f),
+            (_, _, _, Some(d)) => Output::Dir(d),
+            _ => Output::Default,
+        };
+        jobs.push(Job {
+            source,
+            output,
+            langs,
+            opts,
+        });
+    }
+    let emit_package_json = matches.is_present("emit-package-json");
+    if emit_package_json {
+        let langs = jobs
+            .iter()
+            .flat_map(|job| job.langs.iter())
+            .collect::<Vec<&String>>();
+        if langs.iter().any(|lang| npm_dependencies(lang).is_none()) {
+            eprintln!("Error: --emit-package-json only applies to javascript and node-cli");
+            std::process::exit(2);
+        }
+        if langs.len() > 1 {
+            eprintln!("Error: --emit-package-json takes a single schema and language");
+            std::process::exit(2);
+        }
+    }
+    let emit_ir = matches.value_of("emit-ir").map(PathBuf::from);
+    if emit_ir.is_some() && jobs.len() > 1 {
+        eprintln!("Error: --emit-ir takes a single schema");
+        std::process::exit(2);
+    }
+    for job in &jobs {
+        if let Output::Dir(d) = &job.output {
+            if let Err(e) = std::fs::create_dir_all(d) {
+                eprintln!("Error: {}: {}", d.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let only = matches.values_of("only").map_or(Vec::new(), |vs| {
+        vs.map(|v| v.split('.').map(|s| s.to_string()).collect())
+            .collect()
+    });
+    let check = if matches.is_present("check") {
+        match matches.value_of("check") {
+            Some("full") => Some(CheckMode::Full),
+            _ => Some(CheckMode::Transform),
+        }
+    } else {
+        None
+    };
+    let settings = Settings {
+        registry: &registry,
+        only,
+        check,
+        preview: if matches.is_present("dry-run") {
+            Some(Preview::DryRun)
+        } else if matches.is_present("diff") {
+            Some(Preview::Diff)
+        } else {
+            None
+        },
+        deny_warnings: matches.is_present("deny-warnings"),
+        emit_package_json,
+        force: matches.is_present("force"),
+        split: matches.is_present("split"),
+        ir_only: emit_ir.is_some() && !matches.is_present("lang"),
+        emit_ir,
+        error_format,
+        color,
+    };
+    if matches.is_present("watch") {
+        ctrlc::set_handler(|| {
+            println!("Stopped watching");
+            std::process::exit(0);
+        })
+        .expect("Failed to set the Ctrl-C handler");
+        if let Err(e) = watch(&settings, &jobs) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    } else {
+        std::process::exit(process_all(&settings, &jobs, false));
     }
 }
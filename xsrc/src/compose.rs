@@ -0,0 +1,163 @@
+use super::schema::{parse_file, APIData, APIDataMap, APISetSchema, ParserError, RootSchema};
+use super::timing::Instant;
+use linked_hash_map::LinkedHashMap;
+use log::info;
+use std::fs;
+use std::path::Path;
+
+fn is_schema_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("yaml") | Some("yml")
+    )
+}
+
+/// A root schema nested in a composed root becomes an APISet. A root without
+/// `$url` inherits the URL of the composed root.
+fn into_apiset(root: RootSchema) -> APISetSchema {
+    APISetSchema {
+        url: root.url.unwrap_or_else(APISetSchema::default_url),
+        headers: LinkedHashMap::new(),
+        description: root.description,
+        apisets: root.apisets,
+    }
+}
+
+fn parse_namespace(dir: &Path) -> Result<APIDataMap, ParserError> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|v| v.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    // Directory listings come in no particular order
+    paths.sort();
+    let mut map = LinkedHashMap::new();
+    for path in paths {
+        let name = match path.file_stem().and_then(|v| v.to_str()) {
+            Some(name) if !name.starts_with('.') => name.to_string(),
+            _ => continue,
+        };
+        let apiset = if path.is_dir() {
+            APISetSchema {
+                url: APISetSchema::default_url(),
+                headers: LinkedHashMap::new(),
+                description: None,
+                apisets: parse_namespace(&path)?,
+            }
+        } else if is_schema_file(&path) {
+            into_apiset(parse_file(&path)?)
+        } else {
+            continue;
+        };
+        if map.insert(name, APIData::APISet(apiset)).is_some() {
+            return Err(ParserError::DuplicateNamespace(path));
+        }
+    }
+    Ok(APIDataMap(map))
+}
+
+/// Composes the schemas in a directory tree into a single root. Each
+/// subdirectory becomes an APISet and so does each schema file, named after
+/// the file. The URL of the composed root is left to the client.
+pub fn parse_dir<P: AsRef<Path>>(path: P) -> Result<RootSchema, ParserError> {
+    let start = Instant::now();
+    let apisets = parse_namespace(path.as_ref())?;
+    let (n, m) = apisets.count();
+    info!(
+        "Schema dir {} composed: {} APISets, {} APIs in {:?}",
+        path.as_ref().display(),
+        n,
+        m,
+        start.elapsed()
+    );
+    Ok(RootSchema {
+        url: None,
+        klsname: RootSchema::default_klsname(),
+        description: None,
+        apisets,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformer::{transform, Param, UrlPart};
+
+    const SCHEMA_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/schema_dir");
+
+    #[test]
+    fn test_parse_dir() {
+        let root = transform(parse_dir(SCHEMA_DIR).unwrap()).unwrap();
+        let paths = root
+            .iter_apis()
+            .into_iter()
+            .map(|(path, _)| path.join("."))
+            .collect::<Vec<String>>();
+        assert_eq!(
+            paths,
+            vec![
+                "accounts.roles.all",
+                "accounts.users.all",
+                "accounts.users.get",
+                "billing.invoices.all",
+                "billing.invoices.create",
+                "status.ping",
+            ]
+        );
+        assert_eq!(
+            root.apisets()
+                .iter()
+                .map(|v| v.name())
+                .collect::<Vec<&str>>(),
+            vec!["accounts", "billing", "status"]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "backend-javascript")]
+    fn test_parse_dir_gen() {
+        use crate::rewriter::javascript;
+
+        let root = transform(parse_dir(SCHEMA_DIR).unwrap()).unwrap();
+        let code = javascript::gen(&root, &Default::default()).unwrap();
+        assert!(code.contains("export default class XSClient {"));
+        assert!(code.contains("    get accounts() {"));
+        assert!(code.contains("    get billing() {"));
+    }
+
+    #[test]
+    fn test_parse_dir_urls() {
+        let root = transform(parse_dir(SCHEMA_DIR).unwrap()).unwrap();
+        let url = |path: &str| {
+            let path = path
+                .split('.')
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>();
+            root.url_template(&path).unwrap()
+        };
+        assert_eq!(
+            url("accounts.users.get").0,
+            vec![
+                UrlPart::Lit("http://accounts.example.com/users/".to_string()),
+                UrlPart::Param(Param::new("id", Some("int".to_string()))),
+            ]
+        );
+        assert_eq!(
+            url("accounts.roles.all").0,
+            vec![UrlPart::BaseUrl, UrlPart::Lit("/roles".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_dir_duplicate_namespace() {
+        let dir = std::env::temp_dir().join(format!("xsrc-compose-{}", std::process::id()));
+        fs::create_dir_all(dir.join("users")).unwrap();
+        fs::write(dir.join("users.yaml"), "all: {}\n").unwrap();
+        let result = parse_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        match result {
+            Err(ParserError::DuplicateNamespace(path)) => {
+                assert_eq!(path.file_name().unwrap(), "users.yaml")
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
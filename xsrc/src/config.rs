@@ -0,0 +1,337 @@
+use super::rewriter::{
+    ClientStyle, GenOptions, GenOptionsBuilder, ParamCase, QueryEncoding, ReturnStyle,
+};
+use serde_derive::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the config file, which is looked up from the working directory
+/// upwards
+pub const FILE_NAME: &str = "xsrc.toml";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    IOError(std::io::Error),
+    TomlError(toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::IOError(e) => write!(f, "IO error: {}", e),
+            ConfigError::TomlError(e) => write!(f, "TOML error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::IOError(e) => Some(e),
+            ConfigError::TomlError(e) => Some(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(e: std::io::Error) -> Self {
+        ConfigError::IOError(e)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> Self {
+        ConfigError::TomlError(e)
+    }
+}
+
+/// Generation settings, named as the command line flags. Unset ones fall
+/// back on the defaults of the config, then on those of `xsrc`.
+#[derive(Debug, Default, PartialEq, Clone, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Options {
+    pub lang: Option<String>,
+    pub out_dir: Option<PathBuf>,
+    pub param_case: Option<ParamCase>,
+    pub query_encoding: Option<QueryEncoding>,
+    pub annotate: Option<bool>,
+    pub branded_types: Option<bool>,
+    pub commonjs_interop: Option<bool>,
+    pub no_import: Option<bool>,
+    pub minify: Option<bool>,
+    pub node_keep_alive: Option<bool>,
+    pub cancellation: Option<bool>,
+    pub return_style: Option<ReturnStyle>,
+    pub style: Option<ClientStyle>,
+    pub banner: Option<String>,
+}
+
+impl Options {
+    /// The settings that are set, and those of `other` for the others
+    pub fn or(&self, other: &Options) -> Options {
+        Options {
+            lang: self.lang.clone().or_else(|| other.lang.clone()),
+            out_dir: self.out_dir.clone().or_else(|| other.out_dir.clone()),
+            param_case: self.param_case.or(other.param_case),
+            query_encoding: self.query_encoding.or(other.query_encoding),
+            annotate: self.annotate.or(other.annotate),
+            branded_types: self.branded_types.or(other.branded_types),
+            commonjs_interop: self.commonjs_interop.or(other.commonjs_interop),
+            no_import: self.no_import.or(other.no_import),
+            minify: self.minify.or(other.minify),
+            node_keep_alive: self.node_keep_alive.or(other.node_keep_alive),
+            cancellation: self.cancellation.or(other.cancellation),
+            return_style: self.return_style.or(other.return_style),
+            style: self.style.or(other.style),
+            banner: self.banner.clone().or_else(|| other.banner.clone()),
+        }
+    }
+
+    /// The generation settings, with the defaults of `GenOptions` for the
+    /// unset ones
+    pub fn gen_options(&self) -> GenOptionsBuilder {
+        let builder = GenOptions::builder()
+            .param_case(self.param_case.unwrap_or_default())
+            .query_encoding(self.query_encoding.unwrap_or_default())
+            .annotate(self.annotate.unwrap_or(false))
+            .branded_types(self.branded_types.unwrap_or(false))
+            .commonjs_interop(self.commonjs_interop.unwrap_or(false))
+            .no_import(self.no_import.unwrap_or(false))
+            .minify(self.minify.unwrap_or(false))
+            .node_keep_alive(self.node_keep_alive.unwrap_or(false))
+            .cancellation(self.cancellation.unwrap_or(false))
+            .return_style(self.return_style.unwrap_or_default())
+            .style(self.style.unwrap_or_default());
+        match &self.banner {
+            Some(banner) => builder.banner(banner),
+            None => builder,
+        }
+    }
+}
+
+/// A schema to generate when `xsrc` is run without one
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Target {
+    pub schema: PathBuf,
+    pub output: Option<PathBuf>,
+    #[serde(flatten)]
+    pub options: Options,
+}
+
+/// Settings of `xsrc lint`
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lint {
+    /// The rules that aren't run
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+/// The contents of `xsrc.toml`:
+///
+/// ```toml
+/// [defaults]
+/// lang = "javascript"
+/// out-dir = "generated"
+///
+/// [[target]]
+/// schema = "schemas/ratina.yaml"
+/// lang = "ruby"
+/// output = "clients/ratina.rb"
+///
+/// [lint]
+/// allow = ["missing-description"]
+/// ```
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub defaults: Options,
+    #[serde(default, rename = "target")]
+    pub targets: Vec<Target>,
+    #[serde(default)]
+    pub lint: Lint,
+}
+
+impl Config {
+    /// Makes the relative paths relative to `dir` instead
+    fn resolve(&mut self, dir: &Path) {
+        let mut paths = vec![&mut self.defaults.out_dir];
+        for target in &mut self.targets {
+            target.schema = dir.join(&target.schema);
+            paths.push(&mut target.output);
+            paths.push(&mut target.options.out_dir);
+        }
+        for path in paths.into_iter().filter_map(|v| v.as_mut()) {
+            *path = dir.join(&path);
+        }
+    }
+}
+
+pub fn parse_str(s: &str) -> Result<Config, ConfigError> {
+    Ok(toml::from_str(s)?)
+}
+
+/// Parses a config file. The paths in it are relative to its directory.
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+    let mut config = parse_str(&fs::read_to_string(&path)?)?;
+    if let Some(dir) = path.as_ref().parent() {
+        config.resolve(dir);
+    }
+    Ok(config)
+}
+
+/// Finds the config file in `dir` or the nearest of its ancestors.
+pub fn discover<P: AsRef<Path>>(dir: P) -> Option<PathBuf> {
+    dir.as_ref()
+        .ancestors()
+        .map(|v| v.join(FILE_NAME))
+        .find(|v| v.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = parse_str(
+            "\
+[defaults]
+lang = \"javascript\"
+param-case = \"camel\"
+
+[[target]]
+schema = \"ratina.yaml\"
+lang = \"ruby\"
+output = \"ratina.rb\"
+
+[[target]]
+schema = \"ahcro.yaml\"
+
+[lint]
+allow = [\"missing-description\"]
+",
+        )
+        .unwrap();
+        assert_eq!(config.defaults.lang, Some("javascript".to_string()));
+        assert_eq!(config.defaults.param_case, Some(ParamCase::Camel));
+        assert_eq!(config.targets.len(), 2);
+        assert_eq!(config.targets[0].schema, PathBuf::from("ratina.yaml"));
+        assert_eq!(config.targets[0].output, Some(PathBuf::from("ratina.rb")));
+        assert_eq!(config.targets[0].options.lang, Some("ruby".to_string()));
+        assert_eq!(config.targets[1].options, Options::default());
+        assert_eq!(config.lint.allow, vec!["missing-description".to_string()]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let config = parse_str(
+            "\
+[defaults]
+lang = \"javascript\"
+out-dir = \"generated\"
+annotate = true
+
+[[target]]
+schema = \"ratina.yaml\"
+lang = \"ruby\"
+annotate = false
+",
+        )
+        .unwrap();
+        let options = config.targets[0].options.or(&config.defaults);
+        assert_eq!(options.lang, Some("ruby".to_string()));
+        assert_eq!(options.out_dir, Some(PathBuf::from("generated")));
+        assert_eq!(options.annotate, Some(false));
+        assert_eq!(options.param_case, None);
+        assert_eq!(
+            options.gen_options().build().unwrap(),
+            GenOptions::default()
+        );
+        let options = config.defaults.gen_options().build().unwrap();
+        assert!(options.annotate);
+    }
+
+    #[test]
+    fn test_banner() {
+        let config = parse_str(
+            "\
+[defaults]
+banner = \"Do not edit.\"
+
+[[target]]
+schema = \"ratina.yaml\"
+",
+        )
+        .unwrap();
+        let options = config.targets[0].options.or(&config.defaults);
+        assert_eq!(options.banner.as_deref(), Some("Do not edit."));
+        assert_eq!(
+            options.gen_options().build().unwrap(),
+            GenOptions::builder()
+                .banner("Do not edit.")
+                .build()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_javascript_options() {
+        let config = parse_str(
+            "\
+[defaults]
+style = \"nested-object\"
+cancellation = true
+
+[[target]]
+schema = \"ratina.yaml\"
+return-style = \"callback\"
+node-keep-alive = true
+",
+        )
+        .unwrap();
+        let options = config.targets[0].options.or(&config.defaults);
+        assert_eq!(
+            options.gen_options().build().unwrap(),
+            GenOptions::builder()
+                .node_keep_alive(true)
+                .cancellation(true)
+                .return_style(ReturnStyle::Callback)
+                .style(ClientStyle::NestedObject)
+                .build()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        for s in &[
+            "[defaults]\nlanguage = \"ruby\"\n",
+            "[[target]]\nschema = \"ratina.yaml\"\nquotes = \"single\"\n",
+            "[default]\nlang = \"ruby\"\n",
+            "[defaults]\nparam-case = \"kebab\"\n",
+        ] {
+            assert!(matches!(parse_str(s), Err(ConfigError::TomlError(_))));
+        }
+    }
+
+    #[test]
+    fn test_discover() {
+        let root = std::env::temp_dir().join(format!("xsrc-discover-{}", std::process::id()));
+        let nested = root.join("schemas").join("ratina");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(discover(&nested), None);
+        fs::write(
+            root.join(FILE_NAME),
+            "[[target]]\nschema = \"schemas/ratina.yaml\"\n",
+        )
+        .unwrap();
+        assert_eq!(discover(&nested), Some(root.join(FILE_NAME)));
+        let config = parse_file(discover(&nested).unwrap()).unwrap();
+        assert_eq!(config.targets[0].schema, root.join("schemas/ratina.yaml"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
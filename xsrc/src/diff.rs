@@ -0,0 +1,442 @@
+//! Changes between two versions of a schema, compared by the dotted paths of
+//! their APIs and APISets, as reported by `xsrc diff`
+use super::transformer::*;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ParamKind {
+    Url,
+    Query,
+    Body,
+}
+
+impl fmt::Display for ParamKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamKind::Url => write!(f, "URL param"),
+            ParamKind::Query => write!(f, "query param"),
+            ParamKind::Body => write!(f, "body param"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    RemovedAPISet,
+    AddedAPISet,
+    RemovedAPI,
+    AddedAPI,
+    Method {
+        from: String,
+        to: String,
+    },
+    /// A change of the URL up to the root URL, or of the root URL itself
+    Url {
+        from: String,
+        to: String,
+    },
+    RemovedParam {
+        kind: ParamKind,
+        name: String,
+    },
+    /// A param taken in place of another of the same type
+    RenamedParam {
+        kind: ParamKind,
+        from: String,
+        to: String,
+    },
+    RetypedParam {
+        kind: ParamKind,
+        name: String,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    /// A new param, which callers have to pass unless it's optional or has a
+    /// default. Query and body params can always be left out.
+    AddedParam {
+        kind: ParamKind,
+        name: String,
+        required: bool,
+    },
+}
+
+impl Change {
+    /// Whether callers of the old version break with the new one
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            Change::AddedAPISet | Change::AddedAPI => false,
+            Change::AddedParam { required, .. } => *required,
+            _ => true,
+        }
+    }
+}
+
+fn display_type(typ: &Option<String>) -> &str {
+    typ.as_ref().map(|v| v.as_str()).unwrap_or("no type")
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::RemovedAPISet => write!(f, "APISet removed"),
+            Change::AddedAPISet => write!(f, "APISet added"),
+            Change::RemovedAPI => write!(f, "API removed"),
+            Change::AddedAPI => write!(f, "API added"),
+            Change::Method { from, to } => write!(f, "method changed from {} to {}", from, to),
+            Change::Url { from, to } => write!(f, "URL changed from {} to {}", from, to),
+            Change::RemovedParam { kind, name } => write!(f, "{} \"{}\" removed", kind, name),
+            Change::RenamedParam { kind, from, to } => {
+                write!(f, "{} \"{}\" renamed to \"{}\"", kind, from, to)
+            }
+            Change::RetypedParam {
+                kind,
+                name,
+                from,
+                to,
+            } => write!(
+                f,
+                "{} \"{}\" retyped from {} to {}",
+                kind,
+                name,
+                display_type(from),
+                display_type(to)
+            ),
+            Change::AddedParam {
+                kind,
+                name,
+                required,
+            } => write!(
+                f,
+                "{} {} \"{}\" added",
+                if *required { "required" } else { "optional" },
+                kind,
+                name
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    /// The path of the APISet or API from the root, empty for the root
+    pub path: Vec<String>,
+    pub change: Change,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "the root: {}", self.change)
+        } else {
+            write!(f, "{}: {}", self.path.join("."), self.change)
+        }
+    }
+}
+
+/// Renders a URL with `{param}` placeholders, leaving out the root URL, which
+/// is compared on its own
+fn display_url(root: &ContextBoundedRoot, path: &[String]) -> Result<String, ContextLookupError> {
+    Ok(display_template(
+        &root.url_template(path)?,
+        &UrlTemplate(Vec::new()),
+    ))
+}
+
+/// The params of an API by kind. URL params come in the order they appear,
+/// those of the root included.
+fn params_of(
+    root: &ContextBoundedRoot,
+    path: &[String],
+    api: &ContextBoundedAPI,
+) -> Result<Vec<(ParamKind, Vec<Param>)>, ContextLookupError> {
+    Ok(vec![
+        (
+            ParamKind::Url,
+            root.url_params(path)?.into_iter().map(|(_, p)| p).collect(),
+        ),
+        (ParamKind::Query, api.params().values().cloned().collect()),
+        (ParamKind::Body, api.data().values().cloned().collect()),
+    ])
+}
+
+/// Compares params of one kind. A removed param and an added one at the same
+/// position with the same type make a rename.
+fn diff_params(kind: ParamKind, old: &[Param], new: &[Param], changes: &mut Vec<Change>) {
+    let find = |params: &[Param], name: &str| params.iter().position(|p| p.name == name);
+    for (i, p) in old.iter().enumerate() {
+        match find(new, &p.name) {
+            Some(j) if new[j].typ != p.typ => changes.push(Change::RetypedParam {
+                kind,
+                name: p.name.to_string(),
+                from: p.typ.clone(),
+                to: new[j].typ.clone(),
+            }),
+            Some(_) => {}
+            None => match new.get(i) {
+                Some(q) if find(old, &q.name).is_none() && q.typ == p.typ => {
+                    changes.push(Change::RenamedParam {
+                        kind,
+                        from: p.name.to_string(),
+                        to: q.name.to_string(),
+                    })
+                }
+                _ => changes.push(Change::RemovedParam {
+                    kind,
+                    name: p.name.to_string(),
+                }),
+            },
+        }
+    }
+    for (j, q) in new.iter().enumerate() {
+        let is_rename = match old.get(j) {
+            Some(p) => find(new, &p.name).is_none() && p.typ == q.typ,
+            None => false,
+        };
+        if find(old, &q.name).is_none() && !is_rename {
+            changes.push(Change::AddedParam {
+                kind,
+                name: q.name.to_string(),
+                required: kind == ParamKind::Url && !q.optional && q.default.is_none(),
+            });
+        }
+    }
+}
+
+fn diff_api(
+    old_root: &ContextBoundedRoot,
+    new_root: &ContextBoundedRoot,
+    path: &[String],
+    old: &ContextBoundedAPI,
+    new: &ContextBoundedAPI,
+) -> Result<Vec<Change>, ContextLookupError> {
+    let mut changes = Vec::new();
+    if old.method() != new.method() {
+        changes.push(Change::Method {
+            from: format!("{:?}", old.method()),
+            to: format!("{:?}", new.method()),
+        });
+    }
+    let (old_url, new_url) = (display_url(old_root, path)?, display_url(new_root, path)?);
+    if old_url != new_url {
+        changes.push(Change::Url {
+            from: old_url,
+            to: new_url,
+        });
+    }
+    let old_params = params_of(old_root, path, old)?;
+    let new_params = params_of(new_root, path, new)?;
+    for ((kind, old), (_, new)) in old_params.iter().zip(new_params.iter()) {
+        diff_params(*kind, old, new, &mut changes);
+    }
+    Ok(changes)
+}
+
+/// Compares two versions of a schema. Within removed or added APISets, only
+/// the APISets themselves are reported.
+pub fn diff(
+    old: &ContextBoundedRoot,
+    new: &ContextBoundedRoot,
+) -> Result<Vec<Finding>, ContextLookupError> {
+    let mut findings = Vec::new();
+    let (old_base, new_base) = (old.base_url(), new.base_url());
+    if old_base != new_base {
+        findings.push(Finding {
+            path: Vec::new(),
+            change: Change::Url {
+                from: display_template(&old_base, &old_base),
+                to: display_template(&new_base, &new_base),
+            },
+        });
+    }
+    let (old_apisets, new_apisets) = (old.iter_apisets(), new.iter_apisets());
+    let (old_apis, new_apis) = (old.iter_apis(), new.iter_apis());
+    let is_apiset = |apisets: &[(Vec<String>, &ContextBoundedAPISet)], path: &[String]| {
+        apisets.iter().any(|(v, _)| v.as_slice() == path)
+    };
+    // Whether a proper prefix of the path is an APISet of `apisets` but not
+    // of `others`, so that the path was already reported with it
+    let under_reported = |apisets: &[(Vec<String>, &ContextBoundedAPISet)],
+                          others: &[(Vec<String>, &ContextBoundedAPISet)],
+                          path: &[String]| {
+        (1..path.len()).any(|n| is_apiset(apisets, &path[..n]) && !is_apiset(others, &path[..n]))
+    };
+    for (path, _) in &old_apisets {
+        if !is_apiset(&new_apisets, path) && !under_reported(&old_apisets, &new_apisets, path) {
+            findings.push(Finding {
+                path: path.clone(),
+                change: Change::RemovedAPISet,
+            });
+        }
+    }
+    for (path, api) in &old_apis {
+        if under_reported(&old_apisets, &new_apisets, path) {
+            continue;
+        }
+        match new_apis.iter().find(|(v, _)| v == path) {
+            Some((_, new_api)) => {
+                for change in diff_api(old, new, path, api, new_api)? {
+                    findings.push(Finding {
+                        path: path.clone(),
+                        change,
+                    });
+                }
+            }
+            None => findings.push(Finding {
+                path: path.clone(),
+                change: Change::RemovedAPI,
+            }),
+        }
+    }
+    for (path, _) in &new_apisets {
+        if !is_apiset(&old_apisets, path) && !under_reported(&new_apisets, &old_apisets, path) {
+            findings.push(Finding {
+                path: path.clone(),
+                change: Change::AddedAPISet,
+            });
+        }
+    }
+    for (path, _) in &new_apis {
+        let is_new = !old_apis.iter().any(|(v, _)| v == path);
+        if is_new && !under_reported(&new_apisets, &old_apisets, path) {
+            findings.push(Finding {
+                path: path.clone(),
+                change: Change::AddedAPI,
+            });
+        }
+    }
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+
+    fn diff_str(old: &str, new: &str) -> Vec<(String, bool)> {
+        let old = transform(parse_str(old).unwrap()).unwrap();
+        let new = transform(parse_str(new).unwrap()).unwrap();
+        diff(&old, &new)
+            .unwrap()
+            .into_iter()
+            .map(|v| (v.to_string(), v.change.is_breaking()))
+            .collect()
+    }
+
+    const OLD: &str = "\
+$url: \"http://ratina.org\"
+~ratincren:
+  $url: \"${!super}/ratincren/<group:int>\"
+  get:
+    $url: \"${!super}/<id:int>\"
+    $params:
+      detail: \"boolean\"
+  create.POST:
+    $data:
+      name: \"string\"
+  ~members:
+    $url: \"${!super}/members\"
+    all: {}
+";
+
+    #[test]
+    fn test_no_changes() {
+        assert_eq!(diff_str(OLD, OLD), vec![]);
+    }
+
+    #[test]
+    fn test_removed_and_added() {
+        let new = "\
+$url: \"http://ratina.org\"
+~ratincren:
+  $url: \"${!super}/ratincren/<group:int>\"
+  create.POST:
+    $data:
+      name: \"string\"
+  search: {}
+~ahcros:
+  $url: \"${!super}/ahcros\"
+  all: {}
+";
+        assert_eq!(
+            diff_str(OLD, new),
+            vec![
+                ("ratincren.members: APISet removed".to_string(), true),
+                ("ratincren.get: API removed".to_string(), true),
+                ("ahcros: APISet added".to_string(), false),
+                ("ratincren.search: API added".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changed_api() {
+        let new = "\
+$url: \"https://ratina.org\"
+~ratincren:
+  $url: \"${!super}/ratincren/<group:int>\"
+  get.PUT:
+    $url: \"${!super}/by-id/<uid:int>/<version?:int>\"
+    $params:
+      detail: \"string\"
+      verbose: \"boolean\"
+  create.POST:
+    $url: \"${!super}/<section:string>\"
+    $data:
+      title: \"string\"
+  ~members:
+    $url: \"${!super}/members\"
+    all: {}
+";
+        assert_eq!(
+            diff_str(OLD, new),
+            vec![
+                (
+                    "the root: URL changed from http://ratina.org to https://ratina.org"
+                        .to_string(),
+                    true
+                ),
+                (
+                    "ratincren.get: method changed from GET to PUT".to_string(),
+                    true
+                ),
+                (
+                    "ratincren.get: URL changed from /ratincren/{group}/{id} to \
+                     /ratincren/{group}/by-id/{uid}/{version}"
+                        .to_string(),
+                    true
+                ),
+                (
+                    "ratincren.get: URL param \"id\" renamed to \"uid\"".to_string(),
+                    true
+                ),
+                (
+                    "ratincren.get: optional URL param \"version\" added".to_string(),
+                    false
+                ),
+                (
+                    "ratincren.get: query param \"detail\" retyped from boolean to string"
+                        .to_string(),
+                    true
+                ),
+                (
+                    "ratincren.get: optional query param \"verbose\" added".to_string(),
+                    false
+                ),
+                (
+                    "ratincren.create: URL changed from /ratincren/{group} to \
+                     /ratincren/{group}/{section}"
+                        .to_string(),
+                    true
+                ),
+                (
+                    "ratincren.create: required URL param \"section\" added".to_string(),
+                    true
+                ),
+                (
+                    "ratincren.create: body param \"name\" renamed to \"title\"".to_string(),
+                    true
+                ),
+            ]
+        );
+    }
+}
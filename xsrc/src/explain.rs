@@ -0,0 +1,290 @@
+//! A single API as the backends see it, with its URL resolved, printed by
+//! `xsrc explain`
+use super::transformer::*;
+use linked_hash_map::LinkedHashMap;
+use std::fmt;
+
+/// Where the value of an argument goes in the request
+#[derive(Debug, PartialEq)]
+pub enum ArgSource {
+    /// A param of the URL, declared by the URL of the APISet or API at the
+    /// path, which is empty for the root
+    Path(Vec<String>),
+    /// A param of `$params`
+    Query,
+    /// A param of `$data`
+    Body,
+}
+
+impl fmt::Display for ArgSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgSource::Path(owner) if owner.is_empty() => write!(f, "path, of the root"),
+            ArgSource::Path(owner) => write!(f, "path, of {}", owner.join(".")),
+            ArgSource::Query => write!(f, "query"),
+            ArgSource::Body => write!(f, "body"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Arg {
+    pub param: Param,
+    pub source: ArgSource,
+}
+
+#[derive(Debug)]
+pub struct Explanation<'a> {
+    pub path: Vec<String>,
+    pub method: &'a HttpMethod,
+    /// The URL with `{param}` placeholders and the root URL expanded
+    pub url: String,
+    /// The URL params in the order they appear, then the query params and the
+    /// body params
+    pub args: Vec<Arg>,
+    pub headers: &'a LinkedHashMap<String, String>,
+}
+
+impl<'a> fmt::Display for Explanation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}\n  {:?} {}",
+            self.path.join("."),
+            self.method,
+            self.url
+        )?;
+        if !self.args.is_empty() {
+            write!(f, "\nArguments:")?;
+            for arg in &self.args {
+                let p = &arg.param;
+                write!(f, "\n  {}", p.name)?;
+                if p.optional {
+                    write!(f, "?")?;
+                }
+                if let Some(typ) = &p.typ {
+                    write!(f, ": {}", typ)?;
+                }
+                if let Some(default) = &p.default {
+                    write!(f, " = {}", default)?;
+                }
+                write!(f, "  ({})", arg.source)?;
+            }
+        }
+        if !self.headers.is_empty() {
+            write!(f, "\nHeaders:")?;
+            for (name, value) in self.headers {
+                write!(f, "\n  {}: {}", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ExplainError {
+    /// Nothing at the path, with the names next to where it goes wrong
+    NoSuchPath {
+        path: Vec<String>,
+        siblings: Vec<String>,
+    },
+    /// An APISet at the path, with the names of its children
+    NotAnAPI {
+        path: Vec<String>,
+        children: Vec<String>,
+    },
+    ContextLookupError(ContextLookupError),
+}
+
+impl From<ContextLookupError> for ExplainError {
+    fn from(e: ContextLookupError) -> Self {
+        ExplainError::ContextLookupError(e)
+    }
+}
+
+impl fmt::Display for ExplainError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExplainError::NoSuchPath { path, siblings } if siblings.is_empty() => {
+                write!(f, "No API at {}", path.join("."))
+            }
+            ExplainError::NoSuchPath { path, siblings } => write!(
+                f,
+                "No API at {}, expected one of: {}",
+                path.join("."),
+                siblings.join(", ")
+            ),
+            ExplainError::NotAnAPI { path, children } => write!(
+                f,
+                "{} is an APISet, expected one of: {}",
+                path.join("."),
+                children.join(", ")
+            ),
+            ExplainError::ContextLookupError(e) => write!(f, "Context lookup error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExplainError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExplainError::ContextLookupError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// The names of `children`, dotted after `prefix`
+fn names(
+    children: &LinkedHashMap<String, ContextBoundedAPIData>,
+    prefix: &[String],
+) -> Vec<String> {
+    children
+        .keys()
+        .map(|name| {
+            let mut path = prefix.to_vec();
+            path.push(name.to_string());
+            path.join(".")
+        })
+        .collect()
+}
+
+/// Resolves the API at `path`
+pub fn explain<'a>(
+    root: &'a ContextBoundedRoot,
+    path: &[String],
+) -> Result<Explanation<'a>, ExplainError> {
+    let mut children = root.children();
+    let mut api = None;
+    for (i, name) in path.iter().enumerate() {
+        match children.get(name) {
+            Some(ContextBoundedAPIData::APISet(apiset)) => children = apiset.children(),
+            Some(ContextBoundedAPIData::API(v)) if i == path.len() - 1 => api = Some(v),
+            _ => {
+                return Err(ExplainError::NoSuchPath {
+                    path: path.to_vec(),
+                    siblings: names(children, &path[..i]),
+                })
+            }
+        }
+    }
+    let api = api.ok_or_else(|| ExplainError::NotAnAPI {
+        path: path.to_vec(),
+        children: names(children, path),
+    })?;
+    let base_url = root.base_url();
+    let url = display_template(&root.url_template(path)?, &base_url);
+    let mut args = root
+        .url_params(path)?
+        .into_iter()
+        .map(|(owner, param)| Arg {
+            param,
+            source: ArgSource::Path(owner),
+        })
+        .collect::<Vec<Arg>>();
+    for param in api.params().values() {
+        args.push(Arg {
+            param: param.clone(),
+            source: ArgSource::Query,
+        });
+    }
+    for param in api.data().values() {
+        args.push(Arg {
+            param: param.clone(),
+            source: ArgSource::Body,
+        });
+    }
+    Ok(Explanation {
+        path: path.to_vec(),
+        method: api.method(),
+        url,
+        args,
+        headers: api.headers(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+
+    fn path(s: &str) -> Vec<String> {
+        s.split('.').map(String::from).collect()
+    }
+
+    #[test]
+    fn test_explain() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org/<region>\"
+~ratincren:
+  $url: \"${!super}/ratincren/<group:int>\"
+  ~members:
+    $url: \"${!super}/members\"
+    update.PUT:
+      $url: \"${!super}/<id:uuid>\"
+      $headers:
+        Accept: \"application/json\"
+      $params:
+        notify: \"boolean\"
+      $data:
+        name: \"string\"
+",
+        )
+        .unwrap();
+        let root = transform(schema).unwrap();
+        let explanation = explain(&root, &path("ratincren.members.update")).unwrap();
+        assert_eq!(
+            explanation.url,
+            "http://ratina.org/{region}/ratincren/{group}/members/{id}"
+        );
+        assert_eq!(
+            explanation.to_string(),
+            "\
+ratincren.members.update
+  PUT http://ratina.org/{region}/ratincren/{group}/members/{id}
+Arguments:
+  region  (path, of the root)
+  group: int  (path, of ratincren)
+  id: uuid  (path, of ratincren.members.update)
+  notify: boolean  (query)
+  name: string  (body)
+Headers:
+  Accept: application/json"
+        );
+    }
+
+    #[test]
+    fn test_explain_unknown_path() {
+        let schema = parse_str(include_str!("../tests/fixtures/cases/sample/schema.yaml")).unwrap();
+        let root = transform(schema).unwrap();
+        assert_eq!(
+            explain(&root, &path("users.remove")).err().unwrap(),
+            ExplainError::NoSuchPath {
+                path: path("users.remove"),
+                siblings: vec![
+                    "users.all".to_string(),
+                    "users.get".to_string(),
+                    "users.create".to_string(),
+                    "users.update".to_string(),
+                    "users.budgets".to_string(),
+                ]
+            }
+        );
+        assert_eq!(
+            explain(&root, &path("users.get.id"))
+                .err()
+                .unwrap()
+                .to_string(),
+            "No API at users.get.id, expected one of: \
+             users.all, users.get, users.create, users.update, users.budgets"
+        );
+        assert_eq!(
+            explain(&root, &path("users.budgets"))
+                .err()
+                .unwrap()
+                .to_string(),
+            "users.budgets is an APISet, expected one of: users.budgets.all"
+        );
+    }
+}
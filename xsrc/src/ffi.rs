@@ -0,0 +1,153 @@
+//! A C interface to the pipeline, for embedding xsrc in toolchains that would
+//! otherwise run the command line tool for every schema. Built with the
+//! `ffi` feature into the `cdylib` of the crate, e.g.:
+//!
+//! ```sh
+//! cargo build --release -p xsrc --no-default-features --features ffi,backends
+//! ```
+//!
+//! The functions are declared in `include/xsrc.h`. Strings are UTF-8 and
+//! NUL-terminated. Panics are caught and returned as [`XSRC_ERR_PANIC`].
+use super::pipeline::{generate_with, SchemaSource, XsrcError};
+use super::rewriter::{GenOptions, Registry};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+pub const XSRC_OK: c_int = 0;
+/// A pointer argument is null or its string isn't UTF-8
+pub const XSRC_ERR_ARGUMENT: c_int = 1;
+/// `options_json` isn't a JSON object of valid `GenOptions`
+pub const XSRC_ERR_OPTIONS: c_int = 2;
+pub const XSRC_ERR_PARSE: c_int = 3;
+pub const XSRC_ERR_TRANSFORM: c_int = 4;
+/// No backend for the language, or the backend failed
+pub const XSRC_ERR_GENERATE: c_int = 5;
+pub const XSRC_ERR_PANIC: c_int = 6;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // The message is cut at a NUL rather than lost
+    let message = match CString::new(message) {
+        Ok(v) => v,
+        Err(e) => {
+            let end = e.nul_position();
+            let mut bytes = e.into_vec();
+            bytes.truncate(end);
+            CString::new(bytes).unwrap()
+        }
+    };
+    LAST_ERROR.with(|v| *v.borrow_mut() = Some(message));
+}
+
+fn error_code(e: &XsrcError) -> c_int {
+    match e {
+        XsrcError::ParserError(_) | XsrcError::IOError(_) => XSRC_ERR_PARSE,
+        XsrcError::TransformerError(_) => XSRC_ERR_TRANSFORM,
+        XsrcError::BackendError(_)
+        | XsrcError::UnsupportedLanguage(..)
+        | XsrcError::NotCompiledIn(_) => XSRC_ERR_GENERATE,
+        XsrcError::InvalidOptions(_) => XSRC_ERR_OPTIONS,
+    }
+}
+
+/// The string of a non-null pointer, or `None` for a null one
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<Option<&'a str>, (c_int, String)> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map(Some)
+        .map_err(|e| (XSRC_ERR_ARGUMENT, format!("{} isn't UTF-8: {}", name, e)))
+}
+
+unsafe fn generate_c(
+    schema: *const c_char,
+    lang: *const c_char,
+    options_json: *const c_char,
+) -> Result<CString, (c_int, String)> {
+    let required = |name: &str| (XSRC_ERR_ARGUMENT, format!("{} is null", name));
+    let schema = str_arg(schema, "schema")?.ok_or_else(|| required("schema"))?;
+    let lang = str_arg(lang, "lang")?.ok_or_else(|| required("lang"))?;
+    let opts: GenOptions = match str_arg(options_json, "options_json")? {
+        Some(v) if !v.trim().is_empty() => serde_json::from_str(v)
+            .map_err(|e| (XSRC_ERR_OPTIONS, format!("Invalid options: {}", e)))?,
+        _ => Default::default(),
+    };
+    let output = generate_with(&Registry::default(), SchemaSource::Str(schema), lang, &opts)
+        .map_err(|e| (error_code(&e), e.to_string()))?;
+    let nul = "The generated code has a NUL byte";
+    CString::new(output.code).map_err(|_| (XSRC_ERR_GENERATE, nul.to_string()))
+}
+
+/// Generates the code of the YAML `schema` in `lang`, a backend name as
+/// taken by `--lang`. `options_json` is a JSON object of `GenOptions` keyed
+/// as in configs, or null or empty for the defaults. On success the code is
+/// stored in `*out`, to be freed with [`xsrc_free_string`], and
+/// [`XSRC_OK`] is returned. Otherwise `*out` is set to null and an error
+/// code is returned, with the message available from [`xsrc_last_error`].
+///
+/// # Safety
+///
+/// The strings must be null or valid NUL-terminated strings, and `out` must
+/// be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn xsrc_generate(
+    schema: *const c_char,
+    lang: *const c_char,
+    options_json: *const c_char,
+    out: *mut *mut c_char,
+) -> c_int {
+    LAST_ERROR.with(|v| *v.borrow_mut() = None);
+    if out.is_null() {
+        set_last_error("out is null".to_string());
+        return XSRC_ERR_ARGUMENT;
+    }
+    *out = ptr::null_mut();
+    let result = catch_unwind(AssertUnwindSafe(|| generate_c(schema, lang, options_json)));
+    match result {
+        Ok(Ok(code)) => {
+            *out = code.into_raw();
+            XSRC_OK
+        }
+        Ok(Err((code, message))) => {
+            set_last_error(message);
+            code
+        }
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|v| v.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "Unknown panic".to_string());
+            set_last_error(format!("Panicked: {}", message));
+            XSRC_ERR_PANIC
+        }
+    }
+}
+
+/// Frees a string from [`xsrc_generate`]. Null is ignored.
+///
+/// # Safety
+///
+/// `s` must be null or a string from `xsrc_generate` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn xsrc_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// The message of the last error of [`xsrc_generate`] on this thread, or
+/// null if the last call succeeded. The string is owned by xsrc and valid
+/// until the next call of `xsrc_generate` on the thread.
+#[no_mangle]
+pub extern "C" fn xsrc_last_error() -> *const c_char {
+    LAST_ERROR.with(|v| v.borrow().as_ref().map_or(ptr::null(), |v| v.as_ptr()))
+}
@@ -0,0 +1,200 @@
+//! The transformed schema as JSON, with every URL resolved, for tools that
+//! would otherwise have to reimplement the transform. Written by
+//! `xsrc --emit-ir`.
+use super::transformer::*;
+use linked_hash_map::LinkedHashMap;
+use serde_json::{json, Value};
+
+/// The version of the shape of the JSON, bumped whenever fields are removed
+/// or change meaning
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A param with where it goes in the request. URL params also have the path
+/// of the APISet or API whose URL declares them, empty for the root, and the
+/// range of chars they take up in that URL, from the `<` to past the `>`.
+fn param(p: &Param, location: &str, owner: Option<&[String]>) -> Value {
+    let mut value = json!({
+        "name": p.name,
+        "type": p.typ,
+        "optional": p.optional,
+        "default": p.default,
+        "in": location,
+    });
+    if let Some(owner) = owner {
+        value["of"] = Value::from(owner.join("."));
+    }
+    if let Some(span) = &p.span {
+        value["range"] = json!({ "start": span.start, "end": span.end });
+    }
+    value
+}
+
+fn url_params(
+    root: &ContextBoundedRoot,
+    path: &[String],
+) -> Result<Vec<Value>, ContextLookupError> {
+    Ok(root
+        .url_params(path)?
+        .iter()
+        .map(|(owner, p)| param(p, "path", Some(owner)))
+        .collect())
+}
+
+fn gen_api(
+    root: &ContextBoundedRoot,
+    path: &[String],
+    api: &ContextBoundedAPI,
+) -> Result<Value, ContextLookupError> {
+    let base_url = root.base_url();
+    let mut params = url_params(root, path)?;
+    params.extend(api.params().values().map(|p| param(p, "query", None)));
+    params.extend(api.data().values().map(|p| param(p, "body", None)));
+    Ok(json!({
+        "kind": "api",
+        "name": api.name(),
+        "path": path.join("."),
+        "description": api.description(),
+        "method": format!("{:?}", api.method()),
+        "url": display_template(&root.url_template(path)?, &base_url),
+        "params": params,
+        "headers": api.headers(),
+        "stream": api.stream(),
+        "responseType": api.response_type().map(|v| v.as_str()),
+        "paginate": api.paginate().map(|v| json!({
+            "cursor": v.cursor,
+            "items": v.items,
+            "param": v.param(),
+        })),
+        "deprecated": match api.deprecated() {
+            None => Value::from(false),
+            Some(None) => Value::from(true),
+            Some(Some(reason)) => Value::from(reason),
+        },
+    }))
+}
+
+fn gen_children(
+    root: &ContextBoundedRoot,
+    children: &LinkedHashMap<String, ContextBoundedAPIData>,
+    path: &mut Vec<String>,
+) -> Result<Vec<Value>, ContextLookupError> {
+    let base_url = root.base_url();
+    let mut values = Vec::new();
+    for (name, child) in children {
+        path.push(name.to_string());
+        values.push(match child {
+            ContextBoundedAPIData::API(api) => gen_api(root, path, api)?,
+            ContextBoundedAPIData::APISet(apiset) => json!({
+                "kind": "apiset",
+                "name": apiset.name(),
+                "path": path.join("."),
+                "description": apiset.description(),
+                "url": display_template(&root.url_template(path)?, &base_url),
+                "params": url_params(root, path)?,
+                "children": gen_children(root, apiset.children(), path)?,
+            }),
+        });
+        path.pop();
+    }
+    Ok(values)
+}
+
+/// The JSON of the transformed schema. APIs and APISets are listed in
+/// declaration order, each with its dotted path, its URL with `{param}`
+/// placeholders and the root URL expanded, and its params.
+pub fn to_json(root: &ContextBoundedRoot) -> Result<Value, ContextLookupError> {
+    let base_url = root.base_url();
+    Ok(json!({
+        "formatVersion": FORMAT_VERSION,
+        "xsrcVersion": env!("CARGO_PKG_VERSION"),
+        "name": root.klsname(),
+        "description": root.description(),
+        "url": display_template(&base_url, &base_url),
+        "params": url_params(root, &[])?,
+        "children": gen_children(root, root.children(), &mut Vec::new())?,
+    }))
+}
+
+pub fn to_string(root: &ContextBoundedRoot) -> Result<String, ContextLookupError> {
+    Ok(format!(
+        "{}\n",
+        serde_json::to_string_pretty(&to_json(root)?).unwrap()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+
+    #[test]
+    fn test_to_string_sample() {
+        let schema = parse_str(include_str!("../tests/fixtures/cases/sample/schema.yaml")).unwrap();
+        let ir = to_string(&transform(schema).unwrap()).unwrap();
+        assert_eq!(ir, include_str!("../tests/fixtures/sample.ir.json"));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org/<region>\"
+~ratincren:
+  $url: \"${!super}/ratincren/<group:int>\"
+  list:
+    $url: \"${!super}?page=<page:int=1>\"
+    $paginate:
+      cursor: \"next\"
+      items: \"data\"
+    $deprecated: \"Use search\"
+",
+        )
+        .unwrap();
+        let ir = to_json(&transform(schema).unwrap()).unwrap();
+        assert_eq!(ir["formatVersion"], json!(FORMAT_VERSION));
+        assert_eq!(ir["xsrcVersion"], json!(env!("CARGO_PKG_VERSION")));
+        assert_eq!(
+            ir["params"],
+            json!([{
+                "name": "region",
+                "type": null,
+                "optional": false,
+                "default": null,
+                "in": "path",
+                "of": "",
+                "range": { "start": 18, "end": 26 },
+            }])
+        );
+        let apiset = &ir["children"][0];
+        assert_eq!(
+            apiset["url"],
+            json!("http://ratina.org/{region}/ratincren/{group}")
+        );
+        let api = &apiset["children"][0];
+        assert_eq!(api["path"], json!("ratincren.list"));
+        assert_eq!(
+            api["params"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|p| format!("{}@{}", p["name"], p["of"]))
+                .collect::<Vec<String>>(),
+            vec![
+                "\"region\"@\"\"",
+                "\"group\"@\"ratincren\"",
+                "\"page\"@\"ratincren.list\""
+            ]
+        );
+        assert_eq!(api["params"][2]["default"], json!("1"));
+        // Each URL param points back at its declaration in the schema
+        assert_eq!(api["params"][1]["range"], json!({ "start": 20, "end": 31 }));
+        for p in api["params"].as_array().unwrap() {
+            assert!(p["range"]["start"].as_u64() < p["range"]["end"].as_u64());
+        }
+        assert_eq!(
+            api["paginate"],
+            json!({ "cursor": "next", "items": "data", "param": "next" })
+        );
+        assert_eq!(api["deprecated"], json!("Use search"));
+    }
+}
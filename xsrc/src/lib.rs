@@ -1,12 +1,54 @@
-#![feature(try_blocks)]
-#![feature(custom_attribute)]
-#![feature(non_ascii_idents)]
-#![feature(box_syntax)]
+//! Generates API clients from YAML schemas. [`generate`] runs the whole
+//! pipeline, which is also available stage by stage: [`schema`] parses,
+//! [`transformer`] resolves the URLs and [`rewriter`] generates the code.
+//! The errors of every stage implement `std::error::Error`:
+//!
+//! ```
+//! use std::error::Error;
+//! use xsrc::rewriter::{generate, Registry};
+//!
+//! fn gen(yaml: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+//!     let schema = xsrc::schema::parse_str(yaml)?;
+//!     let root = xsrc::transformer::transform(schema)?;
+//!     let registry = Registry::default();
+//!     let backend = xsrc::backend(&registry, "ruby")?;
+//!     Ok(generate(backend, &root, &Default::default())?.code)
+//! }
+//!
+//! # #[cfg(feature = "backend-ruby")]
+//! assert!(gen("$url: \"http://ratina.org\"\nall: {}\n").is_ok());
+//! let e = gen("all:\n  $url: \"${!super}/<id\"\n").unwrap_err();
+//! assert_eq!(e.to_string(), "Invalid $url of all: Unexpected EOF");
+//! assert_eq!(e.source().unwrap().to_string(), "Unexpected EOF");
+//! ```
 
+pub mod compose;
+pub mod config;
+pub mod diff;
+pub mod explain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod ir;
+pub mod lint;
+mod pipeline;
 pub mod rewriter;
 pub mod schema;
 pub mod se_parser;
+mod timing;
 pub mod transformer;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use pipeline::{backend, generate, generate_with, Lang, SchemaSource, XsrcError};
+pub use rewriter::GenOptions;
+
+/// The version of xsrc: that of the crate, followed by `git describe` of the
+/// tree it was built from if that was a git checkout, e.g.
+/// `0.1.0 (v0.1.0-3-g1a2b3c4)`. Generated code and the IR carry the version
+/// of the crate alone, so that they don't change with every commit.
+pub fn version() -> &'static str {
+    env!("XSRC_VERSION")
+}
 
 #[cfg(test)]
 mod tests {
@@ -21,8 +63,35 @@ mod tests {
         let x2 = X { x: 2 };
         let xs = vec![x1, x2];
         for x in &xs {
-            let z = x;
+            let _z = x;
         }
         assert_eq!(xs[0], X { x: 1 })
     }
+
+    #[test]
+    fn test_version() {
+        let version = crate::version();
+        assert!(!version.is_empty());
+        assert!(version.starts_with(env!("CARGO_PKG_VERSION")));
+        let describe = &version[env!("CARGO_PKG_VERSION").len()..];
+        assert!(
+            describe.is_empty() || describe.starts_with(" ("),
+            "{}",
+            version
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        fn is_error<E: std::error::Error + Send + Sync + 'static>() {}
+        is_error::<crate::schema::ParserError>();
+        is_error::<crate::se_parser::ParserError>();
+        is_error::<crate::transformer::TransformerError>();
+        is_error::<crate::transformer::ContextLookupError>();
+        is_error::<crate::rewriter::GenError>();
+        is_error::<crate::config::ConfigError>();
+        is_error::<crate::explain::ExplainError>();
+        is_error::<crate::rewriter::GenOptionsError>();
+        is_error::<crate::XsrcError>();
+    }
 }
@@ -0,0 +1,384 @@
+//! Rules about the style and the likely mistakes of a schema, run over the
+//! transformed schema by `xsrc lint`
+use super::se_parser::Expr;
+use super::transformer::*;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Something a rule found at a path of the schema
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    /// The path of the APISet or API from the root, empty for the root
+    pub path: Vec<String>,
+    pub message: String,
+}
+
+/// The paths and the messages of what a rule found
+type Found = Result<Vec<(Vec<String>, String)>, ContextLookupError>;
+
+type Check = fn(&ContextBoundedRoot) -> Found;
+
+pub struct Rule {
+    /// The name that disables the rule, e.g. with `--allow`
+    pub name: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+    check: Check,
+}
+
+/// All the rules, in the order their findings are reported
+pub const RULES: &[Rule] = &[
+    Rule {
+        name: "empty-apiset",
+        severity: Severity::Warning,
+        description: "APISets with no APIs",
+        check: empty_apiset,
+    },
+    Rule {
+        name: "shadowed-param",
+        severity: Severity::Error,
+        description: "Query or body params named after a URL param of the same API",
+        check: shadowed_param,
+    },
+    Rule {
+        name: "unused-url-param",
+        severity: Severity::Warning,
+        description: "URL params of APISets that none of their APIs take",
+        check: unused_url_param,
+    },
+    Rule {
+        name: "get-with-data",
+        severity: Severity::Error,
+        description: "GET and HEAD APIs with $data, which most servers ignore",
+        check: get_with_data,
+    },
+    Rule {
+        name: "missing-description",
+        severity: Severity::Warning,
+        description: "APIs without $description",
+        check: missing_description,
+    },
+    Rule {
+        name: "non-kebab-url",
+        severity: Severity::Warning,
+        description: "URL path segments with uppercase letters or underscores",
+        check: non_kebab_url,
+    },
+];
+
+pub fn rule(name: &str) -> Option<&'static Rule> {
+    RULES.iter().find(|v| v.name == name)
+}
+
+/// Runs the rules that aren't in `allow`
+pub fn lint(
+    root: &ContextBoundedRoot,
+    allow: &[String],
+) -> Result<Vec<Finding>, ContextLookupError> {
+    let mut findings = Vec::new();
+    for rule in RULES
+        .iter()
+        .filter(|v| !allow.iter().any(|name| name == v.name))
+    {
+        for (path, message) in (rule.check)(root)? {
+            findings.push(Finding {
+                rule: rule.name,
+                severity: rule.severity,
+                path,
+                message,
+            });
+        }
+    }
+    Ok(findings)
+}
+
+/// The warnings of `ContextBoundedRoot::check` that `pick` picks
+fn warnings(root: &ContextBoundedRoot, pick: fn(&TransformerWarning) -> bool) -> Found {
+    Ok(root
+        .check()?
+        .into_iter()
+        .filter(pick)
+        .map(|w| (w.path().to_vec(), w.to_string()))
+        .collect())
+}
+
+fn empty_apiset(root: &ContextBoundedRoot) -> Found {
+    warnings(root, |w| {
+        matches!(w, TransformerWarning::EmptyAPISet { .. })
+    })
+}
+
+fn shadowed_param(root: &ContextBoundedRoot) -> Found {
+    warnings(root, |w| {
+        matches!(w, TransformerWarning::ShadowedParam { .. })
+    })
+}
+
+fn unused_url_param(root: &ContextBoundedRoot) -> Found {
+    let apis = root.iter_apis();
+    let mut findings = Vec::new();
+    for (path, apiset) in root.iter_apisets() {
+        let mut templates = Vec::new();
+        for (api_path, _) in apis.iter().filter(|(v, _)| v.starts_with(&path)) {
+            templates.push(root.url_template(api_path)?);
+        }
+        // Empty APISets have a rule of their own
+        if templates.is_empty() {
+            continue;
+        }
+        for name in apiset.bounded_vars().keys() {
+            let is_used = templates
+                .iter()
+                .any(|t| t.params().iter().any(|p| &p.name == name));
+            if !is_used {
+                findings.push((
+                    path.clone(),
+                    format!(
+                        "URL param \"{}\" of {} isn't taken by any of its APIs",
+                        name,
+                        path.join(".")
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(findings)
+}
+
+fn get_with_data(root: &ContextBoundedRoot) -> Found {
+    Ok(root
+        .iter_apis()
+        .into_iter()
+        .filter(|(_, api)| {
+            (api.method() == &HttpMethod::GET || api.method() == &HttpMethod::HEAD)
+                && !api.data().is_empty()
+        })
+        .map(|(path, api)| {
+            let message = format!("{} is a {:?} API with $data", path.join("."), api.method());
+            (path, message)
+        })
+        .collect())
+}
+
+fn missing_description(root: &ContextBoundedRoot) -> Found {
+    Ok(root
+        .iter_apis()
+        .into_iter()
+        .filter(|(_, api)| api.description().is_none())
+        .map(|(path, _)| {
+            let message = format!("{} has no $description", path.join("."));
+            (path, message)
+        })
+        .collect())
+}
+
+/// The literal text of a URL up to its query string or fragment
+fn url_literals(url: &ContextValue) -> Vec<String> {
+    fn walk(expr: &Expr, lits: &mut Vec<String>, in_query: &mut bool) {
+        match expr {
+            Expr::Concat(l, r) => {
+                walk(l, lits, in_query);
+                walk(r, lits, in_query);
+            }
+            Expr::Lit(s) if !*in_query => {
+                let end = s.find(&['?', '#'][..]).unwrap_or(s.len());
+                *in_query = end < s.len();
+                lits.push(s[..end].to_string());
+            }
+            _ => {}
+        }
+    }
+    let ContextValue::Expr(expr) = url;
+    let mut lits = Vec::new();
+    walk(expr, &mut lits, &mut false);
+    lits
+}
+
+fn non_kebab_url(root: &ContextBoundedRoot) -> Found {
+    let urls = root
+        .iter_apisets()
+        .into_iter()
+        .map(|(path, apiset)| (path, apiset.url()))
+        .chain(
+            root.iter_apis()
+                .into_iter()
+                .map(|(path, api)| (path, api.url())),
+        );
+    let mut findings = Vec::new();
+    for (path, url) in urls {
+        let segments = url_literals(url)
+            .iter()
+            .flat_map(|lit| {
+                lit.split('/')
+                    .map(|v| v.to_string())
+                    .collect::<Vec<String>>()
+            })
+            .filter(|seg| seg.chars().any(|ch| ch.is_ascii_uppercase() || ch == '_'))
+            .collect::<Vec<String>>();
+        for segment in segments {
+            let message = format!(
+                "URL of {} has \"{}\", which isn't kebab-case",
+                path.join("."),
+                segment
+            );
+            findings.push((path.clone(), message));
+        }
+    }
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+
+    fn lint_str(s: &str, rule: &str) -> Vec<String> {
+        let root = transform(parse_str(s).unwrap()).unwrap();
+        lint(&root, &[])
+            .unwrap()
+            .into_iter()
+            .filter(|v| v.rule == rule)
+            .map(|v| format!("{}: {}", v.path.join("."), v.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_sample_is_clean() {
+        let schema = parse_str(include_str!("../tests/fixtures/cases/sample/schema.yaml")).unwrap();
+        let root = transform(schema).unwrap();
+        let rules = lint(&root, &["missing-description".to_string()])
+            .unwrap()
+            .into_iter()
+            .map(|v| v.rule)
+            .collect::<Vec<&str>>();
+        assert_eq!(rules, Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_allow() {
+        let schema = parse_str("$url: \"http://ratina.org\"\nall: {}\n").unwrap();
+        let root = transform(schema).unwrap();
+        assert_eq!(lint(&root, &[]).unwrap().len(), 1);
+        assert_eq!(
+            lint(&root, &["missing-description".to_string()]).unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            rule("missing-description").unwrap().severity,
+            Severity::Warning
+        );
+        assert!(rule("cobol").is_none());
+    }
+
+    #[test]
+    fn test_transformer_warnings() {
+        let s = "\
+~ratincren:
+  $url: \"${!super}/ratincren/<id:int>\"
+  ~empty: {}
+  get:
+    $params:
+      id: \"int\"
+";
+        assert_eq!(
+            lint_str(s, "empty-apiset"),
+            vec!["ratincren.empty: APISet ratincren.empty has no APIs"]
+        );
+        assert_eq!(
+            lint_str(s, "shadowed-param"),
+            vec![
+                "ratincren.get: Param \"id\" of ratincren.get shadows the URL param of the same name"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_url_param() {
+        let s = "\
+$url: \"http://ratina.org\"
+~ratincren:
+  $url: \"${!super}/ratincren/<group:int>\"
+  get:
+    $url: \"${!super}/<name:string>\"
+  avatar:
+    $url: \"https://cdn.ratina.org/avatars\"
+~ahcros:
+  $url: \"${!super}/ahcros/<ahcroId:uuid>\"
+  avatar:
+    $url: \"https://cdn.ratina.org/avatars\"
+";
+        assert_eq!(
+            lint_str(s, "unused-url-param"),
+            vec!["ahcros: URL param \"ahcroId\" of ahcros isn't taken by any of its APIs"]
+        );
+    }
+
+    #[test]
+    fn test_get_with_data() {
+        let s = "\
+$url: \"http://ratina.org\"
+search:
+  $data:
+    q: \"string\"
+create.POST:
+  $data:
+    name: \"string\"
+";
+        assert_eq!(
+            lint_str(s, "get-with-data"),
+            vec!["search: search is a GET API with $data"]
+        );
+    }
+
+    #[test]
+    fn test_missing_description() {
+        let s = "\
+$url: \"http://ratina.org\"
+~ratincren:
+  all:
+    $description: \"All of them\"
+  get: {}
+";
+        assert_eq!(
+            lint_str(s, "missing-description"),
+            vec!["ratincren.get: ratincren.get has no $description"]
+        );
+    }
+
+    #[test]
+    fn test_non_kebab_url() {
+        let s = "\
+$url: \"http://ratina.org\"
+~userBudgets:
+  $url: \"${!super}/userBudgets/<budget_id:int>\"
+  all:
+    $url: \"${!super}/all_of_them?sort_by=<sortBy>\"
+  get: {}
+  toc:
+    $url: \"${!super}/toc#Table_of_contents\"
+";
+        assert_eq!(
+            lint_str(s, "non-kebab-url"),
+            vec![
+                "userBudgets: URL of userBudgets has \"userBudgets\", which isn't kebab-case",
+                "userBudgets.all: URL of userBudgets.all has \"all_of_them\", which isn't kebab-case",
+            ]
+        );
+    }
+}
@@ -0,0 +1,351 @@
+//! A schema in, the generated code out, in a single call. This is what the
+//! binary does for each schema, for crates embedding xsrc in a build script or
+//! a server.
+use super::compose;
+use super::rewriter::{self, Backend, GenOptions, GenOptionsError, GeneratedOutput, Registry};
+use super::schema::{self, ParserError, RootSchema};
+use super::transformer::{self, TransformerError};
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Where the YAML of a schema comes from
+pub enum SchemaSource<'a> {
+    Str(&'a str),
+    /// A schema file, or a directory of schema files composed into one
+    Path(&'a Path),
+    Reader(Box<dyn Read + 'a>),
+}
+
+impl<'a> SchemaSource<'a> {
+    pub fn parse(self) -> Result<RootSchema, XsrcError> {
+        Ok(match self {
+            SchemaSource::Str(s) => schema::parse_str(s)?,
+            SchemaSource::Path(p) if p.is_dir() => compose::parse_dir(p)?,
+            SchemaSource::Path(p) => schema::parse_file(p)?,
+            SchemaSource::Reader(r) => schema::parse_reader(r)?,
+        })
+    }
+}
+
+/// The built-in backends of [`Registry::default`]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Lang {
+    JavaScript,
+    Ruby,
+    Php,
+    Curl,
+    Postman,
+    Insomnia,
+    Http,
+    Markdown,
+    Angular,
+    K6,
+    NodeCli,
+    JsonSchema,
+}
+
+impl Lang {
+    pub const ALL: &'static [Lang] = &[
+        Lang::JavaScript,
+        Lang::Ruby,
+        Lang::Php,
+        Lang::Curl,
+        Lang::Postman,
+        Lang::Insomnia,
+        Lang::Http,
+        Lang::Markdown,
+        Lang::Angular,
+        Lang::K6,
+        Lang::NodeCli,
+        Lang::JsonSchema,
+    ];
+
+    /// The name of the backend in the registry, as taken by `--lang`
+    pub fn name(self) -> &'static str {
+        match self {
+            Lang::JavaScript => "javascript",
+            Lang::Ruby => "ruby",
+            Lang::Php => "php",
+            Lang::Curl => "curl",
+            Lang::Postman => "postman",
+            Lang::Insomnia => "insomnia",
+            Lang::Http => "http",
+            Lang::Markdown => "markdown",
+            Lang::Angular => "angular",
+            Lang::K6 => "k6",
+            Lang::NodeCli => "node-cli",
+            Lang::JsonSchema => "json-schema",
+        }
+    }
+
+    /// Whether the backend is compiled in, with the `backend-*` feature of
+    /// its name, e.g. `backend-node-cli`
+    pub fn is_compiled_in(self) -> bool {
+        match self {
+            Lang::JavaScript => cfg!(feature = "backend-javascript"),
+            Lang::Ruby => cfg!(feature = "backend-ruby"),
+            Lang::Php => cfg!(feature = "backend-php"),
+            Lang::Curl => cfg!(feature = "backend-curl"),
+            Lang::Postman => cfg!(feature = "backend-postman"),
+            Lang::Insomnia => cfg!(feature = "backend-insomnia"),
+            Lang::Http => cfg!(feature = "backend-http"),
+            Lang::Markdown => cfg!(feature = "backend-markdown"),
+            Lang::Angular => cfg!(feature = "backend-angular"),
+            Lang::K6 => cfg!(feature = "backend-k6"),
+            Lang::NodeCli => cfg!(feature = "backend-node-cli"),
+            Lang::JsonSchema => cfg!(feature = "backend-json-schema"),
+        }
+    }
+}
+
+impl fmt::Display for Lang {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Lang {
+    type Err = XsrcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Lang::ALL
+            .iter()
+            .find(|v| v.name() == s)
+            .cloned()
+            .ok_or_else(|| unsupported_language(s, &Registry::default()))
+    }
+}
+
+/// Any error of the stages of the generation
+#[derive(Debug)]
+pub enum XsrcError {
+    ParserError(ParserError),
+    TransformerError(TransformerError),
+    BackendError(rewriter::GenError),
+    InvalidOptions(GenOptionsError),
+    /// The language asked for, and the names of those in the registry
+    UnsupportedLanguage(String, Vec<String>),
+    /// A built-in language whose backend isn't compiled in
+    NotCompiledIn(Lang),
+    IOError(std::io::Error),
+}
+
+impl From<ParserError> for XsrcError {
+    fn from(e: ParserError) -> Self {
+        XsrcError::ParserError(e)
+    }
+}
+
+impl From<TransformerError> for XsrcError {
+    fn from(e: TransformerError) -> Self {
+        XsrcError::TransformerError(e)
+    }
+}
+
+impl From<rewriter::GenError> for XsrcError {
+    fn from(e: rewriter::GenError) -> Self {
+        XsrcError::BackendError(e)
+    }
+}
+
+impl From<GenOptionsError> for XsrcError {
+    fn from(e: GenOptionsError) -> Self {
+        XsrcError::InvalidOptions(e)
+    }
+}
+
+impl From<std::io::Error> for XsrcError {
+    fn from(e: std::io::Error) -> Self {
+        XsrcError::IOError(e)
+    }
+}
+
+impl fmt::Display for XsrcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XsrcError::ParserError(e) => write!(f, "Parser error: {}", e),
+            XsrcError::TransformerError(e) => write!(f, "Transformer error: {}", e),
+            XsrcError::BackendError(e) => write!(f, "Backend error: {}", e),
+            XsrcError::InvalidOptions(e) => write!(f, "Invalid options: {}", e),
+            XsrcError::UnsupportedLanguage(lang, available) => write!(
+                f,
+                "Unsupported language: {} (available: {}, see `xsrc langs`)",
+                lang,
+                available.join(", ")
+            ),
+            XsrcError::NotCompiledIn(lang) => write!(
+                f,
+                "Language not compiled in: {} (build xsrc with the backend-{} feature)",
+                lang, lang
+            ),
+            XsrcError::IOError(e) => write!(f, "IO error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for XsrcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            XsrcError::ParserError(e) => Some(e),
+            XsrcError::TransformerError(e) => Some(e),
+            XsrcError::BackendError(e) => Some(e),
+            XsrcError::InvalidOptions(e) => Some(e),
+            XsrcError::UnsupportedLanguage(..) | XsrcError::NotCompiledIn(_) => None,
+            XsrcError::IOError(e) => Some(e),
+        }
+    }
+}
+
+/// The error of a language missing from the registry: a built-in one that
+/// isn't compiled in, or one unknown to it
+fn unsupported_language(lang: &str, registry: &Registry) -> XsrcError {
+    let builtin = Lang::ALL.iter().find(|v| v.name() == lang);
+    if let Some(lang) = builtin.filter(|v| !v.is_compiled_in()) {
+        return XsrcError::NotCompiledIn(*lang);
+    }
+    XsrcError::UnsupportedLanguage(
+        lang.to_string(),
+        registry.names().iter().map(|v| v.to_string()).collect(),
+    )
+}
+
+/// The backend registered under `lang`
+pub fn backend<'a>(registry: &'a Registry, lang: &str) -> Result<&'a dyn Backend, XsrcError> {
+    registry
+        .get(lang)
+        .ok_or_else(|| unsupported_language(lang, registry))
+}
+
+/// Parses and transforms a schema, and generates its code in a built-in
+/// language.
+///
+/// The output is deterministic: it depends on the schema and the options
+/// alone, and generating them again gives the same bytes, with the APIs and
+/// APISets in the order of the schema. Generated clients can be committed
+/// without spurious diffs.
+///
+/// ```
+/// use xsrc::{generate, Lang, SchemaSource};
+///
+/// let schema = "$url: \"http://ratina.org\"\n$as: \"RatinaClient\"\nall: {}\n";
+/// let output = generate(SchemaSource::Str(schema), Lang::JavaScript, &Default::default())
+///     .unwrap();
+/// assert!(output.code.contains("export default class RatinaClient {"));
+/// ```
+pub fn generate(
+    source: SchemaSource,
+    lang: Lang,
+    opts: &GenOptions,
+) -> Result<GeneratedOutput, XsrcError> {
+    generate_with(&Registry::default(), source, lang.name(), opts)
+}
+
+/// Like [`generate`], with a backend of `registry`, which may be one defined
+/// outside xsrc. The options are validated first.
+pub fn generate_with(
+    registry: &Registry,
+    source: SchemaSource,
+    lang: &str,
+    opts: &GenOptions,
+) -> Result<GeneratedOutput, XsrcError> {
+    opts.validate()?;
+    let backend = backend(registry, lang)?;
+    let root = transformer::transform(source.parse()?)?;
+    Ok(rewriter::generate(backend, &root, opts)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_langs_are_registered() {
+        let registry = Registry::default();
+        assert_eq!(
+            Lang::ALL
+                .iter()
+                .filter(|v| v.is_compiled_in())
+                .map(|v| v.name())
+                .collect::<Vec<&str>>(),
+            registry.names()
+        );
+        assert_eq!("node-cli".parse::<Lang>().unwrap(), Lang::NodeCli);
+        assert_eq!(
+            "cobol".parse::<Lang>().err().unwrap().to_string(),
+            format!(
+                "Unsupported language: cobol (available: {}, see `xsrc langs`)",
+                registry.names().join(", ")
+            )
+        );
+    }
+
+    #[test]
+    fn test_langs_not_compiled_in() {
+        assert_eq!(
+            XsrcError::NotCompiledIn(Lang::Ruby).to_string(),
+            "Language not compiled in: ruby (build xsrc with the backend-ruby feature)"
+        );
+        for lang in Lang::ALL.iter().filter(|v| !v.is_compiled_in()) {
+            match generate(SchemaSource::Str("{}"), *lang, &Default::default()) {
+                Err(XsrcError::NotCompiledIn(v)) => assert_eq!(v, *lang),
+                v => panic!("Expected {} not compiled in, got {:?}", lang, v),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "backend-php")]
+    fn test_generate() {
+        let output = generate(
+            SchemaSource::Path(Path::new("tests/fixtures/cases/sample/schema.yaml")),
+            Lang::Php,
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(output.code, include_str!("../tests/fixtures/sample.php"));
+        let reader = include_str!("../tests/fixtures/cases/sample/schema.yaml").as_bytes();
+        let output = generate(
+            SchemaSource::Reader(Box::new(reader)),
+            Lang::Php,
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(output.code, include_str!("../tests/fixtures/sample.php"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "backend-ruby", feature = "backend-javascript"))]
+    fn test_generate_errors() {
+        let opts = Default::default();
+        match generate(SchemaSource::Str("$url: [\n"), Lang::Ruby, &opts) {
+            Err(XsrcError::ParserError(_)) => {}
+            v => panic!("Expected a parser error, got {:?}", v),
+        }
+        match generate(
+            SchemaSource::Str("get:\n  $url: \"<id\"\n"),
+            Lang::Ruby,
+            &opts,
+        ) {
+            Err(XsrcError::TransformerError(_)) => {}
+            v => panic!("Expected a transformer error, got {:?}", v),
+        }
+        match generate_with(&Registry::new(), SchemaSource::Str("{}"), "ruby", &opts) {
+            Err(XsrcError::UnsupportedLanguage(lang, available)) => {
+                assert_eq!(lang, "ruby");
+                assert!(available.is_empty());
+            }
+            v => panic!("Expected an unsupported language, got {:?}", v),
+        }
+        let opts = GenOptions {
+            annotate: true,
+            minify: true,
+            ..Default::default()
+        };
+        match generate(SchemaSource::Str("{}"), Lang::JavaScript, &opts) {
+            Err(XsrcError::InvalidOptions(GenOptionsError::Conflict("minify", "annotate"))) => {}
+            v => panic!("Expected invalid options, got {:?}", v),
+        }
+    }
+}
@@ -0,0 +1,487 @@
+use super::{
+    banner_lines, flat_names, jsdoc_tags, reject_optional_params, Backend, GenError, GenOptions,
+    GeneratedOutput,
+};
+use crate::transformer::*;
+use codegen::typescript::*;
+use codegen::utils::to_snake_case;
+
+const COMPACT: &str = "\
+function compact(values: { [key: string]: any }): { [key: string]: any } {
+    const result: { [key: string]: any } = {};
+    for (const key of Object.keys(values)) {
+        if (values[key] !== undefined) {
+            result[key] = values[key];
+        }
+    }
+    return result;
+}";
+
+/// The branded alias of `string` that `uuid` params take with
+/// `GenOptions::branded_types`
+const UUID: &str = "Uuid";
+
+/// An inline enum becomes a union of its values
+fn ts_type(p: &Param, opts: &GenOptions) -> String {
+    let typ = match p.param_type() {
+        Some(ParamType::Enum(values)) => {
+            return values
+                .iter()
+                .map(|v| Literal::String(v.to_string()).gen())
+                .collect::<Vec<String>>()
+                .join(" | ");
+        }
+        Some(ParamType::Named(typ)) => Some(typ),
+        None => None,
+    };
+    match typ.as_deref() {
+        Some("int") | Some("integer") | Some("number") | Some("float") | Some("double") => {
+            "number".to_string()
+        }
+        Some("bool") | Some("boolean") => "boolean".to_string(),
+        Some("uuid") if opts.branded_types => UUID.to_string(),
+        Some("string") | Some("uuid") => "string".to_string(),
+        _ => "any".to_string(),
+    }
+}
+
+fn ident(name: &str) -> Expr {
+    Expr::Ident(name.to_string())
+}
+
+fn this_member(name: &str) -> Expr {
+    Expr::Member {
+        base: Box::new(ident("this")),
+        name: name.to_string(),
+    }
+}
+
+fn string(s: &str) -> Expr {
+    Expr::Literal(Literal::String(s.to_string()))
+}
+
+fn param(name: &str, typ: &str, is_optional: bool) -> MethodParam {
+    MethodParam {
+        decorators: Vec::new(),
+        modifier: None,
+        name: name.to_string(),
+        typ: Some(typ.to_string()),
+        is_optional,
+    }
+}
+
+fn token_name(klsname: &str) -> String {
+    format!("{}_BASE_URL", to_snake_case(klsname).to_uppercase())
+}
+
+/// The union of the names of the methods of the service
+fn method_type_name(klsname: &str) -> String {
+    format!("{}Method", klsname)
+}
+
+/// The injection token for the root URL. It defaults to the schema's URL
+/// unless the URL has params, in which case it has to be provided.
+fn gen_token(root: &ContextBoundedRoot) -> Stmt {
+    let mut args = vec![string(&format!("{}.baseUrl", root.klsname()))];
+    let mut base_url = String::new();
+    let mut is_static = true;
+    for part in root.base_url().0 {
+        match part {
+            UrlPart::Lit(s) => base_url.push_str(&s),
+            UrlPart::Param(_) | UrlPart::BaseUrl => is_static = false,
+        }
+    }
+    if is_static {
+        args.push(Expr::Object(vec![
+            ("providedIn".to_string(), string("root")),
+            (
+                "factory".to_string(),
+                Expr::Arrow {
+                    params: Vec::new(),
+                    body: Box::new(string(&base_url)),
+                },
+            ),
+        ]));
+    }
+    Stmt::Const {
+        is_export: true,
+        ident: token_name(root.klsname()),
+        expr: Expr::New {
+            class: "InjectionToken".to_string(),
+            type_args: vec!["string".to_string()],
+            args,
+        },
+    }
+}
+
+fn gen_url(template: &UrlTemplate) -> Expr {
+    Expr::Template(
+        template
+            .0
+            .iter()
+            .map(|part| match part {
+                UrlPart::Lit(s) => TemplatePart::Lit(s.to_string()),
+                UrlPart::Param(p) => TemplatePart::Interp(Expr::Call {
+                    callee: Box::new(ident("encodeURIComponent")),
+                    type_args: Vec::new(),
+                    args: vec![Expr::Call {
+                        callee: Box::new(ident("String")),
+                        type_args: Vec::new(),
+                        args: vec![ident(&p.name)],
+                    }],
+                }),
+                UrlPart::BaseUrl => TemplatePart::Interp(this_member("baseUrl")),
+            })
+            .collect(),
+    )
+}
+
+fn gen_api(
+    root: &ContextBoundedRoot,
+    name: &str,
+    path: &[String],
+    api: &ContextBoundedAPI,
+    opts: &GenOptions,
+) -> Result<Method, ContextLookupError> {
+    let template = root.url_template(path)?;
+    let mut params = template
+        .params()
+        .into_iter()
+        .map(|p| param(&p.name, &ts_type(p, opts), false))
+        .collect::<Vec<MethodParam>>();
+    // Query and body params are optional, so they come after the path params
+    for p in api.params().values().chain(api.data().values()) {
+        if !params.iter().any(|v| v.name == p.name) {
+            params.push(param(&p.name, &ts_type(p, opts), true));
+        }
+    }
+    let mut options = Vec::new();
+    if !api.params().is_empty() {
+        let query = api
+            .params()
+            .keys()
+            .map(|k| (k.to_string(), ident(k)))
+            .collect();
+        options.push((
+            "params".to_string(),
+            Expr::Call {
+                callee: Box::new(ident("compact")),
+                type_args: Vec::new(),
+                args: vec![Expr::Object(query)],
+            },
+        ));
+    }
+    if !api.data().is_empty() {
+        let body = api
+            .data()
+            .keys()
+            .map(|k| (k.to_string(), ident(k)))
+            .collect();
+        options.push(("body".to_string(), Expr::Object(body)));
+    }
+    if !api.headers().is_empty() {
+        let headers = api
+            .headers()
+            .iter()
+            .map(|(k, v)| (k.to_string(), string(v)))
+            .collect();
+        options.push(("headers".to_string(), Expr::Object(headers)));
+    }
+    let mut args = vec![string(&format!("{:?}", api.method())), gen_url(&template)];
+    if !options.is_empty() {
+        args.push(Expr::Object(options));
+    }
+    Ok(Method {
+        doc: jsdoc_tags(api),
+        name: name.to_string(),
+        type_params: vec!["T = any".to_string()],
+        params,
+        ret: Some("Observable<T>".to_string()),
+        stmts: vec![Stmt::Return(Expr::Call {
+            callee: Box::new(Expr::Member {
+                base: Box::new(this_member("http")),
+                name: "request".to_string(),
+            }),
+            type_args: vec!["T".to_string()],
+            args,
+        })],
+    })
+}
+
+fn gen_constructor(root: &ContextBoundedRoot) -> Method {
+    Method {
+        doc: Vec::new(),
+        name: "constructor".to_string(),
+        type_params: Vec::new(),
+        params: vec![
+            MethodParam {
+                decorators: Vec::new(),
+                modifier: Some("private".to_string()),
+                name: "http".to_string(),
+                typ: Some("HttpClient".to_string()),
+                is_optional: false,
+            },
+            MethodParam {
+                decorators: vec![Decorator {
+                    name: "Inject".to_string(),
+                    args: vec![ident(&token_name(root.klsname()))],
+                }],
+                modifier: Some("private".to_string()),
+                name: "baseUrl".to_string(),
+                typ: Some("string".to_string()),
+                is_optional: false,
+            },
+        ],
+        ret: None,
+        stmts: Vec::new(),
+    }
+}
+
+/// Generates an injectable Angular service. APIs of nested APISets are
+/// flattened into methods of the service, prefixed by the APISet names, e.g.
+/// `usersBudgetsAll`, which fails if two of them come out the same.
+pub fn gen(root: &ContextBoundedRoot) -> Result<String, GenError> {
+    gen_with_options(root, &Default::default())
+}
+
+pub fn gen_with_options(root: &ContextBoundedRoot, opts: &GenOptions) -> Result<String, GenError> {
+    let apis = root.iter_apis();
+    let mut methods = vec![gen_constructor(root)];
+    for (name, (path, api)) in flat_names(&apis)?.iter().zip(&apis) {
+        methods.push(gen_api(root, name, path, api, opts)?);
+    }
+    let mut stmts = banner_lines(&root.klsname, opts.banner.as_deref())
+        .into_iter()
+        .map(Stmt::LineComment)
+        .collect::<Vec<Stmt>>();
+    stmts.extend(vec![
+        Stmt::Import {
+            names: vec![
+                "Inject".to_string(),
+                "Injectable".to_string(),
+                "InjectionToken".to_string(),
+            ],
+            from: "@angular/core".to_string(),
+        },
+        Stmt::Import {
+            names: vec!["HttpClient".to_string()],
+            from: "@angular/common/http".to_string(),
+        },
+        Stmt::Import {
+            names: vec!["Observable".to_string()],
+            from: "rxjs".to_string(),
+        },
+        gen_token(root),
+    ]);
+    if apis.iter().any(|(_, api)| !api.params().is_empty()) {
+        stmts.push(Stmt::Verbatim(COMPACT.to_string()));
+    }
+    let uses_uuid = methods
+        .iter()
+        .flat_map(|m| &m.params)
+        .any(|p| p.typ.as_deref() == Some(UUID));
+    if uses_uuid {
+        stmts.push(Stmt::Type {
+            is_export: true,
+            ident: UUID.to_string(),
+            typ: format!("string & {{ readonly __brand: '{}' }}", UUID),
+        });
+    }
+    // The names of the methods, for tooling that completes or checks them
+    let names = methods
+        .iter()
+        .filter(|m| m.name != "constructor")
+        .map(|m| Literal::String(m.name.to_string()).gen())
+        .collect::<Vec<String>>();
+    stmts.push(Stmt::Type {
+        is_export: true,
+        ident: method_type_name(root.klsname()),
+        typ: if names.is_empty() {
+            "never".to_string()
+        } else {
+            names.join(" | ")
+        },
+    });
+    stmts.push(Stmt::Class(Class {
+        decorators: vec![Decorator {
+            name: "Injectable".to_string(),
+            args: vec![Expr::Object(vec![(
+                "providedIn".to_string(),
+                string("root"),
+            )])],
+        }],
+        is_export: true,
+        ident: root.klsname().to_string(),
+        methods,
+    }));
+    Ok(Code { stmts }.gen())
+}
+
+pub struct Angular;
+
+impl Backend for Angular {
+    fn name(&self) -> &str {
+        "angular"
+    }
+
+    fn extension(&self) -> &str {
+        ".service.ts"
+    }
+
+    fn description(&self) -> &str {
+        "Angular service using HttpClient"
+    }
+
+    fn generate(
+        &self,
+        root: &ContextBoundedRoot,
+        opts: &GenOptions,
+    ) -> Result<GeneratedOutput, GenError> {
+        reject_optional_params(root, self.name())?;
+        Ok(GeneratedOutput {
+            code: gen_with_options(root, opts)?,
+            files: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+
+    #[test]
+    fn test_gen_sample() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert_eq!(code, include_str!("../../tests/fixtures/sample.angular.ts"));
+    }
+
+    #[test]
+    fn test_gen_parameterized_root() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org/<tenant:string>\"
+$as: \"RatinaClient\"
+~ratincren:
+  $url: \"${!super}/ratincren/<group:int>\"
+  get:
+    $url: \"${!super}/<name:string>\"
+    $params:
+      verbose: \"boolean|default:true\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert!(code.contains(
+            "export const RATINA_CLIENT_BASE_URL = new InjectionToken<string>('RatinaClient.baseUrl');"
+        ));
+        assert!(code.contains(
+            "ratincrenGet<T = any>(group: number, name: string, verbose?: boolean): Observable<T> {"
+        ));
+        assert!(code.contains(
+            "`${this.baseUrl}/ratincren/${encodeURIComponent(String(group))}/${encodeURIComponent(String(name))}`"
+        ));
+    }
+
+    #[test]
+    fn test_gen_branded_types() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+~ahcros:
+  $url: \"${!super}/ahcros/<ahcroId:uuid>\"
+  update:
+    $method: \"PUT\"
+    $params:
+      owner: \"uuid\"
+    $data:
+      name: \"string\"
+",
+        )
+        .unwrap();
+        let root = transform(schema).unwrap();
+        let code = gen(&root).unwrap();
+        assert!(!code.contains("Uuid"));
+        assert!(code.contains(
+            "ahcrosUpdate<T = any>(ahcroId: string, owner?: string, name?: string): Observable<T> {"
+        ));
+        let opts = GenOptions {
+            branded_types: true,
+            ..Default::default()
+        };
+        let code = gen_with_options(&root, &opts).unwrap();
+        assert!(code.contains("export type Uuid = string & { readonly __brand: 'Uuid' };"));
+        assert!(code.contains(
+            "ahcrosUpdate<T = any>(ahcroId: Uuid, owner?: Uuid, name?: string): Observable<T> {"
+        ));
+        // Only when there are uuid params
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen_with_options(&transform(schema).unwrap(), &opts).unwrap();
+        assert_eq!(code, include_str!("../../tests/fixtures/sample.angular.ts"));
+    }
+
+    #[test]
+    fn test_gen_enum_union() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+ahcros:
+  $url: \"${!super}/ahcros/<status:enum(open,closed)>\"
+  $params:
+    order: \"enum(asc,desc)|default:asc\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert!(code.contains(
+            "ahcros<T = any>(status: 'open' | 'closed', order?: 'asc' | 'desc'): Observable<T> {"
+        ));
+    }
+
+    #[test]
+    fn test_gen_method_union() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+$as: \"RatinaClient\"
+all: {}
+~ratincren:
+  $url: \"${!super}/ratincren\"
+  get: {}
+  ~budgets:
+    $url: \"${!super}/budgets\"
+    all: {}
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert!(code.contains(
+            "export type RatinaClientMethod = 'all' | 'ratincrenGet' | 'ratincrenBudgetsAll';"
+        ));
+    }
+
+    #[test]
+    fn test_gen_name_clash() {
+        let schema = parse_str(include_str!("../../tests/fixtures/name_clash.yaml")).unwrap();
+        match gen(&transform(schema).unwrap()) {
+            Err(GenError::NameClash { name, paths }) => {
+                assert_eq!(name, "ahcroAll");
+                assert_eq!(
+                    paths,
+                    (
+                        vec!["ahcroAll".to_string()],
+                        vec!["ahcro".to_string(), "all".to_string()]
+                    )
+                );
+            }
+            v => panic!("Expected a name clash, got {:?}", v),
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use super::{
+    banner_lines, default_base_url, path_ident, reject_optional_params, Backend, GenError,
+    GenOptions, GeneratedOutput, UrlSplit,
+};
+use crate::transformer::*;
+use linked_hash_map::LinkedHashMap;
+
+const INDENT: &str = "    ";
+
+/// Percent-encodes its argument byte by byte. Relies on `printf` treating a
+/// leading quote as a request for the character code, which POSIX mandates.
+const URLENCODE_FN: &str = "\
+xsrc_urlencode() (
+    LC_ALL=C
+    xsrc_s=\"$1\"
+    while [ -n \"$xsrc_s\" ]; do
+        xsrc_rest=\"${xsrc_s#?}\"
+        xsrc_c=\"${xsrc_s%\"$xsrc_rest\"}\"
+        xsrc_s=\"$xsrc_rest\"
+        case \"$xsrc_c\" in
+            [a-zA-Z0-9.~_-]) printf '%s' \"$xsrc_c\" ;;
+            *) printf '%%%02X' \"'$xsrc_c\" ;;
+        esac
+    done
+)";
+
+/// Escapes a string so that it can be embedded in a double-quoted word.
+fn escape_double_quoted(s: &str) -> String {
+    let mut escaped = String::new();
+    for ch in s.chars() {
+        match ch {
+            '"' | '\\' | '$' | '`' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Quotes a string as a single shell word.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace("'", "'\\''"))
+}
+
+fn encoded_arg(pos: usize) -> String {
+    format!("$(xsrc_urlencode \"${{{}}}\")", pos)
+}
+
+fn gen_base_url(root: &ContextBoundedRoot) -> String {
+    match default_base_url(root) {
+        Some(s) => format!("BASE_URL=\"${{BASE_URL:-{}}}\"", escape_double_quoted(&s)),
+        None => ": \"${BASE_URL:?BASE_URL must be set}\"".to_string(),
+    }
+}
+
+/// Appends `key=value` pairs for the non-empty arguments to `var`.
+fn gen_pairs(var: &str, params: &LinkedHashMap<String, Param>, first_pos: usize) -> Vec<String> {
+    params
+        .keys()
+        .enumerate()
+        .map(|(idx, key)| {
+            let pos = first_pos + idx;
+            format!(
+                "if [ -n \"${{{pos}}}\" ]; then {var}=\"${{{var}}}&{key}={value}\"; fi",
+                pos = pos,
+                var = var,
+                key = escape_double_quoted(key),
+                value = encoded_arg(pos)
+            )
+        })
+        .collect()
+}
+
+fn gen_api(
+    root: &ContextBoundedRoot,
+    path: &[String],
+    api: &ContextBoundedAPI,
+) -> Result<String, ContextLookupError> {
+    let split = UrlSplit::new(&root.url_template(path)?);
+    let mut usage = Vec::new();
+    let mut pos = 0;
+    let mut render = |parts: &[UrlPart]| {
+        let mut url = String::new();
+        for part in parts {
+            match part {
+                UrlPart::Lit(s) => url.push_str(&escape_double_quoted(s)),
+                UrlPart::Param(p) => {
+                    pos += 1;
+                    url.push_str(&encoded_arg(pos));
+                    usage.push(p.name.to_uppercase());
+                }
+                UrlPart::BaseUrl => url.push_str("${BASE_URL}"),
+            }
+        }
+        url
+    };
+    let mut url = render(&split.path);
+    if let Some(query) = &split.query {
+        url.push_str(&format!("?{}", render(query)));
+    }
+    let fragment = split.fragment.as_deref().map(&mut render);
+    let name = path_ident(path);
+    let mut stmts = Vec::new();
+    if pos > 0 {
+        stmts.push(format!(
+            "if [ $# -lt {} ]; then echo {} >&2; return 2; fi",
+            pos,
+            quote(&format!("usage: {} {}", name, usage.join(" ")))
+        ));
+    }
+    stmts.push(format!("xsrc_url=\"{}\"", url));
+    if !api.params.is_empty() {
+        stmts.push("xsrc_query=\"\"".to_string());
+        stmts.extend(gen_pairs("xsrc_query", &api.params, pos + 1));
+        // The URL may have a query already, if only from BASE_URL
+        stmts.push(
+            "if [ -n \"$xsrc_query\" ]; then case \"$xsrc_url\" in \
+             *\\?*) xsrc_url=\"${xsrc_url}&${xsrc_query#&}\" ;; \
+             *) xsrc_url=\"${xsrc_url}?${xsrc_query#&}\" ;; esac; fi"
+                .to_string(),
+        );
+        pos += api.params.len();
+    }
+    // After the query, which would be taken for part of it otherwise
+    if let Some(fragment) = fragment {
+        stmts.push(format!("xsrc_url=\"${{xsrc_url}}#{}\"", fragment));
+    }
+    let mut curl = format!("curl -sS -X {:?}", api.method);
+    for (name, value) in &api.headers {
+        curl.push_str(" -H ");
+        curl.push_str(&quote(&format!("{}: {}", name, value)));
+    }
+    if !api.data.is_empty() {
+        stmts.push("xsrc_data=\"\"".to_string());
+        stmts.extend(gen_pairs("xsrc_data", &api.data, pos + 1));
+        curl.push_str(" --data \"${xsrc_data#&}\"");
+    }
+    curl.push_str(" \"$xsrc_url\"");
+    stmts.push(curl);
+    let optional = api
+        .params
+        .keys()
+        .chain(api.data.keys())
+        .map(|k| format!("[{}]", k.to_uppercase()));
+    usage.extend(optional);
+    Ok(format!(
+        "\
+# {usage}
+{name}() {{
+{body}
+}}",
+        usage = [vec![path.join(".")], usage].concat().join(" "),
+        name = name,
+        body = stmts
+            .iter()
+            .map(|v| format!("{}{}", INDENT, v))
+            .collect::<Vec<String>>()
+            .join("\n")
+    ))
+}
+
+/// Generates a POSIX sh script with one curl function per API. Params are
+/// taken positionally: path params first, then query and body params, which
+/// are omitted when empty. Running the script with arguments invokes the
+/// function named by the first one.
+pub fn gen(root: &ContextBoundedRoot) -> Result<String, ContextLookupError> {
+    gen_with_options(root, &Default::default())
+}
+
+pub fn gen_with_options(
+    root: &ContextBoundedRoot,
+    opts: &GenOptions,
+) -> Result<String, ContextLookupError> {
+    let banner = banner_lines(&root.klsname, opts.banner.as_deref())
+        .iter()
+        .map(|v| format!("# {}", v))
+        .collect::<Vec<String>>();
+    let mut sections = vec![
+        format!("#!/bin/sh\n{}", banner.join("\n")),
+        gen_base_url(root),
+        URLENCODE_FN.to_string(),
+    ];
+    for (path, api) in root.iter_apis() {
+        sections.push(gen_api(root, &path, api)?);
+    }
+    sections.push("if [ $# -gt 0 ]; then\n    \"$@\"\nfi".to_string());
+    Ok(format!("{}\n", sections.join("\n\n")))
+}
+
+pub struct Curl;
+
+impl Backend for Curl {
+    fn name(&self) -> &str {
+        "curl"
+    }
+
+    fn extension(&self) -> &str {
+        ".sh"
+    }
+
+    fn description(&self) -> &str {
+        "Shell script of curl commands"
+    }
+
+    fn generate(
+        &self,
+        root: &ContextBoundedRoot,
+        opts: &GenOptions,
+    ) -> Result<GeneratedOutput, GenError> {
+        reject_optional_params(root, self.name())?;
+        Ok(GeneratedOutput {
+            code: gen_with_options(root, opts)?,
+            files: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+    use std::process::Command;
+
+    #[test]
+    fn test_gen_sample() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert_eq!(code, include_str!("../../tests/fixtures/sample.sh"));
+    }
+
+    #[test]
+    fn test_gen_headers() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/headers/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert!(code.contains(
+            "curl -sS -X GET -H 'Authorization: Bearer xiaosi' -H 'Accept: image/png' \"$xsrc_url\""
+        ));
+        assert!(code.contains("curl -sS -X GET \"$xsrc_url\""));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape_double_quoted("a\"$`\\b"), "a\\\"\\$\\`\\\\b");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_quoting_in_sh() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org/`pwd`\"
+getAhcro:
+  $url: \"${!super}/ahcro/<ahcroId:string>\"
+  $method: \"POST\"
+  $params:
+    withDetail: \"boolean\"
+  $data:
+    name: \"string\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        // Print the arguments curl would have received instead of running it
+        let script = format!(
+            "curl() {{ for a in \"$@\"; do printf '<%s>\\n' \"$a\"; done; }}\n{}",
+            code
+        );
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .arg("xsrc")
+            .arg("getAhcro")
+            .arg("a b/'\"$(id)`")
+            .arg("")
+            .arg("x&y=z")
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "\
+<-sS>
+<-X>
+<POST>
+<--data>
+<name=x%26y%3Dz>
+<http://ratina.org/`pwd`/ahcro/a%20b%2F%27%22%24%28id%29%60>
+"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_query_in_sh() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+search:
+  $url: \"${!super}/search?lang=<lang:string>#results\"
+  $params:
+    q: \"string\"
+list:
+  $params:
+    page: \"int\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        let run = |base_url: &str, args: &[&str]| {
+            let script = format!("curl() {{ printf '<%s>\\n' \"$4\"; }}\n{}", code);
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(script)
+                .arg("xsrc")
+                .args(args)
+                .env("BASE_URL", base_url)
+                .output()
+                .unwrap();
+            String::from_utf8(output.stdout).unwrap()
+        };
+        // The params join the query of the URL, before its fragment
+        assert_eq!(
+            run("http://ratina.org", &["search", "zh", "a b"]),
+            "<http://ratina.org/search?lang=zh&q=a%20b#results>\n"
+        );
+        assert_eq!(
+            run("http://ratina.org", &["search", "zh", ""]),
+            "<http://ratina.org/search?lang=zh#results>\n"
+        );
+        // Or that of BASE_URL
+        assert_eq!(
+            run("http://ratina.org/?key=k", &["list", "2"]),
+            "<http://ratina.org/?key=k&page=2>\n"
+        );
+    }
+}
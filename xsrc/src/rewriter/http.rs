@@ -0,0 +1,182 @@
+use super::{
+    banner_lines, example_body, reject_optional_params, Backend, GenError, GenOptions,
+    GeneratedOutput, UrlSplit,
+};
+use crate::transformer::*;
+
+const BASE_URL_VAR: &str = "baseUrl";
+
+fn placeholder(name: &str) -> String {
+    format!("{{{{{}}}}}", name)
+}
+
+/// File variables: the root URL, preceded by the params it is built from
+fn gen_variables(root: &ContextBoundedRoot) -> String {
+    let mut base_url = String::new();
+    let mut lines = Vec::new();
+    for part in root.base_url().0 {
+        match part {
+            UrlPart::Lit(s) => base_url.push_str(&s),
+            UrlPart::Param(p) => {
+                base_url.push_str(&placeholder(&p.name));
+                lines.push(format!("@{} =", p.name));
+            }
+            UrlPart::BaseUrl => {}
+        }
+    }
+    lines.push(format!("@{} = {}", BASE_URL_VAR, base_url));
+    lines.join("\n")
+}
+
+fn gen_request(
+    root: &ContextBoundedRoot,
+    path: &[String],
+    api: &ContextBoundedAPI,
+) -> Result<String, ContextLookupError> {
+    let query = api
+        .params
+        .keys()
+        .map(|k| format!("{}={}", k, placeholder(k)))
+        .collect::<Vec<String>>()
+        .join("&");
+    let url = UrlSplit::new(&root.url_template(path)?).render(
+        |part| match part {
+            UrlPart::Lit(s) => s.to_string(),
+            UrlPart::Param(p) => placeholder(&p.name),
+            UrlPart::BaseUrl => placeholder(BASE_URL_VAR),
+        },
+        Some(query.as_str()).filter(|v| !v.is_empty()),
+    );
+    let mut lines = vec![
+        format!("### {}", path.join(".")),
+        format!("{:?} {}", api.method, url),
+    ];
+    for (name, value) in &api.headers {
+        lines.push(format!("{}: {}", name, value));
+    }
+    if !api.data.is_empty() {
+        if !api
+            .headers
+            .keys()
+            .any(|k| k.eq_ignore_ascii_case("content-type"))
+        {
+            lines.push("Content-Type: application/json".to_string());
+        }
+        lines.push(String::new());
+        lines.push(example_body(&api.data));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Generates a `.http` file for the REST clients of VS Code and JetBrains
+/// IDEs, with one request per API.
+pub fn gen(root: &ContextBoundedRoot) -> Result<String, ContextLookupError> {
+    gen_with_options(root, &Default::default())
+}
+
+pub fn gen_with_options(
+    root: &ContextBoundedRoot,
+    opts: &GenOptions,
+) -> Result<String, ContextLookupError> {
+    let banner = banner_lines(&root.klsname, opts.banner.as_deref())
+        .iter()
+        .map(|v| format!("# {}", v))
+        .collect::<Vec<String>>();
+    let mut sections = vec![banner.join("\n"), gen_variables(root)];
+    for (path, api) in root.iter_apis() {
+        sections.push(gen_request(root, &path, api)?);
+    }
+    Ok(format!("{}\n", sections.join("\n\n")))
+}
+
+pub struct Http;
+
+impl Backend for Http {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    fn extension(&self) -> &str {
+        ".http"
+    }
+
+    fn description(&self) -> &str {
+        "HTTP requests for VS Code REST Client and JetBrains HTTP Client"
+    }
+
+    fn generate(
+        &self,
+        root: &ContextBoundedRoot,
+        opts: &GenOptions,
+    ) -> Result<GeneratedOutput, GenError> {
+        reject_optional_params(root, self.name())?;
+        Ok(GeneratedOutput {
+            code: gen_with_options(root, opts)?,
+            files: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+
+    #[test]
+    fn test_gen_sample() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert_eq!(code, include_str!("../../tests/fixtures/sample.http"));
+    }
+
+    #[test]
+    fn test_gen_nested() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org/<tenant:string>\"
+~ratincren:
+  $url: \"${!super}/ratincren/<group:int>\"
+  ~members:
+    $url: \"${!super}/members\"
+    get:
+      $url: \"${!super.!super}/member/<name:string>\"
+      $headers:
+        Authorization: \"Bearer {{token}}\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert_eq!(
+            code,
+            "\
+# XSClient generated by xsrc 0.1.0
+
+@tenant =
+@baseUrl = http://ratina.org/{{tenant}}
+
+### ratincren.members.get
+GET {{baseUrl}}/ratincren/{{group}}/member/{{name}}
+Authorization: Bearer {{token}}
+"
+        );
+    }
+
+    #[test]
+    fn test_gen_url_with_query() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+search:
+  $url: \"${!super}/search?lang=<lang:string>#results\"
+  $params:
+    q: \"string\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert!(code.contains("GET {{baseUrl}}/search?lang={{lang}}&q={{q}}#results\n"));
+    }
+}
@@ -0,0 +1,316 @@
+use super::{
+    banner, example_body, reject_optional_params, Backend, GenError, GenOptions, GeneratedOutput,
+};
+use crate::transformer::*;
+use linked_hash_map::LinkedHashMap;
+use serde_derive::Serialize;
+
+const BASE_URL_VAR: &str = "base_url";
+const WORKSPACE_ID: &str = "wrk_xsrc";
+const ENVIRONMENT_ID: &str = "env_xsrc";
+
+#[derive(Debug, Serialize)]
+struct Export {
+    #[serde(rename = "_type")]
+    typ: String,
+    __export_format: u32,
+    __export_source: String,
+    resources: Vec<Resource>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "_type", rename_all = "snake_case")]
+enum Resource {
+    Workspace {
+        #[serde(rename = "_id")]
+        id: String,
+        #[serde(rename = "parentId")]
+        parent_id: Option<String>,
+        name: String,
+        scope: String,
+    },
+    Environment {
+        #[serde(rename = "_id")]
+        id: String,
+        #[serde(rename = "parentId")]
+        parent_id: String,
+        name: String,
+        data: LinkedHashMap<String, String>,
+    },
+    RequestGroup {
+        #[serde(rename = "_id")]
+        id: String,
+        #[serde(rename = "parentId")]
+        parent_id: String,
+        name: String,
+        #[serde(rename = "metaSortKey")]
+        meta_sort_key: usize,
+    },
+    Request {
+        #[serde(rename = "_id")]
+        id: String,
+        #[serde(rename = "parentId")]
+        parent_id: String,
+        name: String,
+        method: String,
+        url: String,
+        body: Body,
+        parameters: Vec<Pair>,
+        headers: Vec<Pair>,
+        #[serde(rename = "metaSortKey")]
+        meta_sort_key: usize,
+    },
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Body {
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Pair {
+    name: String,
+    value: String,
+}
+
+/// Refers to an environment variable with a template tag
+fn template_tag(name: &str) -> String {
+    format!("{{{{ _.{} }}}}", name)
+}
+
+struct Exporter<'a> {
+    root: &'a ContextBoundedRoot,
+    resources: Vec<Resource>,
+    env: LinkedHashMap<String, String>,
+}
+
+impl<'a> Exporter<'a> {
+    fn gen_url(&mut self, template: &UrlTemplate) -> String {
+        let mut url = String::new();
+        for part in &template.0 {
+            match part {
+                UrlPart::Lit(s) => url.push_str(s),
+                UrlPart::Param(p) => {
+                    url.push_str(&template_tag(&p.name));
+                    if !self.env.contains_key(&p.name) {
+                        self.env.insert(p.name.to_string(), String::new());
+                    }
+                }
+                UrlPart::BaseUrl => url.push_str(&template_tag(BASE_URL_VAR)),
+            }
+        }
+        url
+    }
+
+    fn gen_request(
+        &mut self,
+        path: &[String],
+        api: &ContextBoundedAPI,
+        parent_id: &str,
+        meta_sort_key: usize,
+    ) -> Result<Resource, ContextLookupError> {
+        let template = self.root.url_template(path)?;
+        let mut headers = api
+            .headers
+            .iter()
+            .map(|(k, v)| Pair {
+                name: k.to_string(),
+                value: v.to_string(),
+            })
+            .collect::<Vec<Pair>>();
+        let body = if !api.data.is_empty() {
+            let has_content_type = api
+                .headers
+                .keys()
+                .any(|k| k.eq_ignore_ascii_case("content-type"));
+            if !has_content_type {
+                headers.push(Pair {
+                    name: "Content-Type".to_string(),
+                    value: "application/json".to_string(),
+                });
+            }
+            Body {
+                mime_type: Some("application/json".to_string()),
+                text: Some(example_body(&api.data)),
+            }
+        } else {
+            Body::default()
+        };
+        Ok(Resource::Request {
+            id: format!("req_{}", path.join(".")),
+            parent_id: parent_id.to_string(),
+            name: api.name.to_string(),
+            method: format!("{:?}", api.method),
+            url: self.gen_url(&template),
+            body,
+            parameters: api
+                .params
+                .keys()
+                .map(|k| Pair {
+                    name: k.to_string(),
+                    value: String::new(),
+                })
+                .collect(),
+            headers,
+            meta_sort_key,
+        })
+    }
+
+    fn gen_resources(
+        &mut self,
+        apisets: &LinkedHashMap<String, ContextBoundedAPIData>,
+        path: &mut Vec<String>,
+        parent_id: &str,
+    ) -> Result<(), ContextLookupError> {
+        for (idx, (name, child)) in apisets.iter().enumerate() {
+            path.push(name.to_string());
+            match child {
+                ContextBoundedAPIData::API(api) => {
+                    let request = self.gen_request(path, api, parent_id, idx)?;
+                    self.resources.push(request);
+                }
+                ContextBoundedAPIData::APISet(apiset) => {
+                    let id = format!("fld_{}", path.join("."));
+                    self.resources.push(Resource::RequestGroup {
+                        id: id.to_string(),
+                        parent_id: parent_id.to_string(),
+                        name: name.to_string(),
+                        meta_sort_key: idx,
+                    });
+                    self.gen_resources(&apiset.apisets, path, &id)?;
+                }
+            }
+            path.pop();
+        }
+        Ok(())
+    }
+}
+
+/// Exports the APIs as an Insomnia v4 export, with one request group per
+/// APISet. The root URL and path params become variables of the base
+/// environment.
+pub fn gen(root: &ContextBoundedRoot) -> Result<String, ContextLookupError> {
+    let mut exporter = Exporter {
+        root,
+        resources: Vec::new(),
+        env: LinkedHashMap::new(),
+    };
+    let mut base_url = String::new();
+    let mut root_params = Vec::new();
+    for part in root.base_url().0 {
+        match part {
+            UrlPart::Lit(s) => base_url.push_str(&s),
+            UrlPart::Param(p) => {
+                base_url.push_str(&template_tag(&p.name));
+                root_params.push(p.name);
+            }
+            UrlPart::BaseUrl => {}
+        }
+    }
+    exporter.env.insert(BASE_URL_VAR.to_string(), base_url);
+    for name in root_params {
+        exporter.env.insert(name, String::new());
+    }
+    exporter.gen_resources(&root.apisets, &mut Vec::new(), WORKSPACE_ID)?;
+    let mut resources = vec![
+        Resource::Workspace {
+            id: WORKSPACE_ID.to_string(),
+            parent_id: None,
+            name: root.klsname.to_string(),
+            scope: "collection".to_string(),
+        },
+        Resource::Environment {
+            id: ENVIRONMENT_ID.to_string(),
+            parent_id: WORKSPACE_ID.to_string(),
+            name: "Base Environment".to_string(),
+            data: exporter.env,
+        },
+    ];
+    resources.extend(exporter.resources);
+    let export = Export {
+        typ: "export".to_string(),
+        __export_format: 4,
+        __export_source: banner(&root.klsname),
+        resources,
+    };
+    Ok(format!(
+        "{}\n",
+        serde_json::to_string_pretty(&export).unwrap()
+    ))
+}
+
+pub struct Insomnia;
+
+impl Backend for Insomnia {
+    fn name(&self) -> &str {
+        "insomnia"
+    }
+
+    fn extension(&self) -> &str {
+        ".insomnia.json"
+    }
+
+    fn description(&self) -> &str {
+        "Insomnia export (format 4)"
+    }
+
+    fn generate(
+        &self,
+        root: &ContextBoundedRoot,
+        _opts: &GenOptions,
+    ) -> Result<GeneratedOutput, GenError> {
+        reject_optional_params(root, self.name())?;
+        Ok(GeneratedOutput {
+            code: gen(root)?,
+            files: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+
+    #[test]
+    fn test_gen_sample() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert_eq!(
+            code,
+            include_str!("../../tests/fixtures/sample.insomnia.json")
+        );
+    }
+
+    #[test]
+    fn test_gen_template_tags() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org/<tenant:string>\"
+getAhcro:
+  $url: \"${!super}/ahcro/<ahcroId:int>\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        let export: serde_json::Value = serde_json::from_str(&code).unwrap();
+        assert_eq!(
+            export["resources"][1]["data"],
+            serde_json::json!({
+                "base_url": "http://ratina.org/{{ _.tenant }}",
+                "tenant": "",
+                "ahcroId": ""
+            })
+        );
+        assert_eq!(
+            export["resources"][2]["url"],
+            "{{ _.base_url }}/ahcro/{{ _.ahcroId }}"
+        );
+    }
+}
@@ -1,95 +1,496 @@
+use super::{
+    banner_lines, flat_names, jsdoc_tags, Backend, GenError, GenOptions, GeneratedFile,
+    GeneratedOutput, ParamCase, QueryEncoding,
+};
+pub use super::{ClientStyle, ReturnStyle};
 use crate::se_parser as sp;
 use crate::transformer::*;
 use codegen::javascript::*;
+use codegen::utils::{is_ident, to_pascal_case, Left};
 use linked_hash_map::LinkedHashMap;
-use std::collections::HashMap;
+use log::debug;
+use std::fmt;
+use std::io;
 use std::iter::FromIterator;
+use std::path::PathBuf;
 use utils::linked_hashmap;
 
-fn gen_ref(ms: &[sp::Member]) -> Expr {
-    let mut expr = Expr::Var("this".to_string());
-    for m in ms {
-        match m {
-            sp::Member::Super => {
-                expr = Expr::Member {
-                    base: box expr,
-                    member: Ident("_super".to_string()),
-                }
-            }
-            sp::Member::Member(m) => {
-                expr = Expr::Member {
-                    base: box expr,
-                    member: Ident(m.to_string()),
-                }
-            }
-        }
+/// Options controlling the shape of the generated JavaScript client
+#[derive(Debug, Default)]
+pub struct JsGenOptions {
+    /// Configure a keep-alive `http.Agent` on a per-client axios instance
+    /// (Node only)
+    pub node_keep_alive: bool,
+    /// Casing of the identifiers generated for params. URL and query keys
+    /// keep the names from the schema.
+    pub param_case: ParamCase,
+    /// Append an optional `signal` param to every method, passed on to axios
+    /// so that requests can be aborted with an `AbortController`
+    pub cancellation: bool,
+    /// Encoding of arrays in query params. Anything but `None` emits an
+    /// inline `paramsSerializer`.
+    pub query_encoding: QueryEncoding,
+    /// Start each method with a comment naming the dotted path of its API in
+    /// the schema
+    pub annotate: bool,
+    pub return_style: ReturnStyle,
+    /// Also set `module.exports` to the root class, for CommonJS code that
+    /// `require`s the client
+    pub commonjs_interop: bool,
+    /// Leave out the import of axios. The root class takes the HTTP client as
+    /// its first constructor param instead, for environments that provide
+    /// axios globally or inject it.
+    pub no_import: bool,
+    pub style: ClientStyle,
+    /// A comment above the banner of every file, see [`GenOptions::banner`]
+    pub banner: Option<String>,
+}
+
+/// Renders a reference relative to `base`, which stands for the parent of the
+/// context the reference was declared in: the instance itself in the methods
+/// of the APIs, and its `_super` in the constructor of an APISet.
+fn gen_ref(ms: &[sp::Member], base: Expr) -> Expr {
+    let mut ms = ms.iter().peekable();
+    // The first `!super` is the base itself
+    if ms.peek() == Some(&&sp::Member::Super) {
+        ms.next();
     }
-    expr
+    ms.fold(base, |expr, m| Expr::Member {
+        base: Box::new(expr),
+        member: Ident(match m {
+            sp::Member::Super => "_super".to_string(),
+            sp::Member::Member(m) => m.to_string(),
+        }),
+    })
 }
 
-fn gen_context_value(v: &ContextValue) -> Expr {
-    fn folder(expr: &sp::Expr) -> Expr {
+fn this() -> Expr {
+    Expr::Var("this".to_string())
+}
+
+fn this_super() -> Expr {
+    Expr::Member {
+        base: Box::new(this()),
+        member: Ident("_super".to_string()),
+    }
+}
+
+fn gen_context_value(v: &ContextValue, base: &dyn Fn() -> Expr, opts: &JsGenOptions) -> Expr {
+    fn folder(expr: &sp::Expr, base: &dyn Fn() -> Expr, opts: &JsGenOptions) -> Expr {
         match expr {
             sp::Expr::Lit(s) => Expr::Literal(Literal::String(s.to_string())),
-            sp::Expr::Ref(r) => gen_ref(r),
-            sp::Expr::Var(s) => Expr::Var(s.to_string()),
+            sp::Expr::Ref(r) => gen_ref(r, base()),
+            sp::Expr::Var(s) => Expr::Var(param_ident(s, opts)),
             sp::Expr::Concat(l, r) => Expr::Arith {
                 op: ArithOp("+".to_string()),
-                l: box folder(l),
-                r: box folder(r),
+                l: Box::new(folder(l, base, opts)),
+                r: Box::new(folder(r, base, opts)),
             },
         }
     }
     match v {
-        ContextValue::Expr(expr) => folder(expr),
+        ContextValue::Expr(expr) => folder(expr, base, opts),
     }
 }
 
-fn root_constructor(root: &ContextBoundedRoot) -> Option<Constructor> {
-    if root.bounded_vars.len() > 0 {
-        let stmts = root
-            .bounded_vars
-            .iter()
-            .map(|(_, p)| {
-                Stmt::Assign(Assign {
-                    typ: None,
-                    assignee: Expr::Member {
-                        base: box Expr::Var("this".to_string()),
-                        member: Ident(format!("_{}", p.name.clone())),
-                    },
-                    expr: Expr::Var(p.name.clone()),
-                })
+/// Throws if a param of an inline enum type gets a value outside the enum.
+/// `optional` params, those of the query and the body, are only checked when
+/// they aren't `undefined`.
+fn gen_enum_guards<'a>(
+    params: impl Iterator<Item = &'a Param>,
+    optional: bool,
+    opts: &JsGenOptions,
+) -> Vec<Stmt> {
+    params
+        .filter_map(|p| match p.param_type() {
+            Some(ParamType::Enum(values)) => Some((p, values)),
+            _ => None,
+        })
+        .map(|(p, values)| {
+            let ident = param_ident(&p.name, opts);
+            let allowed = Expr::Array(
+                values
+                    .iter()
+                    .map(|v| Expr::Literal(Literal::String(v.to_string())))
+                    .collect(),
+            );
+            let mut cond = Expr::Not(Box::new(Expr::FuncCall {
+                func: Box::new(Expr::Member {
+                    base: Box::new(allowed),
+                    member: Ident("includes".to_string()),
+                }),
+                args: vec![Expr::Var(ident.to_string())],
+            }));
+            if optional {
+                cond = Expr::Arith {
+                    op: ArithOp("&&".to_string()),
+                    l: Box::new(Expr::Arith {
+                        op: ArithOp("!==".to_string()),
+                        l: Box::new(Expr::Var(ident.to_string())),
+                        r: Box::new(Expr::Var("undefined".to_string())),
+                    }),
+                    r: Box::new(cond),
+                };
+            }
+            Stmt::If {
+                cond,
+                stmts: vec![Stmt::Throw(Expr::Instantiate {
+                    constructor: Box::new(Expr::Var("Error".to_string())),
+                    args: vec![Expr::Arith {
+                        op: ArithOp("+".to_string()),
+                        l: Box::new(Expr::Literal(Literal::String(format!(
+                            "{} must be one of {}, got ",
+                            p.name,
+                            values.join(", ")
+                        )))),
+                        r: Box::new(Expr::Var(ident)),
+                    }],
+                })],
+            }
+        })
+        .collect()
+}
+
+/// The words of JavaScript that can't name a param in a module, which is
+/// always in strict mode
+const RESERVED_WORDS: &[&str] = &[
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// The names the generated code declares or uses next to the params of a
+/// method, which a param would shadow
+const GENERATED_NAMES: &[&str] = &[
+    "axios",
+    "cb",
+    "cursor",
+    "encodeURIComponent",
+    "err",
+    "Error",
+    "http",
+    "readEventStream",
+    "response",
+    "serializeParams",
+    "signal",
+    "undefined",
+    BASE_URL_PARAM,
+    FACTORY_HTTP_VAR,
+    HTTP_CLIENT_PARAM,
+];
+
+/// The identifier of a param in the code: its name in the casing of `opts`,
+/// made an identifier like the classes of APISets, with `_` appended if it's
+/// a reserved word or a name of the generated code, as in `class_` for
+/// `class`. The keys of the query and the body keep the name.
+pub(crate) fn param_ident(name: &str, opts: &JsGenOptions) -> String {
+    let ident = to_ident(&opts.param_case.apply(name));
+    if RESERVED_WORDS.contains(&ident.as_str()) || GENERATED_NAMES.contains(&ident.as_str()) {
+        format!("{}_", ident)
+    } else {
+        ident
+    }
+}
+
+/// Fails if two params in the scope of the API or APISet at `path` get the
+/// same identifier, as `a_b` and `aB` do with `ParamCase::Snake`
+fn check_param_idents<'a>(
+    path: &[String],
+    params: impl Iterator<Item = &'a Param>,
+    opts: &JsGenOptions,
+) -> Result<(), GenError> {
+    let mut seen: LinkedHashMap<String, &str> = LinkedHashMap::new();
+    for p in params {
+        let ident = param_ident(&p.name, opts);
+        match seen.get(&ident) {
+            Some(other) if *other != p.name => {
+                let param_path = |name: &str| {
+                    let mut v = path.to_vec();
+                    v.push(name.to_string());
+                    v
+                };
+                return Err(GenError::NameClash {
+                    name: ident,
+                    paths: (param_path(other), param_path(&p.name)),
+                });
+            }
+            Some(_) => {}
+            None => {
+                seen.insert(ident, &p.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks the params of every API, with those of the URLs of its APISets that
+/// the factory styles take too, and of every APISet
+pub(crate) fn check_all_param_idents(
+    root: &ContextBoundedRoot,
+    opts: &JsGenOptions,
+) -> Result<(), GenError> {
+    for (path, api) in root.iter_apis() {
+        let template = root.url_template(&path)?;
+        check_param_idents(
+            &path,
+            template
+                .params()
+                .into_iter()
+                .chain(api.bounded_vars.values()),
+            opts,
+        )?;
+    }
+    for (path, apiset) in root.iter_apisets() {
+        check_param_idents(&path, apiset.bounded_vars.values(), opts)?;
+    }
+    Ok(())
+}
+
+/// The guards of the params of the URL of an API, then of its query and body
+fn gen_api_enum_guards<'a>(
+    url_params: impl Iterator<Item = &'a Param>,
+    api: &'a ContextBoundedAPI,
+    opts: &JsGenOptions,
+) -> Vec<Stmt> {
+    let mut stmts = gen_enum_guards(url_params, false, opts);
+    stmts.extend(gen_enum_guards(
+        api.params.values().chain(api.data.values()),
+        true,
+        opts,
+    ));
+    stmts
+}
+
+/// Params that come from the URL, rather than the query or the body
+fn url_params(api: &ContextBoundedAPI) -> impl Iterator<Item = &Param> {
+    api.bounded_vars
+        .values()
+        .filter(move |p| !api.params.contains_key(&p.name) && !api.data.contains_key(&p.name))
+}
+
+/// The default of a param as a literal of its type, a string if it's neither
+/// a number nor a boolean. Defaults that aren't of the type are rejected by
+/// the transformer, and are left as they are written.
+fn default_literal(p: &Param, default: &str) -> Literal {
+    match p.coerce(default) {
+        Some(DefaultValue::Number(n)) => Literal::Number(n),
+        Some(DefaultValue::Boolean(b)) => Literal::Boolean(b),
+        Some(DefaultValue::String(s)) => Literal::String(s),
+        None => Literal::String(default.to_string()),
+    }
+}
+
+/// A param of a method, with its default if it has one, e.g. `page = 1`
+fn param_decl(p: &Param, opts: &JsGenOptions) -> String {
+    let ident = param_ident(&p.name, opts);
+    match &p.default {
+        Some(default) => format!(
+            "{} = {}",
+            ident,
+            default_literal(p, default).gen(&Default::default())
+        ),
+        None => ident,
+    }
+}
+
+/// The params of the method of an API
+fn method_params(api: &ContextBoundedAPI, opts: &JsGenOptions) -> Vec<String> {
+    api.bounded_vars
+        .values()
+        .map(|p| param_decl(p, opts))
+        .collect()
+}
+
+/// The member of the client classes holding the axios instance, if requests
+/// don't go through the imported axios
+fn http_member(opts: &JsGenOptions) -> Option<&'static str> {
+    if opts.no_import {
+        Some("_http")
+    } else if opts.node_keep_alive {
+        Some("_axios")
+    } else {
+        None
+    }
+}
+
+/// The constructor param of the root class taking the HTTP client, with
+/// `no_import`
+const HTTP_CLIENT_PARAM: &str = "httpClient";
+
+/// The variable of the factory holding the axios instance with a keep-alive
+/// agent
+const FACTORY_HTTP_VAR: &str = "client";
+
+/// The name of the function generated with `ClientStyle::FactoryFunction`
+pub const FACTORY_NAME: &str = "createClient";
+
+/// The param of the factory taking the URL of the root
+const BASE_URL_PARAM: &str = "baseURL";
+
+fn axios_func(opts: &JsGenOptions) -> Expr {
+    if opts.style != ClientStyle::Class {
+        let var = if opts.node_keep_alive {
+            FACTORY_HTTP_VAR
+        } else if opts.no_import {
+            HTTP_CLIENT_PARAM
+        } else {
+            "axios"
+        };
+        return Expr::Var(var.to_string());
+    }
+    match http_member(opts) {
+        Some(member) => Expr::Member {
+            base: Box::new(Expr::Var("this".to_string())),
+            member: Ident(member.to_string()),
+        },
+        None => Expr::Var("axios".to_string()),
+    }
+}
+
+/// The instance with a keep-alive agent, created from the injected client
+/// with `no_import`
+fn keep_alive_axios_instance(opts: &JsGenOptions) -> Expr {
+    let agent = Expr::Instantiate {
+        constructor: Box::new(Expr::Member {
+            base: Box::new(Expr::Var("http".to_string())),
+            member: Ident("Agent".to_string()),
+        }),
+        args: vec![Expr::Object(linked_hashmap! {
+            "keepAlive".to_string() => Expr::Literal(Literal::Boolean(true)),
+        })],
+    };
+    let axios = if opts.no_import {
+        HTTP_CLIENT_PARAM
+    } else {
+        "axios"
+    };
+    Expr::FuncCall {
+        func: Box::new(Expr::Member {
+            base: Box::new(Expr::Var(axios.to_string())),
+            member: Ident("create".to_string()),
+        }),
+        args: vec![Expr::Object(linked_hashmap! {
+            "httpAgent".to_string() => agent,
+        })],
+    }
+}
+
+fn root_constructor(root: &ContextBoundedRoot, opts: &JsGenOptions) -> Option<Constructor> {
+    let mut constructor = root_constructor_base(root, opts);
+    if opts.no_import {
+        constructor
+            .params
+            .insert(0, Ident(HTTP_CLIENT_PARAM.to_string()));
+    }
+    if opts.node_keep_alive {
+        constructor.stmts.push(Stmt::Assign(Assign {
+            typ: None,
+            assignee: Expr::Member {
+                base: Box::new(Expr::Var("this".to_string())),
+                member: Ident(http_member(opts).unwrap().to_string()),
+            },
+            expr: keep_alive_axios_instance(opts),
+        }));
+    } else if opts.no_import {
+        constructor.stmts.push(Stmt::Assign(Assign {
+            typ: None,
+            assignee: Expr::Member {
+                base: Box::new(Expr::Var("this".to_string())),
+                member: Ident("_http".to_string()),
+            },
+            expr: Expr::Var(HTTP_CLIENT_PARAM.to_string()),
+        }));
+    }
+    Some(constructor)
+}
+
+fn root_constructor_base(root: &ContextBoundedRoot, opts: &JsGenOptions) -> Constructor {
+    if !root.bounded_vars.is_empty() {
+        let mut stmts = gen_enum_guards(root.bounded_vars.values(), false, opts);
+        stmts.extend(root.bounded_vars.iter().map(|(_, p)| {
+            Stmt::Assign(Assign {
+                typ: None,
+                assignee: Expr::Member {
+                    base: Box::new(Expr::Var("this".to_string())),
+                    member: Ident(format!("_{}", param_ident(&p.name, opts))),
+                },
+                expr: Expr::Var(param_ident(&p.name, opts)),
             })
-            .collect::<Vec<Stmt>>();
-        Some(Constructor {
+        }));
+        Constructor {
             params: root
                 .bounded_vars
                 .iter()
-                .map(|(_, p)| Ident(p.name.clone()))
+                .map(|(_, p)| Ident(param_ident(&p.name, opts)))
                 .collect::<Vec<Ident>>(),
             stmts,
-        })
+        }
     } else {
-        Some(Constructor {
+        Constructor {
             params: Vec::new(),
             stmts: vec![Stmt::Assign(Assign {
                 typ: None,
                 assignee: Expr::Member {
-                    base: box Expr::Var("this".to_string()),
+                    base: Box::new(Expr::Var("this".to_string())),
                     member: Ident("_url".to_string()),
                 },
-                expr: gen_context_value(&root.url),
+                expr: gen_context_value(&root.url, &this, opts),
             })],
-        })
+        }
     }
 }
 
-fn apiset_constructor(apiset: &ContextBoundedAPISet) -> Option<Constructor> {
+fn apiset_constructor(apiset: &ContextBoundedAPISet, opts: &JsGenOptions) -> Option<Constructor> {
     let mut stmts = vec![
         Stmt::Assign(Assign {
             typ: None,
             assignee: Expr::Member {
-                base: box Expr::Var("this".to_string()),
+                base: Box::new(Expr::Var("this".to_string())),
                 member: Ident("_super".to_string()),
             },
             expr: Expr::Var("_super".to_string()),
@@ -97,69 +498,136 @@ fn apiset_constructor(apiset: &ContextBoundedAPISet) -> Option<Constructor> {
         Stmt::Assign(Assign {
             typ: None,
             assignee: Expr::Member {
-                base: box Expr::Var("this".to_string()),
+                base: Box::new(Expr::Var("this".to_string())),
                 member: Ident("_url".to_string()),
             },
-            expr: gen_context_value(&apiset.url),
+            expr: gen_context_value(&apiset.url, &this_super, opts),
         }),
     ];
+    if let Some(member) = http_member(opts) {
+        // Share the root's axios instance, so that every request reuses its
+        // agent or goes through the injected client
+        stmts.push(Stmt::Assign(Assign {
+            typ: None,
+            assignee: Expr::Member {
+                base: Box::new(Expr::Var("this".to_string())),
+                member: Ident(member.to_string()),
+            },
+            expr: Expr::Member {
+                base: Box::new(Expr::Var("_super".to_string())),
+                member: Ident(member.to_string()),
+            },
+        }));
+    }
     let mut params = vec![Ident("_super".to_string())];
     stmts.extend(apiset.bounded_vars.iter().map(|(_, p)| {
         Stmt::Assign(Assign {
             typ: None,
             assignee: Expr::Member {
-                base: box Expr::Var("this".to_string()),
-                member: Ident(p.name.clone()),
+                base: Box::new(Expr::Var("this".to_string())),
+                member: Ident(param_ident(&p.name, opts)),
             },
-            expr: Expr::Var(p.name.clone()),
+            expr: Expr::Var(param_ident(&p.name, opts)),
         })
     }));
     params.extend(
         apiset
             .bounded_vars
             .iter()
-            .map(|(_, p)| Ident(p.name.clone()))
+            .map(|(_, p)| Ident(param_ident(&p.name, opts)))
             .collect::<Vec<Ident>>(),
     );
     Some(Constructor { params, stmts })
 }
 
-fn gen_apiset(apiset: &ContextBoundedAPISet, code: &mut Code, parent_kls: &mut Class) {
+/// The getter of the URL of the root or an APISet, which the refs of the
+/// URLs under it go through, as in `this._super.url`
+fn url_getter() -> Getter {
+    Getter {
+        ident: Ident("url".to_string()),
+        stmts: vec![Stmt::Return(Expr::Member {
+            base: Box::new(this()),
+            member: Ident("_url".to_string()),
+        })],
+    }
+}
+
+/// `name` with the characters that can't be in an identifier replaced, as in
+/// `users_GET` for `users.GET`, and `_` put before it if it starts with a
+/// digit. It names the classes of APISets, whose getters keep the key.
+fn to_ident(name: &str) -> String {
+    let ident = name
+        .chars()
+        .map(|ch| match ch {
+            _ if ch.is_alphanumeric() || ch == '_' || ch == '$' => ch,
+            _ => '_',
+        })
+        .collect::<String>();
+    if is_ident(&ident) {
+        ident
+    } else {
+        format!("_{}", ident)
+    }
+}
+
+/// The class generated for an APISet
+struct APISetClass<'a> {
+    path: Vec<String>,
+    apiset: &'a ContextBoundedAPISet,
+    kls: Class,
+}
+
+fn gen_apiset<'a>(
+    apiset: &'a ContextBoundedAPISet,
+    path: &mut Vec<String>,
+    classes: &mut Vec<APISetClass<'a>>,
+    opts: &JsGenOptions,
+) {
     let mut kls = Class {
-        ident: Ident(apiset.name.to_string()),
+        ident: Ident(to_ident(&apiset.name)),
         extends: None,
-        constructor: apiset_constructor(apiset),
-        getters: Vec::new(),
+        constructor: apiset_constructor(apiset, opts),
+        getters: vec![url_getter()],
         methods: Vec::new(),
     };
     for (k, child) in &apiset.apisets {
+        path.push(k.to_string());
         match child {
             ContextBoundedAPIData::API(child) => {
-                gen_api(child, &mut kls);
+                gen_api(child, path, &mut kls, opts);
             }
             ContextBoundedAPIData::APISet(child) => {
-                gen_apiset(&child, code, &mut kls);
+                gen_apiset(child, path, classes, opts);
                 kls.getters.push(Getter {
                     ident: Ident(k.to_string()),
                     stmts: vec![Stmt::Return(Expr::Instantiate {
-                        constructor: box Expr::Var(k.to_string()),
+                        constructor: Box::new(Expr::Var(to_ident(k))),
                         args: vec![Expr::Var("this".to_string())],
                     })],
                 })
             }
         }
+        path.pop();
     }
     // This must come at the end because Vec will take ownership of kls
-    code.stmts.push(Stmt::Class(kls));
+    classes.push(APISetClass {
+        path: path.clone(),
+        apiset,
+        kls,
+    });
 }
 
-fn gen_axios_call(
-    url: &ContextValue,
+/// The axios call of an API. `url_expr` is the URL of the request, which the
+/// client classes resolve relative to `this`.
+pub(crate) fn gen_axios_call(
+    url_expr: Expr,
     method: &HttpMethod,
     params: &LinkedHashMap<String, Param>,
     data: &LinkedHashMap<String, Param>,
+    headers: &LinkedHashMap<String, String>,
+    response_type: Option<ResponseType>,
+    opts: &JsGenOptions,
 ) -> Expr {
-    let url_expr = gen_context_value(url);
     let method = match method {
         HttpMethod::GET => "get",
         HttpMethod::POST => "post",
@@ -174,96 +642,963 @@ fn gen_axios_call(
         "method".to_string() => Expr::Literal(Literal::String(method)),
         "url".to_string() => url_expr,
     };
-    if params.len() > 0 {
+    if !params.is_empty() {
         let params = LinkedHashMap::from_iter(
             params
                 .iter()
-                .map(|(k, v)| (k.to_string(), Expr::Var(v.name.to_string()))),
+                .map(|(k, v)| (k.to_string(), Expr::Var(param_ident(&v.name, opts)))),
         );
         axios_config.insert("params".to_string(), Expr::Object(params));
+        if opts.query_encoding != QueryEncoding::None {
+            axios_config.insert(
+                "paramsSerializer".to_string(),
+                Expr::Var("serializeParams".to_string()),
+            );
+        }
     }
-    if data.len() > 0 {
+    if !data.is_empty() {
         let data = LinkedHashMap::from_iter(
             data.iter()
-                .map(|(k, v)| (k.to_string(), Expr::Var(v.name.to_string()))),
+                .map(|(k, v)| (k.to_string(), Expr::Var(param_ident(&v.name, opts)))),
         );
         axios_config.insert("data".to_string(), Expr::Object(data));
     }
+    if !headers.is_empty() {
+        let headers = LinkedHashMap::from_iter(
+            headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), Expr::Literal(Literal::String(v.to_string())))),
+        );
+        axios_config.insert("headers".to_string(), Expr::Object(headers));
+    }
+    if let Some(response_type) = response_type {
+        axios_config.insert(
+            "responseType".to_string(),
+            Expr::Literal(Literal::String(response_type.as_str().to_string())),
+        );
+    }
+    if opts.cancellation {
+        axios_config.insert("signal".to_string(), Expr::Var("signal".to_string()));
+    }
     let args = vec![Expr::Object(axios_config)];
     Expr::FuncCall {
-        func: box Expr::Var("axios".to_string()),
+        func: Box::new(axios_func(opts)),
         args,
     }
 }
 
-fn gen_api(api: &ContextBoundedAPI, kls: &mut Class) {
-    let stmts = vec![Stmt::Return(gen_axios_call(
-        &api.url,
-        &api.method,
-        &api.params,
-        &api.data,
-    ))];
+/// Splits the query string off the URL of an API when it's made of
+/// `key=<param>` pairs only, as in `${!super}/search?page=<page:int=1>`.
+/// Returns the URL without it, and the keys with their params. A fragment
+/// stays at the end of the URL, as in `${!super}/search#results`.
+fn split_query<'a>(
+    url: &sp::Expr,
+    vars: &'a LinkedHashMap<String, Param>,
+) -> Option<(sp::Expr, Vec<(String, &'a Param)>)> {
+    fn flatten(expr: &sp::Expr, parts: &mut Vec<sp::Expr>) {
+        match expr {
+            sp::Expr::Concat(l, r) => {
+                flatten(l, parts);
+                flatten(r, parts);
+            }
+            _ => parts.push(expr.clone()),
+        }
+    }
+    let mut parts = Vec::new();
+    flatten(url, &mut parts);
+    // Everything from the `#` on is the fragment, even a `?`
+    let mut fragment = Vec::new();
+    let hash = parts.iter().enumerate().find_map(|(i, part)| match part {
+        sp::Expr::Lit(s) => s.find('#').map(|pos| (i, pos)),
+        _ => None,
+    });
+    if let Some((i, pos)) = hash {
+        fragment = parts.split_off(i);
+        if let sp::Expr::Lit(s) = &fragment[0] {
+            if pos > 0 {
+                parts.push(sp::Expr::Lit(s[..pos].to_string()));
+                fragment[0] = sp::Expr::Lit(s[pos..].to_string());
+            }
+        }
+    }
+    let (i, before, after) = parts.iter().enumerate().find_map(|(i, part)| match part {
+        sp::Expr::Lit(s) => s.find('?').map(|pos| (i, &s[..pos], &s[pos + 1..])),
+        _ => None,
+    })?;
+    let mut pairs = Vec::new();
+    let mut key = Some(after);
+    for part in &parts[i + 1..] {
+        match (part, key.take()) {
+            (sp::Expr::Var(name), Some(k)) => {
+                let k = if pairs.is_empty() {
+                    k
+                } else {
+                    k.strip_prefix('&')?
+                };
+                let k = k.strip_suffix('=')?;
+                if k.is_empty() || k.contains(&['&', '='][..]) {
+                    return None;
+                }
+                pairs.push((k.to_string(), vars.get(name)?));
+            }
+            (sp::Expr::Lit(s), None) => key = Some(s),
+            _ => return None,
+        }
+    }
+    if key.is_some() {
+        return None;
+    }
+    let mut path = parts[..i].to_vec();
+    if !before.is_empty() {
+        path.push(sp::Expr::Lit(before.to_string()));
+    }
+    for part in fragment {
+        match (path.last_mut(), part) {
+            (Some(sp::Expr::Lit(last)), sp::Expr::Lit(s)) => last.push_str(&s),
+            (_, part) => path.push(part),
+        }
+    }
+    let url = path
+        .into_iter()
+        .fold(None, |l, r| match l {
+            Some(l) => Some(sp::Expr::Concat(Box::new(l), Box::new(r))),
+            None => Some(r),
+        })
+        .unwrap_or_else(|| sp::Expr::Lit(String::new()));
+    Some((url, pairs))
+}
+
+/// The axios call of an API. Params in the query string of its URL are sent
+/// as axios params along with those of `$params`, so that optional ones left
+/// undefined are omitted.
+fn gen_api_call(
+    api: &ContextBoundedAPI,
+    response_type: Option<ResponseType>,
+    opts: &JsGenOptions,
+) -> Expr {
+    let ContextValue::Expr(url) = &api.url;
+    match split_query(url, &api.bounded_vars) {
+        Some((url, query)) => {
+            let mut params =
+                LinkedHashMap::from_iter(query.into_iter().map(|(k, p)| (k, p.clone())));
+            params.extend(api.params.iter().map(|(k, p)| (k.to_string(), p.clone())));
+            gen_axios_call(
+                gen_context_value(&ContextValue::Expr(url), &this, opts),
+                &api.method,
+                &params,
+                &api.data,
+                &api.headers,
+                response_type,
+                opts,
+            )
+        }
+        None => gen_axios_call(
+            gen_context_value(&api.url, &this, opts),
+            &api.method,
+            &api.params,
+            &api.data,
+            &api.headers,
+            response_type,
+            opts,
+        ),
+    }
+}
+
+/// Serializes query params with arrays encoded as given, and nested objects
+/// in brackets. Inlined to avoid depending on `qs`.
+fn serialize_params(encoding: QueryEncoding) -> Option<String> {
+    let add_array = match encoding {
+        QueryEncoding::None => return None,
+        QueryEncoding::Repeat => "value.forEach((v) => add(key, v));",
+        QueryEncoding::Brackets => "value.forEach((v) => add(`${key}[]`, v));",
+        QueryEncoding::Comma => "add(key, value.join(\",\"));",
+    };
+    Some(format!(
+        r#"function serializeParams(params) {{
+    const parts = [];
+    const add = (key, value) => {{
+        if (value === undefined || value === null) {{
+            return;
+        }}
+        if (Array.isArray(value)) {{
+            {add_array}
+        }} else if (value instanceof Date) {{
+            add(key, value.toISOString());
+        }} else if (typeof value === "object") {{
+            for (const [k, v] of Object.entries(value)) {{
+                add(`${{key}}[${{k}}]`, v);
+            }}
+        }} else {{
+            parts.push(`${{encodeURIComponent(key)}}=${{encodeURIComponent(value)}}`);
+        }}
+    }};
+    for (const [key, value] of Object.entries(params)) {{
+        add(key, value);
+    }}
+    return parts.join("&");
+}}"#,
+        add_array = add_array
+    ))
+}
+
+/// Parses a `text/event-stream` body into `{event, data, id}` objects
+const READ_EVENT_STREAM: &str = r#"async function* readEventStream(stream) {
+    const decoder = new TextDecoder();
+    let buffer = "";
+    for await (const chunk of stream) {
+        buffer += typeof chunk === "string" ? chunk : decoder.decode(chunk, { stream: true });
+        let match;
+        while ((match = /\r?\n\r?\n/.exec(buffer)) !== null) {
+            const block = buffer.slice(0, match.index);
+            buffer = buffer.slice(match.index + match[0].length);
+            const event = { event: "message", data: [], id: undefined };
+            for (const line of block.split(/\r?\n/)) {
+                if (line.startsWith(":")) {
+                    continue;
+                }
+                const colon = line.indexOf(":");
+                const field = colon >= 0 ? line.slice(0, colon) : line;
+                const value = colon >= 0 ? line.slice(colon + 1).replace(/^ /, "") : "";
+                if (field === "data") {
+                    event.data.push(value);
+                } else if (field === "event") {
+                    event.event = value;
+                } else if (field === "id") {
+                    event.id = value;
+                }
+            }
+            if (event.data.length > 0) {
+                yield { ...event, data: event.data.join("\n") };
+            }
+        }
+    }
+}"#;
+
+/// Streaming APIs become async generators over the server-sent events. They
+/// rely on axios' `stream` response type, which is only available on Node.
+fn gen_stream_stmts(api: &ContextBoundedAPI, opts: &JsGenOptions) -> Vec<Stmt> {
+    let mut call = gen_api_call(api, None, opts);
+    if let Expr::FuncCall { args, .. } = &mut call {
+        if let Some(Expr::Object(config)) = args.first_mut() {
+            config.insert(
+                "responseType".to_string(),
+                Expr::Literal(Literal::String("stream".to_string())),
+            );
+        }
+    }
+    vec![
+        Stmt::Assign(Assign {
+            typ: Some(DeclType::Const),
+            assignee: Expr::Var("response".to_string()),
+            expr: Expr::Await(Box::new(call)),
+        }),
+        Stmt::Yield {
+            expr: Some(Expr::FuncCall {
+                func: Box::new(Expr::Var("readEventStream".to_string())),
+                args: vec![Expr::Member {
+                    base: Box::new(Expr::Var("response".to_string())),
+                    member: Ident("data".to_string()),
+                }],
+            }),
+            is_delegate: true,
+        },
+    ]
+}
+
+/// A field of the response body, which may be dotted
+fn gen_response_field(field: &str) -> Expr {
+    field.split('.').fold(
+        Expr::Member {
+            base: Box::new(Expr::Var("response".to_string())),
+            member: Ident("data".to_string()),
+        },
+        |base, member| Expr::Member {
+            base: Box::new(base),
+            member: Ident(member.to_string()),
+        },
+    )
+}
+
+/// Paginated APIs also get an async generator named after them, e.g.
+/// `iterAll` for `all`. It requests page after page, sending the cursor of
+/// the previous one, and yields the items of each until a page has no cursor.
+fn gen_paginate_method(
+    api: &ContextBoundedAPI,
+    paginate: &Paginate,
+    path: &[String],
+    opts: &JsGenOptions,
+) -> Method {
+    let mut stmts = Vec::new();
+    if opts.annotate {
+        stmts.push(Stmt::LineComment(format!(
+            "from schema: {}",
+            path.join(".")
+        )));
+    }
+    stmts.extend(gen_api_enum_guards(url_params(api), api, opts));
+    let mut call = gen_api_call(api, api.response_type, opts);
+    if let Expr::FuncCall { args, .. } = &mut call {
+        if let Some(Expr::Object(config)) = args.first_mut() {
+            let cursor = (
+                paginate.param().to_string(),
+                Expr::Var("cursor".to_string()),
+            );
+            match config.get_mut("params") {
+                Some(Expr::Object(params)) => {
+                    params.insert(cursor.0, cursor.1);
+                }
+                _ => {
+                    config.insert(
+                        "params".to_string(),
+                        Expr::Object(LinkedHashMap::from_iter(vec![cursor])),
+                    );
+                }
+            }
+        }
+    }
+    stmts.push(Stmt::Assign(Assign {
+        typ: Some(DeclType::Let),
+        assignee: Expr::Var("cursor".to_string()),
+        expr: Expr::Var("undefined".to_string()),
+    }));
+    stmts.push(Stmt::While {
+        cond: Expr::Var("cursor".to_string()),
+        stmts: vec![
+            Stmt::Assign(Assign {
+                typ: Some(DeclType::Const),
+                assignee: Expr::Var("response".to_string()),
+                expr: Expr::Await(Box::new(call)),
+            }),
+            Stmt::Yield {
+                expr: Some(gen_response_field(&paginate.items)),
+                is_delegate: true,
+            },
+            Stmt::Assign(Assign {
+                typ: None,
+                assignee: Expr::Var("cursor".to_string()),
+                expr: gen_response_field(&paginate.cursor),
+            }),
+        ],
+        is_do: true,
+    });
+    let mut params = method_params(api, opts);
+    if opts.cancellation {
+        params.push("signal".to_string());
+    }
+    Method {
+        doc: jsdoc_tags(api),
+        ident: Ident(format!("iter{}", to_pascal_case(&api.name))),
+        params,
+        stmts,
+        is_async: true,
+        is_generator: true,
+    }
+}
+
+/// Hands the promise of the axios response over as `return_style` says
+fn gen_return(call: Expr, return_style: ReturnStyle) -> Stmt {
+    match return_style {
+        ReturnStyle::Promise => Stmt::Return(call),
+        ReturnStyle::AwaitData => Stmt::Return(Expr::Member {
+            base: Box::new(Expr::Await(Box::new(call))),
+            member: Ident("data".to_string()),
+        }),
+        ReturnStyle::Callback => {
+            let then = Expr::FuncCall {
+                func: Box::new(Expr::Member {
+                    base: Box::new(call),
+                    member: Ident("then".to_string()),
+                }),
+                args: vec![Expr::Var("cb".to_string())],
+            };
+            Stmt::Expr(Expr::FuncCall {
+                func: Box::new(Expr::Member {
+                    base: Box::new(then),
+                    member: Ident("catch".to_string()),
+                }),
+                args: vec![Expr::Var("err".to_string())],
+            })
+        }
+    }
+}
+
+/// The `@returns` tag of an API whose response body is of a known type, for
+/// what a method of `return_style` gives back
+fn returns_tag(api: &ContextBoundedAPI, return_style: ReturnStyle) -> Option<String> {
+    let typ = api.returns()?;
+    match return_style {
+        ReturnStyle::Promise => Some(format!(
+            "@returns {{Promise<import(\"axios\").AxiosResponse<{}>>}}",
+            typ
+        )),
+        ReturnStyle::AwaitData => Some(format!("@returns {{Promise<{}>}}", typ)),
+        ReturnStyle::Callback => None,
+    }
+}
+
+fn gen_api(api: &ContextBoundedAPI, path: &[String], kls: &mut Class, opts: &JsGenOptions) {
+    let mut stmts = Vec::new();
+    if opts.annotate {
+        stmts.push(Stmt::LineComment(format!(
+            "from schema: {}",
+            path.join(".")
+        )));
+    }
+    stmts.extend(gen_api_enum_guards(url_params(api), api, opts));
+    let return_style = if api.stream {
+        stmts.extend(gen_stream_stmts(api, opts));
+        ReturnStyle::Promise
+    } else {
+        let call = gen_api_call(api, api.response_type, opts);
+        stmts.push(gen_return(call, opts.return_style));
+        opts.return_style
+    };
+    let mut params = method_params(api, opts);
+    if opts.cancellation {
+        params.push("signal".to_string());
+    }
+    if return_style == ReturnStyle::Callback {
+        params.push("cb".to_string());
+        params.push("err".to_string());
+    }
+    let mut doc = jsdoc_tags(api);
+    // Streams yield events rather than the body
+    if !api.stream {
+        doc.extend(returns_tag(api, return_style));
+    }
     let method = Method {
+        doc,
         ident: Ident(api.name.to_string()),
-        params: api
-            .bounded_vars
-            .iter()
-            .map(|(_, p)| p.name.to_string())
-            .collect::<Vec<String>>(),
+        params,
         stmts,
-        is_async: true,
+        is_async: return_style != ReturnStyle::Callback,
+        is_generator: api.stream,
     };
     kls.methods.push(method);
+    if let Some(paginate) = &api.paginate {
+        kls.methods
+            .push(gen_paginate_method(api, paginate, path, opts));
+    }
 }
 
-fn gen_root(root: &ContextBoundedRoot, code: &mut Code) {
+/// The root class. The classes of the APISets are collected into `classes`,
+/// children first.
+fn gen_root<'a>(
+    root: &'a ContextBoundedRoot,
+    classes: &mut Vec<APISetClass<'a>>,
+    opts: &JsGenOptions,
+) -> Class {
     let mut root_kls = Class {
         ident: Ident(root.klsname.to_string()),
         extends: None,
-        constructor: root_constructor(root),
-        getters: vec![Getter {
-            ident: Ident("url".to_string()),
-            stmts: vec![Stmt::Return(Expr::Member {
-                base: box Expr::Var("this".to_string()),
-                member: Ident("_url".to_string()),
-            })],
-        }],
+        constructor: root_constructor(root, opts),
+        getters: vec![url_getter()],
         methods: Vec::new(),
     };
+    let mut path = Vec::new();
     for (k, child) in &root.apisets {
+        path.push(k.to_string());
         match child {
-            ContextBoundedAPIData::API(child) => gen_api(&child, &mut root_kls),
+            ContextBoundedAPIData::API(child) => gen_api(child, &path, &mut root_kls, opts),
             ContextBoundedAPIData::APISet(child) => {
-                gen_apiset(&child, code, &mut root_kls);
+                gen_apiset(child, &mut path, classes, opts);
                 root_kls.getters.push(Getter {
                     ident: Ident(k.to_string()),
                     stmts: vec![Stmt::Return(Expr::Instantiate {
-                        constructor: box Expr::Var(k.to_string()),
+                        constructor: Box::new(Expr::Var(to_ident(k))),
                         args: vec![Expr::Var("this".to_string())],
                     })],
                 })
             }
         }
+        path.pop();
     }
-    code.stmts.push(Stmt::Export {
-        stmt: box Stmt::Class(root_kls),
-        is_default: true,
+    root_kls
+}
+
+/// The URL of an API of the factory. The URL of the root is the `baseURL`
+/// param, and the other params are encoded.
+fn gen_factory_url(template: &UrlTemplate, opts: &JsGenOptions) -> Expr {
+    let mut exprs = template.0.iter().map(|part| match part {
+        UrlPart::Lit(s) => Expr::Literal(Literal::String(s.to_string())),
+        UrlPart::Param(p) => Expr::FuncCall {
+            func: Box::new(Expr::Var("encodeURIComponent".to_string())),
+            args: vec![Expr::Var(param_ident(&p.name, opts))],
+        },
+        UrlPart::BaseUrl => Expr::Var(BASE_URL_PARAM.to_string()),
     });
+    let first = exprs
+        .next()
+        .unwrap_or_else(|| Expr::Literal(Literal::String(String::new())));
+    exprs.fold(first, |l, r| Expr::Arith {
+        op: ArithOp("+".to_string()),
+        l: Box::new(l),
+        r: Box::new(r),
+    })
+}
+
+/// The function of the factory for the API at `path`. It takes the params of the URL in the order they appear, then those of the
+/// query and the body.
+fn gen_factory_api(
+    root: &ContextBoundedRoot,
+    path: &[String],
+    api: &ContextBoundedAPI,
+    opts: &JsGenOptions,
+) -> Result<Expr, ContextLookupError> {
+    let template = root.url_template(path)?;
+    let url_params = template.params();
+    let mut stmts = Vec::new();
+    if opts.annotate {
+        stmts.push(Stmt::LineComment(format!(
+            "from schema: {}",
+            path.join(".")
+        )));
+    }
+    stmts.extend(gen_api_enum_guards(url_params.iter().copied(), api, opts));
+    let mut call = gen_axios_call(
+        gen_factory_url(&template, opts),
+        &api.method,
+        &api.params,
+        &api.data,
+        &api.headers,
+        api.response_type,
+        opts,
+    );
+    let return_style = if api.stream {
+        if let Expr::FuncCall { args, .. } = &mut call {
+            if let Some(Expr::Object(config)) = args.first_mut() {
+                config.insert(
+                    "responseType".to_string(),
+                    Expr::Literal(Literal::String("stream".to_string())),
+                );
+            }
+        }
+        ReturnStyle::Promise
+    } else {
+        opts.return_style
+    };
+    stmts.push(gen_return(call, return_style));
+    let mut params: Vec<&Param> = Vec::new();
+    for p in url_params
+        .into_iter()
+        .chain(api.params.values())
+        .chain(api.data.values())
+    {
+        if !params.iter().any(|v| v.name == p.name) {
+            params.push(p);
+        }
+    }
+    let mut params = params
+        .into_iter()
+        .map(|p| param_decl(p, opts))
+        .collect::<Vec<String>>();
+    if opts.cancellation {
+        params.push("signal".to_string());
+    }
+    if return_style == ReturnStyle::Callback {
+        params.push("cb".to_string());
+        params.push("err".to_string());
+    }
+    Ok(Expr::ArrowFunc {
+        params,
+        body: Left(stmts),
+        is_async: return_style != ReturnStyle::Callback,
+    })
+}
+
+/// The functions of the APIs in `apisets`, with an object of its own for each
+/// APISet, e.g. `{ users: { get: async (id) => ... } }`
+fn gen_nested_object(
+    root: &ContextBoundedRoot,
+    apisets: &LinkedHashMap<String, ContextBoundedAPIData>,
+    path: &mut Vec<String>,
+    opts: &JsGenOptions,
+) -> Result<LinkedHashMap<String, Expr>, ContextLookupError> {
+    let mut members = LinkedHashMap::new();
+    for (k, child) in apisets {
+        path.push(k.to_string());
+        let member = match child {
+            ContextBoundedAPIData::API(api) => gen_factory_api(root, path, api, opts)?,
+            ContextBoundedAPIData::APISet(apiset) => {
+                Expr::Object(gen_nested_object(root, &apiset.apisets, path, opts)?)
+            }
+        };
+        members.insert(k.to_string(), member);
+        path.pop();
+    }
+    Ok(members)
+}
+
+/// `createClient(baseURL)`, returning an object of the functions of every
+/// API, flat or nested as in the style of `opts`. `baseURL` defaults to the
+/// URL of the root unless it has params. The flat style names the functions
+/// after the paths of their APIs, which fails if two of them come out the
+/// same.
+fn gen_factory(root: &ContextBoundedRoot, opts: &JsGenOptions) -> Result<Stmt, GenError> {
+    let mut params = Vec::new();
+    if opts.no_import {
+        params.push(HTTP_CLIENT_PARAM.to_string());
+    }
+    if root.bounded_vars.is_empty() {
+        params.push(format!(
+            "{} = {}",
+            BASE_URL_PARAM,
+            gen_context_value(&root.url, &this, opts).gen(&Default::default())
+        ));
+    } else {
+        params.push(BASE_URL_PARAM.to_string());
+    }
+    let mut stmts = Vec::new();
+    if opts.node_keep_alive {
+        stmts.push(Stmt::Assign(Assign {
+            typ: Some(DeclType::Const),
+            assignee: Expr::Var(FACTORY_HTTP_VAR.to_string()),
+            expr: keep_alive_axios_instance(opts),
+        }));
+    }
+    let methods = if opts.style == ClientStyle::NestedObject {
+        gen_nested_object(root, &root.apisets, &mut Vec::new(), opts)?
+    } else {
+        let apis = root.iter_apis();
+        let mut methods = LinkedHashMap::new();
+        for (name, (path, api)) in flat_names(&apis)?.into_iter().zip(&apis) {
+            methods.insert(name, gen_factory_api(root, path, api, opts)?);
+        }
+        methods
+    };
+    stmts.push(Stmt::Return(Expr::Object(methods)));
+    Ok(Stmt::Func(Method {
+        doc: Vec::new(),
+        ident: Ident(FACTORY_NAME.to_string()),
+        params,
+        stmts,
+        is_async: false,
+        is_generator: false,
+    }))
+}
+
+pub fn gen(root: &ContextBoundedRoot, gen_ctx: &GenContext) -> Result<String, GenError> {
+    gen_with_options(root, gen_ctx, &Default::default())
+}
+
+/// The banner, then the imports and helpers needed by the methods of `apis`.
+/// `http` is only needed by the module of the root class.
+fn gen_prelude<'a>(
+    root: &ContextBoundedRoot,
+    mut apis: impl Iterator<Item = &'a ContextBoundedAPI> + Clone,
+    is_root: bool,
+    opts: &JsGenOptions,
+) -> Vec<Stmt> {
+    let mut stmts = banner_lines(&root.klsname, opts.banner.as_deref())
+        .into_iter()
+        .map(Stmt::LineComment)
+        .collect::<Vec<Stmt>>();
+    if !opts.no_import {
+        stmts.push(import_default("axios", "axios"));
+    }
+    if is_root && opts.node_keep_alive {
+        stmts.push(import_default("http", "http"));
+    }
+    if opts.style == ClientStyle::Class && apis.clone().any(|api| api.stream) {
+        stmts.push(Stmt::Verbatim(READ_EVENT_STREAM.to_string()));
+    }
+    if apis.any(|api| !api.params.is_empty()) {
+        if let Some(serializer) = serialize_params(opts.query_encoding) {
+            stmts.push(Stmt::Verbatim(serializer));
+        }
+    }
+    stmts
 }
 
-pub fn gen(root: &ContextBoundedRoot, gen_ctx: &GenContext) -> String {
-    let stmts = vec![Stmt::Import(Import {
-        def: Some(Ident("axios".to_string())),
+fn import_default(ident: &str, path: &str) -> Stmt {
+    Stmt::Import(Import {
+        def: Some(Ident(ident.to_string())),
         imps: None,
-        path: "axios".to_string(),
-    })];
-    let mut code = Code { stmts };
-    gen_root(root, &mut code);
-    code.gen(gen_ctx)
+        path: path.to_string(),
+    })
+}
+
+/// `module.exports = Root; module.exports.default = Root;`, after the ES
+/// export, so that `require` gets the client whichever of the two it expects
+fn commonjs_exports(klsname: &str) -> Vec<Stmt> {
+    let exports = || Expr::Member {
+        base: Box::new(Expr::Var("module".to_string())),
+        member: Ident("exports".to_string()),
+    };
+    vec![
+        Stmt::Assign(Assign {
+            typ: None,
+            assignee: exports(),
+            expr: Expr::Var(klsname.to_string()),
+        }),
+        Stmt::Assign(Assign {
+            typ: None,
+            assignee: Expr::Member {
+                base: Box::new(exports()),
+                member: Ident("default".to_string()),
+            },
+            expr: Expr::Var(klsname.to_string()),
+        }),
+    ]
+}
+
+fn export_default(kls: Class) -> Stmt {
+    Stmt::Export {
+        stmt: Box::new(Stmt::Class(kls)),
+        is_default: true,
+    }
+}
+
+/// Generates the client in the style of `opts`. Only the factory resolves the
+/// URLs of the APIs here, which fails on references to nothing, and names
+/// its functions, which fails on names that clash.
+pub fn gen_with_options(
+    root: &ContextBoundedRoot,
+    gen_ctx: &GenContext,
+    opts: &JsGenOptions,
+) -> Result<String, GenError> {
+    Ok(render(&root.klsname, main_stmts(root, opts)?, gen_ctx))
+}
+
+/// Generates the client like [`gen_with_options`], but writes it into `w` a
+/// statement at a time, so that the code of a large schema is never held
+/// whole
+pub fn gen_to<W: io::Write>(
+    root: &ContextBoundedRoot,
+    gen_ctx: &GenContext,
+    opts: &JsGenOptions,
+    w: W,
+) -> Result<(), GenError> {
+    let stmts = main_stmts(root, opts)?;
+    let mut adapter = IoAdapter { w, error: None };
+    if render_into(&root.klsname, stmts, gen_ctx, &mut adapter).is_err() {
+        let e = adapter.error.take();
+        return Err(e
+            .unwrap_or_else(|| io::Error::other("Formatting failed"))
+            .into());
+    }
+    adapter.w.flush()?;
+    Ok(())
+}
+
+/// Writes into an `io::Write` what is written into it, keeping the IO error
+/// that `fmt::Error` can't carry
+struct IoAdapter<W> {
+    w: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> fmt::Write for IoAdapter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.w.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// The statements of the main module in the style of `opts`
+fn main_stmts(root: &ContextBoundedRoot, opts: &JsGenOptions) -> Result<Vec<Stmt>, GenError> {
+    check_all_param_idents(root, opts)?;
+    match opts.style {
+        ClientStyle::Class => Ok(class_stmts(root, opts)),
+        ClientStyle::FactoryFunction | ClientStyle::NestedObject => {
+            let apis = root.iter_apis();
+            let mut stmts = gen_prelude(root, apis.iter().map(|(_, api)| *api), true, opts);
+            stmts.push(Stmt::Export {
+                stmt: Box::new(gen_factory(root, opts)?),
+                is_default: true,
+            });
+            if opts.commonjs_interop {
+                stmts.extend(commonjs_exports(FACTORY_NAME));
+            }
+            Ok(stmts)
+        }
+    }
+}
+
+fn class_stmts(root: &ContextBoundedRoot, opts: &JsGenOptions) -> Vec<Stmt> {
+    let apis = root.iter_apis();
+    let mut stmts = gen_prelude(root, apis.iter().map(|(_, api)| *api), true, opts);
+    let mut classes = Vec::new();
+    let root_kls = gen_root(root, &mut classes, opts);
+    stmts.extend(classes.into_iter().map(|v| Stmt::Class(v.kls)));
+    stmts.push(export_default(root_kls));
+    if opts.commonjs_interop {
+        stmts.extend(commonjs_exports(&root.klsname));
+    }
+    stmts
+}
+
+/// Renders the statements of a module, logging how many there are
+fn render(module: &str, stmts: Vec<Stmt>, gen_ctx: &GenContext) -> String {
+    let mut s = String::new();
+    render_into(module, stmts, gen_ctx, &mut s).unwrap();
+    s
+}
+
+fn render_into(
+    module: &str,
+    stmts: Vec<Stmt>,
+    gen_ctx: &GenContext,
+    w: &mut dyn fmt::Write,
+) -> fmt::Result {
+    debug!("Rendering {}: {} statements", module, stmts.len());
+    Code { stmts }.gen_into(gen_ctx, w)
+}
+
+/// The path of the module of an APISet class, relative to `dir`, e.g.
+/// `users/budgets.js` for `users.budgets`
+fn module_path(dir: &str, path: &[String]) -> PathBuf {
+    let mut module = PathBuf::from(dir);
+    module.extend(&path[..path.len() - 1]);
+    module.push(format!("{}.js", path[path.len() - 1]));
+    module
+}
+
+/// Imports the classes of the child APISets from the modules in `dir`, which
+/// is relative to the importing module
+fn import_children(apisets: &LinkedHashMap<String, ContextBoundedAPIData>, dir: &str) -> Vec<Stmt> {
+    apisets
+        .iter()
+        .filter(|(_, child)| match child {
+            ContextBoundedAPIData::APISet(_) => true,
+            ContextBoundedAPIData::API(_) => false,
+        })
+        .map(|(k, _)| import_default(&to_ident(k), &format!("./{}/{}.js", dir, k)))
+        .collect()
+}
+
+/// Generates the root class as the main module, and the class of each APISet
+/// as a module of its own under `dir`, e.g. `dir/users/budgets.js` for
+/// `users.budgets`. Each module imports the classes of its child APISets.
+/// The classes are generated whatever the `style` of `opts`.
+pub fn gen_modules(
+    root: &ContextBoundedRoot,
+    gen_ctx: &GenContext,
+    opts: &JsGenOptions,
+    dir: &str,
+) -> Result<(String, Vec<GeneratedFile>), GenError> {
+    check_all_param_idents(root, opts)?;
+    let mut classes = Vec::new();
+    let root_kls = gen_root(root, &mut classes, opts);
+    let mut files = Vec::new();
+    for APISetClass { path, apiset, kls } in classes {
+        let mut stmts = gen_prelude(root, direct_apis(&apiset.apisets), false, opts);
+        stmts.extend(import_children(&apiset.apisets, &path[path.len() - 1]));
+        stmts.push(export_default(kls));
+        files.push(GeneratedFile {
+            path: module_path(dir, &path),
+            code: render(&path.join("."), stmts, gen_ctx),
+        });
+    }
+    let mut stmts = gen_prelude(root, direct_apis(&root.apisets), true, opts);
+    stmts.extend(import_children(&root.apisets, dir));
+    stmts.push(export_default(root_kls));
+    if opts.commonjs_interop {
+        stmts.extend(commonjs_exports(&root.klsname));
+    }
+    Ok((render(&root.klsname, stmts, gen_ctx), files))
+}
+
+fn direct_apis(
+    apisets: &LinkedHashMap<String, ContextBoundedAPIData>,
+) -> impl Iterator<Item = &ContextBoundedAPI> + Clone {
+    apisets.values().filter_map(|child| match child {
+        ContextBoundedAPIData::API(api) => Some(api),
+        ContextBoundedAPIData::APISet(_) => None,
+    })
+}
+
+pub struct JavaScript;
+
+impl Backend for JavaScript {
+    fn name(&self) -> &str {
+        "javascript"
+    }
+
+    fn extension(&self) -> &str {
+        ".js"
+    }
+
+    fn description(&self) -> &str {
+        "JavaScript (ECMAScript 6) client"
+    }
+
+    fn generate(
+        &self,
+        root: &ContextBoundedRoot,
+        opts: &GenOptions,
+    ) -> Result<GeneratedOutput, GenError> {
+        let (js_opts, gen_ctx) = js_options(opts);
+        match &opts.module_dir {
+            Some(dir) => {
+                let (code, files) = gen_modules(root, &gen_ctx, &js_opts, dir)?;
+                Ok(GeneratedOutput { code, files })
+            }
+            None => Ok(GeneratedOutput {
+                code: gen_with_options(root, &gen_ctx, &js_opts)?,
+                files: Vec::new(),
+            }),
+        }
+    }
+
+    fn generate_to(
+        &self,
+        root: &ContextBoundedRoot,
+        opts: &GenOptions,
+        w: &mut dyn io::Write,
+    ) -> Result<Vec<GeneratedFile>, GenError> {
+        let (js_opts, gen_ctx) = js_options(opts);
+        match &opts.module_dir {
+            Some(dir) => {
+                let (code, files) = gen_modules(root, &gen_ctx, &js_opts, dir)?;
+                w.write_all(code.as_bytes())?;
+                Ok(files)
+            }
+            None => {
+                gen_to(root, &gen_ctx, &js_opts, w)?;
+                Ok(Vec::new())
+            }
+        }
+    }
+}
+
+/// The options of the generator and of the rendering that `opts` asks for
+fn js_options(opts: &GenOptions) -> (JsGenOptions, GenContext) {
+    let js_opts = JsGenOptions {
+        node_keep_alive: opts.node_keep_alive,
+        param_case: opts.param_case,
+        cancellation: opts.cancellation,
+        query_encoding: opts.query_encoding,
+        annotate: opts.annotate,
+        return_style: opts.return_style,
+        commonjs_interop: opts.commonjs_interop,
+        no_import: opts.no_import,
+        style: opts.style,
+        banner: opts.banner.clone(),
+    };
+    let gen_ctx = if opts.minify {
+        GenContext::new()
+    } else {
+        Default::default()
+    };
+    (js_opts, gen_ctx)
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+
+    fn sample_root() -> ContextBoundedRoot {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        transform(schema).unwrap()
+    }
+
     #[test]
     fn test_gen() {
         // let root = ContextBoundedRoot {
@@ -272,4 +1607,948 @@ mod tests {
         // };
         println!("===== TODO =====");
     }
+
+    #[test]
+    fn test_gen_node_keep_alive_disabled() {
+        let code = gen(&sample_root(), &Default::default()).unwrap();
+        assert!(!code.contains("import http from \"http\";"));
+        assert!(!code.contains("keepAlive"));
+        assert!(code.contains("return axios("));
+    }
+
+    #[test]
+    fn test_gen_node_keep_alive_enabled() {
+        let opts = JsGenOptions {
+            node_keep_alive: true,
+            ..Default::default()
+        };
+        let code = gen_with_options(&sample_root(), &Default::default(), &opts).unwrap();
+        assert!(code.contains("import http from \"http\";"));
+        assert!(code.contains("(this)._axios = (axios).create({"));
+        assert!(code.contains("httpAgent: new ((http).Agent)({"));
+        assert!(code.contains("keepAlive: true"));
+        assert!(code.contains("(this)._axios = (_super)._axios;"));
+        assert!(code.contains("return (this)._axios("));
+        assert!(!code.contains("return axios("));
+    }
+
+    #[test]
+    fn test_gen_cancellation() {
+        let code = gen(&sample_root(), &Default::default()).unwrap();
+        assert!(!code.contains("signal"));
+        let opts = JsGenOptions {
+            cancellation: true,
+            ..Default::default()
+        };
+        let code = gen_with_options(&sample_root(), &Default::default(), &opts).unwrap();
+        assert!(code.contains("async all(signal) {"));
+        assert!(code.contains("async get(id, detail, signal) {"));
+        assert!(code.contains("signal: signal"));
+    }
+
+    #[test]
+    fn test_gen_query_encoding() {
+        let code = gen(&sample_root(), &Default::default()).unwrap();
+        assert!(!code.contains("serializeParams"));
+        let gen_encoded = |query_encoding| {
+            let opts = JsGenOptions {
+                query_encoding,
+                ..Default::default()
+            };
+            gen_with_options(&sample_root(), &Default::default(), &opts).unwrap()
+        };
+        let repeat = gen_encoded(QueryEncoding::Repeat);
+        assert!(repeat.contains("function serializeParams(params) {"));
+        assert!(repeat.contains("            value.forEach((v) => add(key, v));"));
+        assert!(repeat.contains("paramsSerializer: serializeParams"));
+        let brackets = gen_encoded(QueryEncoding::Brackets);
+        assert!(brackets.contains("            value.forEach((v) => add(`${key}[]`, v));"));
+        assert!(brackets.contains("paramsSerializer: serializeParams"));
+        let comma = gen_encoded(QueryEncoding::Comma);
+        assert!(comma.contains("            add(key, value.join(\",\"));"));
+        assert_ne!(repeat, brackets);
+        assert_ne!(brackets, comma);
+        // Only APIs with query params are given the serializer
+        assert_eq!(repeat.matches("paramsSerializer:").count(), 1);
+    }
+
+    #[test]
+    fn test_gen_context_value_single_ref() {
+        let value = ContextValue::Expr(sp::Expr::Ref(vec![
+            sp::Member::Super,
+            sp::Member::Member("url".to_string()),
+        ]));
+        let expr = gen_context_value(&value, &this_super, &Default::default());
+        assert_eq!(expr.gen(&GenContext::new()), "((this)._super).url");
+        let expr = gen_context_value(&value, &this, &Default::default());
+        assert_eq!(expr.gen(&GenContext::new()), "(this).url");
+    }
+
+    #[test]
+    fn test_gen_empty_url() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+~ratincren:
+  $url: \"\"
+  all:
+    $url: \"\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &GenContext::new()).unwrap();
+        assert!(code.contains("(this)._url = ((this)._super).url;"));
+        assert!(code.contains("url: (this).url"));
+    }
+
+    #[test]
+    fn test_gen_root_api() {
+        // The root has no `_super`, its APIs are relative to its own URL
+        let schema = parse_str(
+            "\
+public:
+  $url: \"${!super}/public\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &GenContext::new()).unwrap();
+        assert!(code.contains("url: ((this).url) + (\"/public\")"));
+        assert!(!code.contains("_super"));
+    }
+
+    #[test]
+    fn test_gen_no_root_url() {
+        // Without a root URL, the one passed to the client is what the
+        // top-level APISets resolve against
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/no_klsname_no_url/schema.yaml"
+        ));
+        let code = gen(&transform(schema.unwrap()).unwrap(), &GenContext::new()).unwrap();
+        assert!(code.contains("constructor(url) {\n(this)._url = url;"));
+        assert!(code.contains("(this)._url = (((this)._super).url) + (\"/users/\");"));
+        assert!(code.contains("url: (((this).url) + (\"/\")) + (id)"));
+        assert_eq!(code.matches("get url()").count(), 3);
+    }
+
+    #[test]
+    fn test_gen_sibling_url() {
+        let schema = include_str!("../../tests/fixtures/cases/sibling_url/schema.yaml");
+        let schema = parse_str(schema).unwrap();
+        let code = gen(&transform(schema).unwrap(), &GenContext::new()).unwrap();
+        // Refs to an APISet go through the getter of its URL
+        assert!(code.contains("(this)._url = ((((this)._super).ratincren).url) + (\"/ahcro\");"));
+        assert!(code.contains("url: ((((this)._super).ratincren).url) + (\"/budgets\")"));
+        assert_eq!(code.matches("get url()").count(), 3);
+    }
+
+    #[test]
+    fn test_gen_headers() {
+        let schema = include_str!("../../tests/fixtures/cases/headers/schema.yaml");
+        let schema = parse_str(schema).unwrap();
+        let code = gen(&transform(schema).unwrap(), &GenContext::new()).unwrap();
+        let headers = "headers: {\nAuthorization: \"Bearer xiaosi\",\nAccept: \"image/png\"\n}";
+        assert!(code.contains(headers));
+        assert_eq!(code.matches("headers: {").count(), 3);
+    }
+
+    #[test]
+    fn test_gen_absolute_url() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+~ratincren:
+  $url: \"${!super}/ratincren\"
+  avatar:
+    $url: \"https://cdn.ratina.org/avatars/<name:string>\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &GenContext::new()).unwrap();
+        assert!(code.contains("url: (\"https://cdn.ratina.org/avatars/\") + (name)"));
+        assert_eq!(code.matches("_super).url").count(), 1);
+    }
+
+    fn param_case_root() -> ContextBoundedRoot {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+getAhcro:
+  $url: \"${!super}/ahcro/<ahcro_id:uuid>\"
+  $params:
+    withDetail: \"boolean\"
+",
+        )
+        .unwrap();
+        transform(schema).unwrap()
+    }
+
+    #[test]
+    fn test_gen_param_case_camel() {
+        let opts = JsGenOptions {
+            param_case: ParamCase::Camel,
+            ..Default::default()
+        };
+        let code = gen_with_options(&param_case_root(), &GenContext::new(), &opts).unwrap();
+        assert!(code.contains("async getAhcro(ahcroId, withDetail) {"));
+        assert!(code.contains("(\"/ahcro/\")) + (ahcroId)"));
+        assert!(code.contains("withDetail: withDetail"));
+    }
+
+    #[test]
+    fn test_gen_param_case_snake() {
+        let opts = JsGenOptions {
+            param_case: ParamCase::Snake,
+            ..Default::default()
+        };
+        let code = gen_with_options(&param_case_root(), &GenContext::new(), &opts).unwrap();
+        assert!(code.contains("async getAhcro(ahcro_id, with_detail) {"));
+        assert!(code.contains("(\"/ahcro/\")) + (ahcro_id)"));
+        assert!(code.contains("withDetail: with_detail"));
+    }
+
+    #[test]
+    fn test_gen_param_idents() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+getAhcro:
+  $url: \"${!super}/ahcro/<class:uuid>\"
+  $params:
+    x-detail: \"boolean\"
+    2fa: \"string\"
+    response: \"string\"
+",
+        )
+        .unwrap();
+        let root = transform(schema).unwrap();
+        let code = gen(&root, &GenContext::new()).unwrap();
+        assert!(code.contains("async getAhcro(class_, x_detail, _2fa, response_) {"));
+        assert!(code.contains("(\"/ahcro/\")) + (class_)"));
+        assert!(code.contains("\"x-detail\": x_detail"));
+        assert!(code.contains("\"2fa\": _2fa"));
+        assert!(code.contains("response: response_"));
+        let opts = JsGenOptions {
+            style: ClientStyle::FactoryFunction,
+            ..Default::default()
+        };
+        let code = gen_with_options(&root, &GenContext::new(), &opts).unwrap();
+        assert!(code.contains("getAhcro: async (class_, x_detail, _2fa, response_) => {"));
+    }
+
+    #[test]
+    fn test_gen_param_ident_clash() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+getAhcro:
+  $url: \"${!super}/ahcro/<ahcro_id:uuid>\"
+  $params:
+    ahcroId: \"boolean\"
+",
+        )
+        .unwrap();
+        let root = transform(schema).unwrap();
+        assert!(gen(&root, &GenContext::new()).is_ok());
+        let opts = JsGenOptions {
+            param_case: ParamCase::Snake,
+            ..Default::default()
+        };
+        match gen_with_options(&root, &GenContext::new(), &opts) {
+            Err(GenError::NameClash { name, paths }) => {
+                assert_eq!(name, "ahcro_id");
+                assert_eq!(paths.0, vec!["getAhcro", "ahcro_id"]);
+                assert_eq!(paths.1, vec!["getAhcro", "ahcroId"]);
+            }
+            v => panic!("Expected a name clash, got {:?}", v),
+        }
+        assert!(gen_modules(&root, &GenContext::new(), &opts, "XiaoSiClient").is_err());
+    }
+
+    #[test]
+    fn test_gen_pruned() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+ahcro:
+  $url: \"${!super}/ahcro/<ahcroId:uuid>\"
+~ratincren:
+  $url: \"${!super}/ratincren\"
+  get:
+    $url: \"${!super}/<name:string>\"
+",
+        )
+        .unwrap();
+        let mut root = transform(schema).unwrap();
+        root.prune(&[vec!["ratincren".to_string()]]).unwrap();
+        let code = gen(&root, &Default::default()).unwrap();
+        assert!(code.contains("ratincren"));
+        assert!(!code.contains("ahcro"));
+    }
+
+    #[test]
+    fn test_gen_stream() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/stream/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &Default::default()).unwrap();
+        assert!(code.contains("async function* readEventStream(stream) {"));
+        assert!(code.contains("async *events(topic) {"));
+        assert!(code.contains("const response = await axios({"));
+        assert!(code.contains("responseType: \"stream\""));
+        assert!(code.contains("yield* readEventStream((response).data);"));
+        // Non-streaming APIs keep returning the response
+        assert!(code.contains("async get(id) {"));
+        assert!(!code.contains("*get("));
+    }
+
+    #[test]
+    fn test_gen_response_type() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/download/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &Default::default()).unwrap();
+        assert!(code.contains(
+            "\
+    async download() {
+        return axios({
+            method: \"get\",
+            url: ((this).url) + (\"/download\"),
+            responseType: \"blob\"
+        });"
+        ));
+        assert_eq!(code.matches("responseType").count(), 1);
+    }
+
+    #[test]
+    fn test_gen_paginate() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/paginate/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &Default::default()).unwrap();
+        // The API keeps its plain method
+        assert!(code.contains("    async all(status) {\n        return axios({"));
+        assert!(code.contains(
+            "\
+    async *iterAll(status) {
+        let cursor = undefined;
+        do {
+            const response = await axios({
+                method: \"get\",
+                url: (this).url,
+                params: {
+                    status: status,
+                    next: cursor
+                }
+            });
+            yield* ((response).data).results;
+            cursor = ((response).data).next;
+        } while (cursor);
+    }"
+        ));
+        assert!(code.contains("    async *iterSearch(term) {"));
+        assert!(code.contains(
+            "\
+                params: {
+                    after: cursor
+                }"
+        ));
+        assert!(code.contains("yield* (((response).data).data).items;"));
+        assert!(code.contains("cursor = (((response).data).meta).nextCursor;"));
+    }
+
+    #[test]
+    fn test_gen_query_params() {
+        let schema =
+            parse_str(include_str!("../../tests/fixtures/cases/query/schema.yaml")).unwrap();
+        let code = gen(&transform(schema).unwrap(), &Default::default()).unwrap();
+        // Defaulted params are always sent, and axios leaves out the optional
+        // ones that are undefined
+        assert!(code.contains(
+            "\
+    async search(q, filter, page = 1, size = 20, order) {
+        if (((order) !== (undefined)) && (!(([
+            \"asc\",
+            \"desc\"
+        ]).includes(order)))) {
+            throw new (Error)((\"order must be one of asc, desc, got \") + (order));
+        }
+        return axios({
+            method: \"get\",
+            url: ((this).url) + (\"/search\"),
+            params: {
+                q: q,
+                filter: filter,
+                page: page,
+                size: size,
+                order: order
+            }
+        });
+    }"
+        ));
+    }
+
+    #[test]
+    fn test_gen_crlf_url() {
+        // As a block scalar of a schema saved with CRLF line endings
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\\r\"
+~ratincren:
+  $url: \"${!super}/ratincren/\\r\\n  <group:int>\\r\"
+  all: {}
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &Default::default()).unwrap();
+        assert!(!code.contains('\r'));
+        assert!(!code.contains("\\r"));
+        assert!(code.contains("(this)._url = \"http://ratina.org\";"));
+    }
+
+    #[test]
+    fn test_gen_literal_url() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org/\\\\$\\\\<\"
+status:
+  $url: \"https://status.ratina.org\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &Default::default()).unwrap();
+        assert!(code.contains("(this)._url = \"http://ratina.org/$<\";"));
+        assert!(code.contains("url: \"https://status.ratina.org\"\n"));
+    }
+
+    #[test]
+    fn test_split_query() {
+        let (url, vars) = sp::parse_expr("${!super}/search?q=<term>&page=<page:int=1>").unwrap();
+        let (path, query) = split_query(&url, &vars).unwrap();
+        assert_eq!(path, sp::parse_expr("${!super}/search").unwrap().0);
+        assert_eq!(
+            query
+                .iter()
+                .map(|(k, p)| (k.as_str(), p.name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("q", "term"), ("page", "page")]
+        );
+        // The fragment stays at the end of the URL, without the query
+        for (url, path) in &[
+            ("${!super}?q=<term>#results", "${!super}#results"),
+            ("${!super}?q=<term>#<section>", "${!super}#<section>"),
+            ("${!super}/search?q=<term>#", "${!super}/search#"),
+        ] {
+            let (url, vars) = sp::parse_expr(url).unwrap();
+            let (url, query) = split_query(&url, &vars).unwrap();
+            assert_eq!(url, sp::parse_expr(path).unwrap().0);
+            assert_eq!(query.len(), 1);
+        }
+        // Left in the URL unless made of `key=<param>` pairs only
+        for url in &[
+            "${!super}/search",
+            "${!super}/search?q=all",
+            "${!super}/search?q=<q>&all",
+            "${!super}/search?q=<q>/<page>",
+            "${!super}/search?<q>",
+            "${!super}/search?q=<q>&q2=${!super.q}",
+            "${!super}/search#results?q=<q>",
+            "${!super}/search?q=<q>&#results",
+        ] {
+            let (url, vars) = sp::parse_expr(url).unwrap();
+            assert!(split_query(&url, &vars).is_none());
+        }
+    }
+
+    #[test]
+    fn test_default_literal() {
+        let ctx = Default::default();
+        let p = Param::new("page", Some("int".to_string()));
+        assert_eq!(default_literal(&p, "1").gen(&ctx), "1");
+        assert_eq!(default_literal(&p, "first").gen(&ctx), "\"first\"");
+        let p = Param::new("verbose", Some("boolean".to_string()));
+        assert_eq!(default_literal(&p, "true").gen(&ctx), "true");
+        let p = Param::new("sort", None);
+        assert_eq!(default_literal(&p, "1").gen(&ctx), "\"1\"");
+    }
+
+    #[test]
+    fn test_gen_data_default() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+create:
+  $method: POST
+  $data:
+    name: string
+    count: int = 10
+    draft: bool = true
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &Default::default()).unwrap();
+        assert!(code.contains("async create(name, count = 10, draft = true) {"));
+        assert!(!code.contains("\"10\""));
+    }
+
+    #[test]
+    fn test_gen_enum_guard() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org/<region:enum(eu,us)>\"
+~ratincren:
+  $url: \"${!super}/ratincren\"
+  list:
+    $url: \"${!super}/<status:enum(open,closed)>\"
+    $params:
+      order: \"enum(asc,desc)\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &Default::default()).unwrap();
+        assert!(code.contains(
+            "\
+    async list(status, order) {
+        if (!(([
+            \"open\",
+            \"closed\"
+        ]).includes(status))) {
+            throw new (Error)((\"status must be one of open, closed, got \") + (status));
+        }
+        if (((order) !== (undefined)) && (!(([
+            \"asc\",
+            \"desc\"
+        ]).includes(order)))) {
+            throw new (Error)((\"order must be one of asc, desc, got \") + (order));
+        }
+        return axios({"
+        ));
+        assert!(code
+            .contains("throw new (Error)((\"region must be one of eu, us, got \") + (region));"));
+    }
+
+    #[test]
+    fn test_gen_annotate() {
+        let opts = JsGenOptions {
+            annotate: true,
+            ..Default::default()
+        };
+        let code = gen_with_options(&sample_root(), &Default::default(), &opts).unwrap();
+        assert!(code.contains(
+            "\
+    async get(id, detail) {
+        // from schema: users.get
+        return axios({"
+        ));
+        assert!(code.contains("// from schema: users.budgets.all\n"));
+        assert!(!gen(&sample_root(), &Default::default())
+            .unwrap()
+            .contains("// from schema"));
+    }
+
+    #[test]
+    fn test_gen_commonjs_interop() {
+        let opts = JsGenOptions {
+            commonjs_interop: true,
+            ..Default::default()
+        };
+        let code = gen_with_options(&sample_root(), &Default::default(), &opts).unwrap();
+        assert!(code.ends_with(
+            "\
+(module).exports = XiaoSiClient;
+((module).exports).default = XiaoSiClient;
+"
+        ));
+        let (code, _) =
+            gen_modules(&sample_root(), &Default::default(), &opts, "XiaoSiClient").unwrap();
+        assert!(code.ends_with("((module).exports).default = XiaoSiClient;\n"));
+        assert!(!gen(&sample_root(), &Default::default())
+            .unwrap()
+            .contains("module.exports"));
+        assert!(!gen(&sample_root(), &Default::default())
+            .unwrap()
+            .contains("(module)"));
+    }
+
+    #[test]
+    fn test_gen_no_import() {
+        let opts = JsGenOptions {
+            no_import: true,
+            ..Default::default()
+        };
+        let code = gen_with_options(&sample_root(), &Default::default(), &opts).unwrap();
+        assert!(!code.contains("import"));
+        assert!(code.contains(
+            "\
+export default class XiaoSiClient {
+    constructor(httpClient) {
+        (this)._url = \"http://httpbin.org/\";
+        (this)._http = httpClient;
+    }"
+        ));
+        assert!(code.contains("(this)._http = (_super)._http;"));
+        assert!(code.contains("return (this)._http({"));
+        assert!(!code.contains("axios"));
+        let (code, files) =
+            gen_modules(&sample_root(), &Default::default(), &opts, "XiaoSiClient").unwrap();
+        assert!(!code.contains("import axios"));
+        assert!(files.iter().all(|f| !f.code.contains("import axios")));
+        let opts = JsGenOptions {
+            no_import: true,
+            node_keep_alive: true,
+            ..Default::default()
+        };
+        let code = gen_with_options(&sample_root(), &Default::default(), &opts).unwrap();
+        assert!(code.contains("(this)._http = (httpClient).create({"));
+        assert!(!code.contains("(this)._http = httpClient;"));
+    }
+
+    #[test]
+    fn test_gen_minify() {
+        let opts = GenOptions {
+            minify: true,
+            ..Default::default()
+        };
+        let output = JavaScript.generate(&sample_root(), &opts).unwrap();
+        assert!(output
+            .code
+            .contains("class users {\nconstructor(_super) {\n"));
+        assert!(output.code.lines().all(|l| !l.starts_with("  ")));
+        let output = JavaScript
+            .generate(&sample_root(), &Default::default())
+            .unwrap();
+        assert!(output
+            .code
+            .contains("class users {\n    constructor(_super) {\n"));
+    }
+
+    #[test]
+    fn test_gen_options_of_the_client() {
+        let opts = GenOptions::builder()
+            .node_keep_alive(true)
+            .cancellation(true)
+            .return_style(ReturnStyle::AwaitData)
+            .style(ClientStyle::FactoryFunction)
+            .build()
+            .unwrap();
+        let code = JavaScript.generate(&sample_root(), &opts).unwrap().code;
+        assert!(code.contains("httpAgent: new ((http).Agent)({"));
+        assert!(code.contains("usersGet: async (id, detail, signal) => {"));
+        assert!(code.contains("return (await client({"));
+        assert!(code.contains("export default function createClient("));
+    }
+
+    #[test]
+    fn test_gen_deprecated() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/deprecated/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &Default::default()).unwrap();
+        assert!(code.contains(
+            "\
+    /**
+     * @deprecated
+     */
+    async all() {"
+        ));
+        assert!(code.contains(
+            "\
+    /**
+     * @deprecated Use `search` instead
+     */
+    async list() {"
+        ));
+        assert!(code.contains(
+            "\
+    /**
+     * @deprecated Use `search` instead
+     */
+    async *iterList() {"
+        ));
+        assert!(code.contains("     * @deprecated\n     */\n    async search(q) {"));
+        assert!(code.contains("}\n    async get(id) {"));
+    }
+
+    #[test]
+    fn test_gen_returns() {
+        let yaml = include_str!("../../tests/fixtures/cases/returns/schema.yaml");
+        let gen_returns = |return_style| {
+            let schema = parse_str(yaml).unwrap();
+            let opts = JsGenOptions {
+                return_style,
+                ..Default::default()
+            };
+            gen_with_options(&transform(schema).unwrap(), &Default::default(), &opts).unwrap()
+        };
+        let code = gen_returns(ReturnStyle::Promise);
+        assert!(code.contains(
+            "\
+    /**
+     * @returns {Promise<import(\"axios\").AxiosResponse<User>>}
+     */
+    async get(id) {"
+        ));
+        assert!(code.contains(
+            "\
+    /**
+     * @deprecated Use `search` instead
+     * @returns {Promise<import(\"axios\").AxiosResponse<User[]>>}
+     */
+    async all() {"
+        ));
+        assert!(code.contains("}\n    async *events() {"));
+        assert!(code.contains("}\n    async create(name) {"));
+        let code = gen_returns(ReturnStyle::AwaitData);
+        assert!(code.contains("     * @returns {Promise<User>}\n     */\n    async get(id) {"));
+        let code = gen_returns(ReturnStyle::Callback);
+        assert!(!code.contains("@returns"));
+    }
+
+    #[test]
+    fn test_gen_return_styles() {
+        let gen_all = |return_style| {
+            let opts = JsGenOptions {
+                return_style,
+                ..Default::default()
+            };
+            gen_with_options(&sample_root(), &Default::default(), &opts).unwrap()
+        };
+        let code = gen_all(ReturnStyle::Promise);
+        assert!(code.contains("    async get(id, detail) {\n        return axios({"));
+
+        let code = gen_all(ReturnStyle::AwaitData);
+        assert!(code.contains("    async get(id, detail) {\n        return (await axios({"));
+        assert!(code.contains(
+            "\
+            params: {
+                detail: detail
+            },
+            headers: {
+                Accept: \"application/json\"
+            }
+        })).data;"
+        ));
+
+        let code = gen_all(ReturnStyle::Callback);
+        assert!(code.contains("    get(id, detail, cb, err) {\n        ((axios({"));
+        assert!(code.contains(
+            "\
+            params: {
+                detail: detail
+            },
+            headers: {
+                Accept: \"application/json\"
+            }
+        })).then(cb)).catch(err);"
+        ));
+        assert!(!code.contains("async "));
+    }
+
+    #[test]
+    fn test_gen_modules() {
+        let root = sample_root();
+        let opts = Default::default();
+        let (code, files) = gen_modules(&root, &Default::default(), &opts, "XiaoSiClient").unwrap();
+        assert!(code.contains("import users from \"./XiaoSiClient/users.js\";\n"));
+        assert!(code.contains("export default class XiaoSiClient {"));
+        assert!(!code.contains("class users"));
+        let paths = files
+            .iter()
+            .map(|f| f.path.clone())
+            .collect::<Vec<PathBuf>>();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("XiaoSiClient/users/budgets.js"),
+                PathBuf::from("XiaoSiClient/users.js"),
+            ]
+        );
+        assert!(files[0].code.contains("export default class budgets {"));
+        assert!(files[1]
+            .code
+            .contains("import budgets from \"./users/budgets.js\";\n"));
+        assert!(files[1].code.contains("export default class users {"));
+        // Together, the modules have the classes of the single file
+        let single = gen_with_options(&root, &Default::default(), &opts).unwrap();
+        for f in &files {
+            let kls = f.code.split("export default ").nth(1).unwrap();
+            assert!(single.contains(kls));
+        }
+    }
+
+    #[test]
+    fn test_gen_factory_function() {
+        let opts = JsGenOptions {
+            style: ClientStyle::FactoryFunction,
+            ..Default::default()
+        };
+        let code = gen_with_options(&sample_root(), &Default::default(), &opts).unwrap();
+        assert!(code.contains(
+            "export default function createClient(baseURL = \"http://httpbin.org/\") {\n    return {"
+        ));
+        assert!(!code.contains("class"));
+        assert!(!code.contains("this"));
+        let keys = code
+            .lines()
+            .filter_map(|line| line.strip_prefix("        "))
+            .filter_map(|line| line.strip_suffix(" => {"))
+            .map(|line| line.split(": async").next().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            keys,
+            vec![
+                "usersAll",
+                "usersGet",
+                "usersCreate",
+                "usersUpdate",
+                "usersBudgetsAll"
+            ]
+        );
+        assert!(code.contains(
+            "\
+        usersGet: async (id, detail) => {
+            return axios({
+                method: \"get\",
+                url: ((baseURL) + (\"/users/\")) + (encodeURIComponent(id)),
+                params: {
+                    detail: detail
+                },
+                headers: {
+                    Accept: \"application/json\"
+                }
+            });
+        },"
+        ));
+    }
+
+    #[test]
+    fn test_gen_factory_function_parameterized_root() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org/<region>\"
+~ratincren:
+  $url: \"${!super}/ratincren/<group:int>\"
+  list:
+    $url: \"${!super}?page=<page:int=1>\"
+",
+        )
+        .unwrap();
+        let opts = JsGenOptions {
+            style: ClientStyle::FactoryFunction,
+            no_import: true,
+            node_keep_alive: true,
+            commonjs_interop: true,
+            ..Default::default()
+        };
+        let code =
+            gen_with_options(&transform(schema).unwrap(), &Default::default(), &opts).unwrap();
+        assert!(code.contains(
+            "\
+export default function createClient(httpClient, baseURL) {
+    const client = (httpClient).create({"
+        ));
+        assert!(code.contains("ratincrenList: async (group, page = 1) => {"));
+        assert!(code.contains("return client({"));
+        assert!(code.contains("(module).exports = createClient;"));
+    }
+
+    #[test]
+    fn test_gen_factory_function_name_clash() {
+        let schema = parse_str(include_str!("../../tests/fixtures/name_clash.yaml")).unwrap();
+        let root = transform(schema).unwrap();
+        let opts = JsGenOptions {
+            style: ClientStyle::FactoryFunction,
+            ..Default::default()
+        };
+        match gen_with_options(&root, &Default::default(), &opts) {
+            Err(GenError::NameClash { name, paths }) => {
+                assert_eq!(name, "ahcroAll");
+                assert_eq!(paths.0, vec!["ahcroAll"]);
+                assert_eq!(paths.1, vec!["ahcro", "all"]);
+            }
+            v => panic!("Expected a name clash, got {:?}", v),
+        }
+        // Nested, the two don't meet
+        let opts = JsGenOptions {
+            style: ClientStyle::NestedObject,
+            ..Default::default()
+        };
+        assert!(gen_with_options(&root, &Default::default(), &opts).is_ok());
+    }
+
+    #[test]
+    fn test_gen_to() {
+        let root = sample_root();
+        for style in &[ClientStyle::Class, ClientStyle::FactoryFunction] {
+            let opts = JsGenOptions {
+                style: *style,
+                ..Default::default()
+            };
+            let mut buf = Vec::new();
+            gen_to(&root, &Default::default(), &opts, &mut buf).unwrap();
+            let code = gen_with_options(&root, &Default::default(), &opts).unwrap();
+            assert_eq!(String::from_utf8(buf).unwrap(), code);
+        }
+        // The error of the writer is kept
+        let mut full = [0u8; 16];
+        let e = gen_to(
+            &root,
+            &Default::default(),
+            &Default::default(),
+            &mut full[..],
+        );
+        match e {
+            Err(GenError::IOError(e)) => assert_eq!(e.kind(), io::ErrorKind::WriteZero),
+            e => panic!("Unexpected {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_gen_nested_object() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+~ratincren:
+  $url: \"${!super}/ratincren/<group:int>\"
+  get:
+    $url: \"${!super}/<id:int>\"
+  ~budgets:
+    $url: \"${!super}/budgets\"
+    all:
+      $url: \"${!super}\"
+list:
+  $url: \"${!super}/list\"
+",
+        )
+        .unwrap();
+        let opts = JsGenOptions {
+            style: ClientStyle::NestedObject,
+            ..Default::default()
+        };
+        let code =
+            gen_with_options(&transform(schema).unwrap(), &Default::default(), &opts).unwrap();
+        // `client.ratincren.get`, and `client.ratincren.budgets.all`
+        assert!(code.contains(
+            "\
+export default function createClient(baseURL = \"http://ratina.org\") {
+    return {
+        ratincren: {
+            get: async (group, id) => {
+                return axios({"
+        ));
+        assert!(code.contains(
+            "\
+            budgets: {
+                all: async (group) => {"
+        ));
+        let url =
+            "(((baseURL) + (\"/ratincren/\")) + (encodeURIComponent(group))) + (\"/budgets\")";
+        assert!(code.contains(&format!("url: {}\n", url)));
+        assert!(code.contains("        },\n        list: async () => {"));
+        assert!(!code.contains("class"));
+        assert!(!code.contains("this"));
+    }
 }
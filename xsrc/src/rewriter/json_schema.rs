@@ -0,0 +1,171 @@
+use super::{banner, Backend, GenError, GenOptions, GeneratedOutput};
+use crate::transformer::*;
+use serde_json::{json, Map, Value};
+
+const SCHEMA_URL: &str = "http://json-schema.org/draft-07/schema#";
+
+fn param_schema(p: &Param) -> Value {
+    let typ = match p.param_type() {
+        Some(ParamType::Enum(values)) => return json!({ "enum": values }),
+        Some(ParamType::Named(typ)) => typ,
+        None => return json!({}),
+    };
+    match typ.as_str() {
+        "int" | "integer" => json!({ "type": "integer" }),
+        "number" | "float" | "double" => json!({ "type": "number" }),
+        "bool" | "boolean" => json!({ "type": "boolean" }),
+        "string" => json!({ "type": "string" }),
+        "uuid" => json!({ "type": "string", "format": "uuid" }),
+        // Types unknown to JSON Schema accept anything
+        _ => json!({}),
+    }
+}
+
+/// The schema of the arguments of an API: the URL params, then the query and
+/// body params, all of which are required.
+fn gen_api(
+    root: &ContextBoundedRoot,
+    path: &[String],
+    api: &ContextBoundedAPI,
+) -> Result<Value, ContextLookupError> {
+    let template = root.url_template(path)?;
+    let mut properties = Map::new();
+    for p in template
+        .params()
+        .into_iter()
+        .chain(api.params.values())
+        .chain(api.data.values())
+    {
+        if !properties.contains_key(&p.name) {
+            properties.insert(p.name.to_string(), param_schema(p));
+        }
+    }
+    let required = properties.keys().cloned().collect::<Vec<String>>();
+    let mut schema = json!({
+        "title": path.join("."),
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    });
+    if let Some(description) = &api.description {
+        schema["description"] = Value::from(description.to_string());
+    }
+    Ok(schema)
+}
+
+/// Generates a JSON Schema (draft 7) document with a definition per API,
+/// named by its dotted path, e.g. `#/definitions/users.get`. Each definition
+/// describes the arguments of the API as an object.
+pub fn gen(root: &ContextBoundedRoot) -> Result<String, ContextLookupError> {
+    let mut definitions = Map::new();
+    for (path, api) in root.iter_apis() {
+        definitions.insert(path.join("."), gen_api(root, &path, api)?);
+    }
+    let document = json!({
+        "$schema": SCHEMA_URL,
+        "$comment": banner(&root.klsname),
+        "title": root.klsname,
+        "definitions": definitions,
+    });
+    Ok(format!(
+        "{}\n",
+        serde_json::to_string_pretty(&document).unwrap()
+    ))
+}
+
+pub struct JsonSchema;
+
+impl Backend for JsonSchema {
+    fn name(&self) -> &str {
+        "json-schema"
+    }
+
+    fn extension(&self) -> &str {
+        ".schema.json"
+    }
+
+    fn description(&self) -> &str {
+        "JSON Schema of the API params"
+    }
+
+    fn generate(
+        &self,
+        root: &ContextBoundedRoot,
+        _opts: &GenOptions,
+    ) -> Result<GeneratedOutput, GenError> {
+        Ok(GeneratedOutput {
+            code: gen(root)?,
+            files: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+    use jsonschema::JSONSchema;
+
+    #[test]
+    fn test_gen_sample() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert_eq!(
+            code,
+            include_str!("../../tests/fixtures/sample.schema.json")
+        );
+    }
+
+    #[test]
+    fn test_gen_required_params() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+~ahcros:
+  $url: \"${!super}/ahcros/<ahcroId:uuid>\"
+  update:
+    $method: \"PUT\"
+    $params:
+      force: \"boolean\"
+    $data:
+      size: \"int\"
+      status: \"enum(open,closed)\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        let document: Value = serde_json::from_str(&code).unwrap();
+        let update = &document["definitions"]["ahcros.update"];
+        assert_eq!(
+            update["required"],
+            json!(["ahcroId", "force", "size", "status"])
+        );
+        assert_eq!(
+            update["properties"],
+            json!({
+                "ahcroId": { "type": "string", "format": "uuid" },
+                "force": { "type": "boolean" },
+                "size": { "type": "integer" },
+                "status": { "enum": ["open", "closed"] },
+            })
+        );
+        let compiled = JSONSchema::compile(update).unwrap();
+        assert!(compiled.is_valid(&json!({
+            "ahcroId": "00000000-0000-0000-0000-000000000000",
+            "force": true,
+            "size": 3,
+            "status": "open",
+        })));
+        assert!(!compiled.is_valid(&json!({
+            "ahcroId": "00000000-0000-0000-0000-000000000000",
+            "force": true,
+            "size": "3",
+            "status": "open",
+        })));
+        assert!(!compiled.is_valid(&json!({ "force": true })));
+    }
+}
@@ -0,0 +1,285 @@
+use super::{
+    banner_lines, default_base_url, path_ident, reject_optional_params, Backend, GenError,
+    GenOptions, GeneratedOutput, UrlSplit,
+};
+use crate::transformer::*;
+
+const INDENT: &str = "    ";
+const EXAMPLE_UUID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Quotes a string as a JavaScript string literal.
+fn quote(s: &str) -> String {
+    serde_json::to_string(s).unwrap()
+}
+
+fn escape_template(s: &str) -> String {
+    s.replace("\\", "\\\\")
+        .replace("`", "\\`")
+        .replace("${", "\\${")
+}
+
+/// The declared default of the param, or else a value of its type, used as
+/// its default so that every request can be made without arguments.
+fn placeholder(p: &Param) -> String {
+    match p.declared_default().and_then(|v| p.coerce(v)) {
+        Some(DefaultValue::Number(n)) => return n.to_string(),
+        Some(DefaultValue::Boolean(b)) => return b.to_string(),
+        Some(DefaultValue::String(s)) => return quote(&s),
+        None => {}
+    }
+    match p.typ.as_ref().and_then(|v| v.split('|').next()) {
+        Some("int") | Some("integer") | Some("number") | Some("float") | Some("double") => {
+            "0".to_string()
+        }
+        Some("bool") | Some("boolean") => "false".to_string(),
+        Some("uuid") => quote(EXAMPLE_UUID),
+        _ => quote("example"),
+    }
+}
+
+fn gen_base_url(root: &ContextBoundedRoot) -> String {
+    match default_base_url(root) {
+        Some(s) => format!("const BASE_URL = __ENV.BASE_URL || {};", quote(&s)),
+        None => "const BASE_URL = __ENV.BASE_URL;".to_string(),
+    }
+}
+
+/// The URL as a template literal. The params of `$params` are added to the
+/// query of the URL, if it has one, and go before its fragment.
+fn gen_url(template: &UrlTemplate, api: &ContextBoundedAPI) -> String {
+    let query = api
+        .params
+        .keys()
+        .map(|k| format!("{}=${{encodeURIComponent({})}}", escape_template(k), k))
+        .collect::<Vec<String>>()
+        .join("&");
+    let url = UrlSplit::new(template).render(
+        |part| match part {
+            UrlPart::Lit(s) => escape_template(s),
+            UrlPart::Param(p) => format!("${{encodeURIComponent({})}}", p.name),
+            UrlPart::BaseUrl => "${BASE_URL}".to_string(),
+        },
+        Some(query.as_str()).filter(|v| !v.is_empty()),
+    );
+    format!("`{}`", url)
+}
+
+/// The request call. `http.get` and `http.head` take no body, the others take
+/// one before the params.
+fn gen_request(url: String, api: &ContextBoundedAPI) -> String {
+    let mut headers = api
+        .headers
+        .iter()
+        .map(|(k, v)| format!("{}: {}", quote(k), quote(v)))
+        .collect::<Vec<String>>();
+    let body = if !api.data.is_empty() {
+        if !api
+            .headers
+            .keys()
+            .any(|k| k.eq_ignore_ascii_case("content-type"))
+        {
+            headers.push(format!(
+                "{}: {}",
+                quote("Content-Type"),
+                quote("application/json")
+            ));
+        }
+        let fields = api
+            .data
+            .keys()
+            .map(|k| format!("{}: {}", quote(k), k))
+            .collect::<Vec<String>>()
+            .join(", ");
+        Some(format!("JSON.stringify({{ {} }})", fields))
+    } else {
+        None
+    };
+    let (func, takes_body) = match api.method {
+        HttpMethod::GET => ("get", false),
+        HttpMethod::HEAD => ("head", false),
+        HttpMethod::POST => ("post", true),
+        HttpMethod::PUT => ("put", true),
+        HttpMethod::PATCH => ("patch", true),
+        HttpMethod::DELETE => ("del", true),
+        HttpMethod::OPTIONS => ("options", true),
+    };
+    let mut args = vec![url];
+    if takes_body && (body.is_some() || !headers.is_empty()) {
+        args.push(body.unwrap_or("null".to_string()));
+    }
+    if !headers.is_empty() {
+        args.push(format!("{{ headers: {{ {} }} }}", headers.join(", ")));
+    }
+    format!("http.{}({})", func, args.join(", "))
+}
+
+fn gen_api(
+    root: &ContextBoundedRoot,
+    path: &[String],
+    api: &ContextBoundedAPI,
+) -> Result<String, ContextLookupError> {
+    let template = root.url_template(path)?;
+    let params = template
+        .params()
+        .into_iter()
+        .chain(api.params.values())
+        .chain(api.data.values())
+        .map(|p| format!("{} = {}", p.name, placeholder(p)))
+        .collect::<Vec<String>>();
+    Ok(format!(
+        "\
+export function {name}({params}) {{
+{indent}return {request};
+}}",
+        name = path_ident(path),
+        params = params.join(", "),
+        indent = INDENT,
+        request = gen_request(gen_url(&template, api), api)
+    ))
+}
+
+/// Calls each GET endpoint once with the placeholder values.
+fn gen_default(apis: &[(Vec<String>, &ContextBoundedAPI)]) -> String {
+    let checks = apis
+        .iter()
+        .filter(|(_, api)| api.method == HttpMethod::GET)
+        .map(|(path, _)| {
+            format!(
+                "{}check({}(), {{ {}: (r) => r.status === 200 }});",
+                INDENT,
+                path_ident(path),
+                quote(&format!("{} is status 200", path.join(".")))
+            )
+        })
+        .collect::<Vec<String>>();
+    format!("export default function () {{\n{}\n}}", checks.join("\n"))
+}
+
+/// Generates a k6 load test script with one exported function per API. The
+/// params of each function default to placeholders of their types, and the
+/// default function checks that every GET endpoint responds with 200. The
+/// base URL can be overridden with the `BASE_URL` environment variable.
+pub fn gen(root: &ContextBoundedRoot) -> Result<String, ContextLookupError> {
+    gen_with_options(root, &Default::default())
+}
+
+pub fn gen_with_options(
+    root: &ContextBoundedRoot,
+    opts: &GenOptions,
+) -> Result<String, ContextLookupError> {
+    let banner = banner_lines(&root.klsname, opts.banner.as_deref())
+        .iter()
+        .map(|v| format!("// {}", v))
+        .collect::<Vec<String>>();
+    let apis = root.iter_apis();
+    let mut sections = vec![
+        format!(
+            "{}\nimport http from \"k6/http\";\nimport {{ check }} from \"k6\";",
+            banner.join("\n")
+        ),
+        format!(
+            "export const options = {{\n{indent}vus: 1,\n{indent}iterations: 1,\n}};",
+            indent = INDENT
+        ),
+        gen_base_url(root),
+    ];
+    for (path, api) in &apis {
+        sections.push(gen_api(root, path, api)?);
+    }
+    sections.push(gen_default(&apis));
+    Ok(format!("{}\n", sections.join("\n\n")))
+}
+
+pub struct K6;
+
+impl Backend for K6 {
+    fn name(&self) -> &str {
+        "k6"
+    }
+
+    fn extension(&self) -> &str {
+        ".k6.js"
+    }
+
+    fn description(&self) -> &str {
+        "k6 load test script"
+    }
+
+    fn generate(
+        &self,
+        root: &ContextBoundedRoot,
+        opts: &GenOptions,
+    ) -> Result<GeneratedOutput, GenError> {
+        reject_optional_params(root, self.name())?;
+        Ok(GeneratedOutput {
+            code: gen_with_options(root, opts)?,
+            files: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+
+    #[test]
+    fn test_gen_sample() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert_eq!(code, include_str!("../../tests/fixtures/sample.k6.js"));
+    }
+
+    #[test]
+    fn test_gen_placeholders() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org/<tenant:string>\"
+getAhcro:
+  $url: \"${!super}/ahcro/<ahcroId:uuid>\"
+  $params:
+    withDetail: \"boolean|default:true\"
+    limit: \"int\"
+    order: \"string = asc\"
+deleteAhcro:
+  $url: \"${!super}/ahcro/<ahcroId:uuid>\"
+  $method: \"DELETE\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert!(code.contains("const BASE_URL = __ENV.BASE_URL;"));
+        assert!(code.contains(
+            "export function getAhcro(ahcroId = \"00000000-0000-0000-0000-000000000000\", withDetail = true, limit = 0, order = \"asc\") {"
+        ));
+        assert!(code.contains(
+            "return http.get(`${BASE_URL}/ahcro/${encodeURIComponent(ahcroId)}?withDetail=${encodeURIComponent(withDetail)}&limit=${encodeURIComponent(limit)}&order=${encodeURIComponent(order)}`);"
+        ));
+        assert!(
+            code.contains("return http.del(`${BASE_URL}/ahcro/${encodeURIComponent(ahcroId)}`);")
+        );
+        assert!(code.contains("check(getAhcro(), "));
+        assert!(!code.contains("check(deleteAhcro(), "));
+    }
+
+    #[test]
+    fn test_gen_url_with_query() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+search:
+  $url: \"${!super}/search?lang=<lang:string>#results\"
+  $params:
+    q: \"string\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert!(code.contains(
+            "http.get(`${BASE_URL}/search?lang=${encodeURIComponent(lang)}&q=${encodeURIComponent(q)}#results`)"
+        ));
+    }
+}
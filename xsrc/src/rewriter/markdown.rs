@@ -0,0 +1,219 @@
+use super::{banner_lines, Backend, GenError, GenOptions, GeneratedOutput};
+use crate::transformer::*;
+use linked_hash_map::LinkedHashMap;
+
+fn escape_cell(s: &str) -> String {
+    s.replace("|", "\\|")
+}
+
+fn gen_template(template: &UrlTemplate, base_url: &UrlTemplate) -> String {
+    let mut rendered = String::new();
+    for part in &template.0 {
+        match part {
+            UrlPart::Lit(s) => rendered.push_str(s),
+            UrlPart::Param(p) => rendered.push_str(&format!("{{{}}}", p.name)),
+            UrlPart::BaseUrl => rendered.push_str(&gen_template(base_url, base_url)),
+        }
+    }
+    rendered
+}
+
+fn param_row(p: &Param, location: &str) -> String {
+    format!(
+        "| `{}` | {} | {} |",
+        p.name,
+        location,
+        p.typ
+            .as_ref()
+            .map_or(String::new(), |typ| format!("`{}`", escape_cell(typ)))
+    )
+}
+
+fn gen_api(
+    root: &ContextBoundedRoot,
+    path: &[String],
+    api: &ContextBoundedAPI,
+    sections: &mut Vec<String>,
+) -> Result<(), ContextLookupError> {
+    let template = root.url_template(path)?;
+    sections.push(format!("### {}", path.join(".")));
+    if let Some(reason) = api.deprecated() {
+        sections.push(match reason {
+            Some(reason) => format!("> **Deprecated:** {}", reason),
+            None => "> **Deprecated**".to_string(),
+        });
+    }
+    if let Some(description) = api.description() {
+        sections.push(description.trim_end().to_string());
+    }
+    sections.push(format!(
+        "`{:?} {}`",
+        api.method(),
+        gen_template(&template, &root.base_url())
+    ));
+    let mut rows = template
+        .params()
+        .into_iter()
+        .map(|p| param_row(p, "path"))
+        .collect::<Vec<String>>();
+    rows.extend(api.params().values().map(|p| param_row(p, "query")));
+    rows.extend(api.data().values().map(|p| param_row(p, "body")));
+    if !rows.is_empty() {
+        let mut table = vec![
+            "| Name | In | Type |".to_string(),
+            "| --- | --- | --- |".to_string(),
+        ];
+        table.extend(rows);
+        sections.push(table.join("\n"));
+    }
+    Ok(())
+}
+
+/// APIs come before nested APISets so that they stay under their heading
+fn gen_children(
+    root: &ContextBoundedRoot,
+    children: &LinkedHashMap<String, ContextBoundedAPIData>,
+    path: &mut Vec<String>,
+    sections: &mut Vec<String>,
+) -> Result<(), ContextLookupError> {
+    for (name, child) in children {
+        if let ContextBoundedAPIData::API(api) = child {
+            path.push(name.to_string());
+            gen_api(root, path, api, sections)?;
+            path.pop();
+        }
+    }
+    for (name, child) in children {
+        if let ContextBoundedAPIData::APISet(apiset) = child {
+            path.push(name.to_string());
+            sections.push(format!("## {}", path.join(".")));
+            if let Some(description) = apiset.description() {
+                sections.push(description.trim_end().to_string());
+            }
+            gen_children(root, apiset.children(), path, sections)?;
+            path.pop();
+        }
+    }
+    Ok(())
+}
+
+/// Renders a reference document of the APIs, with a section per APISet.
+pub fn gen(root: &ContextBoundedRoot) -> Result<String, ContextLookupError> {
+    gen_with_options(root, &Default::default())
+}
+
+pub fn gen_with_options(
+    root: &ContextBoundedRoot,
+    opts: &GenOptions,
+) -> Result<String, ContextLookupError> {
+    let banner = banner_lines(&root.klsname, opts.banner.as_deref())
+        .iter()
+        .map(|v| format!("<!-- {} -->", v))
+        .collect::<Vec<String>>();
+    let mut sections = vec![banner.join("\n"), format!("# {}", root.klsname())];
+    if let Some(description) = root.description() {
+        sections.push(description.trim_end().to_string());
+    }
+    let base_url = root.base_url();
+    sections.push(format!(
+        "Base URL: `{}`",
+        gen_template(&base_url, &base_url)
+    ));
+    gen_children(root, root.children(), &mut Vec::new(), &mut sections)?;
+    Ok(format!("{}\n", sections.join("\n\n")))
+}
+
+pub struct Markdown;
+
+impl Backend for Markdown {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn extension(&self) -> &str {
+        ".md"
+    }
+
+    fn description(&self) -> &str {
+        "Markdown API reference"
+    }
+
+    fn generate(
+        &self,
+        root: &ContextBoundedRoot,
+        opts: &GenOptions,
+    ) -> Result<GeneratedOutput, GenError> {
+        Ok(GeneratedOutput {
+            code: gen_with_options(root, opts)?,
+            files: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+
+    #[test]
+    fn test_gen_sample() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert_eq!(code, include_str!("../../tests/fixtures/sample.md"));
+    }
+
+    #[test]
+    fn test_gen_descriptions() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+$as: \"RatinaClient\"
+$description: \"Ratina API\"
+~ratincren:
+  $url: \"${!super}/ratincren/<group:int>\"
+  $description: \"Everything about ratincren\"
+  get:
+    $url: \"${!super}/<name:string>\"
+    $description: \"Fetches a ratincren\"
+    $deprecated: \"Use `find` instead\"
+    $params:
+      verbose: \"boolean|default:true\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert_eq!(
+            code,
+            "\
+<!-- RatinaClient generated by xsrc 0.1.0 -->
+
+# RatinaClient
+
+Ratina API
+
+Base URL: `http://ratina.org`
+
+## ratincren
+
+Everything about ratincren
+
+### ratincren.get
+
+> **Deprecated:** Use `find` instead
+
+Fetches a ratincren
+
+`GET http://ratina.org/ratincren/{group}/{name}`
+
+| Name | In | Type |
+| --- | --- | --- |
+| `group` | path | `int` |
+| `name` | path | `string` |
+| `verbose` | query | `boolean\\|default:true` |
+"
+        );
+    }
+}
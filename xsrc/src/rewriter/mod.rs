@@ -1,7 +1,1235 @@
-use super::transformer::ContextBoundedRoot;
+use super::schema::RootSchema;
+use super::timing::Instant;
+use super::transformer::{ContextBoundedRoot, ContextLookupError};
+#[cfg(any(
+    feature = "backend-curl",
+    feature = "backend-http",
+    feature = "backend-k6",
+    feature = "backend-postman"
+))]
+use super::transformer::{UrlPart, UrlTemplate};
+use codegen::utils::{to_camel_case, to_snake_case};
+use log::{debug, info};
+use serde::de::{self, Deserialize, Deserializer};
+use serde_derive::Deserialize;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
 
+#[cfg(feature = "backend-angular")]
+pub mod angular;
+#[cfg(feature = "backend-curl")]
+pub mod curl;
+#[cfg(feature = "backend-http")]
+pub mod http;
+#[cfg(feature = "backend-insomnia")]
+pub mod insomnia;
+#[cfg(feature = "backend-javascript")]
 pub mod javascript;
+#[cfg(feature = "backend-json-schema")]
+pub mod json_schema;
+#[cfg(feature = "backend-k6")]
+pub mod k6;
+#[cfg(feature = "backend-markdown")]
+pub mod markdown;
+#[cfg(feature = "backend-node-cli")]
+pub mod node_cli;
+#[cfg(feature = "backend-php")]
+pub mod php;
+#[cfg(feature = "backend-postman")]
+pub mod postman;
+#[cfg(feature = "backend-ruby")]
+pub mod ruby;
 
-pub trait CodeGen {
-    fn gen(&self, root: ContextBoundedRoot);
+/// Options for the backends, each of which picks the ones that apply to it.
+/// They are read with the names of the command line flags, e.g. from a TOML
+/// table:
+///
+/// ```
+/// use xsrc::rewriter::ParamCase;
+/// use xsrc::GenOptions;
+///
+/// let opts: GenOptions = toml::from_str("param-case = \"camel\"\nminify = true").unwrap();
+/// assert_eq!(opts.param_case, ParamCase::Camel);
+/// assert!(opts.validate().is_ok());
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GenOptions {
+    pub param_case: ParamCase,
+    pub query_encoding: QueryEncoding,
+    /// Comment the generated methods with the paths of their APIs
+    pub annotate: bool,
+    /// Generate each APISet class into a file of its own, under this
+    /// directory next to the main file. Only some backends can. Set from the
+    /// name of the output file, not read.
+    #[serde(skip)]
+    pub module_dir: Option<String>,
+    /// Type `uuid` params with a branded alias of `string` rather than
+    /// `string` itself, so that other strings can't be passed for them.
+    /// TypeScript only.
+    pub branded_types: bool,
+    /// Also export the client as `module.exports`, for CommonJS consumers.
+    /// JavaScript only.
+    pub commonjs_interop: bool,
+    /// Leave out the import of the HTTP library and take the client as a
+    /// constructor param instead. JavaScript only.
+    pub no_import: bool,
+    /// Leave out the indentation. JavaScript only.
+    pub minify: bool,
+    /// Send the requests through a keep-alive `http.Agent`. JavaScript only,
+    /// for Node.
+    pub node_keep_alive: bool,
+    /// Take an optional `signal` in every method, so that its request can be
+    /// aborted. JavaScript only.
+    pub cancellation: bool,
+    /// What the methods give back. JavaScript only.
+    pub return_style: ReturnStyle,
+    /// The shape of the client. JavaScript only.
+    pub style: ClientStyle,
+    /// A comment put above the banner of xsrc, such as a notice not to edit
+    /// the file, with each of its lines commented. Formats without comments,
+    /// the JSON ones, leave it out.
+    pub banner: Option<String>,
+}
+
+impl GenOptions {
+    pub fn builder() -> GenOptionsBuilder {
+        Default::default()
+    }
+
+    /// Checks that the options go together
+    pub fn validate(&self) -> Result<(), GenOptionsError> {
+        if self.minify && self.annotate {
+            return Err(GenOptionsError::Conflict("minify", "annotate"));
+        }
+        if let Some("") = self.module_dir.as_deref() {
+            return Err(GenOptionsError::EmptyModuleDir);
+        }
+        // Only the classes can be split into modules
+        if self.module_dir.is_some() && self.style != ClientStyle::Class {
+            return Err(GenOptionsError::Conflict("split", "style"));
+        }
+        Ok(())
+    }
+}
+
+/// `GenOptions` set one by one and validated at the end:
+///
+/// ```
+/// use xsrc::rewriter::ParamCase;
+/// use xsrc::GenOptions;
+///
+/// let opts = GenOptions::builder()
+///     .param_case(ParamCase::Snake)
+///     .annotate(true)
+///     .build()
+///     .unwrap();
+/// assert!(opts.annotate);
+/// ```
+#[derive(Debug, Default)]
+pub struct GenOptionsBuilder {
+    opts: GenOptions,
+}
+
+impl GenOptionsBuilder {
+    pub fn param_case(mut self, param_case: ParamCase) -> Self {
+        self.opts.param_case = param_case;
+        self
+    }
+
+    pub fn query_encoding(mut self, query_encoding: QueryEncoding) -> Self {
+        self.opts.query_encoding = query_encoding;
+        self
+    }
+
+    pub fn annotate(mut self, annotate: bool) -> Self {
+        self.opts.annotate = annotate;
+        self
+    }
+
+    pub fn module_dir(mut self, module_dir: &str) -> Self {
+        self.opts.module_dir = Some(module_dir.to_string());
+        self
+    }
+
+    pub fn branded_types(mut self, branded_types: bool) -> Self {
+        self.opts.branded_types = branded_types;
+        self
+    }
+
+    pub fn commonjs_interop(mut self, commonjs_interop: bool) -> Self {
+        self.opts.commonjs_interop = commonjs_interop;
+        self
+    }
+
+    pub fn no_import(mut self, no_import: bool) -> Self {
+        self.opts.no_import = no_import;
+        self
+    }
+
+    pub fn minify(mut self, minify: bool) -> Self {
+        self.opts.minify = minify;
+        self
+    }
+
+    pub fn node_keep_alive(mut self, node_keep_alive: bool) -> Self {
+        self.opts.node_keep_alive = node_keep_alive;
+        self
+    }
+
+    pub fn cancellation(mut self, cancellation: bool) -> Self {
+        self.opts.cancellation = cancellation;
+        self
+    }
+
+    pub fn return_style(mut self, return_style: ReturnStyle) -> Self {
+        self.opts.return_style = return_style;
+        self
+    }
+
+    pub fn style(mut self, style: ClientStyle) -> Self {
+        self.opts.style = style;
+        self
+    }
+
+    pub fn banner(mut self, banner: &str) -> Self {
+        self.opts.banner = Some(banner.to_string());
+        self
+    }
+
+    pub fn build(self) -> Result<GenOptions, GenOptionsError> {
+        self.opts.validate()?;
+        Ok(self.opts)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GenOptionsError {
+    /// Two options, named as the command line flags, that can't be used
+    /// together
+    Conflict(&'static str, &'static str),
+    EmptyModuleDir,
+}
+
+impl fmt::Display for GenOptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenOptionsError::Conflict(a, b) => {
+                write!(f, "The options {} and {} can't be used together", a, b)
+            }
+            GenOptionsError::EmptyModuleDir => write!(f, "The module directory can't be empty"),
+        }
+    }
+}
+
+impl std::error::Error for GenOptionsError {}
+
+/// A file generated besides the main one
+#[derive(Debug, PartialEq)]
+pub struct GeneratedFile {
+    /// Relative to the directory of the main file
+    pub path: PathBuf,
+    pub code: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GeneratedOutput {
+    /// The code of the main file
+    pub code: String,
+    pub files: Vec<GeneratedFile>,
+}
+
+#[derive(Debug)]
+pub enum GenError {
+    ContextLookupError(ContextLookupError),
+    /// An error of a backend defined outside xsrc
+    Other(String),
+    /// An error writing the code with [`Backend::generate_to`]
+    IOError(io::Error),
+    /// Two APIs that would be generated under the same name, such as
+    /// `ahcroAll` and `ahcro.all` flattened into one object
+    NameClash {
+        name: String,
+        paths: (Vec<String>, Vec<String>),
+    },
+    /// A param of the URL of an API that can be left out or has a default,
+    /// as in `<filter?>` or `<page:int=1>`, which the backend can't generate
+    UnsupportedParam {
+        backend: String,
+        path: Vec<String>,
+        param: String,
+    },
+}
+
+impl From<ContextLookupError> for GenError {
+    fn from(e: ContextLookupError) -> Self {
+        GenError::ContextLookupError(e)
+    }
+}
+
+impl From<io::Error> for GenError {
+    fn from(e: io::Error) -> Self {
+        GenError::IOError(e)
+    }
+}
+
+impl fmt::Display for GenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenError::ContextLookupError(e) => write!(f, "Context lookup error: {}", e),
+            GenError::Other(e) => write!(f, "{}", e),
+            GenError::IOError(e) => write!(f, "IO error: {}", e),
+            GenError::NameClash { name, paths } => write!(
+                f,
+                "{} and {} are both generated as {}",
+                paths.0.join("."),
+                paths.1.join("."),
+                name
+            ),
+            GenError::UnsupportedParam {
+                backend,
+                path,
+                param,
+            } => write!(
+                f,
+                "{}: {} can't leave out or fill in the param {} of the URL, \
+                 only JavaScript can",
+                path.join("."),
+                backend,
+                param
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GenError::ContextLookupError(e) => Some(e),
+            GenError::Other(_) => None,
+            GenError::IOError(e) => Some(e),
+            GenError::NameClash { .. } | GenError::UnsupportedParam { .. } => None,
+        }
+    }
+}
+
+/// A code generator for a language or tool.
+///
+/// Crates using xsrc as a library can add languages of their own by
+/// implementing it and registering the backend with [`Registry::register`].
+/// Backends work on the public API of [`ContextBoundedRoot`], which is kept
+/// stable for them.
+pub trait Backend {
+    /// The name that selects the backend, e.g. with `--lang`
+    fn name(&self) -> &str;
+    /// The extension of the generated file, including the leading dot
+    fn extension(&self) -> &str;
+    /// A line about what is generated, for listing the backends
+    fn description(&self) -> &str;
+    /// The name of the file generated for a client class: the class name as
+    /// is, unless the language names its files otherwise
+    fn file_name(&self, klsname: &str) -> String {
+        format!("{}{}", klsname, self.extension())
+    }
+    fn generate(
+        &self,
+        root: &ContextBoundedRoot,
+        opts: &GenOptions,
+    ) -> Result<GeneratedOutput, GenError>;
+    /// Writes the code of the main file into `w` as it is generated, and
+    /// returns the other files. Backends that can't generate piece by piece
+    /// keep the default, which writes the code of [`Backend::generate`].
+    fn generate_to(
+        &self,
+        root: &ContextBoundedRoot,
+        opts: &GenOptions,
+        w: &mut dyn io::Write,
+    ) -> Result<Vec<GeneratedFile>, GenError> {
+        let output = self.generate(root, opts)?;
+        w.write_all(output.code.as_bytes())?;
+        Ok(output.files)
+    }
+}
+
+/// The name of the file generated from a schema when none is given: after its
+/// client class, or after the schema file if the class isn't named.
+/// `schema_stem` is the name of the schema file without the extension.
+pub fn output_file_name(backend: &dyn Backend, klsname: &str, schema_stem: Option<&str>) -> String {
+    match schema_stem {
+        Some(stem) if klsname == RootSchema::default_klsname() => backend.file_name(stem),
+        _ => backend.file_name(klsname),
+    }
+}
+
+/// What the banner of every generated file says after the name of the client
+/// class, so that the files can be told apart from those written by hand
+pub const GENERATED_BY: &str = "generated by xsrc";
+
+/// Whether `code` has the banner of xsrc in its leading comments, which may
+/// follow a shebang or `<?php`. JSON has no comments, and carries the banner
+/// in one of the keys opening its object instead.
+pub fn has_banner(code: &str) -> bool {
+    let is_header = |line: &str| {
+        let line = line.trim();
+        let is_comment = ["//", "#", "/*", "*", "<!--"]
+            .iter()
+            .any(|v| line.starts_with(v));
+        // `{` or `"key": ` followed by a scalar or the opening of an object
+        let is_json_key =
+            line == "{" || (line.starts_with('"') && line.contains("\": ") && !line.ends_with('['));
+        line.is_empty() || line == "<?php" || is_comment || is_json_key
+    };
+    code.lines()
+        .take_while(|line| is_header(line))
+        .any(|line| line.contains(GENERATED_BY))
+}
+
+/// The text of the banner, without the comment syntax of the language, with
+/// the version of xsrc
+pub fn banner(klsname: &str) -> String {
+    format!("{} {} {}", klsname, GENERATED_BY, env!("CARGO_PKG_VERSION"))
+}
+
+/// What `--banner` puts above the banner when given no text
+pub const DEFAULT_BANNER: &str = "AUTO-GENERATED by xsrc. Do not edit.";
+
+/// The lines of the comment at the top of a generated file: those of `text`,
+/// the [`GenOptions::banner`], then the banner itself
+pub fn banner_lines(klsname: &str, text: Option<&str>) -> Vec<String> {
+    let mut lines = text.map_or(Vec::new(), |v| v.lines().map(|v| v.to_string()).collect());
+    lines.push(banner(klsname));
+    lines
+}
+
+/// Generates the code of `root` with `backend`, logging how much came out
+pub fn generate(
+    backend: &dyn Backend,
+    root: &ContextBoundedRoot,
+    opts: &GenOptions,
+) -> Result<GeneratedOutput, GenError> {
+    let start = Instant::now();
+    let output = backend.generate(root, opts)?;
+    for file in &output.files {
+        debug!(
+            "Generated {} ({} bytes)",
+            file.path.display(),
+            file.code.len()
+        );
+    }
+    info!(
+        "Codegen ({}) produced {} bytes in {:?}",
+        backend.name(),
+        output.code.len() + output.files.iter().map(|v| v.code.len()).sum::<usize>(),
+        start.elapsed()
+    );
+    Ok(output)
+}
+
+/// Counts the bytes written through it
+struct Counter<'a> {
+    w: &'a mut dyn io::Write,
+    count: usize,
+}
+
+impl io::Write for Counter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.w.write(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.w.flush()
+    }
+}
+
+/// Generates the code of `root` with `backend` into `w`, logging how much
+/// came out, and returns the files besides the main one
+pub fn generate_to(
+    backend: &dyn Backend,
+    root: &ContextBoundedRoot,
+    opts: &GenOptions,
+    w: &mut dyn io::Write,
+) -> Result<Vec<GeneratedFile>, GenError> {
+    let start = Instant::now();
+    let mut counter = Counter { w, count: 0 };
+    let files = backend.generate_to(root, opts, &mut counter)?;
+    for file in &files {
+        debug!(
+            "Generated {} ({} bytes)",
+            file.path.display(),
+            file.code.len()
+        );
+    }
+    info!(
+        "Codegen ({}) produced {} bytes in {:?}",
+        backend.name(),
+        counter.count + files.iter().map(|v| v.code.len()).sum::<usize>(),
+        start.elapsed()
+    );
+    Ok(files)
+}
+
+/// Backends by name, in the order they were registered
+pub struct Registry {
+    backends: Vec<Box<dyn Backend>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry {
+            backends: Vec::new(),
+        }
+    }
+
+    /// Registers a backend, replacing any registered under the same name.
+    pub fn register(&mut self, backend: Box<dyn Backend>) {
+        self.backends.retain(|v| v.name() != backend.name());
+        self.backends.push(backend);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Backend> {
+        self.backends
+            .iter()
+            .find(|v| v.name() == name)
+            .map(|v| v.as_ref())
+    }
+
+    pub fn backends(&self) -> Vec<&dyn Backend> {
+        self.backends.iter().map(|v| v.as_ref()).collect()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.backends.iter().map(|v| v.name()).collect()
+    }
+}
+
+/// A registry of the built-in backends, those compiled in with their
+/// `backend-*` features
+impl Default for Registry {
+    fn default() -> Self {
+        // Not mutated without any backend compiled in
+        #[allow(unused_mut)]
+        let mut registry = Registry::new();
+        #[cfg(feature = "backend-javascript")]
+        registry.register(Box::new(javascript::JavaScript));
+        #[cfg(feature = "backend-ruby")]
+        registry.register(Box::new(ruby::Ruby));
+        #[cfg(feature = "backend-php")]
+        registry.register(Box::new(php::Php));
+        #[cfg(feature = "backend-curl")]
+        registry.register(Box::new(curl::Curl));
+        #[cfg(feature = "backend-postman")]
+        registry.register(Box::new(postman::Postman));
+        #[cfg(feature = "backend-insomnia")]
+        registry.register(Box::new(insomnia::Insomnia));
+        #[cfg(feature = "backend-http")]
+        registry.register(Box::new(http::Http));
+        #[cfg(feature = "backend-markdown")]
+        registry.register(Box::new(markdown::Markdown));
+        #[cfg(feature = "backend-angular")]
+        registry.register(Box::new(angular::Angular));
+        #[cfg(feature = "backend-k6")]
+        registry.register(Box::new(k6::K6));
+        #[cfg(feature = "backend-node-cli")]
+        registry.register(Box::new(node_cli::NodeCli));
+        #[cfg(feature = "backend-json-schema")]
+        registry.register(Box::new(json_schema::JsonSchema));
+        registry
+    }
+}
+
+/// Casing applied to identifiers derived from schema param names
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum ParamCase {
+    #[default]
+    None,
+    Camel,
+    Snake,
+}
+
+impl ParamCase {
+    pub fn apply(&self, name: &str) -> String {
+        match self {
+            ParamCase::None => name.to_string(),
+            ParamCase::Camel => to_camel_case(name),
+            ParamCase::Snake => to_snake_case(name),
+        }
+    }
+}
+
+/// With the names taken by `FromStr`
+impl<'de> Deserialize<'de> for ParamCase {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl FromStr for ParamCase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(ParamCase::None),
+            "camel" => Ok(ParamCase::Camel),
+            "snake" => Ok(ParamCase::Snake),
+            _ => Err(format!("Unknown param case: {}", s)),
+        }
+    }
+}
+
+/// How arrays in query params are encoded. `None` leaves it to the HTTP
+/// library.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum QueryEncoding {
+    #[default]
+    None,
+    // a=1&a=2
+    Repeat,
+    // a[]=1&a[]=2
+    Brackets,
+    // a=1,2
+    Comma,
+}
+
+/// With the names taken by `FromStr`
+impl<'de> Deserialize<'de> for QueryEncoding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl FromStr for QueryEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(QueryEncoding::None),
+            "repeat" => Ok(QueryEncoding::Repeat),
+            "brackets" => Ok(QueryEncoding::Brackets),
+            "comma" => Ok(QueryEncoding::Comma),
+            _ => Err(format!("Unknown query encoding: {}", s)),
+        }
+    }
+}
+
+/// What the methods of non-stream APIs give back
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum ReturnStyle {
+    /// The promise of the axios response
+    #[default]
+    Promise,
+    /// The response body, awaited
+    AwaitData,
+    /// Nothing. The response is passed to a `cb` param, and the error to an
+    /// `err` param.
+    Callback,
+}
+
+/// With the names taken by `FromStr`
+impl<'de> Deserialize<'de> for ReturnStyle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl FromStr for ReturnStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "promise" => Ok(ReturnStyle::Promise),
+            "await-data" => Ok(ReturnStyle::AwaitData),
+            "callback" => Ok(ReturnStyle::Callback),
+            _ => Err(format!("Unknown return style: {}", s)),
+        }
+    }
+}
+
+/// The shape of the generated client
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum ClientStyle {
+    /// A class per APISet, reached through getters of the root class
+    #[default]
+    Class,
+    /// A default-exported `createClient(baseURL)` returning an object of
+    /// async functions, one per API, named after their paths as in
+    /// `usersBudgetsAll`. Streaming APIs resolve to the axios response of the
+    /// stream, and paginated APIs get no iterator.
+    FactoryFunction,
+    /// Like `FactoryFunction`, but returning the functions in objects nested
+    /// as the APISets, named after their keys, as in
+    /// `client.users.budgets.all()`
+    NestedObject,
+}
+
+/// With the names taken by `FromStr`
+impl<'de> Deserialize<'de> for ClientStyle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl FromStr for ClientStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "class" => Ok(ClientStyle::Class),
+            "factory-function" => Ok(ClientStyle::FactoryFunction),
+            "nested-object" => Ok(ClientStyle::NestedObject),
+            _ => Err(format!("Unknown client style: {}", s)),
+        }
+    }
+}
+
+/// A pretty-printed JSON object with a placeholder value for each body param,
+/// for exporters that show the body as an editable example.
+#[cfg(any(
+    feature = "backend-http",
+    feature = "backend-insomnia",
+    feature = "backend-postman"
+))]
+pub(crate) fn example_body(
+    data: &linked_hash_map::LinkedHashMap<String, super::transformer::Param>,
+) -> String {
+    let example =
+        data.iter()
+            .map(|(k, p)| {
+                let value = match p.typ.as_deref() {
+                    Some("int") | Some("integer") | Some("number") | Some("float")
+                    | Some("double") => serde_json::Value::from(0),
+                    Some("bool") | Some("boolean") => serde_json::Value::from(false),
+                    _ => serde_json::Value::from(""),
+                };
+                (k.to_string(), value)
+            })
+            .collect::<serde_json::Map<String, serde_json::Value>>();
+    serde_json::to_string_pretty(&example).unwrap()
+}
+
+/// The root URL when it's a literal, which scripts reading their base URL
+/// from the environment fall back on. `None` when the root URL is supplied
+/// by the user, or depends on it, so the environment has to give it.
+#[cfg(any(
+    feature = "backend-curl",
+    feature = "backend-k6",
+    feature = "backend-node-cli"
+))]
+pub(crate) fn default_base_url(root: &ContextBoundedRoot) -> Option<String> {
+    match root.base_url().0.as_slice() {
+        [super::transformer::UrlPart::Lit(s)] => Some(s.to_string()),
+        _ => None,
+    }
+}
+
+/// A URL template cut at the `?` of its query and the `#` of its fragment,
+/// both left out. Only literals are looked into, as a param or the base URL
+/// stands for a value of its own.
+#[cfg(any(
+    feature = "backend-curl",
+    feature = "backend-http",
+    feature = "backend-k6",
+    feature = "backend-postman"
+))]
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct UrlSplit {
+    pub path: Vec<UrlPart>,
+    pub query: Option<Vec<UrlPart>>,
+    pub fragment: Option<Vec<UrlPart>>,
+}
+
+#[cfg(any(
+    feature = "backend-curl",
+    feature = "backend-http",
+    feature = "backend-k6",
+    feature = "backend-postman"
+))]
+impl UrlSplit {
+    pub fn new(template: &UrlTemplate) -> Self {
+        fn push(parts: &mut Vec<UrlPart>, part: UrlPart) {
+            match (parts.last_mut(), part) {
+                (Some(UrlPart::Lit(last)), UrlPart::Lit(s)) => last.push_str(&s),
+                (_, UrlPart::Lit(s)) if s.is_empty() => {}
+                (_, part) => parts.push(part),
+            }
+        }
+        let mut split = UrlSplit::default();
+        for part in &template.0 {
+            let s = match part {
+                UrlPart::Lit(s) => s,
+                part => {
+                    let parts = match (&mut split.query, &mut split.fragment) {
+                        (_, Some(parts)) | (Some(parts), None) => parts,
+                        (None, None) => &mut split.path,
+                    };
+                    push(parts, part.clone());
+                    continue;
+                }
+            };
+            // Everything from the `#` on is the fragment, even a `?`
+            let mut rest = s.as_str();
+            while !rest.is_empty() {
+                if let Some(fragment) = &mut split.fragment {
+                    push(fragment, UrlPart::Lit(rest.to_string()));
+                    break;
+                }
+                let (parts, stops): (&mut Vec<UrlPart>, &[char]) = match &mut split.query {
+                    Some(query) => (query, &['#']),
+                    None => (&mut split.path, &['?', '#']),
+                };
+                match rest.find(stops) {
+                    Some(pos) => {
+                        push(parts, UrlPart::Lit(rest[..pos].to_string()));
+                        if rest[pos..].starts_with('?') {
+                            split.query = Some(Vec::new());
+                        } else {
+                            split.fragment = Some(Vec::new());
+                        }
+                        rest = &rest[pos + 1..];
+                    }
+                    None => {
+                        push(parts, UrlPart::Lit(rest.to_string()));
+                        break;
+                    }
+                }
+            }
+        }
+        split
+    }
+
+    /// The `key=value` pairs of the query, split at the `&` and `=` of its
+    /// literals. A pair without `=` has an empty value.
+    #[cfg(feature = "backend-postman")]
+    pub fn query_pairs(&self) -> Vec<(Vec<UrlPart>, Vec<UrlPart>)> {
+        fn push(parts: &mut Vec<UrlPart>, part: UrlPart) {
+            match (parts.last_mut(), part) {
+                (Some(UrlPart::Lit(last)), UrlPart::Lit(s)) => last.push_str(&s),
+                (_, part) => parts.push(part),
+            }
+        }
+        let mut pairs = Vec::new();
+        let mut key = Vec::new();
+        let mut value: Option<Vec<UrlPart>> = None;
+        for part in self.query.iter().flatten() {
+            let s = match part {
+                UrlPart::Lit(s) => s,
+                part => {
+                    push(value.as_mut().unwrap_or(&mut key), part.clone());
+                    continue;
+                }
+            };
+            for ch in s.chars() {
+                match (ch, &mut value) {
+                    ('&', _) => {
+                        if !key.is_empty() || value.is_some() {
+                            pairs
+                                .push((std::mem::take(&mut key), value.take().unwrap_or_default()));
+                        }
+                    }
+                    ('=', None) => value = Some(Vec::new()),
+                    (ch, value) => push(
+                        value.as_mut().unwrap_or(&mut key),
+                        UrlPart::Lit(ch.to_string()),
+                    ),
+                }
+            }
+        }
+        if !key.is_empty() || value.is_some() {
+            pairs.push((key, value.unwrap_or_default()));
+        }
+        pairs
+    }
+
+    /// The URL with each of its parts rendered by `render`, and `extra`
+    /// pairs appended to its query, which goes before the fragment
+    #[cfg(any(
+        feature = "backend-http",
+        feature = "backend-k6",
+        feature = "backend-postman"
+    ))]
+    pub fn render(&self, render: impl Fn(&UrlPart) -> String, extra: Option<&str>) -> String {
+        let join = |parts: &[UrlPart]| parts.iter().map(&render).collect::<String>();
+        let mut url = join(&self.path);
+        let query = self.query.as_deref().map(join);
+        match (query, extra) {
+            (Some(query), Some(extra)) if !query.is_empty() && !query.ends_with('&') => {
+                url.push_str(&format!("?{}&{}", query, extra))
+            }
+            (Some(query), Some(extra)) => url.push_str(&format!("?{}{}", query, extra)),
+            (None, Some(extra)) => url.push_str(&format!("?{}", extra)),
+            (Some(query), None) => url.push_str(&format!("?{}", query)),
+            (None, None) => {}
+        }
+        if let Some(fragment) = &self.fragment {
+            url.push_str(&format!("#{}", join(fragment)));
+        }
+        url
+    }
+}
+
+/// Fails on the first param of a URL that is optional or has a default, as
+/// in `<filter?>` or `<page:int=1>`, for the backends that would send it as
+/// is rather than leave it out or fill it in
+#[cfg(any(
+    feature = "backend-angular",
+    feature = "backend-curl",
+    feature = "backend-http",
+    feature = "backend-insomnia",
+    feature = "backend-k6",
+    feature = "backend-node-cli",
+    feature = "backend-php",
+    feature = "backend-postman",
+    feature = "backend-ruby"
+))]
+pub(crate) fn reject_optional_params(
+    root: &ContextBoundedRoot,
+    backend: &str,
+) -> Result<(), GenError> {
+    for (path, _) in root.iter_apis() {
+        let template = root.url_template(&path)?;
+        if let Some(p) = template
+            .params()
+            .into_iter()
+            .find(|p| p.optional || p.default.is_some())
+        {
+            return Err(GenError::UnsupportedParam {
+                backend: backend.to_string(),
+                path,
+                param: p.name.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// An identifier for the API at a dotted path, for languages that allow no
+/// dots in function names. The segments are joined by underscores instead.
+#[cfg(any(feature = "backend-curl", feature = "backend-k6"))]
+pub(crate) fn path_ident(path: &[String]) -> String {
+    path.iter()
+        .map(|seg| {
+            seg.chars()
+                .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("_")
+}
+
+/// The names of `apis` flattened into methods of one object, each the path
+/// of the API camel-cased, e.g. `usersBudgetsAll`. Two paths that come out
+/// the same are an error.
+#[cfg(any(feature = "backend-javascript", feature = "backend-angular"))]
+pub(crate) fn flat_names(
+    apis: &[(Vec<String>, &super::transformer::ContextBoundedAPI)],
+) -> Result<Vec<String>, GenError> {
+    let mut seen: std::collections::HashMap<String, &[String]> = Default::default();
+    let mut names = Vec::new();
+    for (path, _) in apis {
+        let name = to_camel_case(&path.join("_"));
+        if let Some(other) = seen.insert(name.clone(), path) {
+            return Err(GenError::NameClash {
+                name,
+                paths: (other.to_vec(), path.to_vec()),
+            });
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// The JSDoc tags of an API, for the languages that take them
+#[cfg(any(feature = "backend-javascript", feature = "backend-angular"))]
+pub(crate) fn jsdoc_tags(api: &super::transformer::ContextBoundedAPI) -> Vec<String> {
+    match api.deprecated() {
+        Some(Some(reason)) => vec![format!("@deprecated {}", reason.trim())],
+        Some(None) => vec!["@deprecated".to_string()],
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "backend-javascript")]
+    use crate::schema::parse_str;
+    #[cfg(feature = "backend-javascript")]
+    use crate::transformer::transform;
+
+    #[cfg(feature = "backend-javascript")]
+    struct Echo;
+
+    #[cfg(feature = "backend-javascript")]
+    impl Backend for Echo {
+        fn name(&self) -> &str {
+            "javascript"
+        }
+
+        fn extension(&self) -> &str {
+            ".txt"
+        }
+
+        fn description(&self) -> &str {
+            "The class name"
+        }
+
+        fn generate(
+            &self,
+            root: &ContextBoundedRoot,
+            _opts: &GenOptions,
+        ) -> Result<GeneratedOutput, GenError> {
+            Ok(GeneratedOutput {
+                code: root.klsname().to_string(),
+                files: Vec::new(),
+            })
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "backends")]
+    fn test_registry() {
+        let registry = Registry::default();
+        assert_eq!(
+            registry.names(),
+            vec![
+                "javascript",
+                "ruby",
+                "php",
+                "curl",
+                "postman",
+                "insomnia",
+                "http",
+                "markdown",
+                "angular",
+                "k6",
+                "node-cli",
+                "json-schema",
+            ]
+        );
+        assert_eq!(registry.get("ruby").unwrap().extension(), ".rb");
+        assert!(registry.get("cobol").is_none());
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let output = registry
+            .get("php")
+            .unwrap()
+            .generate(&transform(schema).unwrap(), &Default::default())
+            .unwrap();
+        assert_eq!(output.code, include_str!("../../tests/fixtures/sample.php"));
+    }
+
+    #[test]
+    #[cfg(feature = "backend-javascript")]
+    fn test_registry_replaces_by_name() {
+        let mut registry = Registry::default();
+        let len = registry.names().len();
+        registry.register(Box::new(Echo));
+        assert_eq!(registry.names().len(), len);
+        assert_eq!(registry.names().last(), Some(&"javascript"));
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let output = registry
+            .get("javascript")
+            .unwrap()
+            .generate(&transform(schema).unwrap(), &Default::default())
+            .unwrap();
+        assert_eq!(output.code, "XiaoSiClient");
+    }
+
+    #[test]
+    #[cfg(feature = "backends")]
+    fn test_optional_params() {
+        let schema =
+            parse_str(include_str!("../../tests/fixtures/cases/query/schema.yaml")).unwrap();
+        let root = transform(schema).unwrap();
+        let registry = Registry::default();
+        for backend in registry.backends() {
+            let result = backend.generate(&root, &Default::default());
+            match backend.name() {
+                "javascript" | "markdown" | "json-schema" => assert!(result.is_ok()),
+                name => match result {
+                    Err(GenError::UnsupportedParam {
+                        backend,
+                        path,
+                        param,
+                    }) => {
+                        assert_eq!(backend, name);
+                        assert_eq!(path, vec!["ahcros", "search"]);
+                        assert_eq!(param, "filter");
+                    }
+                    v => panic!("Expected {} to reject filter, got {:?}", name, v),
+                },
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "backend-javascript",
+        feature = "backend-ruby",
+        feature = "backend-angular"
+    ))]
+    fn test_output_file_name() {
+        let registry = Registry::default();
+        let javascript = registry.get("javascript").unwrap();
+        let ruby = registry.get("ruby").unwrap();
+        let angular = registry.get("angular").unwrap();
+        assert_eq!(
+            output_file_name(javascript, "RatinaClient", Some("ratina")),
+            "RatinaClient.js"
+        );
+        assert_eq!(
+            output_file_name(ruby, "RatinaClient", Some("ratina")),
+            "ratina_client.rb"
+        );
+        assert_eq!(
+            output_file_name(angular, "RatinaClient", None),
+            "RatinaClient.service.ts"
+        );
+        // An unnamed class takes the name of the schema file
+        let klsname = RootSchema::default_klsname();
+        assert_eq!(
+            output_file_name(javascript, &klsname, Some("ahcro")),
+            "ahcro.js"
+        );
+        assert_eq!(output_file_name(javascript, &klsname, None), "XSClient.js");
+    }
+
+    #[test]
+    fn test_gen_options_builder() {
+        assert_eq!(
+            GenOptions::builder().build().unwrap(),
+            GenOptions::default()
+        );
+        let opts = GenOptions::builder()
+            .query_encoding(QueryEncoding::Comma)
+            .module_dir("RatinaClient")
+            .minify(true)
+            .build()
+            .unwrap();
+        assert_eq!(opts.query_encoding, QueryEncoding::Comma);
+        assert_eq!(opts.module_dir.as_deref(), Some("RatinaClient"));
+        assert!(opts.minify);
+        assert!(!opts.annotate);
+        let e = GenOptions::builder()
+            .minify(true)
+            .annotate(true)
+            .build()
+            .unwrap_err();
+        assert_eq!(e, GenOptionsError::Conflict("minify", "annotate"));
+        assert_eq!(
+            e.to_string(),
+            "The options minify and annotate can't be used together"
+        );
+        assert_eq!(
+            GenOptions::builder().module_dir("").build(),
+            Err(GenOptionsError::EmptyModuleDir)
+        );
+        let e = GenOptions::builder()
+            .module_dir("RatinaClient")
+            .style(ClientStyle::NestedObject)
+            .build()
+            .unwrap_err();
+        assert_eq!(e, GenOptionsError::Conflict("split", "style"));
+    }
+
+    #[test]
+    fn test_has_banner() {
+        for code in &[
+            "// XiaoSiClient generated by xsrc 0.1.0\nimport axios from \"axios\";\n",
+            "// 1\n// 2\n// 3\n// 4\n// 5\n// XiaoSiClient generated by xsrc 0.1.0\n",
+            "#!/bin/sh\n# XiaoSiClient generated by xsrc 0.1.0\n",
+            "<?php\n\n// XiaoSiClient generated by xsrc 0.1.0\n",
+            "<!-- XiaoSiClient generated by xsrc 0.1.0 -->\n",
+            "{\n  \"info\": {\n    \"description\": \"XiaoSiClient generated by xsrc 0.1.0\"\n",
+        ] {
+            assert!(has_banner(code), "{}", code);
+        }
+        for code in &[
+            "export default {};\n",
+            // Below the code, or in a string of it
+            "import axios from \"axios\";\n// XiaoSiClient generated by xsrc 0.1.0\n",
+            "const banner = \"generated by xsrc\";\n",
+            "{\n  \"item\": [\n    \"XiaoSiClient generated by xsrc 0.1.0\"\n",
+        ] {
+            assert!(!has_banner(code), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_banner_lines() {
+        assert_eq!(
+            banner_lines("RatinaClient", None),
+            vec!["RatinaClient generated by xsrc 0.1.0"]
+        );
+        assert_eq!(
+            banner_lines(
+                "RatinaClient",
+                Some("Do not edit.\nRegenerate with `xsrc`.")
+            ),
+            vec![
+                "Do not edit.",
+                "Regenerate with `xsrc`.",
+                "RatinaClient generated by xsrc 0.1.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gen_options_deserialize() {
+        let opts: GenOptions = toml::from_str(
+            "\
+param-case = \"snake\"
+query-encoding = \"brackets\"
+commonjs-interop = true
+node-keep-alive = true
+cancellation = true
+return-style = \"await-data\"
+style = \"factory-function\"
+",
+        )
+        .unwrap();
+        assert_eq!(
+            opts,
+            GenOptions::builder()
+                .param_case(ParamCase::Snake)
+                .query_encoding(QueryEncoding::Brackets)
+                .commonjs_interop(true)
+                .node_keep_alive(true)
+                .cancellation(true)
+                .return_style(ReturnStyle::AwaitData)
+                .style(ClientStyle::FactoryFunction)
+                .build()
+                .unwrap()
+        );
+        assert_eq!(
+            toml::from_str::<GenOptions>("").unwrap(),
+            GenOptions::default()
+        );
+        let invalid = [
+            "param-case = \"kebab\"",
+            "return-style = \"async\"",
+            "style = \"factory\"",
+            "module-dir = \"ratina\"",
+            "indent = 2",
+        ];
+        for s in &invalid {
+            assert!(toml::from_str::<GenOptions>(s).is_err());
+        }
+    }
 }
@@ -0,0 +1,361 @@
+use super::javascript::{check_all_param_idents, gen_axios_call, param_ident, JsGenOptions};
+use super::{
+    banner_lines, default_base_url, reject_optional_params, Backend, GenError, GenOptions,
+    GeneratedOutput,
+};
+use crate::transformer::*;
+use codegen::javascript::*;
+use codegen::utils::Right;
+use linked_hash_map::LinkedHashMap;
+use utils::linked_hashmap;
+
+/// Parses the arguments of the command named by the first one, calls it and
+/// prints the response body as JSON.
+const CLI_MAIN: &str = r#"class UsageError extends Error {}
+
+function usage(name, command) {
+    const args = command.args.map((arg) => `<${arg.name}>`);
+    const flags = command.flags.map((flag) => `[--${flag.name} <${flag.type}>]`);
+    return [name, ...args, ...flags].join(" ");
+}
+
+function help() {
+    const lines = ["Usage: <command> [args...] [--flag value...]", "", "Commands:"];
+    for (const [name, command] of Object.entries(commands)) {
+        lines.push(`  ${usage(name, command)}`);
+    }
+    return lines.join("\n");
+}
+
+function coerce(value, param) {
+    if (param.type === "number") {
+        const n = Number(value);
+        if (value === "" || Number.isNaN(n)) {
+            throw new UsageError(`${param.name} must be a number: ${value}`);
+        }
+        return n;
+    }
+    if (param.type === "boolean") {
+        if (value !== "true" && value !== "false") {
+            throw new UsageError(`${param.name} must be true or false: ${value}`);
+        }
+        return value === "true";
+    }
+    return value;
+}
+
+function parseArgs(command, argv) {
+    const positional = [];
+    const flags = {};
+    for (let i = 0; i < argv.length; i++) {
+        if (!argv[i].startsWith("--")) {
+            positional.push(argv[i]);
+            continue;
+        }
+        const eq = argv[i].indexOf("=");
+        const key = eq >= 0 ? argv[i].slice(2, eq) : argv[i].slice(2);
+        const flag = command.flags.find((v) => v.name === key);
+        if (flag === undefined) {
+            throw new UsageError(`Unknown flag: --${key}`);
+        }
+        if (eq >= 0) {
+            flags[key] = argv[i].slice(eq + 1);
+        } else if (flag.type === "boolean" && (i + 1 === argv.length || argv[i + 1].startsWith("--"))) {
+            flags[key] = "true";
+        } else if (i + 1 < argv.length) {
+            flags[key] = argv[++i];
+        } else {
+            throw new UsageError(`Missing value for --${key}`);
+        }
+    }
+    if (positional.length !== command.args.length) {
+        throw new UsageError(`Expected ${command.args.length} argument(s), got ${positional.length}`);
+    }
+    return [
+        ...command.args.map((arg, i) => coerce(positional[i], arg)),
+        ...command.flags.map((flag) => (flag.name in flags ? coerce(flags[flag.name], flag) : undefined)),
+    ];
+}
+
+async function main(argv) {
+    const [name, ...rest] = argv;
+    if (name === undefined || name === "--help" || name === "-h") {
+        console.log(help());
+        return 0;
+    }
+    if (!Object.prototype.hasOwnProperty.call(commands, name)) {
+        console.error(`Unknown command: ${name}\n\n${help()}`);
+        return 2;
+    }
+    const command = commands[name];
+    if (rest.includes("--help")) {
+        console.log(`Usage: ${usage(name, command)}`);
+        return 0;
+    }
+    let values;
+    try {
+        values = parseArgs(command, rest);
+    } catch (e) {
+        if (e instanceof UsageError) {
+            console.error(`${e.message}\nUsage: ${usage(name, command)}`);
+            return 2;
+        }
+        throw e;
+    }
+    if (BASE_URL === undefined) {
+        console.error("BASE_URL must be set");
+        return 2;
+    }
+    const response = await command.call(...values);
+    console.log(JSON.stringify(response.data, null, 2));
+    return 0;
+}
+
+main(process.argv.slice(2)).then(
+    (code) => {
+        process.exitCode = code;
+    },
+    (e) => {
+        console.error(e.response === undefined ? e.message : JSON.stringify(e.response.data, null, 2));
+        process.exitCode = 1;
+    }
+);"#;
+
+/// The type an argument is coerced to
+fn arg_type(p: &Param) -> &'static str {
+    match p.typ.as_ref().and_then(|v| v.split('|').next()) {
+        Some("int") | Some("integer") | Some("number") | Some("float") | Some("double") => "number",
+        Some("bool") | Some("boolean") => "boolean",
+        _ => "string",
+    }
+}
+
+fn string(s: &str) -> Expr {
+    Expr::Literal(Literal::String(s.to_string()))
+}
+
+fn arg_spec(p: &Param) -> Expr {
+    Expr::Object(linked_hashmap! {
+        "name".to_string() => string(&p.name),
+        "type".to_string() => string(arg_type(p)),
+    })
+}
+
+fn gen_url(template: &UrlTemplate) -> Expr {
+    let mut exprs = template.0.iter().map(|part| match part {
+        UrlPart::Lit(s) => string(s),
+        UrlPart::Param(p) => Expr::FuncCall {
+            func: Box::new(Expr::Var("encodeURIComponent".to_string())),
+            args: vec![Expr::Var(param_ident(&p.name, &JsGenOptions::default()))],
+        },
+        UrlPart::BaseUrl => Expr::Var("BASE_URL".to_string()),
+    });
+    let first = exprs.next().unwrap_or_else(|| string(""));
+    exprs.fold(first, |l, r| Expr::Arith {
+        op: ArithOp("+".to_string()),
+        l: Box::new(l),
+        r: Box::new(r),
+    })
+}
+
+/// A command takes the path params as positional args, in the order they
+/// appear in the URL, and the query and body params as flags.
+fn gen_command(
+    root: &ContextBoundedRoot,
+    path: &[String],
+    api: &ContextBoundedAPI,
+) -> Result<Expr, ContextLookupError> {
+    let template = root.url_template(path)?;
+    let args = template.params();
+    let flags = api
+        .params
+        .values()
+        .chain(api.data.values())
+        .filter(|p| !args.iter().any(|arg| arg.name == p.name))
+        .collect::<Vec<&Param>>();
+    let call = gen_axios_call(
+        gen_url(&template),
+        &api.method,
+        &api.params,
+        &api.data,
+        &api.headers,
+        // The response is printed as JSON whatever its `$responseType`
+        None,
+        &JsGenOptions::default(),
+    );
+    Ok(Expr::Object(linked_hashmap! {
+        "args".to_string() => Expr::Array(args.iter().map(|p| arg_spec(p)).collect()),
+        "flags".to_string() => Expr::Array(flags.iter().map(|p| arg_spec(p)).collect()),
+        "call".to_string() => Expr::ArrowFunc {
+            params: args
+                .iter()
+                .chain(flags.iter())
+                .map(|p| param_ident(&p.name, &JsGenOptions::default()))
+                .collect(),
+            body: Right(Box::new(call)),
+            is_async: false,
+        },
+    }))
+}
+
+fn gen_base_url(root: &ContextBoundedRoot) -> Stmt {
+    let env = Expr::Member {
+        base: Box::new(Expr::Member {
+            base: Box::new(Expr::Var("process".to_string())),
+            member: Ident("env".to_string()),
+        }),
+        member: Ident("BASE_URL".to_string()),
+    };
+    let expr = match default_base_url(root) {
+        Some(s) => Expr::Arith {
+            op: ArithOp("||".to_string()),
+            l: Box::new(env),
+            r: Box::new(string(&s)),
+        },
+        None => env,
+    };
+    Stmt::Assign(Assign {
+        typ: Some(DeclType::Const),
+        assignee: Expr::Var("BASE_URL".to_string()),
+        expr,
+    })
+}
+
+/// Generates a Node.js command line tool with a subcommand per API, named by
+/// its dotted path. The base URL can be overridden with the `BASE_URL`
+/// environment variable.
+pub fn gen(root: &ContextBoundedRoot, gen_ctx: &GenContext) -> Result<String, GenError> {
+    gen_with_options(root, gen_ctx, &Default::default())
+}
+
+pub fn gen_with_options(
+    root: &ContextBoundedRoot,
+    gen_ctx: &GenContext,
+    opts: &GenOptions,
+) -> Result<String, GenError> {
+    check_all_param_idents(root, &JsGenOptions::default())?;
+    let mut commands = LinkedHashMap::new();
+    for (path, api) in root.iter_apis() {
+        commands.insert(path.join("."), gen_command(root, &path, api)?);
+    }
+    // The shebang has to stay on the first line
+    let mut stmts = vec![Stmt::Verbatim("#!/usr/bin/env node".to_string())];
+    stmts.extend(
+        banner_lines(&root.klsname, opts.banner.as_deref())
+            .into_iter()
+            .map(Stmt::LineComment),
+    );
+    stmts.extend(vec![
+        Stmt::Import(Import {
+            def: Some(Ident("axios".to_string())),
+            imps: None,
+            path: "axios".to_string(),
+        }),
+        gen_base_url(root),
+        Stmt::Assign(Assign {
+            typ: Some(DeclType::Const),
+            assignee: Expr::Var("commands".to_string()),
+            expr: Expr::Object(commands),
+        }),
+        Stmt::Verbatim(CLI_MAIN.to_string()),
+    ]);
+    Ok(Code { stmts }.gen(gen_ctx))
+}
+
+pub struct NodeCli;
+
+impl Backend for NodeCli {
+    fn name(&self) -> &str {
+        "node-cli"
+    }
+
+    fn extension(&self) -> &str {
+        ".cli.mjs"
+    }
+
+    fn description(&self) -> &str {
+        "Node.js command line tool"
+    }
+
+    fn generate(
+        &self,
+        root: &ContextBoundedRoot,
+        opts: &GenOptions,
+    ) -> Result<GeneratedOutput, GenError> {
+        reject_optional_params(root, self.name())?;
+        Ok(GeneratedOutput {
+            code: gen_with_options(root, &Default::default(), opts)?,
+            files: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+
+    #[test]
+    fn test_gen_sample() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &Default::default()).unwrap();
+        assert_eq!(code, include_str!("../../tests/fixtures/sample.cli.mjs"));
+    }
+
+    #[test]
+    fn test_gen_args_and_flags() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org/<tenant:string>\"
+~ratincren:
+  $url: \"${!super}/ratincren/<group:int>\"
+  update:
+    $url: \"${!super}/<name:string>\"
+    $method: \"PUT\"
+    $params:
+      force: \"boolean|default:false\"
+    $data:
+      size: \"float\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &Default::default()).unwrap();
+        assert!(code.contains("const BASE_URL = ((process).env).BASE_URL;"));
+        assert!(code.contains("    \"ratincren.update\": {"));
+        assert!(code.contains(
+            "\
+            name: \"group\",
+                type: \"number\""
+        ));
+        assert!(code.contains(
+            "\
+            name: \"size\",
+                type: \"number\""
+        ));
+        assert!(code.contains("call: (group, name, force, size) => axios({"));
+        assert!(code.contains(
+            "url: ((((BASE_URL) + (\"/ratincren/\")) + (encodeURIComponent(group))) + (\"/\")) + (encodeURIComponent(name)),"
+        ));
+    }
+
+    #[test]
+    fn test_gen_param_idents() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+get:
+  $url: \"${!super}/<class:string>\"
+  $params:
+    x-detail: \"boolean\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap(), &Default::default()).unwrap();
+        assert!(code.contains("name: \"x-detail\","));
+        assert!(code.contains("call: (class_, x_detail) => axios({"));
+        assert!(code.contains("\"x-detail\": x_detail"));
+    }
+}
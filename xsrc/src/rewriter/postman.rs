@@ -0,0 +1,463 @@
+use super::{
+    banner, example_body, reject_optional_params, Backend, GenError, GenOptions, GeneratedOutput,
+    UrlSplit,
+};
+use crate::transformer::*;
+use linked_hash_map::LinkedHashMap;
+use serde_derive::Serialize;
+
+const SCHEMA_URL: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+const BASE_URL_VAR: &str = "baseUrl";
+
+#[derive(Debug, Serialize)]
+struct Collection {
+    info: Info,
+    item: Vec<Item>,
+    variable: Vec<Variable>,
+}
+
+#[derive(Debug, Serialize)]
+struct Info {
+    name: String,
+    description: String,
+    schema: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Item {
+    Folder { name: String, item: Vec<Item> },
+    Request { name: String, request: Box<Request> },
+}
+
+#[derive(Debug, Serialize)]
+struct Request {
+    method: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    header: Vec<Header>,
+    url: Url,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<Body>,
+}
+
+#[derive(Debug, Serialize)]
+struct Header {
+    key: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Url {
+    raw: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    host: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    path: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    query: Vec<QueryParam>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    variable: Vec<Variable>,
+}
+
+#[derive(Debug, Serialize)]
+struct QueryParam {
+    key: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Variable {
+    key: String,
+    value: String,
+    #[serde(rename = "type")]
+    typ: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Body {
+    mode: String,
+    raw: String,
+    options: BodyOptions,
+}
+
+#[derive(Debug, Serialize)]
+struct BodyOptions {
+    raw: RawOptions,
+}
+
+#[derive(Debug, Serialize)]
+struct RawOptions {
+    language: String,
+}
+
+fn variable_type(p: &Param) -> &'static str {
+    match p.typ.as_deref() {
+        Some("int") | Some("integer") | Some("number") | Some("float") | Some("double") => "number",
+        Some("bool") | Some("boolean") => "boolean",
+        Some(_) => "string",
+        None => "any",
+    }
+}
+
+fn variable(p: &Param) -> Variable {
+    Variable {
+        key: p.name.to_string(),
+        value: String::new(),
+        typ: variable_type(p).to_string(),
+    }
+}
+
+fn base_url_ref() -> String {
+    format!("{{{{{}}}}}", BASE_URL_VAR)
+}
+
+/// The URL of a request. Params of the path are path variables, `:name`,
+/// and those of the query and fragment refer to variables, `{{name}}`, as
+/// Postman takes path variables from the path alone. The pairs of the query
+/// go into `query` along with the params of `$params`, and the fragment into
+/// `hash`.
+fn gen_url(template: &UrlTemplate, params: &LinkedHashMap<String, Param>) -> Url {
+    let split = UrlSplit::new(template);
+    let render = |part: &UrlPart| match part {
+        UrlPart::Lit(s) => s.to_string(),
+        UrlPart::Param(p) => format!("{{{{{}}}}}", p.name),
+        UrlPart::BaseUrl => base_url_ref(),
+    };
+    let mut path_raw = String::new();
+    let mut variables = Vec::new();
+    for part in &split.path {
+        match part {
+            UrlPart::Param(p) => {
+                path_raw.push_str(&format!(":{}", p.name));
+                variables.push(variable(p));
+            }
+            part => path_raw.push_str(&render(part)),
+        }
+    }
+    let (host, path) = match template.0.first() {
+        Some(UrlPart::BaseUrl) => {
+            let host = base_url_ref();
+            let path = path_raw[host.len()..]
+                .split('/')
+                .filter(|seg| !seg.is_empty())
+                .map(|seg| seg.to_string())
+                .collect();
+            (vec![host], path)
+        }
+        // Absolute URLs are left for Postman to parse from `raw`
+        _ => (Vec::new(), Vec::new()),
+    };
+    let render_all = |parts: &[UrlPart]| parts.iter().map(render).collect::<String>();
+    let query = split
+        .query_pairs()
+        .iter()
+        .map(|(k, v)| QueryParam {
+            key: render_all(k),
+            value: render_all(v),
+        })
+        .chain(params.keys().map(|k| QueryParam {
+            key: k.to_string(),
+            value: String::new(),
+        }))
+        .collect::<Vec<QueryParam>>();
+    let extra = params
+        .keys()
+        .map(|k| format!("{}=", k))
+        .collect::<Vec<String>>()
+        .join("&");
+    let rest = UrlSplit {
+        path: Vec::new(),
+        query: split.query.clone(),
+        fragment: split.fragment.clone(),
+    };
+    Url {
+        raw: path_raw + &rest.render(render, Some(extra.as_str()).filter(|v| !v.is_empty())),
+        host,
+        path,
+        query,
+        hash: split.fragment.as_deref().map(render_all),
+        variable: variables,
+    }
+}
+
+fn gen_request(
+    root: &ContextBoundedRoot,
+    path: &[String],
+    api: &ContextBoundedAPI,
+) -> Result<Request, ContextLookupError> {
+    let template = root.url_template(path)?;
+    let mut header = api
+        .headers
+        .iter()
+        .map(|(k, v)| Header {
+            key: k.to_string(),
+            value: v.to_string(),
+        })
+        .collect::<Vec<Header>>();
+    let body = if !api.data.is_empty() {
+        let has_content_type = api
+            .headers
+            .keys()
+            .any(|k| k.eq_ignore_ascii_case("content-type"));
+        if !has_content_type {
+            header.push(Header {
+                key: "Content-Type".to_string(),
+                value: "application/json".to_string(),
+            });
+        }
+        Some(Body {
+            mode: "raw".to_string(),
+            raw: example_body(&api.data),
+            options: BodyOptions {
+                raw: RawOptions {
+                    language: "json".to_string(),
+                },
+            },
+        })
+    } else {
+        None
+    };
+    Ok(Request {
+        method: format!("{:?}", api.method),
+        header,
+        url: gen_url(&template, &api.params),
+        body,
+    })
+}
+
+fn gen_items(
+    root: &ContextBoundedRoot,
+    apisets: &LinkedHashMap<String, ContextBoundedAPIData>,
+    path: &mut Vec<String>,
+) -> Result<Vec<Item>, ContextLookupError> {
+    let mut items = Vec::new();
+    for (name, child) in apisets {
+        path.push(name.to_string());
+        let item = match child {
+            ContextBoundedAPIData::API(api) => Item::Request {
+                name: name.to_string(),
+                request: Box::new(gen_request(root, path, api)?),
+            },
+            ContextBoundedAPIData::APISet(apiset) => Item::Folder {
+                name: name.to_string(),
+                item: gen_items(root, &apiset.apisets, path)?,
+            },
+        };
+        path.pop();
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Collection variables: `baseUrl` plus whatever the root URL is built from
+fn gen_variables(root: &ContextBoundedRoot) -> Vec<Variable> {
+    let mut base_url = String::new();
+    let mut variables = Vec::new();
+    for part in root.base_url().0 {
+        match part {
+            UrlPart::Lit(s) => base_url.push_str(&s),
+            UrlPart::Param(p) => {
+                base_url.push_str(&format!("{{{{{}}}}}", p.name));
+                variables.push(variable(&p));
+            }
+            UrlPart::BaseUrl => {}
+        }
+    }
+    variables.insert(
+        0,
+        Variable {
+            key: BASE_URL_VAR.to_string(),
+            value: base_url,
+            typ: "string".to_string(),
+        },
+    );
+    variables
+}
+
+/// Exports the APIs as a Postman Collection v2.1, with one folder per APISet.
+/// URLs are resolved against a `baseUrl` collection variable.
+pub fn gen(root: &ContextBoundedRoot) -> Result<String, ContextLookupError> {
+    let collection = Collection {
+        info: Info {
+            name: root.klsname.to_string(),
+            description: banner(&root.klsname),
+            schema: SCHEMA_URL.to_string(),
+        },
+        item: gen_items(root, &root.apisets, &mut Vec::new())?,
+        variable: gen_variables(root),
+    };
+    Ok(format!(
+        "{}\n",
+        serde_json::to_string_pretty(&collection).unwrap()
+    ))
+}
+
+pub struct Postman;
+
+impl Backend for Postman {
+    fn name(&self) -> &str {
+        "postman"
+    }
+
+    fn extension(&self) -> &str {
+        ".postman_collection.json"
+    }
+
+    fn description(&self) -> &str {
+        "Postman Collection v2.1"
+    }
+
+    fn generate(
+        &self,
+        root: &ContextBoundedRoot,
+        _opts: &GenOptions,
+    ) -> Result<GeneratedOutput, GenError> {
+        reject_optional_params(root, self.name())?;
+        Ok(GeneratedOutput {
+            code: gen(root)?,
+            files: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+    use jsonschema::JSONSchema;
+
+    #[test]
+    fn test_gen_sample() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        assert_eq!(
+            code,
+            include_str!("../../tests/fixtures/sample.postman.json")
+        );
+    }
+
+    #[test]
+    fn test_gen_sample_is_valid_collection() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        let collection_schema: serde_json::Value = serde_json::from_str(include_str!(
+            "../../tests/fixtures/postman-collection-v2.1.json"
+        ))
+        .unwrap();
+        let compiled = JSONSchema::compile(&collection_schema).unwrap();
+        let instance: serde_json::Value = serde_json::from_str(&code).unwrap();
+        let messages = match compiled.validate(&instance) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors
+                .map(|e| format!("{} at {}", e, e.instance_path))
+                .collect::<Vec<String>>(),
+        };
+        assert!(messages.is_empty(), "Invalid collection: {:?}", messages);
+    }
+
+    #[test]
+    fn test_gen_path_variables() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org/<tenant:string>\"
+getAhcro:
+  $url: \"${!super}/ahcro/<ahcroId:int>\"
+  $params:
+    withDetail: \"boolean\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        let collection: serde_json::Value = serde_json::from_str(&code).unwrap();
+        let url = &collection["item"][0]["request"]["url"];
+        assert_eq!(url["raw"], "{{baseUrl}}/ahcro/:ahcroId?withDetail=");
+        assert_eq!(url["path"], serde_json::json!(["ahcro", ":ahcroId"]));
+        assert_eq!(
+            url["variable"],
+            serde_json::json!([{"key": "ahcroId", "value": "", "type": "number"}])
+        );
+        assert_eq!(
+            collection["variable"],
+            serde_json::json!([
+                {"key": "baseUrl", "value": "http://ratina.org/{{tenant}}", "type": "string"},
+                {"key": "tenant", "value": "", "type": "string"}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_gen_url_with_query() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+search:
+  $url: \"${!super}/search/<page:int>?lang=<lang:string>&format=json#results\"
+  $params:
+    q: \"string\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        let collection: serde_json::Value = serde_json::from_str(&code).unwrap();
+        let url = &collection["item"][0]["request"]["url"];
+        assert_eq!(
+            url["raw"],
+            "{{baseUrl}}/search/:page?lang={{lang}}&format=json&q=#results"
+        );
+        assert_eq!(url["path"], serde_json::json!(["search", ":page"]));
+        assert_eq!(
+            url["query"],
+            serde_json::json!([
+                {"key": "lang", "value": "{{lang}}"},
+                {"key": "format", "value": "json"},
+                {"key": "q", "value": ""}
+            ])
+        );
+        assert_eq!(url["hash"], "results");
+        // Only the params of the path are path variables
+        assert_eq!(
+            url["variable"],
+            serde_json::json!([{"key": "page", "value": "", "type": "number"}])
+        );
+    }
+
+    #[test]
+    fn test_gen_headers() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/headers/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap()).unwrap();
+        let collection: serde_json::Value = serde_json::from_str(&code).unwrap();
+        let items = collection["item"].as_array().unwrap();
+        let nested = items
+            .iter()
+            .flat_map(|item| item["item"].as_array())
+            .flatten();
+        let request = |name: &str| {
+            items
+                .iter()
+                .chain(nested.clone())
+                .find(|item| item["name"] == name)
+                .unwrap()["request"]
+                .clone()
+        };
+        assert_eq!(
+            request("avatar")["header"],
+            serde_json::json!([
+                {"key": "Authorization", "value": "Bearer xiaosi"},
+                {"key": "Accept", "value": "image/png"}
+            ])
+        );
+        assert_eq!(request("public")["method"], "GET");
+        assert!(request("public").get("header").is_none());
+    }
+}
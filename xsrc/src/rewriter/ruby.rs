@@ -0,0 +1,359 @@
+use super::{banner_lines, reject_optional_params, Backend, GenError, GenOptions, GeneratedOutput};
+use crate::se_parser as sp;
+use crate::transformer::*;
+use codegen::ruby::*;
+use codegen::utils::{to_pascal_case, to_snake_case};
+use linked_hash_map::LinkedHashMap;
+
+fn call(recv: Option<Expr>, method: &str, args: Vec<Expr>) -> Expr {
+    Expr::Call {
+        recv: recv.map(Box::new),
+        method: method.to_string(),
+        args,
+        block: None,
+    }
+}
+
+/// Renders a reference relative to `base`, which stands for the parent of the
+/// context the reference was declared in.
+fn gen_ref(ms: &[sp::Member], base: Expr) -> Expr {
+    let mut expr = base;
+    let mut ms = ms.iter();
+    // The first `!super` is the base itself
+    if let Some(sp::Member::Member(m)) = ms.next() {
+        expr = call(Some(expr), &to_snake_case(m), Vec::new());
+    }
+    for m in ms {
+        expr = match m {
+            sp::Member::Super => call(Some(expr), "_super", Vec::new()),
+            sp::Member::Member(m) => call(Some(expr), &to_snake_case(m), Vec::new()),
+        };
+    }
+    expr
+}
+
+fn gen_url_parts(expr: &sp::Expr, base: &dyn Fn() -> Expr, parts: &mut Vec<StrPart>) {
+    match expr {
+        sp::Expr::Lit(s) => parts.push(StrPart::Lit(s.to_string())),
+        sp::Expr::Ref(r) => parts.push(StrPart::Interp(gen_ref(r, base()))),
+        sp::Expr::Var(s) => parts.push(StrPart::Interp(call(
+            Some(Expr::Var("ERB::Util".to_string())),
+            "url_encode",
+            vec![Expr::Var(to_snake_case(s))],
+        ))),
+        sp::Expr::Concat(l, r) => {
+            gen_url_parts(l, base, parts);
+            gen_url_parts(r, base, parts);
+        }
+    }
+}
+
+fn gen_url(v: &ContextValue, base: &dyn Fn() -> Expr) -> Expr {
+    match v {
+        ContextValue::Expr(sp::Expr::Var(s)) => Expr::Var(to_snake_case(s)),
+        ContextValue::Expr(sp::Expr::Ref(r)) => gen_ref(r, base()),
+        ContextValue::Expr(expr) => {
+            let mut parts = Vec::new();
+            gen_url_parts(expr, base, &mut parts);
+            Expr::Interp(parts)
+        }
+    }
+}
+
+fn assign_ivar(name: &str, expr: Expr) -> Stmt {
+    Stmt::Assign {
+        assignee: Expr::IVar(name.to_string()),
+        expr,
+    }
+}
+
+fn apiset_accessor(apiset: &ContextBoundedAPISet) -> Method {
+    let mut args = vec![Expr::Var("self".to_string())];
+    args.extend(
+        apiset
+            .bounded_vars
+            .iter()
+            .map(|(_, p)| Expr::Var(to_snake_case(&p.name))),
+    );
+    Method {
+        ident: to_snake_case(&apiset.name),
+        params: apiset
+            .bounded_vars
+            .iter()
+            .map(|(_, p)| MethodParam::Positional(to_snake_case(&p.name)))
+            .collect(),
+        stmts: vec![Stmt::Expr(call(
+            Some(Expr::Var(to_pascal_case(&apiset.name))),
+            "new",
+            args,
+        ))],
+    }
+}
+
+fn gen_children(apisets: &LinkedHashMap<String, ContextBoundedAPIData>, body: &mut Vec<Stmt>) {
+    let mut classes = Vec::new();
+    for (_, child) in apisets {
+        match child {
+            ContextBoundedAPIData::API(child) => body.push(Stmt::Method(gen_api(child))),
+            ContextBoundedAPIData::APISet(child) => {
+                body.push(Stmt::Method(apiset_accessor(child)));
+                classes.push(Stmt::Class(gen_apiset(child)));
+            }
+        }
+    }
+    body.extend(classes);
+}
+
+fn gen_apiset(apiset: &ContextBoundedAPISet) -> Class {
+    let mut params = vec![MethodParam::Positional("_super".to_string())];
+    params.extend(
+        apiset
+            .bounded_vars
+            .iter()
+            .map(|(_, p)| MethodParam::Positional(to_snake_case(&p.name))),
+    );
+    let initialize = Method {
+        ident: "initialize".to_string(),
+        params,
+        stmts: vec![
+            assign_ivar("_super", Expr::Var("_super".to_string())),
+            assign_ivar(
+                "url",
+                gen_url(&apiset.url, &|| Expr::IVar("_super".to_string())),
+            ),
+        ],
+    };
+    let connection = Method {
+        ident: "connection".to_string(),
+        params: Vec::new(),
+        stmts: vec![Stmt::Expr(call(
+            Some(Expr::IVar("_super".to_string())),
+            "connection",
+            Vec::new(),
+        ))],
+    };
+    let mut body = vec![
+        Stmt::AttrReader(vec!["_super".to_string(), "url".to_string()]),
+        Stmt::Method(initialize),
+        Stmt::Method(connection),
+    ];
+    gen_children(&apiset.apisets, &mut body);
+    Class {
+        ident: to_pascal_case(&apiset.name),
+        body,
+    }
+}
+
+fn hash_of(params: &LinkedHashMap<String, Param>) -> Expr {
+    let pairs = params
+        .iter()
+        .map(|(k, p)| (k.to_string(), Expr::Var(to_snake_case(&p.name))))
+        .collect::<LinkedHashMap<String, Expr>>();
+    call(Some(Expr::Hash(pairs)), "compact", Vec::new())
+}
+
+fn gen_faraday_call(api: &ContextBoundedAPI) -> Expr {
+    let method = match api.method {
+        HttpMethod::GET => "get",
+        HttpMethod::POST => "post",
+        HttpMethod::PUT => "put",
+        HttpMethod::DELETE => "delete",
+        HttpMethod::HEAD => "head",
+        HttpMethod::OPTIONS => "options",
+        HttpMethod::PATCH => "patch",
+    };
+    let body = if !api.data.is_empty() {
+        hash_of(&api.data)
+    } else {
+        Expr::Literal(Literal::Nil)
+    };
+    let headers = if !api.headers.is_empty() {
+        let pairs = api
+            .headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), Expr::Literal(Literal::String(v.to_string()))))
+            .collect::<LinkedHashMap<String, Expr>>();
+        Expr::Hash(pairs)
+    } else {
+        Expr::Literal(Literal::Nil)
+    };
+    let block = if !api.params.is_empty() {
+        let req_params = call(Some(Expr::Var("req".to_string())), "params", Vec::new());
+        Some(Block {
+            params: vec!["req".to_string()],
+            stmts: vec![Stmt::Expr(call(
+                Some(req_params),
+                "update",
+                vec![hash_of(&api.params)],
+            ))],
+        })
+    } else {
+        None
+    };
+    Expr::Call {
+        recv: Some(Box::new(Expr::Var("connection".to_string()))),
+        method: "run_request".to_string(),
+        args: vec![
+            Expr::Literal(Literal::Symbol(method.to_string())),
+            gen_url(&api.url, &|| Expr::Var("self".to_string())),
+            body,
+            headers,
+        ],
+        block,
+    }
+}
+
+fn gen_api(api: &ContextBoundedAPI) -> Method {
+    let mut params = Vec::new();
+    for (name, p) in &api.bounded_vars {
+        let ident = to_snake_case(&p.name);
+        if api.params.contains_key(name) || api.data.contains_key(name) {
+            params.push(MethodParam::Keyword {
+                name: ident,
+                default: Some(Expr::Literal(Literal::Nil)),
+            });
+        } else {
+            params.push(MethodParam::Positional(ident));
+        }
+    }
+    Method {
+        ident: to_snake_case(&api.name),
+        params,
+        stmts: vec![Stmt::Expr(gen_faraday_call(api))],
+    }
+}
+
+fn gen_root(root: &ContextBoundedRoot) -> Class {
+    let mut stmts = vec![assign_ivar(
+        "url",
+        gen_url(&root.url, &|| Expr::Var("self".to_string())),
+    )];
+    let faraday_new = Expr::Call {
+        recv: Some(Box::new(Expr::Var("Faraday".to_string()))),
+        method: "new".to_string(),
+        args: Vec::new(),
+        block: Some(Block {
+            params: vec!["f".to_string()],
+            stmts: vec![Stmt::Expr(call(
+                Some(Expr::Var("f".to_string())),
+                "request",
+                vec![Expr::Literal(Literal::Symbol("json".to_string()))],
+            ))],
+        }),
+    };
+    stmts.push(assign_ivar("connection", faraday_new));
+    let initialize = Method {
+        ident: "initialize".to_string(),
+        params: root
+            .bounded_vars
+            .iter()
+            .map(|(_, p)| MethodParam::Positional(to_snake_case(&p.name)))
+            .collect(),
+        stmts,
+    };
+    let mut body = vec![
+        Stmt::AttrReader(vec!["url".to_string(), "connection".to_string()]),
+        Stmt::Method(initialize),
+    ];
+    gen_children(&root.apisets, &mut body);
+    Class {
+        ident: root.klsname.to_string(),
+        body,
+    }
+}
+
+pub fn gen(root: &ContextBoundedRoot) -> String {
+    gen_with_options(root, &Default::default())
+}
+
+pub fn gen_with_options(root: &ContextBoundedRoot, opts: &GenOptions) -> String {
+    let mut stmts = banner_lines(&root.klsname, opts.banner.as_deref())
+        .into_iter()
+        .map(Stmt::LineComment)
+        .collect::<Vec<Stmt>>();
+    stmts.extend(vec![
+        Stmt::Require("erb".to_string()),
+        Stmt::Require("faraday".to_string()),
+        Stmt::Class(gen_root(root)),
+    ]);
+    Code { stmts }.gen()
+}
+
+pub struct Ruby;
+
+impl Backend for Ruby {
+    fn name(&self) -> &str {
+        "ruby"
+    }
+
+    fn extension(&self) -> &str {
+        ".rb"
+    }
+
+    fn description(&self) -> &str {
+        "Ruby client using Faraday"
+    }
+
+    /// Ruby files are snake_cased, e.g. `ratina_client.rb`
+    fn file_name(&self, klsname: &str) -> String {
+        format!("{}{}", to_snake_case(klsname), self.extension())
+    }
+
+    fn generate(
+        &self,
+        root: &ContextBoundedRoot,
+        opts: &GenOptions,
+    ) -> Result<GeneratedOutput, GenError> {
+        reject_optional_params(root, self.name())?;
+        Ok(GeneratedOutput {
+            code: gen_with_options(root, opts),
+            files: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_str;
+
+    #[test]
+    fn test_gen_sample() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/sample/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap());
+        assert_eq!(code, include_str!("../../tests/fixtures/sample.rb"));
+    }
+
+    #[test]
+    fn test_gen_path_params_are_encoded() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+getAhcro:
+  $url: \"${!super}/ahcro/<ahcroId:uuid>\"
+  $params:
+    withDetail: \"boolean\"
+",
+        )
+        .unwrap();
+        let code = gen(&transform(schema).unwrap());
+        assert!(code.contains("def get_ahcro(ahcro_id, with_detail: nil)"));
+        assert!(code.contains("\"#{self.url}/ahcro/#{ERB::Util.url_encode(ahcro_id)}\""));
+        assert!(code.contains("req.params.update({ \"withDetail\" => with_detail }.compact)"));
+    }
+
+    #[test]
+    fn test_gen_headers() {
+        let schema = parse_str(include_str!(
+            "../../tests/fixtures/cases/headers/schema.yaml"
+        ))
+        .unwrap();
+        let code = gen(&transform(schema).unwrap());
+        let headers = "{ \"Authorization\" => \"Bearer xiaosi\", \"Accept\" => \"image/png\" }";
+        assert!(code.contains(headers));
+        assert!(code.contains("connection.run_request(:get, \"#{self.url}/public\", nil, nil)"));
+    }
+}
@@ -1,17 +1,22 @@
-use serde_derive::{Serialize, Deserialize};
-use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
-use serde::ser::{Serialize, SerializeMap, Serializer};
+use super::timing::Instant;
 use linked_hash_map::LinkedHashMap;
+use log::{debug, info, log_enabled, trace, Level};
+use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_derive::{Deserialize, Serialize};
 use std::convert::From;
 use std::fmt;
 use std::fs::File;
+use std::io::Read;
 use std::ops::Deref;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub enum ParserError {
     IOError(std::io::Error),
     SerdeError(serde_yaml::Error),
+    /// A file and a directory of a schema dir map to the same name
+    DuplicateNamespace(PathBuf),
 }
 
 impl fmt::Display for ParserError {
@@ -19,18 +24,84 @@ impl fmt::Display for ParserError {
         match self {
             ParserError::IOError(e) => write!(f, "IO error: {}", e),
             ParserError::SerdeError(e) => write!(f, "Serde error: {}", e),
+            ParserError::DuplicateNamespace(path) => {
+                write!(f, "Duplicate namespace: {}", path.display())
+            }
         }
     }
 }
 
-#[derive(Debug)]
+impl std::error::Error for ParserError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParserError::IOError(e) => Some(e),
+            ParserError::SerdeError(e) => Some(e),
+            ParserError::DuplicateNamespace(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
 pub struct APIDataMap(pub LinkedHashMap<String, APIData>);
 
+impl APIDataMap {
+    /// The number of APISets and of APIs, nested ones included
+    pub fn count(&self) -> (usize, usize) {
+        self.values()
+            .fold((0, 0), |(apisets, apis), child| match child {
+                APIData::API(_) => (apisets, apis + 1),
+                APIData::APISet(apiset) => {
+                    let (n, m) = apiset.apisets.count();
+                    (apisets + n + 1, apis + m)
+                }
+            })
+    }
+
+    /// Every API with its path, nested ones included, in declaration order
+    pub fn walk(&self) -> impl Iterator<Item = (Vec<String>, &APISchema)> {
+        Walk {
+            stack: vec![self.iter()],
+            path: Vec::new(),
+        }
+    }
+}
+
+/// The APIs of an `APIDataMap`, depth first. `path` is that of the APISet
+/// whose children are iterated by the top of `stack`.
+struct Walk<'a> {
+    stack: Vec<linked_hash_map::Iter<'a, String, APIData>>,
+    path: Vec<String>,
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (Vec<String>, &'a APISchema);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some((name, APIData::API(api))) => {
+                    let mut path = self.path.clone();
+                    path.push(name.to_string());
+                    return Some((path, api));
+                }
+                Some((name, APIData::APISet(apiset))) => {
+                    self.path.push(name.to_string());
+                    self.stack.push(apiset.apisets.iter());
+                }
+                None => {
+                    self.stack.pop();
+                    self.path.pop();
+                }
+            }
+        }
+    }
+}
+
 impl Deref for APIDataMap {
     type Target = LinkedHashMap<String, APIData>;
 
     fn deref(&self) -> &LinkedHashMap<String, APIData> {
-        return &self.0;
+        &self.0
     }
 }
 
@@ -46,6 +117,22 @@ impl From<serde_yaml::Error> for ParserError {
     }
 }
 
+/// Splits an API key of the form `name.METHOD`, e.g. `getUser.POST`, into
+/// the name and the method. Keys whose suffix isn't a method are left alone.
+fn split_method_suffix(key: &str) -> Option<(&str, &str)> {
+    let pos = key.rfind('.')?;
+    let (name, method) = (&key[..pos], &key[pos + 1..]);
+    match method {
+        "GET" | "POST" | "PUT" | "DELETE" | "HEAD" | "OPTIONS" | "PATCH" | "get" | "post"
+        | "put" | "delete" | "head" | "options" | "patch"
+            if !name.is_empty() =>
+        {
+            Some((name, method))
+        }
+        _ => None,
+    }
+}
+
 struct APIDataMapVisitor {}
 
 impl<'de> Visitor<'de> for APIDataMapVisitor {
@@ -64,11 +151,26 @@ impl<'de> Visitor<'de> for APIDataMapVisitor {
         // While there are entries remaining in the input, add them
         // into our map.
         while let Some(key) = access.next_key::<String>()? {
-            if key.starts_with("~") {
+            if let Some(name) = key.strip_prefix('~') {
                 // APISet
-                let name = String::from(&key[1..]);
+                let name = String::from(name);
                 let value = access.next_value::<APISetSchema>()?;
                 map.insert(name, APIData::APISet(value));
+            } else if let Some((name, method)) = split_method_suffix(&key) {
+                // API with the method in the key. An explicit `$method` wins.
+                let mut value = match access.next_value::<serde_yaml::Value>()? {
+                    serde_yaml::Value::Null => serde_yaml::Value::Mapping(Default::default()),
+                    value => value,
+                };
+                if let serde_yaml::Value::Mapping(m) = &mut value {
+                    let method_key = serde_yaml::Value::from("$method");
+                    if !m.contains_key(&method_key) {
+                        m.insert(method_key, serde_yaml::Value::from(method));
+                    }
+                }
+                let value =
+                    serde_yaml::from_value::<APISchema>(value).map_err(de::Error::custom)?;
+                map.insert(name.to_string(), APIData::API(value));
             } else {
                 // API
                 let value = access.next_value::<APISchema>()?;
@@ -110,43 +212,185 @@ impl<'de> Deserialize<'de> for APIDataMap {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A schema serializes back to what it was parsed from, less the settings
+/// that are the defaults, so that parsing the output gives the same schema
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct RootSchema {
-    #[serde(rename = "$url")]
+    #[serde(rename = "$url", skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
 
-    #[serde(rename = "$as", default = "RootSchema::default_klsname")]
+    #[serde(
+        rename = "$as",
+        default = "RootSchema::default_klsname",
+        skip_serializing_if = "RootSchema::is_default_klsname"
+    )]
     pub klsname: String,
 
+    #[serde(
+        rename = "$description",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub description: Option<String>,
+
     #[serde(flatten)]
     pub apisets: APIDataMap,
 }
 
 impl RootSchema {
-    fn default_klsname() -> String {
+    pub fn default_klsname() -> String {
         "XSClient".to_string()
     }
+
+    fn is_default_klsname(klsname: &str) -> bool {
+        klsname == Self::default_klsname()
+    }
+
+    /// A schema set in code, see [`RootSchemaBuilder`]
+    pub fn builder(klsname: &str) -> RootSchemaBuilder {
+        RootSchemaBuilder {
+            schema: RootSchema {
+                url: None,
+                klsname: klsname.to_string(),
+                description: None,
+                apisets: APIDataMap(LinkedHashMap::new()),
+            },
+        }
+    }
+
+    /// Every API of the schema with its path from the root, before the
+    /// transform, e.g. for checks that don't need the URLs resolved
+    pub fn walk(&self) -> impl Iterator<Item = (Vec<String>, &APISchema)> {
+        self.apisets.walk()
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// Boxing the API would change the patterns that code using the schema
+// matches against
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, PartialEq, Deserialize)]
 pub enum APIData {
     API(APISchema),
     APISet(APISetSchema),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// As the API or the APISet itself. Whether it's an APISet is told by the `~`
+/// of its key.
+impl Serialize for APIData {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            APIData::API(api) => api.serialize(serializer),
+            APIData::APISet(apiset) => apiset.serialize(serializer),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct APISchema {
-    #[serde(rename = "$method", default = "APISchema::default_method")]
+    #[serde(
+        rename = "$url",
+        default = "APISchema::default_url",
+        skip_serializing_if = "APISchema::is_default_url"
+    )]
+    pub url: String,
+
+    #[serde(
+        rename = "$method",
+        default = "APISchema::default_method",
+        skip_serializing_if = "APISchema::is_default_method"
+    )]
     pub method: String,
 
-    #[serde(rename = "$params", default = "APISchema::default_params")]
+    #[serde(
+        rename = "$headers",
+        default = "APISchema::default_headers",
+        skip_serializing_if = "LinkedHashMap::is_empty"
+    )]
+    pub headers: LinkedHashMap<String, String>,
+
+    #[serde(
+        rename = "$params",
+        default = "APISchema::default_params",
+        skip_serializing_if = "LinkedHashMap::is_empty"
+    )]
     pub params: LinkedHashMap<String, Option<String>>,
 
-    #[serde(rename = "$data", default = "APISchema::default_data")]
+    #[serde(
+        rename = "$data",
+        default = "APISchema::default_data",
+        skip_serializing_if = "LinkedHashMap::is_empty"
+    )]
     pub data: LinkedHashMap<String, Option<String>>,
 
-    #[serde(rename = "$url", default = "APISchema::default_url")]
-    pub url: String,
+    /// Whether the response is a `text/event-stream`
+    #[serde(
+        rename = "$stream",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub stream: bool,
+
+    /// How the response body is read: `json`, `blob`, `text` or `arraybuffer`
+    #[serde(
+        rename = "$responseType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub response_type: Option<String>,
+
+    #[serde(rename = "$paginate", default, skip_serializing_if = "Option::is_none")]
+    pub paginate: Option<Paginate>,
+
+    #[serde(
+        rename = "$description",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub description: Option<String>,
+
+    #[serde(
+        rename = "$deprecated",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub deprecated: Option<Deprecated>,
+
+    /// The name of the type of the response body, e.g. `User`, for docs
+    #[serde(rename = "$returns", default, skip_serializing_if = "Option::is_none")]
+    pub returns: Option<String>,
+}
+
+/// A cursor-based pagination convention: each page of the response has its
+/// items and the cursor of the next page, which is sent back as a query param
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Paginate {
+    /// The field of the response with the cursor of the next page, which is
+    /// empty on the last page
+    pub cursor: String,
+    /// The field of the response with the items, which may be dotted, e.g.
+    /// `data.results`
+    pub items: String,
+    /// The query param taking the cursor, named after `cursor` if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub param: Option<String>,
+}
+
+impl Paginate {
+    pub fn param(&self) -> &str {
+        self.param.as_ref().unwrap_or(&self.cursor)
+    }
+}
+
+/// `$deprecated` takes either a flag or the reason for the deprecation
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Deprecated {
+    Flag(bool),
+    Reason(String),
 }
 
 impl APISchema {
@@ -154,6 +398,10 @@ impl APISchema {
         "GET".to_string()
     }
 
+    fn is_default_method(method: &str) -> bool {
+        method == Self::default_method()
+    }
+
     fn default_params() -> LinkedHashMap<String, Option<String>> {
         LinkedHashMap::new()
     }
@@ -165,48 +413,733 @@ impl APISchema {
     fn default_url() -> String {
         "${!super.url}".to_string()
     }
+
+    fn is_default_url(url: &str) -> bool {
+        url == Self::default_url()
+    }
+
+    fn default_headers() -> LinkedHashMap<String, String> {
+        LinkedHashMap::new()
+    }
 }
 
 pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<RootSchema, ParserError> {
+    debug!("Reading schema {}", path.as_ref().display());
     let f = File::open(path)?;
-    let result = serde_yaml::from_reader(f)?;
-    Ok(result)
+    parse_reader(f)
 }
 
+pub fn parse_reader<R: Read>(mut reader: R) -> Result<RootSchema, ParserError> {
+    let mut s = String::new();
+    reader.read_to_string(&mut s)?;
+    parse_str(&s)
+}
+
+/// Parses a schema. A leading UTF-8 BOM, which some editors on Windows save,
+/// is skipped.
 pub fn parse_str(s: &str) -> Result<RootSchema, ParserError> {
-    let result = serde_yaml::from_str(s)?;
+    let start = Instant::now();
+    let s = s.trim_start_matches('\u{feff}');
+    let result: RootSchema = serde_yaml::from_str(s)?;
+    log_parsed(&result, start);
     Ok(result)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Parses the schemas of the `---`-separated YAML documents of `s`, e.g. of
+/// several clients in one file, in their order. A file of no document fails
+/// as it does with `parse_str`.
+pub fn parse_multi_str(s: &str) -> Result<Vec<RootSchema>, ParserError> {
+    let s = s.trim_start_matches('\u{feff}');
+    let result = serde_yaml::Deserializer::from_str(s)
+        .map(|document| {
+            let start = Instant::now();
+            let result = RootSchema::deserialize(document).map_err(|e| match e.location() {
+                // Syntax errors come without their location from a stream,
+                // which scanning the text as a single document finds
+                None => serde_yaml::from_str::<RootSchema>(s)
+                    .err()
+                    .filter(|v| v.location().is_some())
+                    .unwrap_or(e),
+                Some(_) => e,
+            })?;
+            log_parsed(&result, start);
+            Ok(result)
+        })
+        .collect::<Result<Vec<RootSchema>, ParserError>>()?;
+    if result.is_empty() {
+        return Ok(vec![parse_str(s)?]);
+    }
+    Ok(result)
+}
+
+pub fn parse_multi_file<P: AsRef<Path>>(path: P) -> Result<Vec<RootSchema>, ParserError> {
+    debug!("Reading schema {}", path.as_ref().display());
+    parse_multi_str(&std::fs::read_to_string(path)?)
+}
+
+fn log_parsed(schema: &RootSchema, start: Instant) {
+    let (apisets, apis) = schema.apisets.count();
+    info!(
+        "Schema parsed: {} APISets, {} APIs in {:?}",
+        apisets,
+        apis,
+        start.elapsed()
+    );
+    if log_enabled!(Level::Debug) {
+        let keys = schema.apisets.keys().cloned().collect::<Vec<String>>();
+        debug!("Top-level keys: {}", keys.join(", "));
+    }
+    if log_enabled!(Level::Trace) {
+        for (path, api) in schema.walk() {
+            trace!("Parsed API {} ({} {})", path.join("."), api.method, api.url);
+        }
+    }
+}
+
+/// The schema in a canonical form: the keys in the order of the fields, then
+/// the APIs and APISets in their order, with the defaults left out. Comments
+/// aren't kept.
+pub fn to_string(schema: &RootSchema) -> Result<String, ParserError> {
+    let s = serde_yaml::to_string(schema)?;
+    // Only the newlines, as trim_end() would also take the Unicode spaces
+    // ending the last string
+    Ok(format!(
+        "{}\n",
+        s.trim_start_matches("---\n").trim_end_matches('\n')
+    ))
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct APISetSchema {
-    #[serde(rename = "$url", default = "APISetSchema::default_url")]
+    #[serde(
+        rename = "$url",
+        default = "APISetSchema::default_url",
+        skip_serializing_if = "APISetSchema::is_default_url"
+    )]
     pub url: String,
+    /// Headers of every API in the APISet, under those of the API itself
+    #[serde(
+        rename = "$headers",
+        default,
+        skip_serializing_if = "LinkedHashMap::is_empty"
+    )]
+    pub headers: LinkedHashMap<String, String>,
+    #[serde(
+        rename = "$description",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub description: Option<String>,
     #[serde(flatten)]
     pub apisets: APIDataMap,
 }
 
 impl APISetSchema {
-    fn default_url() -> String {
+    pub(crate) fn default_url() -> String {
         "${!super.url}".to_string()
     }
+
+    fn is_default_url(url: &str) -> bool {
+        url == Self::default_url()
+    }
+}
+
+/// A schema put together in code rather than parsed, e.g. from another
+/// description of the API. The defaults are those of a schema file, and the
+/// names are checked at the end as `$`, `~` and method suffixes would read
+/// them:
+///
+/// ```
+/// use xsrc::rewriter::{generate, Registry};
+/// use xsrc::schema::{APIBuilder, RootSchema};
+///
+/// let schema = RootSchema::builder("MyClient")
+///     .url("https://api.example.com")
+///     .api(
+///         "getUser",
+///         APIBuilder::get("${!super.url}/users/<id:int>").param("verbose", "bool"),
+///     )
+///     .apiset("admin", |s| {
+///         s.url("${!super.url}/admin")
+///             .api("ban", APIBuilder::post("${!super.url}/ban/<id:int>"))
+///     })
+///     .build()
+///     .unwrap();
+/// let root = xsrc::transformer::transform(schema).unwrap();
+/// # #[cfg(feature = "backend-ruby")]
+/// # {
+/// let registry = Registry::default();
+/// let backend = xsrc::backend(&registry, "ruby").unwrap();
+/// let code = generate(backend, &root, &Default::default()).unwrap().code;
+/// assert!(code.contains("class MyClient"));
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RootSchemaBuilder {
+    schema: RootSchema,
+}
+
+impl RootSchemaBuilder {
+    pub fn url(mut self, url: &str) -> Self {
+        self.schema.url = Some(url.to_string());
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.schema.description = Some(description.to_string());
+        self
+    }
+
+    /// Adds an API, replacing one of the same name as a later key of a
+    /// schema file does
+    pub fn api(mut self, name: &str, api: APIBuilder) -> Self {
+        let api = APIData::API(api.api);
+        self.schema.apisets.0.insert(name.to_string(), api);
+        self
+    }
+
+    /// Adds an APISet, whose settings and children are set by `f`
+    pub fn apiset<F>(mut self, name: &str, f: F) -> Self
+    where
+        F: FnOnce(APISetBuilder) -> APISetBuilder,
+    {
+        let apiset = APIData::APISet(f(APISetBuilder::default()).apiset);
+        self.schema.apisets.0.insert(name.to_string(), apiset);
+        self
+    }
+
+    pub fn build(self) -> Result<RootSchema, SchemaBuilderError> {
+        check_names(&self.schema.apisets)?;
+        Ok(self.schema)
+    }
+}
+
+/// The settings and children of an APISet, for [`RootSchemaBuilder::apiset`]
+#[derive(Debug)]
+pub struct APISetBuilder {
+    apiset: APISetSchema,
+}
+
+impl Default for APISetBuilder {
+    fn default() -> Self {
+        APISetBuilder {
+            apiset: APISetSchema {
+                url: APISetSchema::default_url(),
+                headers: LinkedHashMap::new(),
+                description: None,
+                apisets: APIDataMap(LinkedHashMap::new()),
+            },
+        }
+    }
+}
+
+impl APISetBuilder {
+    pub fn url(mut self, url: &str) -> Self {
+        self.apiset.url = url.to_string();
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        let value = value.to_string();
+        self.apiset.headers.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.apiset.description = Some(description.to_string());
+        self
+    }
+
+    pub fn api(mut self, name: &str, api: APIBuilder) -> Self {
+        let api = APIData::API(api.api);
+        self.apiset.apisets.0.insert(name.to_string(), api);
+        self
+    }
+
+    pub fn apiset<F>(mut self, name: &str, f: F) -> Self
+    where
+        F: FnOnce(APISetBuilder) -> APISetBuilder,
+    {
+        let apiset = APIData::APISet(f(APISetBuilder::default()).apiset);
+        self.apiset.apisets.0.insert(name.to_string(), apiset);
+        self
+    }
+}
+
+/// An API, for [`RootSchemaBuilder::api`]. The default is a `GET` of the URL
+/// of the parent, as `name: {}` is in a schema file.
+#[derive(Debug)]
+pub struct APIBuilder {
+    api: APISchema,
+}
+
+impl Default for APIBuilder {
+    fn default() -> Self {
+        APIBuilder {
+            api: APISchema {
+                url: APISchema::default_url(),
+                method: APISchema::default_method(),
+                headers: APISchema::default_headers(),
+                params: APISchema::default_params(),
+                data: APISchema::default_data(),
+                stream: false,
+                response_type: None,
+                paginate: None,
+                description: None,
+                deprecated: None,
+                returns: None,
+            },
+        }
+    }
+}
+
+impl APIBuilder {
+    fn with_method(method: &str, url: &str) -> Self {
+        let mut builder = APIBuilder::default();
+        builder.api.method = method.to_string();
+        builder.api.url = url.to_string();
+        builder
+    }
+
+    pub fn get(url: &str) -> Self {
+        Self::with_method("GET", url)
+    }
+
+    pub fn post(url: &str) -> Self {
+        Self::with_method("POST", url)
+    }
+
+    pub fn put(url: &str) -> Self {
+        Self::with_method("PUT", url)
+    }
+
+    pub fn delete(url: &str) -> Self {
+        Self::with_method("DELETE", url)
+    }
+
+    pub fn head(url: &str) -> Self {
+        Self::with_method("HEAD", url)
+    }
+
+    pub fn options(url: &str) -> Self {
+        Self::with_method("OPTIONS", url)
+    }
+
+    pub fn patch(url: &str) -> Self {
+        Self::with_method("PATCH", url)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.api.headers.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// A query param, of a type such as `"bool"`, or of none with `None`
+    pub fn param<'a, T: Into<Option<&'a str>>>(mut self, name: &str, typ: T) -> Self {
+        let typ = typ.into().map(String::from);
+        self.api.params.insert(name.to_string(), typ);
+        self
+    }
+
+    /// A field of the body, typed as with [`APIBuilder::param`]
+    pub fn data<'a, T: Into<Option<&'a str>>>(mut self, name: &str, typ: T) -> Self {
+        let typ = typ.into().map(String::from);
+        self.api.data.insert(name.to_string(), typ);
+        self
+    }
+
+    pub fn stream(mut self, stream: bool) -> Self {
+        self.api.stream = stream;
+        self
+    }
+
+    pub fn response_type(mut self, response_type: &str) -> Self {
+        self.api.response_type = Some(response_type.to_string());
+        self
+    }
+
+    pub fn paginate(mut self, paginate: Paginate) -> Self {
+        self.api.paginate = Some(paginate);
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.api.description = Some(description.to_string());
+        self
+    }
+
+    pub fn deprecated(mut self, deprecated: Deprecated) -> Self {
+        self.api.deprecated = Some(deprecated);
+        self
+    }
+
+    pub fn returns(mut self, returns: &str) -> Self {
+        self.api.returns = Some(returns.to_string());
+        self
+    }
 }
 
+/// Checks that each name of an API or APISet would be read back as such from
+/// a schema file: `$` starts the settings, `~` those of APISets, and a method
+/// suffix isn't part of the name
+fn check_names(apisets: &APIDataMap) -> Result<(), SchemaBuilderError> {
+    for (name, child) in apisets.iter() {
+        if name.is_empty()
+            || name.starts_with('$')
+            || name.starts_with('~')
+            || split_method_suffix(name).is_some()
+        {
+            return Err(SchemaBuilderError::InvalidName(name.to_string()));
+        }
+        if let APIData::APISet(apiset) = child {
+            check_names(&apiset.apisets)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SchemaBuilderError {
+    /// A name of an API or APISet that a schema file couldn't have
+    InvalidName(String),
+}
+
+impl fmt::Display for SchemaBuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaBuilderError::InvalidName(name) => {
+                write!(f, "Invalid name of an API or APISet: {:?}", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaBuilderError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::collection::vec;
+    use proptest::option;
+    use proptest::prelude::*;
 
     #[test]
     fn schema_struct_works() {
-        let sample_string = include_str!("../tests/fixtures/sample.yaml");
-        let result: Result<RootSchema, _> = serde_yaml::from_str(&sample_string);
+        let sample_string = include_str!("../tests/fixtures/cases/sample/schema.yaml");
+        let result: Result<RootSchema, _> = serde_yaml::from_str(sample_string);
         assert!(result.is_ok());
     }
 
     #[test]
     fn schema_no_root_url_works() {
-        let sample_string = include_str!("../tests/fixtures/sample_no_klsname_no_url.yaml");
-        let result: RootSchema = serde_yaml::from_str(&sample_string).unwrap();
+        let sample_string = include_str!("../tests/fixtures/cases/no_klsname_no_url/schema.yaml");
+        let result: RootSchema = serde_yaml::from_str(sample_string).unwrap();
         assert_eq!(result.klsname, "XSClient".to_string());
     }
+
+    #[test]
+    fn schema_walk_works() {
+        let schema = parse_str(include_str!("../tests/fixtures/cases/sample/schema.yaml")).unwrap();
+        let paths = schema
+            .walk()
+            .map(|(path, _)| path.join("."))
+            .collect::<Vec<String>>();
+        assert_eq!(
+            paths,
+            vec![
+                "users.all",
+                "users.get",
+                "users.create",
+                "users.update",
+                "users.budgets.all"
+            ]
+        );
+        assert_eq!(schema.walk().count(), schema.apisets.count().1);
+        let (_, api) = schema.walk().nth(2).unwrap();
+        assert_eq!(api.method, "POST");
+    }
+
+    #[test]
+    fn schema_multi_document_works() {
+        let schemas =
+            parse_multi_str(include_str!("../tests/fixtures/multi_document.yaml")).unwrap();
+        let names = schemas
+            .iter()
+            .map(|v| (v.klsname.as_str(), v.apisets.count().1))
+            .collect::<Vec<(&str, usize)>>();
+        assert_eq!(names, vec![("UserClient", 2), ("BudgetClient", 1)]);
+        // A single document is a single schema
+        let sample = include_str!("../tests/fixtures/cases/sample/schema.yaml");
+        let schemas = parse_multi_str(sample).unwrap();
+        assert_eq!(schemas, vec![parse_str(sample).unwrap()]);
+        match parse_multi_str("all: {}\n---\n$url: [\n") {
+            Err(ParserError::SerdeError(e)) => assert_eq!(e.location().unwrap().line(), 4),
+            v => panic!("Expected a syntax error, got {:?}", v),
+        }
+        assert!(parse_multi_str("# Nothing\n").is_err());
+    }
+
+    #[test]
+    fn schema_method_suffix_works() {
+        let sample_string = include_str!("../tests/fixtures/cases/method_suffix/schema.yaml");
+        let result = parse_str(sample_string).unwrap();
+        let methods = result
+            .apisets
+            .iter()
+            .map(|(k, v)| match v {
+                APIData::API(api) => (k.as_str(), api.method.as_str()),
+                APIData::APISet(_) => (k.as_str(), "~"),
+            })
+            .collect::<Vec<(&str, &str)>>();
+        assert_eq!(
+            methods,
+            vec![
+                ("getUser", "GET"),
+                ("createUser", "POST"),
+                ("updateUser", "patch"),
+                ("replaceUser", "PUT"),
+                ("v1.2", "GET"),
+                ("users.GET", "~"),
+            ]
+        );
+        match &result.apisets["createUser"] {
+            APIData::API(api) => assert_eq!(api.url, "${!super}/users"),
+            APIData::APISet(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn schema_to_string_round_trips() {
+        for sample_string in &[
+            include_str!("../tests/fixtures/cases/sample/schema.yaml"),
+            include_str!("../tests/fixtures/cases/download/schema.yaml"),
+            include_str!("../tests/fixtures/cases/method_suffix/schema.yaml"),
+            include_str!("../tests/fixtures/cases/no_klsname_no_url/schema.yaml"),
+            include_str!("../tests/fixtures/cases/paginate/schema.yaml"),
+            include_str!("../tests/fixtures/cases/query/schema.yaml"),
+            include_str!("../tests/fixtures/cases/stream/schema.yaml"),
+        ] {
+            let schema = parse_str(sample_string).unwrap();
+            let formatted = to_string(&schema).unwrap();
+            let reparsed = parse_str(&formatted).unwrap();
+            assert_eq!(format!("{:?}", reparsed), format!("{:?}", schema));
+            // Idempotent
+            assert_eq!(to_string(&reparsed).unwrap(), formatted);
+        }
+    }
+
+    #[test]
+    fn schema_to_string_works() {
+        let schema = parse_str(
+            "\
+$url: http://ratina.org
+$as: XSClient
+~ahcros:
+  $url: \"${!super.url}\"
+  all:
+    $method: GET
+    $params: {}
+    $stream: false
+  create.POST:
+    $data:
+      name: string
+      size: \"1\"
+    $deprecated: use put
+",
+        )
+        .unwrap();
+        assert_eq!(
+            to_string(&schema).unwrap(),
+            "\
+$url: \"http://ratina.org\"
+~ahcros:
+  all: {}
+  create:
+    $method: POST
+    $data:
+      name: string
+      size: \"1\"
+    $deprecated: use put
+"
+        );
+    }
+
+    #[test]
+    fn schema_bom_works() {
+        let sample_string = include_str!("../tests/fixtures/cases/sample/schema.yaml");
+        let expected = parse_str(sample_string).unwrap();
+        let result = parse_str(&format!("\u{feff}{}", sample_string)).unwrap();
+        assert_eq!(result, expected);
+        let result = parse_reader(format!("\u{feff}{}", sample_string).as_bytes()).unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn schema_to_string_keeps_trailing_spaces() {
+        let schema =
+            parse_str(include_str!("../tests/fixtures/sample_trailing_space.yaml")).unwrap();
+        let s = to_string(&schema).unwrap();
+        assert!(s.ends_with("$deprecated: \u{a0}\n"));
+        assert_eq!(parse_str(&s).unwrap(), schema);
+    }
+
+    #[test]
+    fn schema_builder_works() {
+        let yaml = r#"$url: "https://api.example.com"
+$as: MyClient
+getUser:
+  $url: "${!super.url}/users/<id:int>"
+  $params:
+    verbose: bool
+    q:
+all: {}
+~admin:
+  $url: "${!super.url}/admin"
+  $headers:
+    X-Admin: "1"
+  ban.POST:
+    $url: "${!super.url}/ban/<id:int>"
+    $data:
+      reason: string
+    $deprecated: use block
+  ~logs:
+    tail:
+      $stream: true
+"#;
+        let schema = RootSchema::builder("MyClient")
+            .url("https://api.example.com")
+            .api(
+                "getUser",
+                APIBuilder::get("${!super.url}/users/<id:int>")
+                    .param("verbose", "bool")
+                    .param("q", None),
+            )
+            .api("all", APIBuilder::default())
+            .apiset("admin", |s| {
+                s.url("${!super.url}/admin")
+                    .header("X-Admin", "1")
+                    .api(
+                        "ban",
+                        APIBuilder::post("${!super.url}/ban/<id:int>")
+                            .data("reason", "string")
+                            .deprecated(Deprecated::Reason("use block".to_string())),
+                    )
+                    .apiset("logs", |s| {
+                        s.api("tail", APIBuilder::default().stream(true))
+                    })
+            })
+            .build()
+            .unwrap();
+        assert_eq!(schema, parse_str(yaml).unwrap());
+        assert_eq!(parse_str(&to_string(&schema).unwrap()).unwrap(), schema);
+    }
+
+    #[test]
+    fn schema_builder_invalid_name() {
+        for name in &["", "$url", "~users", "users.GET"] {
+            let e = RootSchema::builder("MyClient")
+                .apiset("users", |s| s.api(name, APIBuilder::default()))
+                .build()
+                .unwrap_err();
+            assert_eq!(e, SchemaBuilderError::InvalidName(name.to_string()));
+        }
+        assert!(RootSchema::builder("MyClient")
+            .api("users.json", APIBuilder::default())
+            .build()
+            .is_ok());
+    }
+
+    /// Names of APIs and APISets. Dots are left out, as `name.METHOD` is
+    /// read as the method of an API.
+    fn key() -> impl Strategy<Value = String> {
+        "[a-zA-Z_][a-zA-Z0-9_-]{0,7}"
+    }
+
+    fn text() -> impl Strategy<Value = String> {
+        "\\PC{0,12}"
+    }
+
+    fn map<V: Strategy>(value: V) -> impl Strategy<Value = LinkedHashMap<String, V::Value>> {
+        vec((key(), value), 0..3).prop_map(|v| v.into_iter().collect())
+    }
+
+    fn api() -> impl Strategy<Value = APISchema> {
+        (
+            (text(), text(), map(text())),
+            (map(option::of(text())), map(option::of(text()))),
+            (any::<bool>(), option::of(text())),
+            option::of((text(), text(), option::of(text()))),
+            (
+                option::of(text()),
+                option::of(prop_oneof![
+                    any::<bool>().prop_map(Deprecated::Flag),
+                    text().prop_map(Deprecated::Reason),
+                ]),
+                option::of(text()),
+            ),
+        )
+            .prop_map(
+                |(
+                    (url, method, headers),
+                    (params, data),
+                    (stream, response_type),
+                    paginate,
+                    (description, deprecated, returns),
+                )| APISchema {
+                    url,
+                    method,
+                    headers,
+                    params,
+                    data,
+                    stream,
+                    response_type,
+                    paginate: paginate.map(|(cursor, items, param)| Paginate {
+                        cursor,
+                        items,
+                        param,
+                    }),
+                    description,
+                    deprecated,
+                    returns,
+                },
+            )
+    }
+
+    /// APIs and APISets nested up to 3 deep
+    fn apisets() -> impl Strategy<Value = APIDataMap> {
+        let data = api()
+            .prop_map(APIData::API)
+            .prop_recursive(3, 24, 4, |inner| {
+                (text(), map(text()), option::of(text()), map(inner)).prop_map(
+                    |(url, headers, description, apisets)| {
+                        APIData::APISet(APISetSchema {
+                            url,
+                            headers,
+                            description,
+                            apisets: APIDataMap(apisets),
+                        })
+                    },
+                )
+            });
+        map(data).prop_map(APIDataMap)
+    }
+
+    fn root() -> impl Strategy<Value = RootSchema> {
+        (option::of(text()), key(), option::of(text()), apisets()).prop_map(
+            |(url, klsname, description, apisets)| RootSchema {
+                url,
+                klsname,
+                description,
+                apisets,
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn schema_round_trip(schema in root()) {
+            let s = to_string(&schema).unwrap();
+            prop_assert_eq!(parse_str(&s).unwrap(), schema, "{}", s);
+        }
+    }
 }
@@ -2,6 +2,8 @@
 use self::ParserError::*;
 use linked_hash_map::LinkedHashMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Member {
@@ -17,10 +19,37 @@ pub enum Expr {
     Var(String),
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, Eq, Clone)]
 pub struct Param {
     pub name: String,
     pub typ: Option<String>,
+    /// Whether the param can be left out, as in `<filter?>`
+    pub optional: bool,
+    /// The value of the param when it's left out, as in `<page:int=1>`
+    pub default: Option<String>,
+    /// Where the param is declared in its expression, in chars from its `<`
+    /// to past its `>`. `None` for params that aren't parsed from an
+    /// expression, such as those of `$params`.
+    pub span: Option<Range<usize>>,
+}
+
+/// Params are the same wherever they're declared
+impl PartialEq for Param {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.typ == other.typ
+            && self.optional == other.optional
+            && self.default == other.default
+    }
+}
+
+impl Hash for Param {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.typ.hash(state);
+        self.optional.hash(state);
+        self.default.hash(state);
+    }
 }
 
 impl Param {
@@ -28,8 +57,99 @@ impl Param {
         Param {
             name: name.to_string(),
             typ,
+            optional: false,
+            default: None,
+            span: None,
         }
     }
+
+    /// A param of `$params` or `$data`, declared by its type, which may be
+    /// followed by `=` and its default, as in `count: int = 10`
+    pub fn from_decl(name: &str, decl: Option<String>) -> Self {
+        match decl.as_deref().and_then(|v| v.split_once('=')) {
+            Some((typ, default)) => Param {
+                typ: Some(typ.trim().to_string()).filter(|v| !v.is_empty()),
+                default: Some(default.trim().to_string()),
+                ..Param::new(name, None)
+            },
+            None => Param::new(name, decl),
+        }
+    }
+
+    /// A value given for the param, as a value of its type: a number or a
+    /// boolean if the type is one, otherwise a string. `None` if the type is
+    /// a number or a boolean and the value isn't one, such as `1.5` for an
+    /// `int`.
+    pub fn coerce(&self, value: &str) -> Option<DefaultValue> {
+        let string = || Some(DefaultValue::String(value.to_string()));
+        match self.param_type() {
+            Some(ParamType::Named(typ)) => match typ.as_str() {
+                "int" | "integer" => value
+                    .parse::<i64>()
+                    .ok()
+                    .map(|v| DefaultValue::Number(v as f64)),
+                "number" | "float" | "double" => value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|v| v.is_finite())
+                    .map(DefaultValue::Number),
+                "bool" | "boolean" => value.parse().ok().map(DefaultValue::Boolean),
+                _ => string(),
+            },
+            _ => string(),
+        }
+    }
+
+    /// The default declared for the param, after `=` or in the `|default:`
+    /// section of its type, as in `boolean|default:true`
+    pub fn declared_default(&self) -> Option<&str> {
+        self.default.as_deref().or_else(|| {
+            self.typ
+                .as_ref()?
+                .split('|')
+                .skip(1)
+                .find_map(|v| v.strip_prefix("default:"))
+        })
+    }
+
+    /// The default of the param coerced to its type, `None` if there's none
+    /// or it isn't of the type
+    pub fn default_value(&self) -> Option<DefaultValue> {
+        self.coerce(self.default.as_ref()?)
+    }
+
+    /// The structured form of the type, ignoring extended sections such as
+    /// `|default:true`. Malformed enums are rejected by `parse_expr` and by
+    /// the transformer, and are taken as named types here.
+    pub fn param_type(&self) -> Option<ParamType> {
+        let typ = self.typ.as_ref()?.split('|').next()?;
+        Some(parse_param_type(typ, 0).unwrap_or_else(|_| ParamType::Named(typ.to_string())))
+    }
+
+    /// Like `param_type`, but fails on a malformed enum such as `enum(a,b`,
+    /// for the types of `$params` and `$data` that aren't parsed with a URL
+    pub fn parse_type(&self) -> Result<Option<ParamType>, ParserError> {
+        match self.typ.as_ref().and_then(|v| v.split('|').next()) {
+            Some(typ) => parse_param_type(typ, 0).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// The default of a param, as the literal that code generated for it takes
+#[derive(Debug, PartialEq, Clone)]
+pub enum DefaultValue {
+    Number(f64),
+    Boolean(bool),
+    String(String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParamType {
+    /// A type by name, e.g. `int` or `uuid`
+    Named(String),
+    /// An inline enum of the allowed values, e.g. `enum(open,closed)`
+    Enum(Vec<String>),
 }
 
 #[derive(Debug, PartialEq)]
@@ -46,11 +166,13 @@ impl fmt::Display for ParserError {
             EmptyExpr => write!(f, "Expr is empty"),
             UnexpectedToken(s, pos) => write!(f, "Unexpected token \"{}\" at pos {}", s, pos),
             UnexpectedEOF => write!(f, "Unexpected EOF"),
-            DuplicateParam(s) => write!(f, "Duplicate param \"{}\"", s)
+            DuplicateParam(s) => write!(f, "Duplicate param \"{}\"", s),
         }
     }
 }
 
+impl std::error::Error for ParserError {}
+
 fn ident_to_member(s: &str) -> Member {
     if s == "!super" {
         Member::Super
@@ -59,6 +181,11 @@ fn ident_to_member(s: &str) -> Member {
     }
 }
 
+/// Parses a ref such as `{!super.name}`, starting from its `{`.
+///
+/// Idents are split by `.` only, so any other char, including `$`, is part of
+/// an ident: `${a.$b}` refers to the member `$b` of `a`. Refs don't nest, so a
+/// `{` inside one is rejected rather than read as the start of another ref.
 fn parse_ref(s: &str, pos: usize) -> Result<(Expr, usize), ParserError> {
     let mut idents = Vec::new();
     let mut siter = s.chars().skip(pos).enumerate();
@@ -76,14 +203,14 @@ fn parse_ref(s: &str, pos: usize) -> Result<(Expr, usize), ParserError> {
     let inner_pos = loop {
         if let Some((inner_pos, ch)) = siter.next() {
             match ch {
-                '\\' => {
+                '\\' | '{' => {
                     return Err(ParserError::UnexpectedToken(
                         ch.to_string(),
                         pos + inner_pos,
                     ));
                 }
                 '}' => {
-                    if curr_ident.len() == 0 {
+                    if curr_ident.is_empty() {
                         return Err(ParserError::UnexpectedToken(
                             ch.to_string(),
                             pos + inner_pos,
@@ -94,7 +221,7 @@ fn parse_ref(s: &str, pos: usize) -> Result<(Expr, usize), ParserError> {
                     break inner_pos + 1;
                 }
                 '.' => {
-                    if curr_ident.len() == 0 {
+                    if curr_ident.is_empty() {
                         return Err(ParserError::UnexpectedToken(
                             ch.to_string(),
                             pos + inner_pos,
@@ -112,25 +239,82 @@ fn parse_ref(s: &str, pos: usize) -> Result<(Expr, usize), ParserError> {
             return Err(ParserError::UnexpectedEOF);
         }
     };
-    match idents[idents.len() - 1] {
-        Member::Super => {
-            idents.push(Member::Member("url".to_string()))
-        },
-        _ => {}
+    if idents[idents.len() - 1] == Member::Super {
+        idents.push(Member::Member("url".to_string()))
     }
     Ok((Expr::Ref(idents), pos + inner_pos))
 }
 
+/// Parses the type of a param, where `pos` is where it starts in the expr.
+/// Values of an enum are separated by `,`, and can't be empty.
+fn parse_param_type(s: &str, pos: usize) -> Result<ParamType, ParserError> {
+    let prefix = "enum(";
+    if !s.starts_with(prefix) {
+        return Ok(ParamType::Named(s.to_string()));
+    }
+    let mut values = Vec::new();
+    let mut curr_value = String::new();
+    let mut siter = s.chars().enumerate().skip(prefix.len());
+    loop {
+        if let Some((inner_pos, ch)) = siter.next() {
+            match ch {
+                ',' | ')' => {
+                    if curr_value.is_empty() {
+                        return Err(ParserError::UnexpectedToken(
+                            ch.to_string(),
+                            pos + inner_pos,
+                        ));
+                    }
+                    values.push(curr_value);
+                    curr_value = String::new();
+                    if ch == ')' {
+                        if let Some((inner_pos, ch)) = siter.next() {
+                            return Err(ParserError::UnexpectedToken(
+                                ch.to_string(),
+                                pos + inner_pos,
+                            ));
+                        }
+                        break;
+                    }
+                }
+                '(' => {
+                    return Err(ParserError::UnexpectedToken(
+                        ch.to_string(),
+                        pos + inner_pos,
+                    ));
+                }
+                _ => {
+                    curr_value.push(ch);
+                }
+            }
+        } else {
+            return Err(ParserError::UnexpectedEOF);
+        }
+    }
+    Ok(ParamType::Enum(values))
+}
+
+/// Parses a param such as `<id:int>`, starting after its `<`. A `?` after the
+/// name makes the param optional, and a value after `=` is its default, as in
+/// `<filter?>` and `<page:int=1>`.
 fn parse_param(s: &str, pos: usize) -> Result<(Expr, Param, usize), ParserError> {
+    #[derive(PartialEq)]
+    enum Section {
+        Var,
+        Type,
+        Default,
+    }
     let mut var = String::new();
     let mut typ = String::new();
-    let mut in_var = true;
+    let mut default = None;
+    let mut optional = false;
+    let mut section = Section::Var;
     let mut siter = s.chars().skip(pos).enumerate();
     let inner_pos = loop {
         if let Some((inner_pos, ch)) = siter.next() {
             match ch {
                 '>' => {
-                    if var.len() == 0 {
+                    if var.is_empty() {
                         return Err(ParserError::UnexpectedToken(
                             ch.to_string(),
                             pos + inner_pos,
@@ -138,26 +322,40 @@ fn parse_param(s: &str, pos: usize) -> Result<(Expr, Param, usize), ParserError>
                     }
                     break inner_pos + 1;
                 }
-                ':' => {
-                    if var.len() == 0 {
+                _ if section == Section::Default => {
+                    default.get_or_insert_with(String::new).push(ch);
+                }
+                ':' | '=' | '?' => {
+                    if var.is_empty()
+                        || ch == ':' && section != Section::Var
+                        || ch == '?' && (section != Section::Var || optional)
+                        || ch == '=' && section == Section::Type && typ.is_empty()
+                    {
                         return Err(ParserError::UnexpectedToken(
                             ch.to_string(),
                             pos + inner_pos,
                         ));
                     }
-                    if !in_var {
-                        return Err(ParserError::UnexpectedToken(
-                            ch.to_string(),
-                            pos + inner_pos,
-                        ));
+                    match ch {
+                        ':' => section = Section::Type,
+                        '=' => {
+                            section = Section::Default;
+                            default = Some(String::new());
+                        }
+                        _ => optional = true,
                     }
-                    in_var = false;
                 }
                 _ => {
-                    if in_var {
+                    if section == Section::Var && !optional {
                         var.push(ch);
-                    } else {
+                    } else if section == Section::Type {
                         typ.push(ch);
+                    } else {
+                        // Only `:`, `=` or `>` may follow the `?`
+                        return Err(ParserError::UnexpectedToken(
+                            ch.to_string(),
+                            pos + inner_pos,
+                        ));
                     }
                 }
             }
@@ -165,15 +363,20 @@ fn parse_param(s: &str, pos: usize) -> Result<(Expr, Param, usize), ParserError>
             return Err(ParserError::UnexpectedEOF);
         }
     };
-    if !in_var && typ.len() == 0 {
+    if section == Section::Type && typ.is_empty() {
         // Caught ':' but no succeeding type
         Err(ParserError::UnexpectedEOF)
     } else {
+        let type_pos = pos + var.chars().count() + if optional { 2 } else { 1 };
+        parse_param_type(&typ, type_pos)?;
         Ok((
             Expr::Var(var.to_string()),
             Param {
                 name: var,
-                typ: if typ.len() == 0 { None } else { Some(typ) },
+                typ: if typ.is_empty() { None } else { Some(typ) },
+                optional,
+                default,
+                span: None,
             },
             pos + inner_pos,
         ))
@@ -181,28 +384,33 @@ fn parse_param(s: &str, pos: usize) -> Result<(Expr, Param, usize), ParserError>
 }
 
 fn collect_exprs(mut exprs: Vec<Expr>) -> Result<Expr, ParserError> {
-    if exprs.len() == 0 {
+    if exprs.is_empty() {
         Err(ParserError::EmptyExpr)
     } else {
         // TODO: Fix this ugly code!
         let mut result = exprs.drain(0..1).next().unwrap();
         for expr in exprs.drain(..) {
-            result = Expr::Concat(box result, box expr);
+            result = Expr::Concat(Box::new(result), Box::new(expr));
         }
         Ok(result)
     }
 }
 
+/// Parses an expression such as a `$url`. Carriage returns, which schemas
+/// saved with CRLF line endings may have in block scalars, are dropped from
+/// the literals.
 pub fn parse_expr(s: &str) -> Result<(Expr, LinkedHashMap<String, Param>), ParserError> {
     let mut exprs = Vec::new();
     let mut params = LinkedHashMap::new();
+    // Skipped by zero to be of the type of the iterators reassigned below
+    #[allow(clippy::iter_skip_zero)]
     let mut siter = s.chars().enumerate().skip(0);
     let mut curr_str = String::new();
     loop {
         if let Some((pos, ch)) = siter.next() {
             match ch {
                 '$' => {
-                    if curr_str.len() != 0 {
+                    if !curr_str.is_empty() {
                         exprs.push(Expr::Lit(curr_str));
                         curr_str = String::new();
                     }
@@ -211,11 +419,13 @@ pub fn parse_expr(s: &str) -> Result<(Expr, LinkedHashMap<String, Param>), Parse
                     siter = s.chars().enumerate().skip(pos);
                 }
                 '<' => {
-                    if curr_str.len() != 0 {
+                    if !curr_str.is_empty() {
                         exprs.push(Expr::Lit(curr_str));
                         curr_str = String::new();
                     }
-                    let (expr, param, pos) = parse_param(s, pos + 1)?;
+                    let start = pos;
+                    let (expr, mut param, pos) = parse_param(s, pos + 1)?;
+                    param.span = Some(start..pos);
                     exprs.push(expr);
                     let param_name = param.name.to_string();
                     if params.insert(param_name.to_string(), param).is_some() {
@@ -224,20 +434,20 @@ pub fn parse_expr(s: &str) -> Result<(Expr, LinkedHashMap<String, Param>), Parse
                     siter = s.chars().enumerate().skip(pos);
                 }
                 '\\' => {
+                    // The escaped char is taken literally, e.g. `\$` and `\<`
                     if let Some((_, ch)) = siter.next() {
-                        match ch {
-                            _ => curr_str.push(ch), // TODO: Fix char escape
-                        }
+                        curr_str.push(ch);
                     } else {
                         return Err(ParserError::UnexpectedEOF);
                     }
                 }
+                '\r' => {}
                 _ => {
                     curr_str.push(ch);
                 }
             }
         } else {
-            if curr_str.len() != 0 {
+            if !curr_str.is_empty() {
                 exprs.push(Expr::Lit(curr_str));
             }
             break;
@@ -250,13 +460,15 @@ pub fn parse_expr(s: &str) -> Result<(Expr, LinkedHashMap<String, Param>), Parse
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
     use std::iter::FromIterator;
 
     #[test]
     fn test_parse_ref() {
         let some_ref = "{a.$b.c}";
-        let result = parse_ref(&some_ref, 0);
-        let member = (&["a", "$b", "c"])
+        let result = parse_ref(some_ref, 0);
+        let member = ["a", "$b", "c"]
             .iter()
             .map(|s| Member::Member(s.to_string()))
             .collect::<Vec<_>>();
@@ -266,7 +478,7 @@ mod tests {
     #[test]
     fn test_parse_ref_malformed() {
         let some_ref = "{.hello}";
-        let result = parse_ref(&some_ref, 0);
+        let result = parse_ref(some_ref, 0);
         let err = result.err().unwrap();
         assert_eq!(err, ParserError::UnexpectedToken('.'.to_string(), 1));
     }
@@ -274,7 +486,7 @@ mod tests {
     #[test]
     fn test_parse_ref_malformed2() {
         let some_ref = "{hello..world}";
-        let result = parse_ref(&some_ref, 0);
+        let result = parse_ref(some_ref, 0);
         let err = result.err().unwrap();
         assert_eq!(err, ParserError::UnexpectedToken('.'.to_string(), 7));
     }
@@ -282,22 +494,33 @@ mod tests {
     #[test]
     fn test_parse_ref_unterminated() {
         let some_ref = "{";
-        let result = parse_ref(&some_ref, 0);
+        let result = parse_ref(some_ref, 0);
         let err = result.err().unwrap();
         assert_eq!(err, ParserError::UnexpectedEOF);
     }
 
+    #[test]
+    fn test_parse_ref_nested() {
+        let some_ref = "{a.${b}}";
+        let result = parse_ref(some_ref, 0);
+        let err = result.err().unwrap();
+        assert_eq!(err, ParserError::UnexpectedToken('{'.to_string(), 4));
+    }
+
     #[test]
     fn test_parse_param() {
         let some_param = "hello:world>";
-        let result = parse_param(&some_param, 0);
+        let result = parse_param(some_param, 0);
         let (expr, param, pos) = result.unwrap();
         assert_eq!(expr, Expr::Var("hello".to_string()));
         assert_eq!(
             param,
             Param {
                 name: "hello".to_string(),
-                typ: Some("world".to_string())
+                typ: Some("world".to_string()),
+                optional: false,
+                default: None,
+                span: None
             }
         );
         assert_eq!(pos, 12);
@@ -306,14 +529,17 @@ mod tests {
     #[test]
     fn test_parse_param_no_type() {
         let some_param = "hello>";
-        let result = parse_param(&some_param, 0);
+        let result = parse_param(some_param, 0);
         let (expr, param, pos) = result.unwrap();
         assert_eq!(expr, Expr::Var("hello".to_string()));
         assert_eq!(
             param,
             Param {
                 name: "hello".to_string(),
-                typ: None
+                typ: None,
+                optional: false,
+                default: None,
+                span: None
             }
         );
         assert_eq!(pos, 6);
@@ -322,7 +548,7 @@ mod tests {
     #[test]
     fn test_parse_param_no_var() {
         let some_param = ":world>";
-        let result = parse_param(&some_param, 0);
+        let result = parse_param(some_param, 0);
         let err = result.err().unwrap();
         assert_eq!(err, ParserError::UnexpectedToken(':'.to_string(), 0));
     }
@@ -330,7 +556,7 @@ mod tests {
     #[test]
     fn test_parse_param_colon_inside_type() {
         let some_param = "hello:wor:ld>";
-        let result = parse_param(&some_param, 0);
+        let result = parse_param(some_param, 0);
         let err = result.err().unwrap();
         assert_eq!(err, ParserError::UnexpectedToken(':'.to_string(), 9));
     }
@@ -338,7 +564,7 @@ mod tests {
     #[test]
     fn test_parse_param_unterminated() {
         let some_param = "hello:world";
-        let result = parse_param(&some_param, 0);
+        let result = parse_param(some_param, 0);
         let err = result.err().unwrap();
         assert_eq!(err, ParserError::UnexpectedEOF);
     }
@@ -346,11 +572,152 @@ mod tests {
     #[test]
     fn test_parse_param_colon_no_type() {
         let some_param = "hello:>";
-        let result = parse_param(&some_param, 0);
+        let result = parse_param(some_param, 0);
         let err = result.err().unwrap();
         assert_eq!(err, ParserError::UnexpectedEOF);
     }
 
+    #[test]
+    fn test_parse_param_enum() {
+        let some_param = "status:enum(open,closed)>";
+        let (_, param, pos) = parse_param(some_param, 0).unwrap();
+        assert_eq!(param.typ, Some("enum(open,closed)".to_string()));
+        assert_eq!(
+            param.param_type(),
+            Some(ParamType::Enum(vec![
+                "open".to_string(),
+                "closed".to_string()
+            ]))
+        );
+        assert_eq!(pos, 25);
+    }
+
+    #[test]
+    fn test_parse_param_enum_malformed() {
+        let err = parse_param("status:enum(open,,closed)>", 0).err().unwrap();
+        assert_eq!(err, ParserError::UnexpectedToken(','.to_string(), 17));
+        let err = parse_param("status:enum()>", 0).err().unwrap();
+        assert_eq!(err, ParserError::UnexpectedToken(')'.to_string(), 12));
+        let err = parse_param("status:enum(open)x>", 0).err().unwrap();
+        assert_eq!(err, ParserError::UnexpectedToken('x'.to_string(), 17));
+        let err = parse_param("status:enum(open>", 0).err().unwrap();
+        assert_eq!(err, ParserError::UnexpectedEOF);
+    }
+
+    #[test]
+    fn test_parse_param_optional_default() {
+        let (_, param, pos) = parse_param("filter?>", 0).unwrap();
+        assert_eq!((param.optional, param.default), (true, None));
+        assert_eq!(pos, 8);
+        let (_, param, _) = parse_param("page:int=1>", 0).unwrap();
+        assert_eq!(param.typ, Some("int".to_string()));
+        assert_eq!(
+            (param.optional, param.default),
+            (false, Some("1".to_string()))
+        );
+        let (_, param, _) = parse_param("size?:int=20>", 0).unwrap();
+        assert_eq!(param.name, "size");
+        assert_eq!(param.typ, Some("int".to_string()));
+        assert_eq!(
+            (param.optional, param.default),
+            (true, Some("20".to_string()))
+        );
+        // Anything goes in the default
+        let (_, param, _) = parse_param("sort=name:asc>", 0).unwrap();
+        assert_eq!(param.typ, None);
+        assert_eq!(param.default, Some("name:asc".to_string()));
+    }
+
+    #[test]
+    fn test_parse_param_optional_default_malformed() {
+        let err = parse_param("filter??>", 0).err().unwrap();
+        assert_eq!(err, ParserError::UnexpectedToken('?'.to_string(), 7));
+        let err = parse_param("filter?x>", 0).err().unwrap();
+        assert_eq!(err, ParserError::UnexpectedToken('x'.to_string(), 7));
+        let err = parse_param("page:int?>", 0).err().unwrap();
+        assert_eq!(err, ParserError::UnexpectedToken('?'.to_string(), 8));
+        let err = parse_param("page:=1>", 0).err().unwrap();
+        assert_eq!(err, ParserError::UnexpectedToken('='.to_string(), 5));
+        let err = parse_param("=1>", 0).err().unwrap();
+        assert_eq!(err, ParserError::UnexpectedToken('='.to_string(), 0));
+        let err = parse_param("status?:enum(a,,b)>", 0).err().unwrap();
+        assert_eq!(err, ParserError::UnexpectedToken(','.to_string(), 15));
+    }
+
+    #[test]
+    fn test_param_type() {
+        assert_eq!(Param::new("id", None).param_type(), None);
+        assert_eq!(
+            Param::new("id", Some("int|default:1".to_string())).param_type(),
+            Some(ParamType::Named("int".to_string()))
+        );
+        assert_eq!(
+            Param::new("order", Some("enum(asc,desc)|default:asc".to_string())).param_type(),
+            Some(ParamType::Enum(vec!["asc".to_string(), "desc".to_string()]))
+        );
+    }
+
+    #[test]
+    fn test_param_declared_default() {
+        assert_eq!(Param::new("id", None).declared_default(), None);
+        assert_eq!(
+            Param::new("id", Some("int".to_string())).declared_default(),
+            None
+        );
+        assert_eq!(
+            Param::new("detail", Some("boolean|default:true".to_string())).declared_default(),
+            Some("true")
+        );
+        assert_eq!(
+            Param::from_decl("count", Some("int = 10".to_string())).declared_default(),
+            Some("10")
+        );
+    }
+
+    #[test]
+    fn test_param_parse_type() {
+        assert_eq!(Param::new("id", None).parse_type(), Ok(None));
+        assert_eq!(
+            Param::from_decl("order", Some("enum(asc,desc) = asc".to_string())).parse_type(),
+            Ok(Some(ParamType::Enum(vec![
+                "asc".to_string(),
+                "desc".to_string()
+            ])))
+        );
+        assert_eq!(
+            Param::new("order", Some("enum(asc,desc".to_string())).parse_type(),
+            Err(ParserError::UnexpectedEOF)
+        );
+        assert_eq!(
+            Param::new("order", Some("enum(asc,,desc)".to_string())).parse_type(),
+            Err(ParserError::UnexpectedToken(','.to_string(), 9))
+        );
+    }
+
+    #[test]
+    fn test_param_from_decl() {
+        let param = Param::from_decl("count", Some("int = 10".to_string()));
+        assert_eq!(param.typ, Some("int".to_string()));
+        assert_eq!(param.default, Some("10".to_string()));
+        assert_eq!(param.default_value(), Some(DefaultValue::Number(10.0)));
+        let param = Param::from_decl("verbose", Some("bool=false".to_string()));
+        assert_eq!(param.default_value(), Some(DefaultValue::Boolean(false)));
+        let param = Param::from_decl("ratio", Some("float = 0.5".to_string()));
+        assert_eq!(param.default_value(), Some(DefaultValue::Number(0.5)));
+        for decl in &["float = half", "int = 1.5", "bool = yes", "double = inf"] {
+            let param = Param::from_decl("ratio", Some(decl.to_string()));
+            assert_eq!(param.default_value(), None, "{}", decl);
+        }
+        let param = Param::from_decl("sort", Some("= name".to_string()));
+        assert_eq!(param.typ, None);
+        let default = DefaultValue::String("name".to_string());
+        assert_eq!(param.default_value(), Some(default));
+        let param = Param::from_decl("name", Some("string".to_string()));
+        assert_eq!(param, Param::new("name", Some("string".to_string())));
+        assert_eq!(param.default_value(), None);
+        assert_eq!(Param::from_decl("name", None), Param::new("name", None));
+    }
+
     #[test]
     fn test_collect_exprs() {
         let exprs = vec![
@@ -359,11 +726,11 @@ mod tests {
             Expr::Var("Xiaosi".to_string()),
         ];
         let expected = Expr::Concat(
-            box Expr::Concat(
-                box Expr::Lit("Hello".to_string()),
-                box Expr::Var("World".to_string()),
-            ),
-            box Expr::Var("Xiaosi".to_string()),
+            Box::new(Expr::Concat(
+                Box::new(Expr::Lit("Hello".to_string())),
+                Box::new(Expr::Var("World".to_string())),
+            )),
+            Box::new(Expr::Var("Xiaosi".to_string())),
         );
         assert_eq!(collect_exprs(exprs).unwrap(), expected);
     }
@@ -382,14 +749,17 @@ mod tests {
         assert_eq!(
             expr,
             Expr::Concat(
-                box Expr::Concat(
-                    box Expr::Concat(
-                        box Expr::Lit("abc".to_string()),
-                        box Expr::Ref(vec![Member::Super, Member::Member("def".to_string())])
-                    ),
-                    box Expr::Var("id".to_string())
-                ),
-                box Expr::Lit("hij".to_string())
+                Box::new(Expr::Concat(
+                    Box::new(Expr::Concat(
+                        Box::new(Expr::Lit("abc".to_string())),
+                        Box::new(Expr::Ref(vec![
+                            Member::Super,
+                            Member::Member("def".to_string())
+                        ]))
+                    )),
+                    Box::new(Expr::Var("id".to_string()))
+                )),
+                Box::new(Expr::Lit("hij".to_string()))
             )
         );
         assert_eq!(
@@ -398,10 +768,98 @@ mod tests {
                 "id".to_string(),
                 Param {
                     name: "id".to_string(),
-                    typ: Some("gg".to_string())
+                    typ: Some("gg".to_string()),
+                    optional: false,
+                    default: None,
+                    span: None
                 }
             )])
         );
+        assert_eq!(params["id"].span, Some(16..23));
+    }
+
+    #[test]
+    fn test_parse_expr_dollar_in_ref() {
+        let s = "${a.$b}/c";
+        let (expr, params) = parse_expr(s).unwrap();
+        assert_eq!(
+            expr,
+            Expr::Concat(
+                Box::new(Expr::Ref(vec![
+                    Member::Member("a".to_string()),
+                    Member::Member("$b".to_string())
+                ])),
+                Box::new(Expr::Lit("/c".to_string()))
+            )
+        );
+        assert_eq!(params.len(), 0);
+    }
+
+    #[test]
+    fn test_parse_expr_nested_ref() {
+        let s = "${a.${b}}";
+        let result = parse_expr(s);
+        let err = result.err().unwrap();
+        assert_eq!(err, ParserError::UnexpectedToken('{'.to_string(), 5));
+    }
+
+    #[test]
+    fn test_parse_expr_crlf() {
+        let (expr, params) = parse_expr("${!super}/users/\r\n<id:int>\r\n").unwrap();
+        assert_eq!(
+            expr,
+            Expr::Concat(
+                Box::new(Expr::Concat(
+                    Box::new(Expr::Concat(
+                        Box::new(Expr::Ref(vec![
+                            Member::Super,
+                            Member::Member("url".to_string())
+                        ])),
+                        Box::new(Expr::Lit("/users/\n".to_string()))
+                    )),
+                    Box::new(Expr::Var("id".to_string()))
+                )),
+                Box::new(Expr::Lit("\n".to_string()))
+            )
+        );
+        assert_eq!(params.len(), 1);
+        // A literal of carriage returns only is no literal
+        let (expr, _) = parse_expr("<id>\r").unwrap();
+        assert_eq!(expr, Expr::Var("id".to_string()));
+    }
+
+    #[test]
+    fn test_parse_expr_literal() {
+        let (expr, params) = parse_expr("http://ratina.org/users").unwrap();
+        assert_eq!(expr, Expr::Lit("http://ratina.org/users".to_string()));
+        assert_eq!(params.len(), 0);
+    }
+
+    #[test]
+    fn test_parse_expr_escaped() {
+        let (expr, params) = parse_expr("\\$").unwrap();
+        assert_eq!(expr, Expr::Lit("$".to_string()));
+        assert_eq!(params.len(), 0);
+        let (expr, _) = parse_expr("\\$\\<").unwrap();
+        assert_eq!(expr, Expr::Lit("$<".to_string()));
+        let (expr, _) = parse_expr("\\$${!super}\\\\<id>").unwrap();
+        assert_eq!(
+            expr,
+            Expr::Concat(
+                Box::new(Expr::Concat(
+                    Box::new(Expr::Concat(
+                        Box::new(Expr::Lit("$".to_string())),
+                        Box::new(Expr::Ref(vec![
+                            Member::Super,
+                            Member::Member("url".to_string())
+                        ]))
+                    )),
+                    Box::new(Expr::Lit("\\".to_string()))
+                )),
+                Box::new(Expr::Var("id".to_string()))
+            )
+        );
+        assert_eq!(parse_expr("a\\").err().unwrap(), ParserError::UnexpectedEOF);
     }
 
     #[test]
@@ -411,4 +869,33 @@ mod tests {
         let err = result.err().unwrap();
         assert_eq!(err, ParserError::UnexpectedToken(':'.to_string(), 16))
     }
+
+    /// Strings made mostly of the characters the parser cares about, mixed
+    /// with multibyte ones
+    fn expr_str() -> impl Strategy<Value = String> {
+        let piece = prop_oneof![
+            3 => prop::sample::select(vec![
+                "$", "{", "}", "<", ">", ":", "=", "?", ".", "\\", "\r", "!super", "enum(", "(",
+                ",", ")", "int", "a", "日本", "é", "🦀",
+            ])
+            .prop_map(str::to_string),
+            1 => any::<char>().prop_map(|c| c.to_string()),
+        ];
+        vec(piece, 0..24).prop_map(|pieces| pieces.concat())
+    }
+
+    proptest! {
+        #[test]
+        fn parse_expr_never_panics(s in expr_str()) {
+            match parse_expr(&s) {
+                Ok(_) | Err(ParserError::EmptyExpr) | Err(ParserError::UnexpectedEOF) => {}
+                Err(ParserError::UnexpectedToken(tok, pos)) => {
+                    prop_assert_eq!(s.chars().nth(pos).map(|c| c.to_string()), Some(tok), "{}", s);
+                }
+                Err(ParserError::DuplicateParam(name)) => {
+                    prop_assert!(s.contains(&format!("<{}", name)), "{}", s);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,19 @@
+//! `std::time::Instant` for timing the stages in the logs, which panics on
+//! `wasm32-unknown-unknown`. Nothing is timed there.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Instant;
+
+#[cfg(target_arch = "wasm32")]
+impl Instant {
+    pub(crate) fn now() -> Self {
+        Instant
+    }
+
+    pub(crate) fn elapsed(&self) -> std::time::Duration {
+        Default::default()
+    }
+}
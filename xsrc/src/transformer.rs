@@ -1,16 +1,19 @@
 use self::ContextLookupError::*;
 use self::TransformerError::*;
 use super::schema::{APIData, RootSchema};
-pub use super::se_parser::Param;
+pub use super::schema::{Deprecated, Paginate};
 use super::se_parser::{parse_expr, Expr, Member, ParserError};
+pub use super::se_parser::{DefaultValue, Param, ParamType};
+use super::timing::Instant;
 use linked_hash_map::LinkedHashMap;
+use log::{info, trace};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::From;
 use std::fmt;
 use std::iter::FromIterator;
-use std::rc::Rc;
-use utils::linked_hashmap;
+use std::rc::{Rc, Weak};
+use std::str::FromStr;
 
 #[derive(Debug, PartialEq)]
 pub enum HttpMethod {
@@ -24,29 +27,46 @@ pub enum HttpMethod {
 }
 
 impl HttpMethod {
-    fn from_str(s: &str) -> Self {
+    pub const NAMES: &'static [&'static str] =
+        &["GET", "POST", "PUT", "DELETE", "HEAD", "OPTIONS", "PATCH"];
+}
+
+/// Parses a method in upper or lower case, e.g. `"patch".parse()`
+impl FromStr for HttpMethod {
+    type Err = TransformerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "get" | "GET" => HttpMethod::GET,
-            "post" | "POST" => HttpMethod::POST,
-            "put" | "PUT" => HttpMethod::PUT,
-            "delete" | "DELETE" => HttpMethod::DELETE,
-            "head" | "HEAD" => HttpMethod::HEAD,
-            "options" | "OPTIONS" => HttpMethod::OPTIONS,
-            "patch" | "PATCH" => HttpMethod::PATCH,
-            _ => panic!("Caught unsupported HTTP method: {}", s),
+            "get" | "GET" => Ok(HttpMethod::GET),
+            "post" | "POST" => Ok(HttpMethod::POST),
+            "put" | "PUT" => Ok(HttpMethod::PUT),
+            "delete" | "DELETE" => Ok(HttpMethod::DELETE),
+            "head" | "HEAD" => Ok(HttpMethod::HEAD),
+            "options" | "OPTIONS" => Ok(HttpMethod::OPTIONS),
+            "patch" | "PATCH" => Ok(HttpMethod::PATCH),
+            _ => Err(UnsupportedMethod {
+                path: Vec::new(),
+                method: s.to_string(),
+            }),
         }
     }
 }
 
+/// The schema with its URLs and params resolved, which the backends generate
+/// code from. Its public fields and methods are the API that backends outside
+/// xsrc rely on, so changes to them are breaking.
 #[derive(Debug, PartialEq)]
 pub struct ContextBoundedRoot {
     pub klsname: String,
+    pub description: Option<String>,
     pub url: ContextValue,
     pub bounded_vars: LinkedHashMap<String, Param>,
     pub apisets: LinkedHashMap<String, ContextBoundedAPIData>,
     pub context: Rc<RefCell<Context>>,
 }
 
+// Boxing the API would change the patterns that backends match against
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, PartialEq)]
 pub enum ContextBoundedAPIData {
     API(ContextBoundedAPI),
@@ -61,22 +81,141 @@ pub struct ContextBoundedAPI {
     pub bounded_vars: LinkedHashMap<String, Param>,
     pub data: LinkedHashMap<String, Param>,
     pub params: LinkedHashMap<String, Param>,
+    pub stream: bool,
+    pub response_type: Option<ResponseType>,
+    pub paginate: Option<Paginate>,
+    pub headers: LinkedHashMap<String, String>,
+    pub description: Option<String>,
+    /// `Some(None)` for a deprecation without a reason
+    pub deprecated: Option<Option<String>>,
+    pub returns: Option<String>,
     pub context: Rc<RefCell<Context>>,
 }
 
 #[derive(Debug, PartialEq)]
 pub struct ContextBoundedAPISet {
     pub name: String,
+    pub description: Option<String>,
     pub url: ContextValue,
     pub bounded_vars: LinkedHashMap<String, Param>,
     pub apisets: LinkedHashMap<String, ContextBoundedAPIData>,
     pub context: Rc<RefCell<Context>>,
 }
 
+/// How the body of a response is read, named as axios' `responseType`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ResponseType {
+    Json,
+    Blob,
+    Text,
+    ArrayBuffer,
+}
+
+impl ResponseType {
+    pub const NAMES: &'static [&'static str] = &["json", "blob", "text", "arraybuffer"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResponseType::Json => "json",
+            ResponseType::Blob => "blob",
+            ResponseType::Text => "text",
+            ResponseType::ArrayBuffer => "arraybuffer",
+        }
+    }
+}
+
+impl FromStr for ResponseType {
+    type Err = TransformerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ResponseType::Json),
+            "blob" => Ok(ResponseType::Blob),
+            "text" => Ok(ResponseType::Text),
+            "arraybuffer" => Ok(ResponseType::ArrayBuffer),
+            _ => Err(InvalidResponseType(String::new(), s.to_string())),
+        }
+    }
+}
+
+/// Where an API declares a param
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ParamSource {
+    /// A param of the `$url` of the API itself
+    UrlParam,
+    /// A key of `$params`
+    Param,
+    /// A key of `$data`
+    Data,
+}
+
+impl fmt::Display for ParamSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamSource::UrlParam => write!(f, "URL param"),
+            ParamSource::Param => write!(f, "$params key"),
+            ParamSource::Data => write!(f, "$data key"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum TransformerError {
     ContextLookupError(ContextLookupError),
     ParserError(ParserError),
+    /// The name of the API and the unknown `$responseType`. The name is
+    /// empty if the response type was parsed on its own.
+    InvalidResponseType(String, String),
+    /// A `$url` that doesn't parse, with the path of its APISet or API, which
+    /// is empty for the root
+    InvalidUrl {
+        path: Vec<String>,
+        url: String,
+        error: ParserError,
+    },
+    /// Two params of an API with the same name, with the path of the API and
+    /// where each of them is declared
+    DuplicateParam {
+        path: Vec<String>,
+        name: String,
+        first: ParamSource,
+        second: ParamSource,
+    },
+    /// A `$method` that isn't one of [`HttpMethod::NAMES`], with the path of
+    /// the API, which is empty if the method was parsed on its own
+    UnsupportedMethod {
+        path: Vec<String>,
+        method: String,
+    },
+    /// A default of a param that isn't of its number or boolean type, as in
+    /// `count: int = ten`, with the path of the API
+    InvalidDefault {
+        path: Vec<String>,
+        param: String,
+        ty: String,
+    },
+    /// A type of a param of `$params` or `$data` that doesn't parse, as in
+    /// `order: enum(asc,desc`, with the path of the API
+    InvalidType {
+        path: Vec<String>,
+        param: String,
+        error: ParserError,
+    },
+}
+
+impl TransformerError {
+    /// The path of the APISet or API from the root, if known
+    pub fn path(&self) -> Option<&[String]> {
+        match self {
+            ContextLookupError(e) => e.context_path(),
+            InvalidUrl { path, .. }
+            | DuplicateParam { path, .. }
+            | UnsupportedMethod { path, .. }
+            | InvalidDefault { path, .. }
+            | InvalidType { path, .. } => Some(path),
+            _ => None,
+        }
+    }
 }
 
 impl From<ContextLookupError> for TransformerError {
@@ -95,7 +234,79 @@ impl fmt::Display for TransformerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ContextLookupError(e) => write!(f, "Context lookup error: {}", e),
-            ParserError(e) => write!(f, "Context lookup error: {}", e),
+            ParserError(e) => write!(f, "Parser error: {}", e),
+            InvalidResponseType(api, s) if api.is_empty() => write!(
+                f,
+                "Invalid $responseType \"{}\", expected one of: {}",
+                s,
+                ResponseType::NAMES.join(", ")
+            ),
+            InvalidResponseType(api, s) => write!(
+                f,
+                "Invalid $responseType \"{}\" of API \"{}\", expected one of: {}",
+                s,
+                api,
+                ResponseType::NAMES.join(", ")
+            ),
+            InvalidUrl { path, error, .. } if path.is_empty() => {
+                write!(f, "Invalid $url of the root: {}", error)
+            }
+            InvalidUrl { path, error, .. } => {
+                write!(f, "Invalid $url of {}: {}", path.join("."), error)
+            }
+            DuplicateParam {
+                path,
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "{} \"{}\" of {} duplicates the {} of the same name",
+                second,
+                name,
+                path.join("."),
+                first
+            ),
+            UnsupportedMethod { path, method } if path.is_empty() => write!(
+                f,
+                "Unsupported HTTP method \"{}\", expected one of: {}",
+                method,
+                HttpMethod::NAMES.join(", ")
+            ),
+            UnsupportedMethod { path, method } => write!(
+                f,
+                "Unsupported HTTP method \"{}\" of {}, expected one of: {}",
+                method,
+                path.join("."),
+                HttpMethod::NAMES.join(", ")
+            ),
+            InvalidDefault { path, param, ty } => write!(
+                f,
+                "The default of param \"{}\" of {} isn't a valid {}",
+                param,
+                path.join("."),
+                ty
+            ),
+            InvalidType { path, param, error } => write!(
+                f,
+                "The type of param \"{}\" of {} doesn't parse: {}",
+                param,
+                path.join("."),
+                error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TransformerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ContextLookupError(e) => Some(e),
+            ParserError(e) | InvalidUrl { error: e, .. } | InvalidType { error: e, .. } => Some(e),
+            InvalidResponseType(..)
+            | DuplicateParam { .. }
+            | UnsupportedMethod { .. }
+            | InvalidDefault { .. } => None,
         }
     }
 }
@@ -113,12 +324,25 @@ pub enum ContextLookupError {
         member: String,
         value: ContextValue,
     },
+    CyclicReference {
+        context_path: Vec<String>,
+    },
 }
 
 impl ContextLookupError {
     fn display_context_path(path: &[String]) -> String {
         path.join(".")
     }
+
+    /// Where in the schema the lookup failed, if known
+    pub fn context_path(&self) -> Option<&[String]> {
+        match self {
+            NoSuchMember { context_path, .. }
+            | EmptyKey { context_path }
+            | CyclicReference { context_path } => Some(context_path),
+            LookupOnValue { .. } => None,
+        }
+    }
 }
 
 impl fmt::Display for ContextLookupError {
@@ -131,14 +355,14 @@ impl fmt::Display for ContextLookupError {
                 f,
                 "No member \"{}\" at {}",
                 member,
-                ContextLookupError::display_context_path(&context_path)
+                ContextLookupError::display_context_path(context_path)
             ),
             EmptyKey { context_path } => {
                 // This should indicate a bug
                 write!(
                     f,
                     "Empty key at {}",
-                    ContextLookupError::display_context_path(&context_path)
+                    ContextLookupError::display_context_path(context_path)
                 )
             }
             LookupOnValue { member, value } => write!(
@@ -146,16 +370,78 @@ impl fmt::Display for ContextLookupError {
                 "Attempting to lookup \"{}\" on context value \"{:?}\"",
                 member, value
             ),
+            CyclicReference { context_path } => write!(
+                f,
+                "Cyclic reference at {}",
+                ContextLookupError::display_context_path(context_path)
+            ),
         }
     }
 }
 
+impl std::error::Error for ContextLookupError {}
+
+/// Something that doesn't stop code generation but is likely a mistake in the
+/// schema
 #[derive(Debug, PartialEq)]
+pub enum TransformerWarning {
+    /// An APISet with no API in it or in any of its descendants
+    EmptyAPISet { path: Vec<String> },
+    /// A query or body param named after a URL param of the same API, which
+    /// most backends can't tell apart
+    ShadowedParam { path: Vec<String>, param: String },
+}
+
+impl TransformerWarning {
+    /// The path of the APISet or API from the root
+    pub fn path(&self) -> &[String] {
+        match self {
+            TransformerWarning::EmptyAPISet { path }
+            | TransformerWarning::ShadowedParam { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for TransformerWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransformerWarning::EmptyAPISet { path } => {
+                write!(f, "APISet {} has no APIs", path.join("."))
+            }
+            TransformerWarning::ShadowedParam { path, param } => write!(
+                f,
+                "Param \"{}\" of {} shadows the URL param of the same name",
+                param,
+                path.join(".")
+            ),
+        }
+    }
+}
+
+/// The scope of the root, an APISet or an API, in a tree of scopes that
+/// mirrors the schema. The `url` of each is the expression of its `$url`, as
+/// written, and references in it are looked up from its own scope.
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use xsrc::se_parser::{Expr, Member};
+/// use xsrc::transformer::{Context, ContextValue};
+///
+/// let root = Rc::new(RefCell::new(Context::new("RatinaClient", None)));
+/// let url = ContextValue::Expr(Expr::Lit("http://ratina.org".to_string()));
+/// root.borrow_mut().add_value("url", url.clone());
+/// let ratincren = Context::new_child(&root, "ratincren");
+/// let key = [Member::Super, Member::Member("url".to_string())];
+/// assert_eq!(*ratincren.borrow().lookup(&key).unwrap(), url);
+/// assert_eq!(ratincren.borrow().path(), vec!["RatinaClient", "ratincren"]);
+/// ```
 pub struct Context {
     name: String,
-    parent: Option<Rc<RefCell<Context>>>,
-    children: HashMap<String, Rc<RefCell<Context>>>,
-    scope: HashMap<String, ContextValue>,
+    // Weak, as the parent holds the context among its children
+    parent: Option<Weak<RefCell<Context>>>,
+    children: LinkedHashMap<String, Rc<RefCell<Context>>>,
+    scope: LinkedHashMap<String, Rc<ContextValue>>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -164,27 +450,48 @@ pub enum ContextValue {
 }
 
 impl Context {
-    fn new(name: &str, parent: Option<Rc<RefCell<Context>>>) -> Self {
+    /// A context without values or children. It isn't added to the children
+    /// of `parent`, which `new_child` does.
+    pub fn new(name: &str, parent: Option<Rc<RefCell<Context>>>) -> Self {
         Context {
             name: name.to_string(),
-            parent,
-            children: HashMap::new(),
-            scope: HashMap::new(),
+            parent: parent.as_ref().map(Rc::downgrade),
+            children: LinkedHashMap::new(),
+            scope: LinkedHashMap::new(),
         }
     }
 
-    fn add_value(&mut self, key: &str, val: ContextValue) {
-        self.scope.insert(key.to_string(), val);
+    /// A context added to the children of `parent` under its name
+    pub fn new_child(parent: &Rc<RefCell<Context>>, name: &str) -> Rc<RefCell<Context>> {
+        let child = Rc::new(RefCell::new(Context::new(name, Some(Rc::clone(parent)))));
+        parent.borrow_mut().add_child(name, Rc::clone(&child));
+        trace!("Context {} created", child.borrow().path().join("."));
+        child
+    }
+
+    pub fn add_value(&mut self, key: &str, val: ContextValue) {
+        self.scope.insert(key.to_string(), Rc::new(val));
     }
 
-    fn add_child(&mut self, key: &str, ctx: Rc<RefCell<Context>>) {
+    pub fn add_child(&mut self, key: &str, ctx: Rc<RefCell<Context>>) {
         self.children.insert(key.to_string(), ctx);
     }
 
-    fn path(&self) -> Vec<String> {
-        match &self.parent {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The parent context, unless this is the root or the parent has been
+    /// dropped
+    pub fn parent(&self) -> Option<Rc<RefCell<Context>>> {
+        self.parent.as_ref().and_then(Weak::upgrade)
+    }
+
+    /// The names of the contexts from the root down to this one
+    pub fn path(&self) -> Vec<String> {
+        match self.parent() {
             Some(ctx) => {
-                let mut ret = ctx.borrow().path().clone();
+                let mut ret = ctx.borrow().path();
                 ret.push(self.name.to_string());
                 ret
             }
@@ -192,74 +499,185 @@ impl Context {
         }
     }
 
-    fn lookup_local(&self, key: &str) -> Result<ContextValue, ContextLookupError> {
-        if let Some(val) = self.scope.get(key) {
-            Ok(val.clone())
-        } else {
-            Err(ContextLookupError::NoSuchMember {
+    /// The values of this context alone, in the order they were added
+    pub fn values(&self) -> impl Iterator<Item = (&str, &Rc<ContextValue>)> {
+        self.scope.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// The child contexts, in the order they were added
+    pub fn children(&self) -> impl Iterator<Item = (&str, &Rc<RefCell<Context>>)> {
+        self.children.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Rc<RefCell<Context>>> {
+        self.children.get(name)
+    }
+
+    /// The value of `key` in this context alone
+    pub fn lookup_local(&self, key: &str) -> Result<&Rc<ContextValue>, ContextLookupError> {
+        self.scope
+            .get(key)
+            .ok_or_else(|| ContextLookupError::NoSuchMember {
                 member: key.to_string(),
                 context_path: self.path(),
             })
-        }
     }
 
-    fn lookup(&self, key: &[String]) -> Result<ContextValue, ContextLookupError> {
-        if key.len() == 0 {
-            Err(ContextLookupError::EmptyKey {
-                context_path: self.path(),
-            })
-        } else {
-            if key[0] == "!super" {
-                match &self.parent {
+    /// The value at `key`, a path through `!super` and the child contexts,
+    /// as in a reference. The value is shared with the context rather than
+    /// borrowed from it, as the `RefCell` borrows of the contexts on the way
+    /// end with the lookup.
+    ///
+    /// ```
+    /// use xsrc::schema::parse_str;
+    /// use xsrc::se_parser::{Expr, Member};
+    /// use xsrc::transformer::{transform, ContextValue};
+    ///
+    /// let schema = "$url: \"http://ratina.org\"\n~ratincren:\n  $url: \"${!super}/ratincren\"\n  all: {}\n";
+    /// let root = transform(parse_str(schema).unwrap()).unwrap();
+    /// let all = &root.apisets()[0].apis()[0];
+    /// let key = [Member::Super, Member::Member("url".to_string())];
+    /// let ContextValue::Expr(expr) = &*all.context.borrow().lookup(&key).unwrap();
+    /// assert!(matches!(expr, Expr::Concat(..)));
+    /// ```
+    pub fn lookup(&self, key: &[Member]) -> Result<Rc<ContextValue>, ContextLookupError> {
+        let (first, rest) = match key.split_first() {
+            Some(v) => v,
+            None => {
+                return Err(ContextLookupError::EmptyKey {
+                    context_path: self.path(),
+                })
+            }
+        };
+        let first = match first {
+            Member::Super => {
+                return match self.parent() {
                     None => Err(ContextLookupError::NoSuchMember {
                         member: "!super".to_string(),
                         context_path: self.path(),
                     }),
-                    Some(ctx) => ctx.borrow().lookup(&key[1..]),
+                    Some(ctx) => ctx.borrow().lookup(rest),
                 }
-            } else if let Ok(val) = self.lookup_local(&key[0]) {
-                if key.len() > 1 {
-                    Err(ContextLookupError::LookupOnValue {
-                        member: (&key[0]).to_string(),
-                        value: val,
-                    })
-                } else {
-                    Ok(val)
-                }
-            } else if let Some(child_ctx) = self.children.get(&key[0]) {
-                child_ctx.borrow().lookup(&key[1..])
+            }
+            Member::Member(m) => m,
+        };
+        if let Some(val) = self.scope.get(first) {
+            if rest.is_empty() {
+                Ok(Rc::clone(val))
             } else {
-                Err(ContextLookupError::NoSuchMember {
-                    member: (&key[0]).to_string(),
-                    context_path: self.path(),
+                Err(ContextLookupError::LookupOnValue {
+                    member: first.to_string(),
+                    value: (**val).clone(),
                 })
             }
+        } else if let Some(child_ctx) = self.children.get(first) {
+            child_ctx.borrow().lookup(rest)
+        } else {
+            Err(ContextLookupError::NoSuchMember {
+                member: first.to_string(),
+                context_path: self.path(),
+            })
         }
     }
 }
 
+// The parent is left out of both, as it refers back to the context through
+// its children
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Context")
+            .field("name", &self.name)
+            .field("children", &self.children)
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
+impl PartialEq for Context {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.children == other.children && self.scope == other.scope
+    }
+}
+
+/// Parses the `$url` of an API or APISet. An empty URL inherits the parent's,
+/// the same as an omitted one. An absolute URL refers to nothing and thus
+/// stands on its own.
+fn parse_url(url: &str) -> Result<(Expr, LinkedHashMap<String, Param>), ParserError> {
+    if url.is_empty() {
+        let expr = Expr::Ref(vec![Member::Super, Member::Member("url".to_string())]);
+        Ok((expr, LinkedHashMap::new()))
+    } else {
+        parse_expr(url)
+    }
+}
+
+/// Parses the `$url` of the APISet or API of `ctx`
+fn parse_ctx_url(
+    ctx: &Rc<RefCell<Context>>,
+    url: &str,
+) -> Result<(Expr, LinkedHashMap<String, Param>), TransformerError> {
+    let (expr, vars) = parse_url(url).map_err(|e| invalid_url(ctx, url, e))?;
+    trace!(
+        "$url of {} parsed: {} params",
+        ctx.borrow().path().join("."),
+        vars.len()
+    );
+    Ok((expr, vars))
+}
+
+fn invalid_url(ctx: &Rc<RefCell<Context>>, url: &str, error: ParserError) -> TransformerError {
+    InvalidUrl {
+        // Without the root
+        path: ctx.borrow().path().split_off(1),
+        url: url.to_string(),
+        error,
+    }
+}
+
+/// Headers of `parent` with those of `headers` added, which win on a clash
+fn merge_headers(
+    parent: &LinkedHashMap<String, String>,
+    headers: &LinkedHashMap<String, String>,
+) -> LinkedHashMap<String, String> {
+    let mut merged = parent.clone();
+    merged.extend(headers.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+    merged
+}
+
+/// `headers` are those of the APISets above, which the APIs inherit. The
+/// errors of the APISet or API and of those under it are added to `errors`,
+/// and none is returned if there are any.
 fn transform_apiset(
     name: &str,
     apiset: &APIData,
     root_ctx: Rc<RefCell<Context>>,
-) -> Result<ContextBoundedAPIData, TransformerError> {
-    let mut scope = HashMap::new();
-    let ctx = Rc::new(RefCell::new(Context {
-        name: name.to_string(),
-        parent: Some(root_ctx),
-        children: HashMap::new(),
-        scope: scope,
-    }));
+    headers: &LinkedHashMap<String, String>,
+    errors: &mut Vec<TransformerError>,
+) -> Option<ContextBoundedAPIData> {
+    let ctx = Context::new_child(&root_ctx, name);
+    let found = errors.len();
     match apiset {
         APIData::APISet(schema) => {
             let mut children = LinkedHashMap::new();
+            let headers = merge_headers(headers, &schema.headers);
             for (k, v) in schema.apisets.iter() {
-                let child = transform_apiset(k, v, Rc::clone(&ctx))?;
-                children.insert(k.to_string(), child);
+                if let Some(child) = transform_apiset(k, v, Rc::clone(&ctx), &headers, errors) {
+                    children.insert(k.to_string(), child);
+                }
             }
-            let (expr, mut bounded_vars) = parse_expr(&schema.url)?;
-            Ok(ContextBoundedAPIData::APISet(ContextBoundedAPISet {
+            let (expr, bounded_vars) = match parse_ctx_url(&ctx, &schema.url) {
+                Ok(v) if errors.len() == found => v,
+                Ok(_) => return None,
+                Err(e) => {
+                    errors.push(e);
+                    return None;
+                }
+            };
+            ctx.borrow_mut()
+                .add_value("url", ContextValue::Expr(expr.clone()));
+            Some(ContextBoundedAPIData::APISet(ContextBoundedAPISet {
                 name: name.to_string(),
+                description: schema.description.clone(),
                 url: ContextValue::Expr(expr),
                 bounded_vars,
                 apisets: children,
@@ -267,89 +685,181 @@ fn transform_apiset(
             }))
         }
         APIData::API(schema) => {
-            let (expr, mut bounded_vars) = parse_expr(&schema.url)?;
-            for (name, typ) in &schema.params {
-                let p = Param {
-                    name: name.to_string(),
-                    typ: typ.clone(),
-                };
-                if bounded_vars.insert(name.to_string(), p).is_some() {
-                    return Err(TransformerError::from(ParserError::DuplicateParam(
-                        name.to_string(),
-                    )));
+            let response_type = match &schema.response_type {
+                Some(s) => match s.parse::<ResponseType>() {
+                    Ok(response_type) => Some(response_type),
+                    Err(_) => {
+                        errors.push(InvalidResponseType(name.to_string(), s.to_string()));
+                        None
+                    }
+                },
+                None => None,
+            };
+            let method = match schema.method.parse::<HttpMethod>() {
+                Ok(method) => Some(method),
+                Err(_) => {
+                    errors.push(UnsupportedMethod {
+                        path: ctx.borrow().path().split_off(1),
+                        method: schema.method.to_string(),
+                    });
+                    None
+                }
+            };
+            // The params are checked even if the URL doesn't parse
+            let (expr, mut bounded_vars) = match parse_ctx_url(&ctx, &schema.url) {
+                Ok((expr, vars)) => (Some(expr), vars),
+                Err(e) => {
+                    errors.push(e);
+                    (None, LinkedHashMap::new())
+                }
+            };
+            let mut sources = bounded_vars
+                .keys()
+                .map(|k| (k.to_string(), ParamSource::UrlParam))
+                .collect::<HashMap<String, ParamSource>>();
+            let args = schema
+                .params
+                .iter()
+                .map(|v| (v, ParamSource::Param))
+                .chain(schema.data.iter().map(|v| (v, ParamSource::Data)));
+            for ((name, typ), source) in args {
+                if let Some(first) = sources.insert(name.to_string(), source) {
+                    errors.push(DuplicateParam {
+                        path: ctx.borrow().path().split_off(1),
+                        name: name.to_string(),
+                        first,
+                        second: source,
+                    });
                 }
+                let param = Param::from_decl(name, typ.clone());
+                if let Err(error) = param.parse_type() {
+                    errors.push(InvalidType {
+                        path: ctx.borrow().path().split_off(1),
+                        param: name.to_string(),
+                        error,
+                    });
+                }
+                bounded_vars.insert(name.to_string(), param);
             }
-            for (name, typ) in &schema.data {
-                let p = Param {
-                    name: name.to_string(),
-                    typ: typ.clone(),
-                };
-                if bounded_vars.insert(name.to_string(), p).is_some() {
-                    return Err(TransformerError::from(ParserError::DuplicateParam(
-                        name.to_string(),
-                    )));
+            for param in bounded_vars.values() {
+                if let (Some(default), Some(ty)) = (&param.default, &param.typ) {
+                    if param.coerce(default).is_none() {
+                        errors.push(InvalidDefault {
+                            path: ctx.borrow().path().split_off(1),
+                            param: param.name.to_string(),
+                            ty: ty.to_string(),
+                        });
+                    }
                 }
             }
-            let data = LinkedHashMap::from_iter(schema.data.iter().map(|(k, v)| {
-                (
-                    k.to_string(),
-                    Param {
-                        name: k.to_string(),
-                        typ: v.clone(),
-                    },
-                )
-            }));
-            let params = LinkedHashMap::from_iter(schema.params.iter().map(|(k, v)| {
-                (
-                    k.to_string(),
-                    Param {
-                        name: k.to_string(),
-                        typ: v.clone(),
-                    },
-                )
-            }));
-            Ok(ContextBoundedAPIData::API(ContextBoundedAPI {
+            if errors.len() > found {
+                return None;
+            }
+            let (expr, method) = (expr?, method?);
+            ctx.borrow_mut()
+                .add_value("url", ContextValue::Expr(expr.clone()));
+            let data = LinkedHashMap::from_iter(
+                schema
+                    .data
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), Param::from_decl(k, v.clone()))),
+            );
+            let params = LinkedHashMap::from_iter(
+                schema
+                    .params
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), Param::from_decl(k, v.clone()))),
+            );
+            Some(ContextBoundedAPIData::API(ContextBoundedAPI {
                 name: name.to_string(),
-                method: HttpMethod::from_str(&schema.method),
+                method,
                 url: ContextValue::Expr(expr),
                 bounded_vars,
                 data,
                 params,
+                stream: schema.stream,
+                response_type,
+                paginate: schema.paginate.clone(),
+                headers: merge_headers(headers, &schema.headers),
+                description: schema.description.clone(),
+                deprecated: match &schema.deprecated {
+                    None | Some(Deprecated::Flag(false)) => None,
+                    Some(Deprecated::Flag(true)) => Some(None),
+                    // A blank reason is no reason
+                    Some(Deprecated::Reason(reason)) if reason.trim().is_empty() => Some(None),
+                    Some(Deprecated::Reason(reason)) => Some(Some(reason.to_string())),
+                },
+                returns: schema
+                    .returns
+                    .as_ref()
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty()),
                 context: ctx,
             }))
         }
     }
 }
 
+/// Transforms the schema, failing with the first error found in it. See
+/// [`transform_all`] for all of them.
 pub fn transform(source: RootSchema) -> Result<ContextBoundedRoot, TransformerError> {
-    let scope = HashMap::new();
+    transform_all(source).map_err(|mut errors| errors.remove(0))
+}
+
+/// Transforms the schema, or finds every error in it rather than stopping
+/// at the first, in the order of the schema. An APISet or API with an error
+/// is left out, and its siblings are transformed all the same.
+///
+/// References in the URLs aren't looked up here, as they may point to an
+/// APISet declared after the one referring to it. They are resolved on the
+/// finished tree, by [`ContextBoundedRoot::url_template`] and the like.
+pub fn transform_all(source: RootSchema) -> Result<ContextBoundedRoot, Vec<TransformerError>> {
+    let start = Instant::now();
+    let mut errors = Vec::new();
     let url: ContextValue;
     let mut bounded_vars = LinkedHashMap::new();
     match source.url {
-        Some(ref s) => {
-            let (expr, vars) = parse_expr(s)?;
-            url = ContextValue::Expr(expr);
-            bounded_vars.extend(vars);
-        }
-        None => {
+        Some(ref s) if !s.is_empty() => match parse_expr(s) {
+            Ok((expr, vars)) => {
+                trace!("$url of {} parsed: {} params", source.klsname, vars.len());
+                url = ContextValue::Expr(expr);
+                bounded_vars.extend(vars);
+            }
+            Err(error) => {
+                errors.push(InvalidUrl {
+                    path: Vec::new(),
+                    url: s.to_string(),
+                    error,
+                });
+                url = ContextValue::Expr(Expr::Lit(s.to_string()));
+            }
+        },
+        _ => {
             let url_param = Param::new("url", Some("string".to_string()));
             bounded_vars.insert("url".to_string(), url_param);
             url = ContextValue::Expr(Expr::Var("url".to_string()));
         }
     }
-    let root_ctx = Rc::new(RefCell::new(Context {
-        name: (&source).klsname.to_string(),
-        parent: None,
-        children: HashMap::new(),
-        scope,
-    }));
+    let root_ctx = Rc::new(RefCell::new(Context::new(&source.klsname, None)));
+    root_ctx.borrow_mut().add_value("url", url.clone());
     let mut apisets = LinkedHashMap::new();
     for (k, v) in source.apisets.iter() {
-        let child = transform_apiset(k, v, Rc::clone(&root_ctx))?;
-        apisets.insert(k.to_string(), child);
+        let headers = LinkedHashMap::new();
+        if let Some(child) = transform_apiset(k, v, Rc::clone(&root_ctx), &headers, &mut errors) {
+            apisets.insert(k.to_string(), child);
+        }
     }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    info!(
+        "Transform of {} completed in {:?}",
+        source.klsname,
+        start.elapsed()
+    );
     Ok(ContextBoundedRoot {
         klsname: source.klsname,
+        description: source.description,
         url,
         bounded_vars,
         apisets,
@@ -357,111 +867,854 @@ pub fn transform(source: RootSchema) -> Result<ContextBoundedRoot, TransformerEr
     })
 }
 
-#[cfg(test)]
-pub mod tests {
-    use super::super::schema::*;
-    use super::*;
-    use std::cell::RefCell;
-    use std::rc::Rc;
+fn apis_of(children: &LinkedHashMap<String, ContextBoundedAPIData>) -> Vec<&ContextBoundedAPI> {
+    children
+        .values()
+        .filter_map(|child| match child {
+            ContextBoundedAPIData::API(api) => Some(api),
+            _ => None,
+        })
+        .collect()
+}
 
-    fn prepare_context() -> Rc<RefCell<Context>> {
-        let root_ctx = Rc::new(RefCell::new(Context {
-            name: "root".to_string(),
-            parent: None,
-            children: HashMap::new(),
-            scope: hashmap![
-                "foo".to_string() => ContextValue::Expr(Expr::Lit("hello".to_string())),
-                "bar".to_string() => ContextValue::Expr(Expr::Lit("world".to_string())),
-            ],
-        }));
-        let child1_ctx = Rc::new(RefCell::new(Context {
-            name: "child1".to_string(),
-            parent: Some(root_ctx.clone()),
-            children: HashMap::new(),
-            scope: hashmap![
-                "foo_child1".to_string() => ContextValue::Expr(Expr::Lit("hello_child1".to_string())),
-                "bar_child1".to_string() => ContextValue::Expr(Expr::Lit("world_child1".to_string()))
-            ],
-        }));
-        let child2_ctx = Rc::new(RefCell::new(Context {
-            name: "child2".to_string(),
-            parent: Some(root_ctx.clone()),
-            children: HashMap::new(),
-            scope: hashmap![
-                "foo_child2".to_string() => ContextValue::Expr(
-                    Expr::Lit("hello_child2".to_string()),
-                ),
-                "bar_child2".to_string() => ContextValue::Expr(
-                    Expr::Lit("world_child2".to_string()),
-                ),
-            ],
-        }));
-        root_ctx
-            .borrow_mut()
-            .add_child("child1", Rc::clone(&child1_ctx));
-        root_ctx
-            .borrow_mut()
-            .add_child("child2", Rc::clone(&child2_ctx));
-        return root_ctx;
+fn has_apis(children: &LinkedHashMap<String, ContextBoundedAPIData>) -> bool {
+    children.values().any(|child| match child {
+        ContextBoundedAPIData::API(_) => true,
+        ContextBoundedAPIData::APISet(apiset) => has_apis(apiset.children()),
+    })
+}
+
+fn apisets_of(
+    children: &LinkedHashMap<String, ContextBoundedAPIData>,
+) -> Vec<&ContextBoundedAPISet> {
+    children
+        .values()
+        .filter_map(|child| match child {
+            ContextBoundedAPIData::APISet(apiset) => Some(apiset),
+            _ => None,
+        })
+        .collect()
+}
+
+impl ContextBoundedAPI {
+    pub fn name(&self) -> &str {
+        &self.name
     }
 
-    #[test]
-    fn test_lookup_context() {
-        let root_ctx = prepare_context();
-        let result = root_ctx
-            .borrow()
-            .lookup(&["child1".to_string(), "foo_child1".to_string()])
-            .unwrap();
-        assert_eq!(
-            result,
-            ContextValue::Expr(Expr::Lit("hello_child1".to_string()))
-        );
+    pub fn method(&self) -> &HttpMethod {
+        &self.method
     }
 
-    #[test]
-    fn test_lookup_context_no_such_member() {
-        let root_ctx = prepare_context();
-        let result = root_ctx
-            .borrow()
-            .lookup(&["child1".to_string(), "missing".to_string()])
-            .err()
-            .unwrap();
-        assert_eq!(
-            result,
-            ContextLookupError::NoSuchMember {
-                member: "missing".to_string(),
-                context_path: vec!["root".to_string(), "child1".to_string()]
+    pub fn url(&self) -> &ContextValue {
+        &self.url
+    }
+
+    /// All params of the API: those in the URL, then query and body params
+    pub fn bounded_vars(&self) -> &LinkedHashMap<String, Param> {
+        &self.bounded_vars
+    }
+
+    pub fn params(&self) -> &LinkedHashMap<String, Param> {
+        &self.params
+    }
+
+    pub fn data(&self) -> &LinkedHashMap<String, Param> {
+        &self.data
+    }
+
+    pub fn stream(&self) -> bool {
+        self.stream
+    }
+
+    pub fn response_type(&self) -> Option<ResponseType> {
+        self.response_type
+    }
+
+    pub fn paginate(&self) -> Option<&Paginate> {
+        self.paginate.as_ref()
+    }
+
+    pub fn headers(&self) -> &LinkedHashMap<String, String> {
+        &self.headers
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// `Some(None)` for a deprecation without a reason
+    pub fn deprecated(&self) -> Option<Option<&str>> {
+        self.deprecated
+            .as_ref()
+            .map(|reason| reason.as_ref().map(|v| v.as_str()))
+    }
+
+    /// The type name of the response body from `$returns`
+    pub fn returns(&self) -> Option<&str> {
+        self.returns.as_deref()
+    }
+}
+
+impl ContextBoundedAPISet {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn url(&self) -> &ContextValue {
+        &self.url
+    }
+
+    pub fn bounded_vars(&self) -> &LinkedHashMap<String, Param> {
+        &self.bounded_vars
+    }
+
+    /// APIs and APISets directly under this one, in declaration order
+    pub fn children(&self) -> &LinkedHashMap<String, ContextBoundedAPIData> {
+        &self.apisets
+    }
+
+    /// APIs directly under this APISet
+    pub fn apis(&self) -> Vec<&ContextBoundedAPI> {
+        apis_of(&self.apisets)
+    }
+
+    /// APISets directly under this APISet
+    pub fn apisets(&self) -> Vec<&ContextBoundedAPISet> {
+        apisets_of(&self.apisets)
+    }
+}
+
+/// A piece of a fully resolved URL.
+#[derive(Debug, PartialEq, Clone)]
+pub enum UrlPart {
+    Lit(String),
+    Param(Param),
+    /// The URL of the root, which backends usually expose as a variable
+    BaseUrl,
+}
+
+/// A URL with every reference substituted, leaving only literals, params and
+/// the base URL.
+#[derive(Debug, PartialEq, Clone)]
+pub struct UrlTemplate(pub Vec<UrlPart>);
+
+impl UrlTemplate {
+    fn push(&mut self, part: UrlPart) {
+        if let UrlPart::Lit(s) = &part {
+            if let Some(UrlPart::Lit(last)) = self.0.last_mut() {
+                last.push_str(s);
+                return;
             }
-        );
+        }
+        self.0.push(part);
     }
 
-    #[test]
-    fn test_lookup_super() {
-        let root_ctx = prepare_context();
-        let ref child1_ctx = root_ctx.borrow().children["child1"];
-        let result = child1_ctx
-            .borrow()
-            .lookup(&["!super".to_string(), "foo".to_string()])
-            .unwrap();
-        assert_eq!(result, ContextValue::Expr(Expr::Lit("hello".to_string())))
+    /// Params of the URL in the order they appear
+    pub fn params(&self) -> Vec<&Param> {
+        self.0
+            .iter()
+            .filter_map(|part| match part {
+                UrlPart::Param(p) => Some(p),
+                _ => None,
+            })
+            .collect()
     }
+}
 
-    #[test]
-    fn test_lookup_super_and_child() {
-        let root_ctx = prepare_context();
-        let ref child1_ctx = root_ctx.borrow().children["child1"];
-        let result = child1_ctx
-            .borrow()
-            .lookup(&[
-                "!super".to_string(),
-                "child2".to_string(),
-                "bar_child2".to_string(),
-            ])
-            .unwrap();
-        assert_eq!(
-            result,
-            ContextValue::Expr(Expr::Lit("world_child2".to_string()))
-        )
+impl ContextBoundedRoot {
+    pub fn klsname(&self) -> &str {
+        &self.klsname
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn url(&self) -> &ContextValue {
+        &self.url
+    }
+
+    /// Params of the root URL, which become constructor params
+    pub fn bounded_vars(&self) -> &LinkedHashMap<String, Param> {
+        &self.bounded_vars
+    }
+
+    /// Top-level APIs and APISets, in declaration order
+    pub fn children(&self) -> &LinkedHashMap<String, ContextBoundedAPIData> {
+        &self.apisets
+    }
+
+    /// Top-level APIs
+    pub fn apis(&self) -> Vec<&ContextBoundedAPI> {
+        apis_of(&self.apisets)
+    }
+
+    /// Top-level APISets
+    pub fn apisets(&self) -> Vec<&ContextBoundedAPISet> {
+        apisets_of(&self.apisets)
+    }
+
+    /// All APIs in declaration order, each paired with its path from the root
+    pub fn iter_apis(&self) -> Vec<(Vec<String>, &ContextBoundedAPI)> {
+        fn walk<'a>(
+            children: &'a LinkedHashMap<String, ContextBoundedAPIData>,
+            path: &mut Vec<String>,
+            apis: &mut Vec<(Vec<String>, &'a ContextBoundedAPI)>,
+        ) {
+            for (name, child) in children {
+                path.push(name.to_string());
+                match child {
+                    ContextBoundedAPIData::API(api) => apis.push((path.clone(), api)),
+                    ContextBoundedAPIData::APISet(apiset) => walk(apiset.children(), path, apis),
+                }
+                path.pop();
+            }
+        }
+        let mut apis = Vec::new();
+        walk(self.children(), &mut Vec::new(), &mut apis);
+        apis
+    }
+
+    /// Every APISet with its path from the root, parents first
+    pub fn iter_apisets(&self) -> Vec<(Vec<String>, &ContextBoundedAPISet)> {
+        fn walk<'a>(
+            children: &'a LinkedHashMap<String, ContextBoundedAPIData>,
+            path: &mut Vec<String>,
+            apisets: &mut Vec<(Vec<String>, &'a ContextBoundedAPISet)>,
+        ) {
+            for (name, child) in children {
+                if let ContextBoundedAPIData::APISet(apiset) = child {
+                    path.push(name.to_string());
+                    apisets.push((path.clone(), apiset));
+                    walk(apiset.children(), path, apisets);
+                    path.pop();
+                }
+            }
+        }
+        let mut apisets = Vec::new();
+        walk(self.children(), &mut Vec::new(), &mut apisets);
+        apisets
+    }
+
+    /// The root URL itself as a template
+    pub fn base_url(&self) -> UrlTemplate {
+        let mut template = UrlTemplate(Vec::new());
+        let ContextValue::Expr(expr) = &self.url;
+        fn walk(expr: &Expr, vars: &LinkedHashMap<String, Param>, template: &mut UrlTemplate) {
+            match expr {
+                Expr::Lit(s) => template.push(UrlPart::Lit(s.to_string())),
+                Expr::Var(s) => template.push(UrlPart::Param(var_param(s, vars))),
+                Expr::Concat(l, r) => {
+                    walk(l, vars, template);
+                    walk(r, vars, template);
+                }
+                // The root has nothing to refer to
+                Expr::Ref(_) => {}
+            }
+        }
+        walk(expr, &self.bounded_vars, &mut template);
+        template
+    }
+
+    fn context_path(&self, path: &[String]) -> Vec<String> {
+        let mut ret = vec![self.klsname.to_string()];
+        ret.extend(path.iter().cloned());
+        ret
+    }
+
+    fn node_url(&self, path: &[String]) -> Option<(&ContextValue, &LinkedHashMap<String, Param>)> {
+        let (last, init) = path.split_last()?;
+        let mut apisets = &self.apisets;
+        for name in init {
+            match apisets.get(name)? {
+                ContextBoundedAPIData::APISet(apiset) => apisets = &apiset.apisets,
+                ContextBoundedAPIData::API(_) => return None,
+            }
+        }
+        match apisets.get(last)? {
+            ContextBoundedAPIData::API(api) => Some((&api.url, &api.bounded_vars)),
+            ContextBoundedAPIData::APISet(apiset) => Some((&apiset.url, &apiset.bounded_vars)),
+        }
+    }
+
+    /// Drops every API and APISet that is neither within nor on the way to
+    /// one of `paths`. References into the dropped parts are not checked.
+    pub fn prune(&mut self, paths: &[Vec<String>]) -> Result<(), ContextLookupError> {
+        for path in paths {
+            if self.node_url(path).is_none() {
+                return Err(NoSuchMember {
+                    member: path.join("."),
+                    context_path: self.context_path(&[]),
+                });
+            }
+        }
+        let paths = paths
+            .iter()
+            .map(|v| v.as_slice())
+            .collect::<Vec<&[String]>>();
+        prune_apisets(&mut self.apisets, &paths, 0);
+        Ok(())
+    }
+
+    /// Resolves the URL of the API or APISet at `path`, following references
+    /// up to the root, whose URL is left as `UrlPart::BaseUrl`.
+    pub fn url_template(&self, path: &[String]) -> Result<UrlTemplate, ContextLookupError> {
+        let mut template = UrlTemplate(Vec::new());
+        self.resolve_into(path, &mut Vec::new(), &mut template, &mut Vec::new())?;
+        Ok(template)
+    }
+
+    /// The params of the resolved URL of the API or APISet at `path` in the
+    /// order they appear, each paired with the path of the node whose URL
+    /// declares it, which is empty for the root.
+    pub fn url_params(
+        &self,
+        path: &[String],
+    ) -> Result<Vec<(Vec<String>, Param)>, ContextLookupError> {
+        let mut template = UrlTemplate(Vec::new());
+        let mut owners = Vec::new();
+        self.resolve_into(path, &mut Vec::new(), &mut template, &mut owners)?;
+        let mut owners = owners.into_iter();
+        let mut params = Vec::new();
+        for part in template.0 {
+            match part {
+                UrlPart::Param(p) => params.push((owners.next().unwrap_or_default(), p)),
+                UrlPart::BaseUrl => {
+                    for p in self.base_url().params() {
+                        params.push((Vec::new(), p.clone()));
+                    }
+                }
+                UrlPart::Lit(_) => {}
+            }
+        }
+        Ok(params)
+    }
+
+    /// The URL of every API and APISet by path, as an expression with the
+    /// references replaced by what they refer to, down to the root URL. The
+    /// URL of each node is resolved once and reused by the nodes referring to
+    /// it, rather than resolved again for each as with `url_template`. The
+    /// URLs are in the order they were resolved, each after those it refers
+    /// to.
+    pub fn resolve_all(&self) -> Result<LinkedHashMap<Vec<String>, Expr>, ContextLookupError> {
+        let mut resolved = LinkedHashMap::new();
+        let paths = self
+            .iter_apisets()
+            .into_iter()
+            .map(|(path, _)| path)
+            .chain(self.iter_apis().into_iter().map(|(path, _)| path));
+        for path in paths {
+            self.resolve_cached(&path, &mut Vec::new(), &mut resolved)?;
+        }
+        Ok(resolved)
+    }
+
+    /// Resolves the URL of every API, which `transform` leaves to the
+    /// backends, and collects the warnings about the schema. Fails with the
+    /// first URL that doesn't resolve; see [`Self::check_all`] for all of
+    /// them.
+    pub fn check(&self) -> Result<Vec<TransformerWarning>, ContextLookupError> {
+        self.check_all().map_err(|mut errors| errors.remove(0))
+    }
+
+    /// Like [`Self::check`], but fails with every error resolving the URLs.
+    /// An error shared by APIs, such as a bad reference in the URL of their
+    /// APISet, is there once.
+    pub fn check_all(&self) -> Result<Vec<TransformerWarning>, Vec<ContextLookupError>> {
+        fn walk(
+            children: &LinkedHashMap<String, ContextBoundedAPIData>,
+            path: &mut Vec<String>,
+            warnings: &mut Vec<TransformerWarning>,
+        ) {
+            for (name, child) in children {
+                if let ContextBoundedAPIData::APISet(apiset) = child {
+                    path.push(name.to_string());
+                    if !has_apis(apiset.children()) {
+                        warnings.push(TransformerWarning::EmptyAPISet { path: path.clone() });
+                    }
+                    walk(apiset.children(), path, warnings);
+                    path.pop();
+                }
+            }
+        }
+        let start = Instant::now();
+        let mut warnings = Vec::new();
+        walk(self.children(), &mut Vec::new(), &mut warnings);
+        let mut errors = Vec::new();
+        for (path, api) in self.iter_apis() {
+            let template = match self.url_template(&path) {
+                Ok(v) => v,
+                Err(e) => {
+                    if !errors.contains(&e) {
+                        errors.push(e);
+                    }
+                    continue;
+                }
+            };
+            let url_params = template.params();
+            for name in api.params.keys().chain(api.data.keys()) {
+                if url_params.iter().any(|p| &p.name == name) {
+                    warnings.push(TransformerWarning::ShadowedParam {
+                        path: path.clone(),
+                        param: name.to_string(),
+                    });
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        info!(
+            "Check completed: {} warnings in {:?}",
+            warnings.len(),
+            start.elapsed()
+        );
+        Ok(warnings)
+    }
+
+    fn resolve_into(
+        &self,
+        path: &[String],
+        visiting: &mut Vec<Vec<String>>,
+        template: &mut UrlTemplate,
+        owners: &mut Vec<Vec<String>>,
+    ) -> Result<(), ContextLookupError> {
+        if path.is_empty() {
+            template.push(UrlPart::BaseUrl);
+            return Ok(());
+        }
+        let (url, vars) = self.node_url(path).ok_or_else(|| NoSuchMember {
+            member: path[path.len() - 1].to_string(),
+            context_path: self.context_path(&path[..path.len() - 1]),
+        })?;
+        if visiting.iter().any(|v| v.as_slice() == path) {
+            return Err(CyclicReference {
+                context_path: self.context_path(path),
+            });
+        }
+        visiting.push(path.to_vec());
+        let ContextValue::Expr(expr) = url;
+        self.resolve_expr(expr, path, vars, visiting, template, owners)?;
+        visiting.pop();
+        Ok(())
+    }
+
+    fn resolve_expr(
+        &self,
+        expr: &Expr,
+        path: &[String],
+        vars: &LinkedHashMap<String, Param>,
+        visiting: &mut Vec<Vec<String>>,
+        template: &mut UrlTemplate,
+        owners: &mut Vec<Vec<String>>,
+    ) -> Result<(), ContextLookupError> {
+        match expr {
+            Expr::Lit(s) => template.push(UrlPart::Lit(s.to_string())),
+            Expr::Var(s) => {
+                template.push(UrlPart::Param(var_param(s, vars)));
+                owners.push(path.to_vec());
+            }
+            Expr::Concat(l, r) => {
+                self.resolve_expr(l, path, vars, visiting, template, owners)?;
+                self.resolve_expr(r, path, vars, visiting, template, owners)?;
+            }
+            Expr::Ref(ms) => {
+                let target = self.ref_target(path, ms)?;
+                self.resolve_into(&target, visiting, template, owners)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The resolved URL of the node at `path`, from `resolved` if it's there
+    fn resolve_cached(
+        &self,
+        path: &[String],
+        visiting: &mut Vec<Vec<String>>,
+        resolved: &mut LinkedHashMap<Vec<String>, Expr>,
+    ) -> Result<Expr, ContextLookupError> {
+        if path.is_empty() {
+            let ContextValue::Expr(expr) = &self.url;
+            return Ok(expr.clone());
+        }
+        if let Some(expr) = resolved.get(path) {
+            return Ok(expr.clone());
+        }
+        let (url, _) = self.node_url(path).ok_or_else(|| NoSuchMember {
+            member: path[path.len() - 1].to_string(),
+            context_path: self.context_path(&path[..path.len() - 1]),
+        })?;
+        if visiting.iter().any(|v| v.as_slice() == path) {
+            return Err(CyclicReference {
+                context_path: self.context_path(path),
+            });
+        }
+        visiting.push(path.to_vec());
+        let ContextValue::Expr(expr) = url;
+        let expr = self.substitute_refs(expr, path, visiting, resolved)?;
+        visiting.pop();
+        resolved.insert(path.to_vec(), expr.clone());
+        Ok(expr)
+    }
+
+    fn substitute_refs(
+        &self,
+        expr: &Expr,
+        path: &[String],
+        visiting: &mut Vec<Vec<String>>,
+        resolved: &mut LinkedHashMap<Vec<String>, Expr>,
+    ) -> Result<Expr, ContextLookupError> {
+        Ok(match expr {
+            Expr::Concat(l, r) => Expr::Concat(
+                Box::new(self.substitute_refs(l, path, visiting, resolved)?),
+                Box::new(self.substitute_refs(r, path, visiting, resolved)?),
+            ),
+            Expr::Ref(ms) => {
+                let target = self.ref_target(path, ms)?;
+                self.resolve_cached(&target, visiting, resolved)?
+            }
+            _ => expr.clone(),
+        })
+    }
+
+    /// Finds the node whose `url` a reference declared at `path` points to.
+    fn ref_target(
+        &self,
+        path: &[String],
+        ms: &[Member],
+    ) -> Result<Vec<String>, ContextLookupError> {
+        let mut target = path.to_vec();
+        let (last, init) = ms.split_last().ok_or_else(|| EmptyKey {
+            context_path: self.context_path(path),
+        })?;
+        for m in init {
+            match m {
+                Member::Super => {
+                    if target.pop().is_none() {
+                        return Err(NoSuchMember {
+                            member: "!super".to_string(),
+                            context_path: self.context_path(&target),
+                        });
+                    }
+                }
+                Member::Member(m) => target.push(m.to_string()),
+            }
+        }
+        match last {
+            Member::Member(m) if m == "url" => Ok(target),
+            Member::Member(m) => Err(NoSuchMember {
+                member: m.to_string(),
+                context_path: self.context_path(&target),
+            }),
+            Member::Super => Err(LookupOnValue {
+                member: "!super".to_string(),
+                value: ContextValue::Expr(Expr::Ref(ms.to_vec())),
+            }),
+        }
+    }
+}
+
+/// Renders a URL with `{param}` placeholders and the base URL expanded.
+pub(crate) fn display_template(template: &UrlTemplate, base_url: &UrlTemplate) -> String {
+    let mut rendered = String::new();
+    for part in &template.0 {
+        match part {
+            UrlPart::Lit(s) => rendered.push_str(s),
+            UrlPart::Param(p) => rendered.push_str(&format!("{{{}}}", p.name)),
+            UrlPart::BaseUrl => rendered.push_str(&display_template(base_url, base_url)),
+        }
+    }
+    rendered
+}
+
+impl ContextBoundedRoot {
+    fn fmt_children(
+        &self,
+        f: &mut fmt::Formatter,
+        children: &LinkedHashMap<String, ContextBoundedAPIData>,
+        path: &mut Vec<String>,
+        base_url: &UrlTemplate,
+    ) -> fmt::Result {
+        let indent = "  ".repeat(path.len() + 1);
+        for (name, child) in children {
+            if let ContextBoundedAPIData::API(api) = child {
+                path.push(name.to_string());
+                let url = match self.url_template(path) {
+                    Ok(template) => display_template(&template, base_url),
+                    Err(e) => format!("<{}>", e),
+                };
+                write!(
+                    f,
+                    "\n{}{}  {:?} {}",
+                    indent,
+                    path.join("/"),
+                    api.method,
+                    url
+                )?;
+                path.pop();
+            }
+        }
+        for (name, child) in children {
+            if let ContextBoundedAPIData::APISet(apiset) = child {
+                path.push(name.to_string());
+                write!(f, "\n{}{}/", indent, path.join("/"))?;
+                self.fmt_children(f, &apiset.apisets, path, base_url)?;
+                path.pop();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A concise tree of the APIs with their methods and resolved URLs. APIs are
+/// listed before the APISets next to them.
+impl fmt::Display for ContextBoundedRoot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let base_url = self.base_url();
+        write!(
+            f,
+            "{}  {}",
+            self.klsname,
+            display_template(&base_url, &base_url)
+        )?;
+        self.fmt_children(f, &self.apisets, &mut Vec::new(), &base_url)
+    }
+}
+
+/// Keeps the children named by `paths` at `depth`, along with whatever is on
+/// the way to them.
+fn prune_apisets(
+    apisets: &mut LinkedHashMap<String, ContextBoundedAPIData>,
+    paths: &[&[String]],
+    depth: usize,
+) {
+    let children = std::mem::replace(apisets, LinkedHashMap::new());
+    for (name, mut child) in children {
+        let relevant = paths
+            .iter()
+            .filter(|path| path[depth] == name)
+            .cloned()
+            .collect::<Vec<&[String]>>();
+        if relevant.is_empty() {
+            continue;
+        }
+        if relevant.iter().all(|path| path.len() > depth + 1) {
+            if let ContextBoundedAPIData::APISet(apiset) = &mut child {
+                prune_apisets(&mut apiset.apisets, &relevant, depth + 1);
+            }
+        }
+        apisets.insert(name, child);
+    }
+}
+
+fn var_param(name: &str, vars: &LinkedHashMap<String, Param>) -> Param {
+    vars.get(name)
+        .cloned()
+        .unwrap_or_else(|| Param::new(name, None))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::schema::*;
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use utils::linked_hashmap;
+
+    fn prepare_context() -> Rc<RefCell<Context>> {
+        let lit = |s: &str| ContextValue::Expr(Expr::Lit(s.to_string()));
+        let root_ctx = Rc::new(RefCell::new(Context::new("root", None)));
+        root_ctx.borrow_mut().add_value("foo", lit("hello"));
+        root_ctx.borrow_mut().add_value("bar", lit("world"));
+        let child1_ctx = Context::new_child(&root_ctx, "child1");
+        child1_ctx
+            .borrow_mut()
+            .add_value("foo_child1", lit("hello_child1"));
+        child1_ctx
+            .borrow_mut()
+            .add_value("bar_child1", lit("world_child1"));
+        let child2_ctx = Context::new_child(&root_ctx, "child2");
+        child2_ctx
+            .borrow_mut()
+            .add_value("foo_child2", lit("hello_child2"));
+        child2_ctx
+            .borrow_mut()
+            .add_value("bar_child2", lit("world_child2"));
+        root_ctx
+    }
+
+    /// A key of members as written in a reference, such as `!super.url`
+    fn key(s: &str) -> Vec<Member> {
+        s.split('.')
+            .map(|m| match m {
+                "!super" => Member::Super,
+                m => Member::Member(m.to_string()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_response_type_from_str() {
+        assert_eq!(
+            "arraybuffer".parse::<ResponseType>(),
+            Ok(ResponseType::ArrayBuffer)
+        );
+        for name in ResponseType::NAMES {
+            assert_eq!(name.parse::<ResponseType>().unwrap().as_str(), *name);
+        }
+        let e = "file".parse::<ResponseType>().unwrap_err();
+        assert_eq!(e, InvalidResponseType(String::new(), "file".to_string()));
+        assert_eq!(
+            e.to_string(),
+            "Invalid $responseType \"file\", expected one of: json, blob, text, arraybuffer"
+        );
+    }
+
+    #[test]
+    fn test_http_method_from_str() {
+        assert_eq!("patch".parse::<HttpMethod>(), Ok(HttpMethod::PATCH));
+        assert_eq!("GET".parse::<HttpMethod>(), Ok(HttpMethod::GET));
+        let e = "Patch".parse::<HttpMethod>().unwrap_err();
+        assert_eq!(
+            e,
+            UnsupportedMethod {
+                path: Vec::new(),
+                method: "Patch".to_string(),
+            }
+        );
+        assert_eq!(
+            e.to_string(),
+            "Unsupported HTTP method \"Patch\", expected one of: \
+             GET, POST, PUT, DELETE, HEAD, OPTIONS, PATCH"
+        );
+    }
+
+    #[test]
+    fn test_lookup_context() {
+        let root_ctx = prepare_context();
+        let result = root_ctx.borrow().lookup(&key("child1.foo_child1")).unwrap();
+        assert_eq!(
+            *result,
+            ContextValue::Expr(Expr::Lit("hello_child1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_lookup_shares_value() {
+        let root_ctx = prepare_context();
+        let result = root_ctx.borrow().lookup(&key("child1.foo_child1")).unwrap();
+        let child1_ctx = Rc::clone(root_ctx.borrow().child("child1").unwrap());
+        let local = Rc::clone(child1_ctx.borrow().lookup_local("foo_child1").unwrap());
+        assert!(Rc::ptr_eq(&result, &local));
+        assert_eq!(
+            root_ctx.borrow().lookup(&[]).err().unwrap(),
+            ContextLookupError::EmptyKey {
+                context_path: vec!["root".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn test_lookup_context_no_such_member() {
+        let root_ctx = prepare_context();
+        let result = root_ctx
+            .borrow()
+            .lookup(&key("child1.missing"))
+            .err()
+            .unwrap();
+        assert_eq!(
+            result,
+            ContextLookupError::NoSuchMember {
+                member: "missing".to_string(),
+                context_path: vec!["root".to_string(), "child1".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn test_lookup_super() {
+        let root_ctx = prepare_context();
+        let child1_ctx = Rc::clone(root_ctx.borrow().child("child1").unwrap());
+        let result = child1_ctx.borrow().lookup(&key("!super.foo")).unwrap();
+        assert_eq!(*result, ContextValue::Expr(Expr::Lit("hello".to_string())))
+    }
+
+    #[test]
+    fn test_lookup_super_and_child() {
+        let root_ctx = prepare_context();
+        let child1_ctx = Rc::clone(root_ctx.borrow().child("child1").unwrap());
+        let result = child1_ctx
+            .borrow()
+            .lookup(&key("!super.child2.bar_child2"))
+            .unwrap();
+        assert_eq!(
+            *result,
+            ContextValue::Expr(Expr::Lit("world_child2".to_string()))
+        )
+    }
+
+    #[test]
+    fn test_context_iteration() {
+        let root_ctx = prepare_context();
+        let root = root_ctx.borrow();
+        assert_eq!(
+            root.values().map(|(k, _)| k).collect::<Vec<&str>>(),
+            vec!["foo", "bar"]
+        );
+        assert_eq!(
+            root.children().map(|(k, _)| k).collect::<Vec<&str>>(),
+            vec!["child1", "child2"]
+        );
+        let child2_ctx = root.child("child2").unwrap().borrow();
+        assert_eq!(child2_ctx.name(), "child2");
+        assert_eq!(child2_ctx.path(), vec!["root", "child2"]);
+        assert!(Rc::ptr_eq(&child2_ctx.parent().unwrap(), &root_ctx));
+    }
+
+    #[test]
+    fn test_context_drop() {
+        // Children don't keep their parent alive, so dropping the root frees
+        // the whole tree
+        let root_ctx = prepare_context();
+        let weak_root = Rc::downgrade(&root_ctx);
+        let child2_ctx = Rc::clone(root_ctx.borrow().child("child2").unwrap());
+        drop(root_ctx);
+        assert_eq!(weak_root.strong_count(), 0);
+        assert!(child2_ctx.borrow().parent().is_none());
+        assert_eq!(child2_ctx.borrow().path(), vec!["child2"]);
+    }
+
+    #[test]
+    fn test_transform_fills_contexts() {
+        let schema = parse_str(include_str!(
+            "../tests/fixtures/cases/deprecated/schema.yaml"
+        ))
+        .unwrap();
+        let root_ast = transform(schema).unwrap();
+        // `${!super}` in the URL of ratincren.get is looked up from its context
+        let ratincren = &root_ast.apisets()[0];
+        let get = ratincren.apis()[3];
+        assert_eq!(get.name(), "get");
+        assert_eq!(
+            *get.context.borrow().lookup(&key("!super.url")).unwrap(),
+            *ratincren.url()
+        );
+        let ratincren = root_ast
+            .context
+            .borrow()
+            .lookup(&key("ratincren.url"))
+            .unwrap();
+        assert_eq!(
+            *ratincren,
+            ContextValue::Expr(Expr::Concat(
+                Box::new(Expr::Ref(key("!super.url"))),
+                Box::new(Expr::Lit("/ratincren".to_string()))
+            ))
+        );
     }
 
     #[test]
@@ -469,100 +1722,127 @@ pub mod tests {
         let schema = RootSchema {
             url: Some("http://ratina.org/<id:int>".to_string()),
             klsname: "RatinaClient".to_string(),
+            description: None,
             apisets: APIDataMap(linked_hashmap![
                 "ahcro".to_string() => APIData::API(APISchema{
                     method: "GET".to_string(),
                     url: "${!super.url}/<ahcroId:uuid>".to_string(),
                     params: LinkedHashMap::new(),
-                    data: LinkedHashMap::new()
+                    data: LinkedHashMap::new(),
+                    stream: false,
+                    response_type: None,
+                    paginate: None,
+                    headers: LinkedHashMap::new(),
+                    description: None,
+                    deprecated: None,
+                    returns: None
                 }),
                 "ratincren".to_string() => APIData::APISet(APISetSchema{
                     url: "${!super.url}/ratincren".to_string(),
+                    headers: LinkedHashMap::new(),
+                    description: None,
                     apisets: APIDataMap(linked_hashmap![
                         "get".to_string() => APIData::API(APISchema{
                             method: "GET".to_string(),
                             url: "${!super.url}/<name:string>".to_string(),
                             params: LinkedHashMap::new(),
-                            data: LinkedHashMap::new()
+                            data: LinkedHashMap::new(),
+                            stream: false,
+                            response_type: None,
+                            paginate: None,
+                            headers: LinkedHashMap::new(),
+                            description: None,
+                            deprecated: None,
+                            returns: None
                         })
                     ])
                 })
             ]),
         };
         let root_ast = transform(schema).unwrap();
+        let root_url = ContextValue::Expr(Expr::Concat(
+            Box::new(Expr::Lit("http://ratina.org/".to_string())),
+            Box::new(Expr::Var("id".to_string())),
+        ));
+        let ahcro_url = ContextValue::Expr(Expr::Concat(
+            Box::new(Expr::Concat(
+                Box::new(Expr::Ref(key("!super.url"))),
+                Box::new(Expr::Lit("/".to_string())),
+            )),
+            Box::new(Expr::Var("ahcroId".to_string())),
+        ));
+        let ratincren_url = ContextValue::Expr(Expr::Concat(
+            Box::new(Expr::Ref(key("!super.url"))),
+            Box::new(Expr::Lit("/ratincren".to_string())),
+        ));
+        let ratincren_get_url = ContextValue::Expr(Expr::Concat(
+            Box::new(Expr::Concat(
+                Box::new(Expr::Ref(key("!super.url"))),
+                Box::new(Expr::Lit("/".to_string())),
+            )),
+            Box::new(Expr::Var("name".to_string())),
+        ));
         let root_ctx = Rc::new(RefCell::new(Context::new("RatinaClient", None)));
-        let ahcro_ctx = Rc::new(RefCell::new(Context::new("ahcro", Some(root_ctx.clone()))));
-        let ratincren_ctx = Rc::new(RefCell::new(Context::new(
-            "ratincren",
-            Some(root_ctx.clone()),
-        )));
-        let ratincren_get_ctx = Rc::new(RefCell::new(Context::new(
-            "get",
-            Some(ratincren_ctx.clone()),
-        )));
+        root_ctx.borrow_mut().add_value("url", root_url.clone());
+        let ahcro_ctx = Context::new_child(&root_ctx, "ahcro");
+        ahcro_ctx.borrow_mut().add_value("url", ahcro_url.clone());
+        let ratincren_ctx = Context::new_child(&root_ctx, "ratincren");
+        ratincren_ctx
+            .borrow_mut()
+            .add_value("url", ratincren_url.clone());
+        let ratincren_get_ctx = Context::new_child(&ratincren_ctx, "get");
+        ratincren_get_ctx
+            .borrow_mut()
+            .add_value("url", ratincren_get_url.clone());
         assert_eq!(
             root_ast,
             ContextBoundedRoot {
                 klsname: "RatinaClient".to_string(),
-                url: ContextValue::Expr(Expr::Concat(
-                    box Expr::Lit("http://ratina.org/".to_string()),
-                    box Expr::Var("id".to_string())
-                )),
+                description: None,
+                url: root_url,
                 bounded_vars: linked_hashmap![
-                    "id".to_string() => Param {
-                        name: "id".to_string(),
-                        typ: Some("int".to_string())
-                    }
+                    "id".to_string() => Param::new("id", Some("int".to_string()))
                 ],
                 apisets: linked_hashmap![
                     "ahcro".to_string() => ContextBoundedAPIData::API(ContextBoundedAPI{
                         name: "ahcro".to_string(),
-                        method: HttpMethod::from_str("GET"),
-                        url: ContextValue::Expr(
-                            Expr::Concat(
-                                box Expr::Concat(
-                                    box Expr::Ref(vec![Member::Super, Member::Member("url".to_string())]),
-                                    box Expr::Lit("/".to_string())
-                                ),
-                                box Expr::Var("ahcroId".to_string())
-                            )
-                        ),
+                        method: HttpMethod::GET,
+                        url: ahcro_url,
                         bounded_vars: linked_hashmap![
-                            "ahcroId".to_string() => Param{ name: "ahcroId".to_string(), typ: Some("uuid".to_string())}],
+                            "ahcroId".to_string() => Param::new("ahcroId", Some("uuid".to_string()))],
                         params: LinkedHashMap::new(),
                         data: LinkedHashMap::new(),
+                        stream: false,
+                        response_type: None,
+                        paginate: None,
+                        headers: LinkedHashMap::new(),
+                        description: None,
+                        deprecated: None,
+                        returns: None,
                         context: ahcro_ctx
                     }),
                     "ratincren".to_string() => ContextBoundedAPIData::APISet(ContextBoundedAPISet{
                         name: "ratincren".to_string(),
-                        url: ContextValue::Expr(
-                            Expr::Concat(
-                                box Expr::Ref(vec![Member::Super, Member::Member("url".to_string())]),
-                                box Expr::Lit("/ratincren".to_string())
-                            )
-                        ),
+                        description: None,
+                        url: ratincren_url,
                         bounded_vars: LinkedHashMap::new(),
                         apisets: linked_hashmap![
                             "get".to_string() => ContextBoundedAPIData::API(ContextBoundedAPI{
                                 name: "get".to_string(),
-                                method: HttpMethod::from_str("GET"),
-                                url: ContextValue::Expr(
-                                    Expr::Concat(
-                                        box Expr::Concat(
-                                            box Expr::Ref(vec![Member::Super, Member::Member("url".to_string())]),
-                                            box Expr::Lit("/".to_string())
-                                        ),
-                                        box Expr::Var("name".to_string())
-                                    )
-                                ),
+                                method: HttpMethod::GET,
+                                url: ratincren_get_url,
                                 bounded_vars: linked_hashmap![
-                                    "name".to_string() => Param{
-                                        name: "name".to_string(),
-                                        typ: Some("string".to_string())
-                                    }
+                                    "name".to_string() => Param::new("name", Some("string".to_string()))
                                 ],
                                 params: LinkedHashMap::new(),
                                 data: LinkedHashMap::new(),
+                                stream: false,
+                                response_type: None,
+                                paginate: None,
+                                headers: LinkedHashMap::new(),
+                                description: None,
+                                deprecated: None,
+                                returns: None,
                                 context: ratincren_get_ctx
                             })
                         ],
@@ -573,4 +1853,749 @@ pub mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_transform_empty_url_inherits_parent() {
+        let schema = RootSchema {
+            url: Some("".to_string()),
+            klsname: "RatinaClient".to_string(),
+            description: None,
+            apisets: APIDataMap(linked_hashmap![
+                "ratincren".to_string() => APIData::APISet(APISetSchema{
+                    url: "".to_string(),
+                    headers: LinkedHashMap::new(),
+                    description: None,
+                    apisets: APIDataMap(linked_hashmap![
+                        "all".to_string() => APIData::API(APISchema{
+                            method: "GET".to_string(),
+                            url: "".to_string(),
+                            params: LinkedHashMap::new(),
+                            data: LinkedHashMap::new(),
+                            stream: false,
+                            response_type: None,
+                            paginate: None,
+                            headers: LinkedHashMap::new(),
+                            description: None,
+                            deprecated: None,
+                            returns: None
+                        })
+                    ])
+                })
+            ]),
+        };
+        let root_ast = transform(schema).unwrap();
+        let inherited = ContextValue::Expr(Expr::Ref(vec![
+            Member::Super,
+            Member::Member("url".to_string()),
+        ]));
+        assert_eq!(
+            root_ast.url,
+            ContextValue::Expr(Expr::Var("url".to_string()))
+        );
+        assert!(root_ast.bounded_vars.contains_key("url"));
+        match &root_ast.apisets["ratincren"] {
+            ContextBoundedAPIData::APISet(apiset) => {
+                assert_eq!(apiset.url, inherited);
+                assert!(apiset.bounded_vars.is_empty());
+                match &apiset.apisets["all"] {
+                    ContextBoundedAPIData::API(api) => assert_eq!(api.url, inherited),
+                    _ => panic!("Expected API \"all\""),
+                }
+            }
+            _ => panic!("Expected APISet \"ratincren\""),
+        }
+    }
+
+    fn resolver_schema() -> RootSchema {
+        RootSchema {
+            url: Some("http://ratina.org".to_string()),
+            klsname: "RatinaClient".to_string(),
+            description: None,
+            apisets: APIDataMap(linked_hashmap![
+                "ratincren".to_string() => APIData::APISet(APISetSchema{
+                    url: "${!super.url}/ratincren/<group:int>".to_string(),
+                    headers: LinkedHashMap::new(),
+                    description: None,
+                    apisets: APIDataMap(linked_hashmap![
+                        "get".to_string() => APIData::API(APISchema{
+                            method: "GET".to_string(),
+                            url: "${!super.url}/<name:string>".to_string(),
+                            params: LinkedHashMap::new(),
+                            data: LinkedHashMap::new(),
+                            stream: false,
+                            response_type: None,
+                            paginate: None,
+                            headers: LinkedHashMap::new(),
+                            description: None,
+                            deprecated: None,
+                            returns: None
+                        }),
+                        "loop".to_string() => APIData::API(APISchema{
+                            method: "GET".to_string(),
+                            url: "${url}/loop".to_string(),
+                            params: LinkedHashMap::new(),
+                            data: LinkedHashMap::new(),
+                            stream: false,
+                            response_type: None,
+                            paginate: None,
+                            headers: LinkedHashMap::new(),
+                            description: None,
+                            deprecated: None,
+                            returns: None
+                        })
+                    ])
+                })
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_iter_apis() {
+        let root_ast = transform(resolver_schema()).unwrap();
+        let paths = root_ast
+            .iter_apis()
+            .into_iter()
+            .map(|(path, _)| path.join("."))
+            .collect::<Vec<String>>();
+        assert_eq!(paths, vec!["ratincren.get", "ratincren.loop"]);
+    }
+
+    #[test]
+    fn test_url_template() {
+        let root_ast = transform(resolver_schema()).unwrap();
+        let template = root_ast
+            .url_template(&["ratincren".to_string(), "get".to_string()])
+            .unwrap();
+        assert_eq!(
+            template,
+            UrlTemplate(vec![
+                UrlPart::BaseUrl,
+                UrlPart::Lit("/ratincren/".to_string()),
+                UrlPart::Param(Param::new("group", Some("int".to_string()))),
+                UrlPart::Lit("/".to_string()),
+                UrlPart::Param(Param::new("name", Some("string".to_string()))),
+            ])
+        );
+        assert_eq!(
+            root_ast.base_url(),
+            UrlTemplate(vec![UrlPart::Lit("http://ratina.org".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_url_params() {
+        let root_ast = transform(resolver_schema()).unwrap();
+        let params = root_ast
+            .url_params(&["ratincren".to_string(), "get".to_string()])
+            .unwrap();
+        assert_eq!(
+            params,
+            vec![
+                (
+                    vec!["ratincren".to_string()],
+                    Param::new("group", Some("int".to_string()))
+                ),
+                (
+                    vec!["ratincren".to_string(), "get".to_string()],
+                    Param::new("name", Some("string".to_string()))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_url_template_cyclic() {
+        let root_ast = transform(resolver_schema()).unwrap();
+        let result = root_ast
+            .url_template(&["ratincren".to_string(), "loop".to_string()])
+            .err()
+            .unwrap();
+        assert_eq!(
+            result,
+            ContextLookupError::CyclicReference {
+                context_path: vec![
+                    "RatinaClient".to_string(),
+                    "ratincren".to_string(),
+                    "loop".to_string()
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_url_template_sibling() {
+        let schema = parse_str(include_str!(
+            "../tests/fixtures/cases/sibling_url/schema.yaml"
+        ))
+        .unwrap();
+        let root_ast = transform(schema).unwrap();
+        // ahcro refers to ratincren, which comes after it in the schema
+        let path = |s: &str| s.split('.').map(|v| v.to_string()).collect::<Vec<String>>();
+        assert_eq!(
+            root_ast.url_template(&path("ahcro")).unwrap(),
+            UrlTemplate(vec![
+                UrlPart::BaseUrl,
+                UrlPart::Lit("/ratincren/ahcro".to_string()),
+            ])
+        );
+        assert_eq!(
+            root_ast.url_template(&path("ahcro.budgets")).unwrap(),
+            UrlTemplate(vec![
+                UrlPart::BaseUrl,
+                UrlPart::Lit("/ratincren/budgets".to_string()),
+            ])
+        );
+        let resolved = root_ast.resolve_all().unwrap();
+        let lit = |s: &str| Box::new(Expr::Lit(s.to_string()));
+        assert_eq!(
+            resolved[&path("ahcro")],
+            Expr::Concat(
+                Box::new(Expr::Concat(lit("http://ratina.org"), lit("/ratincren"))),
+                lit("/ahcro")
+            )
+        );
+        assert_eq!(root_ast.check().unwrap(), Vec::new());
+        // The context of ahcro finds its sibling too
+        let ahcro = &root_ast.apisets()[0];
+        assert_eq!(
+            *ahcro
+                .context
+                .borrow()
+                .lookup(&key("!super.ratincren.url"))
+                .unwrap(),
+            *root_ast.apisets()[1].url()
+        );
+        assert_eq!(
+            ahcro
+                .context
+                .borrow()
+                .lookup(&key("!super.missing.url"))
+                .err()
+                .unwrap(),
+            ContextLookupError::NoSuchMember {
+                member: "missing".to_string(),
+                context_path: vec!["RatinaClient".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_resolve_all() {
+        let schema = parse_str(include_str!(
+            "../tests/fixtures/cases/deprecated/schema.yaml"
+        ))
+        .unwrap();
+        let root_ast = transform(schema).unwrap();
+        let resolved = root_ast.resolve_all().unwrap();
+        let lit = |s: &str| Box::new(Expr::Lit(s.to_string()));
+        let ratincren = Expr::Concat(lit("http://ratina.org"), lit("/ratincren"));
+        assert_eq!(resolved[&vec!["ratincren".to_string()]], ratincren);
+        assert_eq!(
+            resolved[&vec!["ratincren".to_string(), "get".to_string()]],
+            Expr::Concat(
+                Box::new(Expr::Concat(Box::new(ratincren), lit("/"))),
+                Box::new(Expr::Var("id".to_string()))
+            )
+        );
+        let paths = resolved
+            .keys()
+            .map(|v| v.join("."))
+            .collect::<Vec<String>>();
+        assert_eq!(
+            paths,
+            vec![
+                "ratincren",
+                "ratincren.all",
+                "ratincren.list",
+                "ratincren.search",
+                "ratincren.get"
+            ]
+        );
+        let result = transform(resolver_schema()).unwrap().resolve_all();
+        assert!(matches!(
+            result,
+            Err(ContextLookupError::CyclicReference { .. })
+        ));
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut root_ast = transform(resolver_schema()).unwrap();
+        root_ast
+            .prune(&[vec!["ratincren".to_string(), "get".to_string()]])
+            .unwrap();
+        let paths = root_ast
+            .iter_apis()
+            .into_iter()
+            .map(|(path, _)| path.join("."))
+            .collect::<Vec<String>>();
+        assert_eq!(paths, vec!["ratincren.get"]);
+        let result = root_ast
+            .prune(&[vec!["missing".to_string()]])
+            .err()
+            .unwrap();
+        assert_eq!(
+            result,
+            ContextLookupError::NoSuchMember {
+                member: "missing".to_string(),
+                context_path: vec!["RatinaClient".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn test_apiset_headers() {
+        let schema =
+            parse_str(include_str!("../tests/fixtures/cases/headers/schema.yaml")).unwrap();
+        let root = transform(schema).unwrap();
+        let apis = root.iter_apis();
+        let headers = |path: &str| {
+            apis.iter()
+                .find(|(p, _)| p.join(".") == path)
+                .map(|(_, api)| api.headers())
+                .unwrap()
+        };
+        for path in &["users.all", "users.avatar", "users.budgets.all"] {
+            assert_eq!(headers(path)["Authorization"], "Bearer xiaosi");
+        }
+        assert_eq!(headers("users.all")["Accept"], "application/json");
+        assert_eq!(headers("users.avatar")["Accept"], "image/png");
+        assert_eq!(
+            headers("users.budgets.all")
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, v))
+                .collect::<Vec<String>>(),
+            vec![
+                "Authorization: Bearer xiaosi",
+                "Accept: application/json",
+                "X-Budget-Year: 2019"
+            ]
+        );
+        assert!(headers("public").is_empty());
+    }
+
+    #[test]
+    fn test_accessors() {
+        let schema = parse_str(include_str!("../tests/fixtures/cases/sample/schema.yaml")).unwrap();
+        let root_ast = transform(schema).unwrap();
+        assert_eq!(root_ast.klsname(), "XiaoSiClient");
+        assert!(root_ast.bounded_vars().is_empty());
+        assert!(root_ast.apis().is_empty());
+        let users = root_ast.apisets();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].name(), "users");
+        let names = users[0]
+            .apis()
+            .iter()
+            .map(|api| api.name())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, vec!["all", "get", "create", "update"]);
+        assert_eq!(users[0].apisets()[0].name(), "budgets");
+        let get = users[0].apis()[1];
+        assert_eq!(get.method(), &HttpMethod::GET);
+        assert_eq!(
+            get.bounded_vars().keys().collect::<Vec<&String>>(),
+            vec!["id", "detail"]
+        );
+        assert_eq!(
+            get.params().keys().collect::<Vec<&String>>(),
+            vec!["detail"]
+        );
+        assert!(get.data().is_empty());
+        assert!(!get.stream());
+        assert_eq!(get.headers()["Accept"], "application/json");
+        assert_eq!(
+            root_ast
+                .url_template(&["users".to_string(), "get".to_string()])
+                .unwrap(),
+            UrlTemplate(vec![
+                UrlPart::BaseUrl,
+                UrlPart::Lit("/users/".to_string()),
+                UrlPart::Param(Param::new("id", Some("number".to_string()))),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_response_type() {
+        let schema =
+            parse_str(include_str!("../tests/fixtures/cases/download/schema.yaml")).unwrap();
+        let root_ast = transform(schema).unwrap();
+        let apis = root_ast.apisets()[0].apis();
+        assert_eq!(apis[0].response_type(), None);
+        assert_eq!(apis[1].response_type(), Some(ResponseType::Blob));
+
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+download:
+  $responseType: \"file\"
+",
+        )
+        .unwrap();
+        assert_eq!(
+            transform(schema),
+            Err(InvalidResponseType(
+                "download".to_string(),
+                "file".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_invalid_url() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+~ratincren:
+  $url: \"${!super}/ratincren\"
+  get:
+    $url: \"${!super}/<name:string\"
+",
+        )
+        .unwrap();
+        assert_eq!(
+            transform(schema),
+            Err(InvalidUrl {
+                path: vec!["ratincren".to_string(), "get".to_string()],
+                url: "${!super}/<name:string".to_string(),
+                error: crate::se_parser::ParserError::UnexpectedEOF,
+            })
+        );
+
+        let schema = parse_str("$url: \"http://ratina.org/${a..b}\"\n").unwrap();
+        let e = transform(schema).unwrap_err();
+        assert_eq!(e.path(), Some(&[][..]));
+        assert_eq!(
+            e.to_string(),
+            "Invalid $url of the root: Unexpected token \".\" at pos 22"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_param() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+~ratincren:
+  $url: \"${!super}/ratincren\"
+  get:
+    $url: \"${!super}/<name:string>\"
+    $params:
+      name: \"string\"
+",
+        )
+        .unwrap();
+        let e = transform(schema).unwrap_err();
+        assert_eq!(
+            e,
+            DuplicateParam {
+                path: vec!["ratincren".to_string(), "get".to_string()],
+                name: "name".to_string(),
+                first: ParamSource::UrlParam,
+                second: ParamSource::Param,
+            }
+        );
+        assert_eq!(
+            e.path(),
+            Some(&["ratincren".to_string(), "get".to_string()][..])
+        );
+        assert_eq!(
+            e.to_string(),
+            "$params key \"name\" of ratincren.get duplicates the URL param of the same name"
+        );
+
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+search.POST:
+  $params:
+    q: \"string\"
+  $data:
+    q: \"string\"
+",
+        )
+        .unwrap();
+        assert_eq!(
+            transform(schema).unwrap_err().to_string(),
+            "$data key \"q\" of search duplicates the $params key of the same name"
+        );
+    }
+
+    #[test]
+    fn test_invalid_default() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+~ratincren:
+  $url: \"${!super}/ratincren\"
+  create:
+    $method: POST
+    $data:
+      count: int = ten
+      draft: bool = yes
+      ratio: float = 0.5
+  list:
+    $params:
+      page: int = 1.5
+",
+        )
+        .unwrap();
+        let errors = transform_all(schema).unwrap_err();
+        let invalid = |api: &str, param: &str, ty: &str| InvalidDefault {
+            path: vec!["ratincren".to_string(), api.to_string()],
+            param: param.to_string(),
+            ty: ty.to_string(),
+        };
+        assert_eq!(
+            errors,
+            vec![
+                invalid("create", "count", "int"),
+                invalid("create", "draft", "bool"),
+                invalid("list", "page", "int"),
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "The default of param \"count\" of ratincren.create isn't a valid int"
+        );
+    }
+
+    #[test]
+    fn test_invalid_type() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+list:
+  $params:
+    order: enum(asc,desc
+    status: enum(open,closed)|default:open
+",
+        )
+        .unwrap();
+        let errors = transform_all(schema).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![InvalidType {
+                path: vec!["list".to_string()],
+                param: "order".to_string(),
+                error: crate::se_parser::ParserError::UnexpectedEOF,
+            }]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "The type of param \"order\" of list doesn't parse: Unexpected EOF"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_method() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+~ratincren:
+  $url: \"${!super}/ratincren\"
+  get:
+    $method: \"Get\"
+  fetch:
+    $method: \"FETCH\"
+  list: {}
+",
+        )
+        .unwrap();
+        let errors = transform_all(schema).unwrap_err();
+        assert_eq!(
+            errors[0],
+            UnsupportedMethod {
+                path: vec!["ratincren".to_string(), "get".to_string()],
+                method: "Get".to_string(),
+            }
+        );
+        let path = ["ratincren".to_string(), "fetch".to_string()];
+        assert_eq!(errors[1].path(), Some(&path[..]));
+        assert_eq!(
+            errors[1].to_string(),
+            "Unsupported HTTP method \"FETCH\" of ratincren.fetch, expected one of: \
+             GET, POST, PUT, DELETE, HEAD, OPTIONS, PATCH"
+        );
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_url_template_absolute() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+~ratincren:
+  $url: \"${!super}/ratincren\"
+  avatar:
+    $url: \"https://cdn.ratina.org/avatars/<name:string>\"
+",
+        )
+        .unwrap();
+        let root_ast = transform(schema).unwrap();
+        assert_eq!(
+            root_ast
+                .url_template(&["ratincren".to_string(), "avatar".to_string()])
+                .unwrap(),
+            UrlTemplate(vec![
+                UrlPart::Lit("https://cdn.ratina.org/avatars/".to_string()),
+                UrlPart::Param(Param::new("name", Some("string".to_string()))),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_display_tree() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org/<id:int>\"
+$as: \"RatinaClient\"
+ahcro:
+  $url: \"${!super.url}/<ahcroId:uuid>\"
+~ratincren:
+  $url: \"${!super.url}/ratincren\"
+  get:
+    $url: \"${!super.url}/<name:string>\"
+  create:
+    $method: \"POST\"
+  ~members:
+    all: {}
+",
+        )
+        .unwrap();
+        let tree = transform(schema).unwrap().to_string();
+        assert!(tree.contains("ahcro  GET"));
+        assert!(tree.contains("ratincren/get"));
+        assert_eq!(
+            tree,
+            "\
+RatinaClient  http://ratina.org/{id}
+  ahcro  GET http://ratina.org/{id}/{ahcroId}
+  ratincren/
+    ratincren/get  GET http://ratina.org/{id}/ratincren/{name}
+    ratincren/create  POST http://ratina.org/{id}/ratincren
+    ratincren/members/
+      ratincren/members/all  GET http://ratina.org/{id}/ratincren"
+        );
+    }
+
+    #[test]
+    fn test_display_tree_unresolved_url() {
+        let tree = transform(resolver_schema()).unwrap().to_string();
+        assert!(tree.contains("    ratincren/loop  GET <"));
+    }
+
+    #[test]
+    fn test_check() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+~ratincren:
+  $url: \"${!super}/ratincren/<group:int>\"
+  get:
+    $params:
+      group: \"int\"
+      limit: \"int\"
+  ~members:
+    ~admins: {}
+",
+        )
+        .unwrap();
+        let warnings = transform(schema).unwrap().check().unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                TransformerWarning::EmptyAPISet {
+                    path: vec!["ratincren".to_string(), "members".to_string()]
+                },
+                TransformerWarning::EmptyAPISet {
+                    path: vec![
+                        "ratincren".to_string(),
+                        "members".to_string(),
+                        "admins".to_string()
+                    ]
+                },
+                TransformerWarning::ShadowedParam {
+                    path: vec!["ratincren".to_string(), "get".to_string()],
+                    param: "group".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            warnings[2].path(),
+            &["ratincren".to_string(), "get".to_string()]
+        );
+        assert_eq!(
+            warnings[2].to_string(),
+            "Param \"group\" of ratincren.get shadows the URL param of the same name"
+        );
+    }
+
+    #[test]
+    fn test_transform_all() {
+        let schema = parse_str(include_str!("../tests/fixtures/three_errors.yaml")).unwrap();
+        let errors = transform_all(schema).unwrap_err();
+        let messages = errors
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            messages,
+            vec![
+                "Invalid $url of ratincren.get: Unexpected EOF",
+                "$params key \"q\" of ratincren.search duplicates the URL param of the same name",
+                "Invalid $responseType \"file\" of API \"download\", expected one of: \
+                 json, blob, text, arraybuffer",
+            ]
+        );
+        // The first of them, as before
+        let schema = parse_str(include_str!("../tests/fixtures/three_errors.yaml")).unwrap();
+        assert_eq!(transform(schema).unwrap_err(), errors[0]);
+    }
+
+    #[test]
+    fn test_check_all() {
+        let schema = parse_str(
+            "\
+$url: \"http://ratina.org\"
+~ratincren:
+  $url: \"${!super.missing}/ratincren\"
+  get: {}
+  list: {}
+search:
+  $url: \"${!super.nothing}/search\"
+all: {}
+",
+        )
+        .unwrap();
+        let root = transform(schema).unwrap();
+        let errors = root.check_all().unwrap_err();
+        // The APIs of ratincren share its error
+        let members = errors
+            .iter()
+            .map(|e| match e {
+                NoSuchMember { member, .. } => member.as_str(),
+                e => panic!("Unexpected {:?}", e),
+            })
+            .collect::<Vec<&str>>();
+        assert_eq!(members, vec!["missing", "nothing"]);
+        assert_eq!(root.check().unwrap_err(), errors[0]);
+    }
+
+    #[test]
+    fn test_check_unresolved_url() {
+        let result = transform(resolver_schema()).unwrap().check().err().unwrap();
+        assert_eq!(
+            result.context_path(),
+            Some(
+                &[
+                    "RatinaClient".to_string(),
+                    "ratincren".to_string(),
+                    "loop".to_string()
+                ][..]
+            )
+        );
+        assert!(matches!(result, ContextLookupError::CyclicReference { .. }));
+    }
 }
@@ -0,0 +1,121 @@
+//! The pipeline for JavaScript as a WebAssembly module, so that clients can be
+//! generated in a web page. Built with the `wasm` feature and without the
+//! default `cli` one, e.g.:
+//!
+//! ```sh
+//! wasm-pack build xsrc -- --no-default-features --features wasm
+//! ```
+use super::pipeline::{generate, Lang, SchemaSource, XsrcError};
+use super::rewriter::GenOptions;
+use super::schema::ParserError;
+use wasm_bindgen::prelude::*;
+
+/// Why `generate_js` failed, thrown to JavaScript as an `Error` with the
+/// fields as properties
+#[derive(Debug, PartialEq)]
+struct GenerateError {
+    /// `options`, `parse`, `transform` or `generate`
+    kind: &'static str,
+    message: String,
+    /// Where a YAML error is in the schema, from 1
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+impl GenerateError {
+    fn new(kind: &'static str, message: String) -> Self {
+        GenerateError {
+            kind,
+            message,
+            line: None,
+            column: None,
+        }
+    }
+}
+
+impl From<XsrcError> for GenerateError {
+    fn from(e: XsrcError) -> Self {
+        let kind = match &e {
+            XsrcError::ParserError(_) | XsrcError::IOError(_) => "parse",
+            XsrcError::TransformerError(_) => "transform",
+            XsrcError::BackendError(_)
+            | XsrcError::UnsupportedLanguage(..)
+            | XsrcError::NotCompiledIn(_) => "generate",
+            XsrcError::InvalidOptions(_) => "options",
+        };
+        let mut error = GenerateError::new(kind, e.to_string());
+        if let XsrcError::ParserError(ParserError::SerdeError(e)) = &e {
+            if let Some(location) = e.location() {
+                error.line = Some(location.line());
+                error.column = Some(location.column());
+            }
+        }
+        error
+    }
+}
+
+impl From<GenerateError> for JsValue {
+    fn from(e: GenerateError) -> Self {
+        let error = js_sys::Error::new(&e.message);
+        let set = |key: &str, value: JsValue| {
+            js_sys::Reflect::set(&error, &JsValue::from_str(key), &value).unwrap();
+        };
+        set("kind", JsValue::from_str(e.kind));
+        if let (Some(line), Some(column)) = (e.line, e.column) {
+            set("line", JsValue::from_f64(line as f64));
+            set("column", JsValue::from_f64(column as f64));
+        }
+        error.into()
+    }
+}
+
+fn generate_str(schema_yaml: &str, options_json: &str) -> Result<String, GenerateError> {
+    let opts: GenOptions = if options_json.trim().is_empty() {
+        Default::default()
+    } else {
+        serde_json::from_str(options_json)
+            .map_err(|e| GenerateError::new("options", format!("Invalid options: {}", e)))?
+    };
+    Ok(generate(SchemaSource::Str(schema_yaml), Lang::JavaScript, &opts)?.code)
+}
+
+/// Generates the JavaScript client of the YAML of a schema. `options_json` is
+/// a JSON object of [`GenOptions`] keyed as in configs, e.g.
+/// `{"param-case": "camel"}`, or empty for the defaults. Errors are thrown
+/// as `Error`s with a `kind` of `options`, `parse`, `transform` or
+/// `generate`, and the `line` and `column` of YAML errors.
+#[wasm_bindgen]
+pub fn generate_js(schema_yaml: &str, options_json: &str) -> Result<String, JsValue> {
+    Ok(generate_str(schema_yaml, options_json)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_str() {
+        let code = generate_str(
+            "$url: \"http://ratina.org\"\n$as: \"RatinaClient\"\nall: {}\n",
+            "{\"commonjs-interop\": true}",
+        )
+        .unwrap();
+        assert!(code.contains("export default class RatinaClient {"));
+        assert!(code.contains("module).exports = RatinaClient;"));
+        let e = generate_str("$url: [\n", "").unwrap_err();
+        assert_eq!(e.kind, "parse");
+        assert_eq!((e.line, e.column), (Some(2), Some(1)));
+        let e = generate_str("all:\n  $url: \"${!super}/<id\"\n", "").unwrap_err();
+        assert_eq!(
+            e,
+            GenerateError::new(
+                "transform",
+                "Transformer error: Invalid $url of all: Unexpected EOF".to_string()
+            )
+        );
+        let e = generate_str("all: {}\n", "{\"minify\": true, \"annotate\": true}").unwrap_err();
+        assert_eq!(e.kind, "options");
+        let e = generate_str("all: {}\n", "{\"ratina\": 1}").unwrap_err();
+        assert_eq!(e.kind, "options");
+    }
+}
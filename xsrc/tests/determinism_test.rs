@@ -0,0 +1,52 @@
+//! Generating a schema again gives the same bytes, so that committed clients
+//! don't change for nothing. Maps iterated in no fixed order, such as those of
+//! `std::collections::HashMap`, would show here, as each map is seeded
+//! differently.
+use std::fs;
+use std::path::Path;
+use xsrc::rewriter::{GenOptions, GeneratedOutput};
+use xsrc::{generate, Lang, SchemaSource};
+
+const RUNS: usize = 20;
+
+/// The schemas of the golden cases that generate
+fn schemas() -> Vec<(String, String)> {
+    let mut schemas =
+        fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/cases"))
+            .unwrap()
+            .map(|v| v.unwrap().path())
+            .filter(|dir| dir.join("expected.js").exists())
+            .map(|dir| {
+                let name = dir.file_name().unwrap().to_str().unwrap().to_string();
+                (name, fs::read_to_string(dir.join("schema.yaml")).unwrap())
+            })
+            .collect::<Vec<(String, String)>>();
+    schemas.sort();
+    schemas
+}
+
+/// The outputs of every language, or their errors for the schemas a language
+/// can't generate
+fn generate_all(schema: &str, opts: &GenOptions) -> Vec<Result<GeneratedOutput, String>> {
+    Lang::ALL
+        .iter()
+        .filter(|lang| lang.is_compiled_in())
+        .map(|lang| generate(SchemaSource::Str(schema), *lang, opts).map_err(|e| e.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_deterministic_output() {
+    let split = GenOptions {
+        module_dir: Some("modules".to_string()),
+        ..Default::default()
+    };
+    for (name, schema) in schemas() {
+        for opts in &[Default::default(), split.clone()] {
+            let first = generate_all(&schema, opts);
+            for _ in 1..RUNS {
+                assert!(generate_all(&schema, opts) == first, "{} differs", name);
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! The C interface, called through the functions linked from the crate. Run
+//! with `cargo test -p xsrc --features ffi --test ffi`.
+#![cfg(feature = "ffi")]
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+use xsrc::ffi::*;
+
+unsafe fn last_error() -> String {
+    CStr::from_ptr(xsrc_last_error())
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+/// The code returned by `xsrc_generate`, and the code or the last error
+fn generate(schema: &str, lang: &str, options_json: Option<&str>) -> (i32, String) {
+    let schema = CString::new(schema).unwrap();
+    let lang = CString::new(lang).unwrap();
+    let options_json = options_json.map(|v| CString::new(v).unwrap());
+    let mut out: *mut c_char = ptr::null_mut();
+    unsafe {
+        let code = xsrc_generate(
+            schema.as_ptr(),
+            lang.as_ptr(),
+            options_json.as_ref().map_or(ptr::null(), |v| v.as_ptr()),
+            &mut out,
+        );
+        if code == XSRC_OK {
+            assert!(xsrc_last_error().is_null());
+            let s = CStr::from_ptr(out).to_str().unwrap().to_string();
+            xsrc_free_string(out);
+            (code, s)
+        } else {
+            assert!(out.is_null());
+            (code, last_error())
+        }
+    }
+}
+
+const SCHEMA: &str = "$url: \"http://ratina.org\"\n$as: \"RatinaClient\"\nall: {}\n";
+
+#[test]
+fn test_generate() {
+    let (code, s) = generate(SCHEMA, "javascript", None);
+    assert_eq!(code, XSRC_OK);
+    assert!(s.contains("export default class RatinaClient {"));
+    let (code, s) = generate(SCHEMA, "javascript", Some("{\"commonjs-interop\": true}"));
+    assert_eq!(code, XSRC_OK);
+    assert!(s.contains("module).exports = RatinaClient;"));
+    let (code, s) = generate(SCHEMA, "ruby", Some(""));
+    assert_eq!(code, XSRC_OK);
+    assert!(s.contains("class RatinaClient"));
+}
+
+#[test]
+fn test_generate_errors() {
+    let (code, s) = generate("$url: [\n", "javascript", None);
+    assert_eq!(code, XSRC_ERR_PARSE);
+    assert!(s.starts_with("Parser error: "), "{}", s);
+    let (code, s) = generate("all:\n  $url: \"${!super}/<id\"\n", "javascript", None);
+    assert_eq!(code, XSRC_ERR_TRANSFORM);
+    assert_eq!(s, "Transformer error: Invalid $url of all: Unexpected EOF");
+    let (code, s) = generate(SCHEMA, "cobol", None);
+    assert_eq!(code, XSRC_ERR_GENERATE);
+    assert!(s.starts_with("Unsupported language: cobol"), "{}", s);
+    let (code, s) = generate(SCHEMA, "javascript", Some("{\"ratina\": 1}"));
+    assert_eq!(code, XSRC_ERR_OPTIONS);
+    assert!(s.starts_with("Invalid options: "), "{}", s);
+    let (code, s) = generate("all:\n  $method: \"\"\n", "javascript", None);
+    assert_eq!(code, XSRC_ERR_TRANSFORM);
+    let expected = "Transformer error: Unsupported HTTP method \"\" of all, ";
+    assert!(s.starts_with(expected), "{}", s);
+    // A success clears the error
+    assert_eq!(generate(SCHEMA, "javascript", None).0, XSRC_OK);
+}
+
+#[test]
+fn test_generate_null_arguments() {
+    let lang = CString::new("javascript").unwrap();
+    let mut out: *mut c_char = ptr::null_mut();
+    unsafe {
+        let code = xsrc_generate(ptr::null(), lang.as_ptr(), ptr::null(), &mut out);
+        assert_eq!(code, XSRC_ERR_ARGUMENT);
+        assert!(out.is_null());
+        assert_eq!(last_error(), "schema is null");
+        let code = xsrc_generate(lang.as_ptr(), lang.as_ptr(), ptr::null(), ptr::null_mut());
+        assert_eq!(code, XSRC_ERR_ARGUMENT);
+        assert_eq!(last_error(), "out is null");
+        xsrc_free_string(ptr::null_mut());
+    }
+}
@@ -0,0 +1,75 @@
+// RatinaClient generated by xsrc 0.1.0
+import axios from "axios";
+class ratincren {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/ratincren");
+    }
+    /**
+     * @deprecated
+     */
+    async all() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    /**
+     * @deprecated Use `search` instead
+     */
+    async list() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    /**
+     * @deprecated Use `search` instead
+     */
+    async *iterList() {
+        let cursor = undefined;
+        do {
+            const response = await axios({
+                method: "get",
+                url: (this).url,
+                params: {
+                    next: cursor
+                }
+            });
+            yield* ((response).data).data;
+            cursor = ((response).data).next;
+        } while (cursor);
+    }
+    /**
+     * @deprecated
+     */
+    async search(q) {
+        return axios({
+            method: "get",
+            url: (this).url,
+            params: {
+                q: q
+            }
+        });
+    }
+    async get(id) {
+        return axios({
+            method: "get",
+            url: (((this).url) + ("/")) + (id)
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+export default class RatinaClient {
+    constructor() {
+        (this)._url = "http://ratina.org";
+    }
+    get url() {
+        return (this)._url;
+    }
+    get ratincren() {
+        return new (ratincren)(this);
+    }
+}
@@ -0,0 +1,36 @@
+// RatinaClient generated by xsrc 0.1.0
+import axios from "axios";
+class reports {
+    constructor(_super, id) {
+        (this)._super = _super;
+        (this)._url = ((((this)._super).url) + ("/reports/")) + (id);
+        (this).id = id;
+    }
+    async get() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    async download() {
+        return axios({
+            method: "get",
+            url: ((this).url) + ("/download"),
+            responseType: "blob"
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+export default class RatinaClient {
+    constructor() {
+        (this)._url = "http://ratina.org";
+    }
+    get url() {
+        return (this)._url;
+    }
+    get reports() {
+        return new (reports)(this);
+    }
+}
@@ -0,0 +1,37 @@
+// RatinaClient generated by xsrc 0.1.0
+import axios from "axios";
+class docs {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/docs");
+    }
+    async page(name, lang = "zh", section) {
+        return axios({
+            method: "get",
+            url: (((((this).url) + ("/")) + (name)) + ("#")) + (section),
+            params: {
+                lang: lang
+            }
+        });
+    }
+    async toc() {
+        return axios({
+            method: "get",
+            url: ((this).url) + ("/toc#top")
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+export default class RatinaClient {
+    constructor() {
+        (this)._url = "http://ratina.org";
+    }
+    get url() {
+        return (this)._url;
+    }
+    get docs() {
+        return new (docs)(this);
+    }
+}
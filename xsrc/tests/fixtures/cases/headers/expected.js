@@ -0,0 +1,71 @@
+// XiaoSiClient generated by xsrc 0.1.0
+import axios from "axios";
+class budgets {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/budgets");
+    }
+    async all() {
+        return axios({
+            method: "get",
+            url: (this).url,
+            headers: {
+                Authorization: "Bearer xiaosi",
+                Accept: "application/json",
+                "X-Budget-Year": "2019"
+            }
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+class users {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/users");
+    }
+    async all() {
+        return axios({
+            method: "get",
+            url: (this).url,
+            headers: {
+                Authorization: "Bearer xiaosi",
+                Accept: "application/json"
+            }
+        });
+    }
+    async avatar() {
+        return axios({
+            method: "get",
+            url: ((this).url) + ("/avatar"),
+            headers: {
+                Authorization: "Bearer xiaosi",
+                Accept: "image/png"
+            }
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+    get budgets() {
+        return new (budgets)(this);
+    }
+}
+export default class XiaoSiClient {
+    constructor() {
+        (this)._url = "http://api_root";
+    }
+    async public() {
+        return axios({
+            method: "get",
+            url: ((this).url) + ("/public")
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+    get users() {
+        return new (users)(this);
+    }
+}
@@ -0,0 +1,61 @@
+// XSClient generated by xsrc 0.1.0
+import axios from "axios";
+class users_GET {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = ((this)._super).url;
+    }
+    async all() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+export default class XSClient {
+    constructor() {
+        (this)._url = "http://api_root";
+    }
+    async getUser(id) {
+        return axios({
+            method: "get",
+            url: (((this).url) + ("/users/")) + (id)
+        });
+    }
+    async createUser(username) {
+        return axios({
+            method: "post",
+            url: ((this).url) + ("/users"),
+            data: {
+                username: username
+            }
+        });
+    }
+    async updateUser() {
+        return axios({
+            method: "patch",
+            url: (this).url
+        });
+    }
+    async replaceUser(id) {
+        return axios({
+            method: "put",
+            url: (((this).url) + ("/users/")) + (id)
+        });
+    }
+    async "v1.2"() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+    get "users.GET"() {
+        return new (users_GET)(this);
+    }
+}
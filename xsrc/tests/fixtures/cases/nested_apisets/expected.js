@@ -0,0 +1,65 @@
+// RatinaClient generated by xsrc 0.1.0
+import axios from "axios";
+class items {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/items");
+    }
+    async get(id) {
+        return axios({
+            method: "get",
+            url: (((this).url) + ("/")) + (id)
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+class budgets {
+    constructor(_super, year) {
+        (this)._super = _super;
+        (this)._url = ((((this)._super).url) + ("/budgets/")) + (year);
+        (this).year = year;
+    }
+    async all() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+    get items() {
+        return new (items)(this);
+    }
+}
+class ratincren {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/ratincren");
+    }
+    async all() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+    get budgets() {
+        return new (budgets)(this);
+    }
+}
+export default class RatinaClient {
+    constructor() {
+        (this)._url = "http://ratina.org";
+    }
+    get url() {
+        return (this)._url;
+    }
+    get ratincren() {
+        return new (ratincren)(this);
+    }
+}
@@ -0,0 +1,71 @@
+// XSClient generated by xsrc 0.1.0
+import axios from "axios";
+class budgets {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/budgets");
+    }
+    async all() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+class users {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/users/");
+    }
+    async all() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    async get(id, detail) {
+        return axios({
+            method: "get",
+            url: (((this).url) + ("/")) + (id),
+            params: {
+                detail: detail
+            }
+        });
+    }
+    async create(username, password) {
+        return axios({
+            method: "post",
+            url: (this).url,
+            data: {
+                username: username,
+                password: password
+            }
+        });
+    }
+    async update(id) {
+        return axios({
+            method: "put",
+            url: ((((this).url) + ("/")) + (id)) + ("/")
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+    get budgets() {
+        return new (budgets)(this);
+    }
+}
+export default class XSClient {
+    constructor(url) {
+        (this)._url = url;
+    }
+    get url() {
+        return (this)._url;
+    }
+    get users() {
+        return new (users)(this);
+    }
+}
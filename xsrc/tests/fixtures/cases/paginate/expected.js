@@ -0,0 +1,66 @@
+// RatinaClient generated by xsrc 0.1.0
+import axios from "axios";
+class ahcros {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/ahcros");
+    }
+    async all(status) {
+        return axios({
+            method: "get",
+            url: (this).url,
+            params: {
+                status: status
+            }
+        });
+    }
+    async *iterAll(status) {
+        let cursor = undefined;
+        do {
+            const response = await axios({
+                method: "get",
+                url: (this).url,
+                params: {
+                    status: status,
+                    next: cursor
+                }
+            });
+            yield* ((response).data).results;
+            cursor = ((response).data).next;
+        } while (cursor);
+    }
+    async search(term) {
+        return axios({
+            method: "get",
+            url: (((this).url) + ("/search/")) + (term)
+        });
+    }
+    async *iterSearch(term) {
+        let cursor = undefined;
+        do {
+            const response = await axios({
+                method: "get",
+                url: (((this).url) + ("/search/")) + (term),
+                params: {
+                    after: cursor
+                }
+            });
+            yield* (((response).data).data).items;
+            cursor = (((response).data).meta).nextCursor;
+        } while (cursor);
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+export default class RatinaClient {
+    constructor() {
+        (this)._url = "http://ratina.org";
+    }
+    get url() {
+        return (this)._url;
+    }
+    get ahcros() {
+        return new (ahcros)(this);
+    }
+}
@@ -0,0 +1,36 @@
+// RatinaClient generated by xsrc 0.1.0
+import axios from "axios";
+class ratincren {
+    constructor(_super, group) {
+        (this)._super = _super;
+        (this)._url = ((((this)._super).url) + ("/ratincren/")) + (group);
+        (this).group = group;
+    }
+    async get(id) {
+        return axios({
+            method: "get",
+            url: (((this).url) + ("/")) + (id)
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+export default class RatinaClient {
+    constructor(region, version) {
+        if (!(([
+            "eu",
+            "us"
+        ]).includes(region))) {
+            throw new (Error)(("region must be one of eu, us, got ") + (region));
+        }
+        (this)._region = region;
+        (this)._version = version;
+    }
+    get url() {
+        return (this)._url;
+    }
+    get ratincren() {
+        return new (ratincren)(this);
+    }
+}
@@ -0,0 +1,60 @@
+// RatinaClient generated by xsrc 0.1.0
+import axios from "axios";
+class ratincren {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/ratincren");
+    }
+    async search(keyword, page) {
+        return axios({
+            method: "get",
+            url: (this).url,
+            params: {
+                keyword: keyword,
+                page: page
+            }
+        });
+    }
+    async create(name, status) {
+        if (((status) !== (undefined)) && (!(([
+            "open",
+            "closed"
+        ]).includes(status)))) {
+            throw new (Error)(("status must be one of open, closed, got ") + (status));
+        }
+        return axios({
+            method: "post",
+            url: (this).url,
+            data: {
+                name: name,
+                status: status
+            }
+        });
+    }
+    async update(id, notify, name) {
+        return axios({
+            method: "put",
+            url: (((this).url) + ("/")) + (id),
+            params: {
+                notify: notify
+            },
+            data: {
+                name: name
+            }
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+export default class RatinaClient {
+    constructor() {
+        (this)._url = "http://ratina.org";
+    }
+    get url() {
+        return (this)._url;
+    }
+    get ratincren() {
+        return new (ratincren)(this);
+    }
+}
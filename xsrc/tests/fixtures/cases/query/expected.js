@@ -0,0 +1,41 @@
+// RatinaClient generated by xsrc 0.1.0
+import axios from "axios";
+class ahcros {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/ahcros");
+    }
+    async search(q, filter, page = 1, size = 20, order) {
+        if (((order) !== (undefined)) && (!(([
+            "asc",
+            "desc"
+        ]).includes(order)))) {
+            throw new (Error)(("order must be one of asc, desc, got ") + (order));
+        }
+        return axios({
+            method: "get",
+            url: ((this).url) + ("/search"),
+            params: {
+                q: q,
+                filter: filter,
+                page: page,
+                size: size,
+                order: order
+            }
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+export default class RatinaClient {
+    constructor() {
+        (this)._url = "http://ratina.org";
+    }
+    get url() {
+        return (this)._url;
+    }
+    get ahcros() {
+        return new (ahcros)(this);
+    }
+}
@@ -0,0 +1,89 @@
+// RatinaClient generated by xsrc 0.1.0
+import axios from "axios";
+async function* readEventStream(stream) {
+    const decoder = new TextDecoder();
+    let buffer = "";
+    for await (const chunk of stream) {
+        buffer += typeof chunk === "string" ? chunk : decoder.decode(chunk, { stream: true });
+        let match;
+        while ((match = /\r?\n\r?\n/.exec(buffer)) !== null) {
+            const block = buffer.slice(0, match.index);
+            buffer = buffer.slice(match.index + match[0].length);
+            const event = { event: "message", data: [], id: undefined };
+            for (const line of block.split(/\r?\n/)) {
+                if (line.startsWith(":")) {
+                    continue;
+                }
+                const colon = line.indexOf(":");
+                const field = colon >= 0 ? line.slice(0, colon) : line;
+                const value = colon >= 0 ? line.slice(colon + 1).replace(/^ /, "") : "";
+                if (field === "data") {
+                    event.data.push(value);
+                } else if (field === "event") {
+                    event.event = value;
+                } else if (field === "id") {
+                    event.id = value;
+                }
+            }
+            if (event.data.length > 0) {
+                yield { ...event, data: event.data.join("\n") };
+            }
+        }
+    }
+}
+class users {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/users");
+    }
+    /**
+     * @returns {Promise<import("axios").AxiosResponse<User>>}
+     */
+    async get(id) {
+        return axios({
+            method: "get",
+            url: (((this).url) + ("/")) + (id)
+        });
+    }
+    /**
+     * @deprecated Use `search` instead
+     * @returns {Promise<import("axios").AxiosResponse<User[]>>}
+     */
+    async all() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    async *events() {
+        const response = await axios({
+            method: "get",
+            url: ((this).url) + ("/events"),
+            responseType: "stream"
+        });
+        yield* readEventStream((response).data);
+    }
+    async create(name) {
+        return axios({
+            method: "post",
+            url: (this).url,
+            data: {
+                name: name
+            }
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+export default class RatinaClient {
+    constructor() {
+        (this)._url = "http://ratina.org";
+    }
+    get url() {
+        return (this)._url;
+    }
+    get users() {
+        return new (users)(this);
+    }
+}
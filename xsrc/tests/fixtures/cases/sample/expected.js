@@ -0,0 +1,77 @@
+// XiaoSiClient generated by xsrc 0.1.0
+import axios from "axios";
+class budgets {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/budgets");
+    }
+    async all() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+class users {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/users");
+    }
+    async all() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    async get(id, detail) {
+        return axios({
+            method: "get",
+            url: (((this).url) + ("/")) + (id),
+            params: {
+                detail: detail
+            },
+            headers: {
+                Accept: "application/json"
+            }
+        });
+    }
+    async create(username, password) {
+        return axios({
+            method: "post",
+            url: (this).url,
+            data: {
+                username: username,
+                password: password
+            }
+        });
+    }
+    /**
+     * @deprecated
+     */
+    async update(id) {
+        return axios({
+            method: "put",
+            url: ((((this).url) + ("/")) + (id)) + ("/")
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+    get budgets() {
+        return new (budgets)(this);
+    }
+}
+export default class XiaoSiClient {
+    constructor() {
+        (this)._url = "http://httpbin.org/";
+    }
+    get url() {
+        return (this)._url;
+    }
+    get users() {
+        return new (users)(this);
+    }
+}
@@ -0,0 +1,52 @@
+// RatinaClient generated by xsrc 0.1.0
+import axios from "axios";
+class ahcro {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = ((((this)._super).ratincren).url) + ("/ahcro");
+    }
+    async all() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    async budgets() {
+        return axios({
+            method: "get",
+            url: ((((this)._super).ratincren).url) + ("/budgets")
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+class ratincren {
+    constructor(_super) {
+        (this)._super = _super;
+        (this)._url = (((this)._super).url) + ("/ratincren");
+    }
+    async all() {
+        return axios({
+            method: "get",
+            url: (this).url
+        });
+    }
+    get url() {
+        return (this)._url;
+    }
+}
+export default class RatinaClient {
+    constructor() {
+        (this)._url = "http://ratina.org";
+    }
+    get url() {
+        return (this)._url;
+    }
+    get ahcro() {
+        return new (ahcro)(this);
+    }
+    get ratincren() {
+        return new (ratincren)(this);
+    }
+}
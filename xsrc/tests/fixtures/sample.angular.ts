@@ -0,0 +1,47 @@
+// XiaoSiClient generated by xsrc 0.1.0
+
+import { Inject, Injectable, InjectionToken } from '@angular/core';
+import { HttpClient } from '@angular/common/http';
+import { Observable } from 'rxjs';
+
+export const XIAO_SI_CLIENT_BASE_URL = new InjectionToken<string>('XiaoSiClient.baseUrl', { providedIn: 'root', factory: () => 'http://httpbin.org/' });
+
+function compact(values: { [key: string]: any }): { [key: string]: any } {
+    const result: { [key: string]: any } = {};
+    for (const key of Object.keys(values)) {
+        if (values[key] !== undefined) {
+            result[key] = values[key];
+        }
+    }
+    return result;
+}
+
+export type XiaoSiClientMethod = 'usersAll' | 'usersGet' | 'usersCreate' | 'usersUpdate' | 'usersBudgetsAll';
+
+@Injectable({ providedIn: 'root' })
+export class XiaoSiClient {
+    constructor(private http: HttpClient, @Inject(XIAO_SI_CLIENT_BASE_URL) private baseUrl: string) {}
+
+    usersAll<T = any>(): Observable<T> {
+        return this.http.request<T>('GET', `${this.baseUrl}/users`);
+    }
+
+    usersGet<T = any>(id: number, detail?: boolean): Observable<T> {
+        return this.http.request<T>('GET', `${this.baseUrl}/users/${encodeURIComponent(String(id))}`, { params: compact({ detail }), headers: { Accept: 'application/json' } });
+    }
+
+    usersCreate<T = any>(username?: string, password?: string): Observable<T> {
+        return this.http.request<T>('POST', `${this.baseUrl}/users`, { body: { username, password } });
+    }
+
+    /**
+     * @deprecated
+     */
+    usersUpdate<T = any>(id: number): Observable<T> {
+        return this.http.request<T>('PUT', `${this.baseUrl}/users/${encodeURIComponent(String(id))}/`);
+    }
+
+    usersBudgetsAll<T = any>(): Observable<T> {
+        return this.http.request<T>('GET', `${this.baseUrl}/users/budgets`);
+    }
+}
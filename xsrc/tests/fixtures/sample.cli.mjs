@@ -0,0 +1,189 @@
+#!/usr/bin/env node
+// XiaoSiClient generated by xsrc 0.1.0
+import axios from "axios";
+const BASE_URL = (((process).env).BASE_URL) || ("http://httpbin.org/");
+const commands = {
+    "users.all": {
+        args: [],
+        flags: [],
+        call: () => axios({
+            method: "get",
+            url: (BASE_URL) + ("/users")
+        })
+    },
+    "users.get": {
+        args: [
+            {
+                name: "id",
+                type: "number"
+            }
+        ],
+        flags: [
+            {
+                name: "detail",
+                type: "boolean"
+            }
+        ],
+        call: (id, detail) => axios({
+            method: "get",
+            url: ((BASE_URL) + ("/users/")) + (encodeURIComponent(id)),
+            params: {
+                detail: detail
+            },
+            headers: {
+                Accept: "application/json"
+            }
+        })
+    },
+    "users.create": {
+        args: [],
+        flags: [
+            {
+                name: "username",
+                type: "string"
+            },
+            {
+                name: "password",
+                type: "string"
+            }
+        ],
+        call: (username, password) => axios({
+            method: "post",
+            url: (BASE_URL) + ("/users"),
+            data: {
+                username: username,
+                password: password
+            }
+        })
+    },
+    "users.update": {
+        args: [
+            {
+                name: "id",
+                type: "number"
+            }
+        ],
+        flags: [],
+        call: (id) => axios({
+            method: "put",
+            url: (((BASE_URL) + ("/users/")) + (encodeURIComponent(id))) + ("/")
+        })
+    },
+    "users.budgets.all": {
+        args: [],
+        flags: [],
+        call: () => axios({
+            method: "get",
+            url: (BASE_URL) + ("/users/budgets")
+        })
+    }
+};
+class UsageError extends Error {}
+
+function usage(name, command) {
+    const args = command.args.map((arg) => `<${arg.name}>`);
+    const flags = command.flags.map((flag) => `[--${flag.name} <${flag.type}>]`);
+    return [name, ...args, ...flags].join(" ");
+}
+
+function help() {
+    const lines = ["Usage: <command> [args...] [--flag value...]", "", "Commands:"];
+    for (const [name, command] of Object.entries(commands)) {
+        lines.push(`  ${usage(name, command)}`);
+    }
+    return lines.join("\n");
+}
+
+function coerce(value, param) {
+    if (param.type === "number") {
+        const n = Number(value);
+        if (value === "" || Number.isNaN(n)) {
+            throw new UsageError(`${param.name} must be a number: ${value}`);
+        }
+        return n;
+    }
+    if (param.type === "boolean") {
+        if (value !== "true" && value !== "false") {
+            throw new UsageError(`${param.name} must be true or false: ${value}`);
+        }
+        return value === "true";
+    }
+    return value;
+}
+
+function parseArgs(command, argv) {
+    const positional = [];
+    const flags = {};
+    for (let i = 0; i < argv.length; i++) {
+        if (!argv[i].startsWith("--")) {
+            positional.push(argv[i]);
+            continue;
+        }
+        const eq = argv[i].indexOf("=");
+        const key = eq >= 0 ? argv[i].slice(2, eq) : argv[i].slice(2);
+        const flag = command.flags.find((v) => v.name === key);
+        if (flag === undefined) {
+            throw new UsageError(`Unknown flag: --${key}`);
+        }
+        if (eq >= 0) {
+            flags[key] = argv[i].slice(eq + 1);
+        } else if (flag.type === "boolean" && (i + 1 === argv.length || argv[i + 1].startsWith("--"))) {
+            flags[key] = "true";
+        } else if (i + 1 < argv.length) {
+            flags[key] = argv[++i];
+        } else {
+            throw new UsageError(`Missing value for --${key}`);
+        }
+    }
+    if (positional.length !== command.args.length) {
+        throw new UsageError(`Expected ${command.args.length} argument(s), got ${positional.length}`);
+    }
+    return [
+        ...command.args.map((arg, i) => coerce(positional[i], arg)),
+        ...command.flags.map((flag) => (flag.name in flags ? coerce(flags[flag.name], flag) : undefined)),
+    ];
+}
+
+async function main(argv) {
+    const [name, ...rest] = argv;
+    if (name === undefined || name === "--help" || name === "-h") {
+        console.log(help());
+        return 0;
+    }
+    if (!Object.prototype.hasOwnProperty.call(commands, name)) {
+        console.error(`Unknown command: ${name}\n\n${help()}`);
+        return 2;
+    }
+    const command = commands[name];
+    if (rest.includes("--help")) {
+        console.log(`Usage: ${usage(name, command)}`);
+        return 0;
+    }
+    let values;
+    try {
+        values = parseArgs(command, rest);
+    } catch (e) {
+        if (e instanceof UsageError) {
+            console.error(`${e.message}\nUsage: ${usage(name, command)}`);
+            return 2;
+        }
+        throw e;
+    }
+    if (BASE_URL === undefined) {
+        console.error("BASE_URL must be set");
+        return 2;
+    }
+    const response = await command.call(...values);
+    console.log(JSON.stringify(response.data, null, 2));
+    return 0;
+}
+
+main(process.argv.slice(2)).then(
+    (code) => {
+        process.exitCode = code;
+    },
+    (e) => {
+        console.error(e.response === undefined ? e.message : JSON.stringify(e.response.data, null, 2));
+        process.exitCode = 1;
+    }
+);
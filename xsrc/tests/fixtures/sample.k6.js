@@ -0,0 +1,36 @@
+// XiaoSiClient generated by xsrc 0.1.0
+import http from "k6/http";
+import { check } from "k6";
+
+export const options = {
+    vus: 1,
+    iterations: 1,
+};
+
+const BASE_URL = __ENV.BASE_URL || "http://httpbin.org/";
+
+export function users_all() {
+    return http.get(`${BASE_URL}/users`);
+}
+
+export function users_get(id = 0, detail = false) {
+    return http.get(`${BASE_URL}/users/${encodeURIComponent(id)}?detail=${encodeURIComponent(detail)}`, { headers: { "Accept": "application/json" } });
+}
+
+export function users_create(username = "example", password = "example") {
+    return http.post(`${BASE_URL}/users`, JSON.stringify({ "username": username, "password": password }), { headers: { "Content-Type": "application/json" } });
+}
+
+export function users_update(id = 0) {
+    return http.put(`${BASE_URL}/users/${encodeURIComponent(id)}/`);
+}
+
+export function users_budgets_all() {
+    return http.get(`${BASE_URL}/users/budgets`);
+}
+
+export default function () {
+    check(users_all(), { "users.all is status 200": (r) => r.status === 200 });
+    check(users_get(), { "users.get is status 200": (r) => r.status === 200 });
+    check(users_budgets_all(), { "users.budgets.all is status 200": (r) => r.status === 200 });
+}
@@ -0,0 +1,85 @@
+<?php
+
+// XiaoSiClient generated by xsrc 0.1.0
+
+declare(strict_types=1);
+
+namespace XiaoSiClient;
+
+use GuzzleHttp\Client;
+use Psr\Http\Message\ResponseInterface;
+
+final class XiaoSiClient
+{
+    public Client $client;
+    public string $url;
+
+    public function __construct(Client $client)
+    {
+        $this->client = $client;
+        $this->url = 'http://httpbin.org/';
+    }
+
+    public function users(): Users
+    {
+        return new Users($this);
+    }
+}
+
+final class Users
+{
+    public XiaoSiClient $super;
+    public Client $client;
+    public string $url;
+
+    public function __construct(XiaoSiClient $super)
+    {
+        $this->super = $super;
+        $this->client = $super->client;
+        $this->url = "{$super->url}/users";
+    }
+
+    public function all(): ResponseInterface
+    {
+        return $this->client->request('GET', $this->url);
+    }
+
+    public function get(int $id, ?bool $detail = null): ResponseInterface
+    {
+        return $this->client->request('GET', $this->url . '/' . rawurlencode((string) $id), ['query' => array_filter(['detail' => $detail], static fn ($v) => $v !== null), 'headers' => ['Accept' => 'application/json']]);
+    }
+
+    public function create(?string $username = null, ?string $password = null): ResponseInterface
+    {
+        return $this->client->request('POST', $this->url, ['json' => array_filter(['username' => $username, 'password' => $password], static fn ($v) => $v !== null)]);
+    }
+
+    public function update(int $id): ResponseInterface
+    {
+        return $this->client->request('PUT', $this->url . '/' . rawurlencode((string) $id) . '/');
+    }
+
+    public function budgets(): UsersBudgets
+    {
+        return new UsersBudgets($this);
+    }
+}
+
+final class UsersBudgets
+{
+    public Users $super;
+    public Client $client;
+    public string $url;
+
+    public function __construct(Users $super)
+    {
+        $this->super = $super;
+        $this->client = $super->client;
+        $this->url = "{$super->url}/budgets";
+    }
+
+    public function all(): ResponseInterface
+    {
+        return $this->client->request('GET', $this->url);
+    }
+}
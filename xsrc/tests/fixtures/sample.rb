@@ -0,0 +1,71 @@
+# XiaoSiClient generated by xsrc 0.1.0
+
+require "erb"
+require "faraday"
+
+class XiaoSiClient
+  attr_reader :url, :connection
+
+  def initialize
+    @url = "http://httpbin.org/"
+    @connection = Faraday.new do |f|
+      f.request(:json)
+    end
+  end
+
+  def users
+    Users.new(self)
+  end
+
+  class Users
+    attr_reader :_super, :url
+
+    def initialize(_super)
+      @_super = _super
+      @url = "#{@_super.url}/users"
+    end
+
+    def connection
+      @_super.connection
+    end
+
+    def all
+      connection.run_request(:get, self.url, nil, nil)
+    end
+
+    def get(id, detail: nil)
+      connection.run_request(:get, "#{self.url}/#{ERB::Util.url_encode(id)}", nil, { "Accept" => "application/json" }) do |req|
+        req.params.update({ "detail" => detail }.compact)
+      end
+    end
+
+    def create(username: nil, password: nil)
+      connection.run_request(:post, self.url, { "username" => username, "password" => password }.compact, nil)
+    end
+
+    def update(id)
+      connection.run_request(:put, "#{self.url}/#{ERB::Util.url_encode(id)}/", nil, nil)
+    end
+
+    def budgets
+      Budgets.new(self)
+    end
+
+    class Budgets
+      attr_reader :_super, :url
+
+      def initialize(_super)
+        @_super = _super
+        @url = "#{@_super.url}/budgets"
+      end
+
+      def connection
+        @_super.connection
+      end
+
+      def all
+        connection.run_request(:get, self.url, nil, nil)
+      end
+    end
+  end
+end
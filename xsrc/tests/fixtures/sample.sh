@@ -0,0 +1,60 @@
+#!/bin/sh
+# XiaoSiClient generated by xsrc 0.1.0
+
+BASE_URL="${BASE_URL:-http://httpbin.org/}"
+
+xsrc_urlencode() (
+    LC_ALL=C
+    xsrc_s="$1"
+    while [ -n "$xsrc_s" ]; do
+        xsrc_rest="${xsrc_s#?}"
+        xsrc_c="${xsrc_s%"$xsrc_rest"}"
+        xsrc_s="$xsrc_rest"
+        case "$xsrc_c" in
+            [a-zA-Z0-9.~_-]) printf '%s' "$xsrc_c" ;;
+            *) printf '%%%02X' "'$xsrc_c" ;;
+        esac
+    done
+)
+
+# users.all
+users_all() {
+    xsrc_url="${BASE_URL}/users"
+    curl -sS -X GET "$xsrc_url"
+}
+
+# users.get ID [DETAIL]
+users_get() {
+    if [ $# -lt 1 ]; then echo 'usage: users_get ID' >&2; return 2; fi
+    xsrc_url="${BASE_URL}/users/$(xsrc_urlencode "${1}")"
+    xsrc_query=""
+    if [ -n "${2}" ]; then xsrc_query="${xsrc_query}&detail=$(xsrc_urlencode "${2}")"; fi
+    if [ -n "$xsrc_query" ]; then case "$xsrc_url" in *\?*) xsrc_url="${xsrc_url}&${xsrc_query#&}" ;; *) xsrc_url="${xsrc_url}?${xsrc_query#&}" ;; esac; fi
+    curl -sS -X GET -H 'Accept: application/json' "$xsrc_url"
+}
+
+# users.create [USERNAME] [PASSWORD]
+users_create() {
+    xsrc_url="${BASE_URL}/users"
+    xsrc_data=""
+    if [ -n "${1}" ]; then xsrc_data="${xsrc_data}&username=$(xsrc_urlencode "${1}")"; fi
+    if [ -n "${2}" ]; then xsrc_data="${xsrc_data}&password=$(xsrc_urlencode "${2}")"; fi
+    curl -sS -X POST --data "${xsrc_data#&}" "$xsrc_url"
+}
+
+# users.update ID
+users_update() {
+    if [ $# -lt 1 ]; then echo 'usage: users_update ID' >&2; return 2; fi
+    xsrc_url="${BASE_URL}/users/$(xsrc_urlencode "${1}")/"
+    curl -sS -X PUT "$xsrc_url"
+}
+
+# users.budgets.all
+users_budgets_all() {
+    xsrc_url="${BASE_URL}/users/budgets"
+    curl -sS -X GET "$xsrc_url"
+}
+
+if [ $# -gt 0 ]; then
+    "$@"
+fi
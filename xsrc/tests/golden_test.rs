@@ -0,0 +1,235 @@
+//! Generates the JavaScript client of every `fixtures/cases/<name>/schema.yaml`
+//! and compares it with `expected.js` in the same directory. A schema that
+//! must fail has `expected.err` instead, with the message of the error.
+//!
+//! Run with `XSRC_BLESS=1` to write what is generated as the expected output.
+//!
+//! The requests of a case, which blessing doesn't write, are in `requests.txt`:
+//! lines of a call on the client and the request it has to make, as in
+//! `new Client().users.get(3) => GET http://api_root/users/3`. The code is run
+//! with node, with axios replaced by a stub recording the requests, so that
+//! code making wrong requests can't be blessed. They are skipped when node
+//! isn't installed, other than when blessing.
+#![cfg(feature = "backend-javascript")]
+use std::fs;
+use std::panic;
+use std::path::Path;
+use std::process::Command;
+use xsrc::{Lang, SchemaSource};
+
+const BLESS_VAR: &str = "XSRC_BLESS";
+
+const EXPECTED_CODE: &str = "expected.js";
+const EXPECTED_ERROR: &str = "expected.err";
+const REQUESTS: &str = "requests.txt";
+
+/// Records the config of each call instead of sending it
+const AXIOS_STUB: &str = "\
+export const requests = [];
+export default function axios(config) {
+    requests.push(config);
+    return Promise.resolve({ data: null });
+}
+";
+
+/// Runs the calls, which are filled in for `CALLS`, and prints the request of
+/// each of them on a line
+const RUNNER: &str = "\
+import Client from \"./client.mjs\";
+import { requests } from \"./axios.mjs\";
+const calls = [CALLS];
+for (const call of calls) {
+    requests.length = 0;
+    try {
+        await call();
+    } catch (e) {
+        console.log(`throws ${e.name}: ${e.message}`);
+        continue;
+    }
+    if (requests.length === 0) {
+        console.log(\"no request\");
+        continue;
+    }
+    const config = requests[0];
+    let line = `${config.method.toUpperCase()} ${config.url}`;
+    for (const key of [\"params\", \"data\", \"headers\"]) {
+        if (config[key] !== undefined) {
+            line += ` ${key}=${JSON.stringify(config[key])}`;
+        }
+    }
+    console.log(line);
+}
+";
+
+fn has_node() -> bool {
+    Command::new("node")
+        .arg("--version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Checks the requests that the calls of `requests.txt` make with `code`
+fn check_requests(dir: &Path, code: &str, bless: bool) -> Result<(), String> {
+    let name = dir.file_name().unwrap().to_string_lossy();
+    let requests = match fs::read_to_string(dir.join(REQUESTS)) {
+        Ok(requests) => requests,
+        Err(_) => return Ok(()),
+    };
+    if !has_node() {
+        if bless {
+            return Err(format!("{}: node is needed to check {}", name, REQUESTS));
+        }
+        eprintln!("{}: node isn't installed, {} is skipped", name, REQUESTS);
+        return Ok(());
+    }
+    let (calls, expected): (Vec<&str>, Vec<&str>) = requests
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut parts = line.splitn(2, " => ");
+            let call = parts.next().unwrap();
+            let request = parts
+                .next()
+                .unwrap_or_else(|| panic!("{}: no ` => ` in {}", name, line));
+            (call, request)
+        })
+        .unzip();
+    let tmp = std::env::temp_dir().join(format!("xsrc-golden-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&tmp).unwrap();
+    fs::write(tmp.join("axios.mjs"), AXIOS_STUB).unwrap();
+    let code = code.replace("from \"axios\"", "from \"./axios.mjs\"");
+    fs::write(tmp.join("client.mjs"), code).unwrap();
+    let funcs = calls
+        .iter()
+        .map(|call| format!("async () => {}", call))
+        .collect::<Vec<String>>();
+    let runner = RUNNER.replace("CALLS", &funcs.join(", "));
+    fs::write(tmp.join("main.mjs"), runner).unwrap();
+    let output = Command::new("node")
+        .arg(tmp.join("main.mjs"))
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&tmp).unwrap();
+    if !output.status.success() {
+        return Err(format!(
+            "{}: node failed: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ));
+    }
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let actual = stdout.lines().collect::<Vec<&str>>();
+    let differences = calls
+        .iter()
+        .zip(&expected)
+        .zip(actual.iter().chain(std::iter::repeat(&"<nothing>")))
+        .filter(|((_, expected), actual)| expected != actual)
+        .map(|((call, expected), actual)| {
+            format!("{}\n  expected: {}\n  actual:   {}", call, expected, actual)
+        })
+        .collect::<Vec<String>>();
+    if differences.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}: the requests of {} differ:\n{}",
+            name,
+            REQUESTS,
+            differences.join("\n")
+        ))
+    }
+}
+
+/// The first line that differs, numbered from 1
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut n = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(l), Some(r)) if l == r => n += 1,
+            (l, r) => {
+                return format!(
+                    "line {}:\n  expected: {}\n  actual:   {}",
+                    n,
+                    l.unwrap_or("<end of file>"),
+                    r.unwrap_or("<end of file>")
+                )
+            }
+        }
+    }
+}
+
+fn run_case(dir: &Path, bless: bool) -> Result<(), String> {
+    let name = dir.file_name().unwrap().to_string_lossy();
+    let schema = fs::read_to_string(dir.join("schema.yaml"))
+        .map_err(|e| format!("{}: can't read schema.yaml: {}", name, e))?;
+    // A panic fails the case rather than the cases after it
+    let result = panic::catch_unwind(|| {
+        xsrc::generate(
+            SchemaSource::Str(&schema),
+            Lang::JavaScript,
+            &Default::default(),
+        )
+    })
+    .map_err(|_| format!("{}: panicked", name))?;
+    let (file, actual, other) = match result {
+        Ok(output) => {
+            check_requests(dir, &output.code, bless)?;
+            (EXPECTED_CODE, output.code, EXPECTED_ERROR)
+        }
+        Err(e) => (EXPECTED_ERROR, format!("{}\n", e), EXPECTED_CODE),
+    };
+    if bless {
+        fs::write(dir.join(file), &actual).unwrap();
+        if dir.join(other).exists() {
+            fs::remove_file(dir.join(other)).unwrap();
+        }
+        return Ok(());
+    }
+    let expected = fs::read_to_string(dir.join(file)).map_err(|_| match file {
+        EXPECTED_CODE => format!("{}: expected it to fail, but it generated code", name),
+        _ => format!(
+            "{}: expected code, but it failed with: {}",
+            name,
+            actual.trim_end()
+        ),
+    })?;
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}: {} differs at {}",
+            name,
+            file,
+            first_difference(&expected, &actual)
+        ))
+    }
+}
+
+#[test]
+fn test_cases() {
+    let cases = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/cases");
+    let bless = std::env::var_os(BLESS_VAR).is_some();
+    let mut dirs = fs::read_dir(&cases)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    assert!(!dirs.is_empty(), "No cases in {}", cases.display());
+    let failures = dirs
+        .iter()
+        .filter_map(|dir| run_case(dir, bless).err())
+        .collect::<Vec<String>>();
+    assert!(
+        failures.is_empty(),
+        "{} of {} cases failed, run with {}=1 to update them:\n\n{}",
+        failures.len(),
+        dirs.len(),
+        BLESS_VAR,
+        failures.join("\n\n")
+    );
+}
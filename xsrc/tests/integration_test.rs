@@ -1,21 +1,1348 @@
 // Note: Per doc, each file in tests directory is a separate crate.
+// The command line tool is run for most of the built-in languages.
+#![cfg(all(feature = "cli", feature = "backends"))]
 extern crate xsrc;
 
 use std::default::Default;
 
+/// A backend outside xsrc, listing the APIs with their methods
+struct Listing;
+
+impl xsrc::rewriter::Backend for Listing {
+    fn name(&self) -> &str {
+        "listing"
+    }
+
+    fn extension(&self) -> &str {
+        ".txt"
+    }
+
+    fn description(&self) -> &str {
+        "The APIs, one per line"
+    }
+
+    fn generate(
+        &self,
+        root: &xsrc::transformer::ContextBoundedRoot,
+        _opts: &xsrc::rewriter::GenOptions,
+    ) -> Result<xsrc::rewriter::GeneratedOutput, xsrc::rewriter::GenError> {
+        let apis = root.iter_apis();
+        if apis.is_empty() {
+            return Err(xsrc::rewriter::GenError::Other(format!(
+                "{} has no APIs",
+                root.klsname()
+            )));
+        }
+        let mut code = String::new();
+        for (path, api) in apis {
+            let template = root.url_template(&path)?;
+            code.push_str(&format!(
+                "{} {:?} {}\n",
+                path.join("."),
+                api.method(),
+                template.params().len()
+            ));
+        }
+        Ok(xsrc::rewriter::GeneratedOutput {
+            code,
+            files: Vec::new(),
+        })
+    }
+}
+
+#[test]
+fn test_custom_backend() {
+    let mut registry = xsrc::rewriter::Registry::default();
+    registry.register(Box::new(Listing));
+    let backend = registry.get("listing").unwrap();
+    assert_eq!(
+        xsrc::rewriter::output_file_name(backend, "RatinaClient", None),
+        "RatinaClient.txt"
+    );
+    let schema = xsrc::schema::parse_str(
+        "\
+$url: \"http://ratina.org\"
+~ahcros:
+  $url: \"${!super}/ahcros\"
+  all: {}
+  get:
+    $url: \"${!super}/<id:int>\"
+  create.POST: {}
+",
+    )
+    .unwrap();
+    let root = xsrc::transformer::transform(schema).unwrap();
+    let output = xsrc::rewriter::generate(backend, &root, &Default::default()).unwrap();
+    assert_eq!(
+        output.code,
+        "ahcros.all GET 0\nahcros.get GET 1\nahcros.create POST 0\n"
+    );
+
+    let schema = xsrc::schema::parse_str("$url: \"http://ratina.org\"\n").unwrap();
+    let root = xsrc::transformer::transform(schema).unwrap();
+    let e = xsrc::rewriter::generate(backend, &root, &Default::default()).unwrap_err();
+    assert_eq!(e.to_string(), "XSClient has no APIs");
+}
+
+#[test]
+fn test_generate() {
+    let schema = "$url: \"http://ratina.org\"\n$as: \"RatinaClient\"\nall: {}\n";
+    let output = xsrc::generate(
+        xsrc::SchemaSource::Str(schema),
+        "ruby".parse().unwrap(),
+        &Default::default(),
+    )
+    .unwrap();
+    assert!(output.code.contains("class RatinaClient\n"));
+
+    let mut registry = xsrc::rewriter::Registry::default();
+    registry.register(Box::new(Listing));
+    let output = xsrc::generate_with(
+        &registry,
+        xsrc::SchemaSource::Str(schema),
+        "listing",
+        &Default::default(),
+    )
+    .unwrap();
+    assert_eq!(output.code, "all GET 0\n");
+    let e = xsrc::generate_with(
+        &registry,
+        xsrc::SchemaSource::Str("$url: \"http://ratina.org\"\n"),
+        "listing",
+        &Default::default(),
+    )
+    .unwrap_err();
+    assert_eq!(e.to_string(), "Backend error: XSClient has no APIs");
+}
+
+/// A fresh directory under the system temp directory
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("xsrc-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_multiple_schemas() {
+    let dir = temp_dir("multiple-schemas");
+    std::fs::write(
+        dir.join("ratina.yaml"),
+        "$url: \"http://ratina.org\"\n$as: \"RatinaClient\"\nall: {}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("ahcro.yaml"),
+        "$url: \"http://ahcro.org\"\nall: {}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("broken.yaml"),
+        "$url: \"http://ratina.org/<id\"\nall: {}\n",
+    )
+    .unwrap();
+    let out_dir = dir.join("generated");
+    // The pattern is quoted, as on platforms where the shell doesn't expand it
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg(dir.join("*.yaml"))
+        .arg("-d")
+        .arg(&out_dir)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stdout.contains("2 succeeded, 1 failed"));
+    assert!(stderr.contains("broken.yaml: "));
+    // Named after the client class, or after the schema file without one
+    assert!(out_dir.join("RatinaClient.js").exists());
+    assert!(out_dir.join("ahcro.js").exists());
+    assert!(!out_dir.join("broken.js").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_multiple_schemas_same_class() {
+    let dir = temp_dir("multiple-schemas-same-class");
+    for name in &["a.yaml", "b.yaml"] {
+        std::fs::write(
+            dir.join(name),
+            format!(
+                "$url: \"http://{}\"\n$as: \"RatinaClient\"\nall: {{}}\n",
+                name
+            ),
+        )
+        .unwrap();
+    }
+    let out_dir = dir.join("generated");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg(dir.join("a.yaml"))
+        .arg(dir.join("b.yaml"))
+        .arg("-d")
+        .arg(&out_dir)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout.contains("1 succeeded, 1 failed"), "{}", stdout);
+    assert!(stderr.contains("b.yaml: "), "{}", stderr);
+    assert!(
+        stderr.contains("RatinaClient.js is already generated from "),
+        "{}",
+        stderr
+    );
+    // The first one is kept
+    let code = std::fs::read_to_string(out_dir.join("RatinaClient.js")).unwrap();
+    assert!(code.contains("http://a.yaml"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_multi_document_schema() {
+    let dir = temp_dir("multi-document");
+    let generate = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .current_dir(&dir)
+            .arg(std::fs::canonicalize("tests/fixtures/multi_document.yaml").unwrap())
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    // A file per document
+    let output = generate(&[]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("2 succeeded, 0 failed"), "{}", stdout);
+    let users = std::fs::read_to_string(dir.join("UserClient.js")).unwrap();
+    assert!(users.contains("export default class UserClient {"));
+    assert!(users.contains("    async get(id) {"));
+    let budgets = std::fs::read_to_string(dir.join("BudgetClient.js")).unwrap();
+    assert!(budgets.contains("export default class BudgetClient {"));
+    // But not into one file
+    let output = generate(&["-o", "clients.js"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr.contains("would all be written to clients.js"));
+    assert!(!dir.join("clients.js").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_unmatched_pattern() {
+    let dir = temp_dir("unmatched-pattern");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg(dir.join("*.yaml"))
+        .arg("--check")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr.contains("No schema file matches"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_emit_package_json() {
+    let dir = temp_dir("emit-package-json");
+    let schema = dir.join("ratina.yaml");
+    std::fs::write(
+        &schema,
+        "$url: \"http://ratina.org\"\n$as: \"RatinaClient\"\nall: {}\n",
+    )
+    .unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg(&schema)
+        .arg("-o")
+        .arg(dir.join("client.js"))
+        .arg("--emit-package-json")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let package: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.join("package.json")).unwrap()).unwrap();
+    assert_eq!(package["name"], "ratina-client");
+    assert_eq!(package["main"], "client.js");
+    assert!(package["dependencies"]["axios"].is_string());
+
+    // Only the JavaScript backends have npm dependencies to declare
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg(&schema)
+        .arg("-x")
+        .arg("ruby")
+        .arg("-o")
+        .arg(dir.join("client.rb"))
+        .arg("--emit-package-json")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(!dir.join("client.rb").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_error_format_json() {
+    let dir = temp_dir("error-format-json");
+    let broken = dir.join("broken.yaml");
+    let empty = dir.join("empty.yaml");
+    std::fs::write(
+        &broken,
+        "$url: \"http://ratina.org\"\nall:\n  $method: [GET\n",
+    )
+    .unwrap();
+    std::fs::write(
+        &empty,
+        "$url: \"http://ratina.org\"\n~ratincren: {}\nall: {}\n",
+    )
+    .unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg(&empty)
+        .arg(&broken)
+        .arg("--check")
+        .arg("--error-format")
+        .arg("json")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let diagnostics = String::from_utf8(output.stderr)
+        .unwrap()
+        .lines()
+        .map(|v| serde_json::from_str(v).unwrap())
+        .collect::<Vec<serde_json::Value>>();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0]["severity"], "warning");
+    assert_eq!(diagnostics[0]["kind"], "empty-apiset");
+    assert_eq!(diagnostics[0]["file"], empty.to_str().unwrap());
+    assert_eq!(diagnostics[0]["path"], serde_json::json!(["ratincren"]));
+    assert!(diagnostics[0]["position"].is_null());
+    assert_eq!(diagnostics[1]["severity"], "error");
+    assert_eq!(diagnostics[1]["file"], broken.to_str().unwrap());
+    assert!(diagnostics[1]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Parser error: "));
+    assert_eq!(diagnostics[1]["kind"], "parse");
+    assert_eq!(
+        diagnostics[1]["position"],
+        serde_json::json!({"line": 4, "column": 1})
+    );
+
+    // Arguments that clap rejects are usage errors as well
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg(&broken)
+        .arg("--error-format")
+        .arg("xml")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_diagnostics_json() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg("tests/fixtures/cases/unclosed_param/schema.yaml")
+        .arg("--dry-run")
+        .arg("--diagnostics")
+        .arg("json")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert!(output.stdout.is_empty());
+    let diagnostic: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(
+        diagnostic,
+        serde_json::json!({
+            "severity": "error",
+            "kind": "transform",
+            "message": "Transformer error: Invalid $url of ratincren: Unexpected EOF",
+            "file": "tests/fixtures/cases/unclosed_param/schema.yaml",
+            "path": ["ratincren"],
+            "position": {"line": 4, "column": 37},
+        })
+    );
+}
+
+#[test]
+fn test_all_schema_errors() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg("tests/fixtures/three_errors.yaml")
+        .args(["--check", "--error-format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let paths = stderr
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["path"].clone())
+        .collect::<Vec<serde_json::Value>>();
+    assert_eq!(
+        paths,
+        vec![
+            serde_json::json!(["ratincren", "get"]),
+            serde_json::json!(["ratincren", "search"]),
+            serde_json::Value::Null,
+        ]
+    );
+}
+
+#[test]
+fn test_url_snippet() {
+    let dir = temp_dir("url-snippet");
+    std::fs::write(
+        dir.join("broken.yaml"),
+        "$url: \"http://ratina.org\"\n~users:\n  $url: \"${!super}/users\"\n  get:\n    $url: \"${!super}/<id:int\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("root.yaml"),
+        "$url: \"http://ratina.org/${a..b}\"\nall: {}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("suffix.yaml"),
+        "$url: \"http://ratina.org\"\nall.GET: {}\nget.POST:\n  $url: '${!super}/<id'\n",
+    )
+    .unwrap();
+    let run = |schema: &str, color: &str| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .current_dir(&dir)
+            .arg(schema)
+            .arg("--check")
+            .arg("--color")
+            .arg(color)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        String::from_utf8(output.stderr).unwrap()
+    };
+    assert_eq!(
+        run("broken.yaml", "never"),
+        "\
+Error: broken.yaml: Transformer error: Invalid $url of users.get: Unexpected EOF
+  --> broken.yaml:5:29: users.get
+   |
+   | ${!super}/<id:int
+   |                  ^
+"
+    );
+    assert_eq!(
+        run("root.yaml", "never"),
+        "\
+Error: root.yaml: Transformer error: Invalid $url of the root: Unexpected token \".\" at pos 22
+  --> root.yaml:1:30: (root)
+   |
+   | http://ratina.org/${a..b}
+   |                       ^
+"
+    );
+    assert!(run("suffix.yaml", "never").contains("  --> suffix.yaml:4:23: get\n"));
+    let stderr = run("broken.yaml", "always");
+    assert!(stderr.starts_with("\x1b[1;31mError\x1b[0m: broken.yaml: "));
+    assert!(stderr.ends_with("\x1b[1;31m^\x1b[0m\n"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_fmt() {
+    let dir = temp_dir("fmt");
+    let schema = dir.join("ratina.yaml");
+    std::fs::write(
+        &schema,
+        "# Comments are dropped\n$url: \"http://ratina.org\"\nall.GET:\n  $params: {}\n",
+    )
+    .unwrap();
+    let fmt = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .arg("fmt")
+            .args(args)
+            .arg(&schema)
+            .output()
+            .unwrap()
+    };
+    let output = fmt(&["--check"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("-all.GET:\n-  $params: {}\n+all: {}\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("(formatted)"));
+    let output = fmt(&["--write"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        std::fs::read_to_string(&schema).unwrap(),
+        "$url: \"http://ratina.org\"\nall: {}\n"
+    );
+    let output = fmt(&["--check"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+
+    // Formatting the fixtures once is enough
+    for fixture in &[
+        "cases/sample/schema.yaml",
+        "cases/download/schema.yaml",
+        "cases/method_suffix/schema.yaml",
+        "cases/paginate/schema.yaml",
+        "cases/query/schema.yaml",
+        "cases/stream/schema.yaml",
+    ] {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(fixture);
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .arg("fmt")
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(0));
+        std::fs::write(&schema, &output.stdout).unwrap();
+        let output = fmt(&["--check"]);
+        assert_eq!(
+            output.status.code(),
+            Some(0),
+            "{} isn't idempotent",
+            fixture
+        );
+    }
+
+    std::fs::write(&schema, "$url: [\n").unwrap();
+    assert_eq!(fmt(&[]).status.code(), Some(3));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_lint() {
+    let dir = temp_dir("lint");
+    std::fs::write(
+        dir.join("ratina.yaml"),
+        "$url: \"http://ratina.org\"\nsearch:\n  $data:\n    q: \"string\"\n",
+    )
+    .unwrap();
+    let lint = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .current_dir(&dir)
+            .arg("lint")
+            .arg("ratina.yaml")
+            .args(args)
+            .arg("--color")
+            .arg("never")
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+    assert_eq!(
+        lint(&[]),
+        (
+            Some(1),
+            "\
+Error: ratina.yaml: search is a GET API with $data [get-with-data]
+Warning: ratina.yaml: search has no $description [missing-description]
+"
+            .to_string()
+        )
+    );
+    // Warnings alone don't fail
+    assert_eq!(
+        lint(&["--allow", "get-with-data"]),
+        (
+            Some(0),
+            "Warning: ratina.yaml: search has no $description [missing-description]\n".to_string()
+        )
+    );
+    assert_eq!(lint(&["--allow", "cobol"]).0, Some(2));
+    // Rules can be allowed in the config as well
+    std::fs::write(
+        dir.join("xsrc.toml"),
+        "[lint]\nallow = [\"missing-description\", \"get-with-data\"]\n",
+    )
+    .unwrap();
+    assert_eq!(lint(&[]), (Some(0), String::new()));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_emit_ir() {
+    let dir = temp_dir("emit-ir");
+    let generate = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .current_dir(&dir)
+            .arg(std::fs::canonicalize("tests/fixtures/cases/sample/schema.yaml").unwrap())
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    // Without a language, only the IR
+    let output = generate(&["--emit-ir", "ir.json"]);
+    assert!(output.status.success());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("ir.json")).unwrap(),
+        include_str!("fixtures/sample.ir.json")
+    );
+    assert!(!dir.join("XiaoSiClient.js").exists());
+    // With one, the code as well
+    std::fs::remove_file(dir.join("ir.json")).unwrap();
+    let output = generate(&["--emit-ir", "ir.json", "-x", "javascript"]);
+    assert!(output.status.success());
+    assert!(dir.join("ir.json").exists());
+    assert!(dir.join("XiaoSiClient.js").exists());
+    let output = generate(&["--emit-ir", "-"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        include_str!("fixtures/sample.ir.json")
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_multiple_langs() {
+    let dir = temp_dir("multiple-langs");
+    let generate = |out_dir: &str, langs: &[&str]| {
+        let mut command = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"));
+        command
+            .arg("tests/fixtures/cases/sample/schema.yaml")
+            .arg("-d")
+            .arg(dir.join(out_dir));
+        for lang in langs {
+            command.arg("-x").arg(lang);
+        }
+        let output = command.output().unwrap();
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+    let (code, stdout, _) = generate("out", &["javascript,php", "ruby"]);
+    assert_eq!(code, Some(0));
+    assert!(stdout.ends_with("3 succeeded, 0 failed\n"));
+    for file in &["XiaoSiClient.js", "XiaoSiClient.php", "xiao_si_client.rb"] {
+        assert!(dir.join("out").join(file).exists());
+    }
+    // A language failing leaves the others be
+    std::fs::create_dir_all(dir.join("partial/XiaoSiClient.php")).unwrap();
+    let (code, stdout, stderr) = generate("partial", &["javascript,php"]);
+    assert_eq!(code, Some(1));
+    assert!(stdout.ends_with("1 succeeded, 1 failed\n"));
+    assert!(stderr.starts_with("Error: tests/fixtures/cases/sample/schema.yaml (php): IO error: "));
+    assert!(dir.join("partial/XiaoSiClient.js").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_overwrite_protection() {
+    let dir = temp_dir("overwrite-protection");
+    let output_file = dir.join("api.js");
+    let generate = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .arg("tests/fixtures/cases/sample/schema.yaml")
+            .arg("-o")
+            .arg(&output_file)
+            .args(args)
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+    // Written by hand
+    std::fs::write(&output_file, "export default {};\n").unwrap();
+    let (code, stderr) = generate(&[]);
+    assert_eq!(code, Some(1));
+    assert_eq!(
+        stderr,
+        format!(
+            "Error: tests/fixtures/cases/sample/schema.yaml: Refusing to overwrite {}, which \
+             wasn't generated by xsrc (use --force to overwrite it)\n",
+            output_file.display()
+        )
+    );
+    assert_eq!(
+        std::fs::read_to_string(&output_file).unwrap(),
+        "export default {};\n"
+    );
+    // The code streamed beside the file is removed
+    assert!(!dir.join(".api.js.xsrc").exists());
+    let (code, _) = generate(&["--force"]);
+    assert_eq!(code, Some(0));
+    let code = std::fs::read_to_string(&output_file).unwrap();
+    assert!(code.starts_with("// XiaoSiClient generated by xsrc 0.1.0\n"));
+    // Generated before, and recognized by the banner
+    std::fs::write(&output_file, format!("{}// edited\n", code)).unwrap();
+    let (code, _) = generate(&[]);
+    assert_eq!(code, Some(0));
+    assert!(!std::fs::read_to_string(&output_file)
+        .unwrap()
+        .contains("// edited"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_banner() {
+    let dir = temp_dir("banner");
+    let generate = |output: &str, args: &[&str]| {
+        let status = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .arg("tests/fixtures/cases/sample/schema.yaml")
+            .arg("-o")
+            .arg(dir.join(output))
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        std::fs::read_to_string(dir.join(output)).unwrap()
+    };
+    let code = generate("api.js", &["--banner"]);
+    assert!(code.starts_with(
+        "// AUTO-GENERATED by xsrc. Do not edit.\n// XiaoSiClient generated by xsrc 0.1.0\n"
+    ));
+    let code = generate(
+        "api.rb",
+        &["-x", "ruby", "--banner=Do not edit.\nSee README."],
+    );
+    assert!(code.starts_with(
+        "# Do not edit.\n# See README.\n# XiaoSiClient generated by xsrc 0.1.0\n\nrequire \"erb\"\n"
+    ));
+    // The shebang stays first
+    let code = generate("api.sh", &["-x", "curl", "--banner"]);
+    assert!(code.starts_with("#!/bin/sh\n# AUTO-GENERATED by xsrc. Do not edit.\n"));
+    // Still recognized as generated, below a banner longer than usual
+    let banner = "--banner=1\n2\n3\n4\n5";
+    generate("api.js", &[banner]);
+    let code = generate("api.js", &[banner, "--minify"]);
+    assert!(
+        code.starts_with("// 1\n// 2\n// 3\n// 4\n// 5\n// XiaoSiClient generated by xsrc 0.1.0\n")
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_javascript_options() {
+    let generate = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .arg("tests/fixtures/cases/sample/schema.yaml")
+            .arg("--dry-run")
+            .args(args)
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+    let (code, stdout, _) = generate(&[
+        "--style",
+        "nested-object",
+        "--return-style",
+        "callback",
+        "--cancellation",
+        "--node-keep-alive",
+    ]);
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("export default function createClient("));
+    assert!(stdout.contains("            get: (id, detail, signal, cb, err) => {"));
+    assert!(stdout.contains("keepAlive: true"));
+    let (code, _, stderr) = generate(&["--style", "factory-function", "--split"]);
+    assert_eq!(code, Some(2));
+    assert_eq!(
+        stderr,
+        "Error: The options split and style can't be used together\n"
+    );
+}
+
+#[test]
+fn test_unchanged_output() {
+    let dir = temp_dir("unchanged-output");
+    let output_file = dir.join("api.js");
+    let generate = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .arg("tests/fixtures/cases/sample/schema.yaml")
+            .arg("-o")
+            .arg(&output_file)
+            .args(args)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(0));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let path = || output_file.canonicalize().unwrap();
+    let stdout = generate(&[]);
+    assert_eq!(
+        stdout,
+        format!("Code file generated at {}\n", path().display())
+    );
+    let mtime = || std::fs::metadata(&output_file).unwrap().modified().unwrap();
+    let written_at = mtime();
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    for args in &[&[][..], &["--force"][..]] {
+        let stdout = generate(args);
+        assert_eq!(stdout, format!("Up to date: {}\n", path().display()));
+        assert_eq!(mtime(), written_at);
+    }
+    let files = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|v| v.unwrap().file_name());
+    assert_eq!(files.collect::<Vec<_>>(), vec!["api.js"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_diff() {
+    let diff = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .arg("diff")
+            .arg("tests/fixtures/cases/sample/schema.yaml")
+            .arg("tests/fixtures/cases/v2/schema.yaml")
+            .args(args)
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap(),
+        )
+    };
+    let report = "\
+Breaking changes:
+  users.get: URL param \"id\" retyped from number to string
+  users.update: API removed
+Other changes:
+  users.all: optional query param \"page\" added
+"
+    .to_string();
+    assert_eq!(diff(&[]), (Some(1), report.clone()));
+    assert_eq!(diff(&["--allow-breaking"]), (Some(0), report));
+}
+
+#[test]
+fn test_explain() {
+    let explain = |path: &str| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .arg("explain")
+            .arg("tests/fixtures/cases/sample/schema.yaml")
+            .arg(path)
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+    assert_eq!(
+        explain("users.get"),
+        (
+            Some(0),
+            "\
+users.get
+  GET http://httpbin.org//users/{id}
+Arguments:
+  id: number  (path, of users.get)
+  detail: boolean  (query)
+Headers:
+  Accept: application/json
+"
+            .to_string(),
+            String::new()
+        )
+    );
+    assert_eq!(
+        explain("users.budgets.get"),
+        (
+            Some(2),
+            String::new(),
+            "Error: tests/fixtures/cases/sample/schema.yaml: No API at users.budgets.get, \
+             expected one of: users.budgets.all\n"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_version() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg("--version")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("XiaoSi Rest Client Code Generator {}\n", xsrc::version())
+    );
+    assert!(xsrc::version().starts_with(env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn test_langs() {
+    let run = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let names = xsrc::rewriter::Registry::default()
+        .names()
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>();
+    let listed = run(&["langs"]);
+    let lines = listed.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), names.len());
+    assert_eq!(
+        lines[0],
+        "javascript   .js                       JavaScript (ECMAScript 6) client"
+    );
+    for (line, name) in lines.iter().zip(&names) {
+        assert_eq!(line.split_whitespace().next(), Some(name.as_str()));
+    }
+    assert_eq!(run(&["--list-langs"]), listed);
+
+    let langs: Vec<serde_json::Value> = serde_json::from_str(&run(&["langs", "--json"])).unwrap();
+    assert_eq!(
+        langs
+            .iter()
+            .map(|v| v["name"].as_str().unwrap())
+            .collect::<Vec<&str>>(),
+        names
+    );
+    assert_eq!(langs[1]["extension"], ".rb");
+    assert!(langs.iter().all(|v| v["description"].is_string()));
+    assert_eq!(run(&["--list-langs", "--json"]), run(&["langs", "--json"]));
+}
+
+#[test]
+fn test_completions() {
+    let run = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    for shell in &["bash", "zsh", "fish"] {
+        let output = run(&["completions", shell]);
+        assert!(output.status.success());
+        let script = String::from_utf8(output.stdout).unwrap();
+        // fish names the flags without the dashes
+        for name in &["langs", "completions", "out-dir", "dry-run", "check"] {
+            assert!(
+                script.contains(name),
+                "{} not in the {} script",
+                name,
+                shell
+            );
+        }
+        // The languages come from the registry
+        for name in xsrc::rewriter::Registry::default().names() {
+            assert!(
+                script.contains(name),
+                "{} not in the {} script",
+                name,
+                shell
+            );
+        }
+    }
+    assert_eq!(run(&["completions", "tcsh"]).status.code(), Some(2));
+    // The same languages are accepted by --lang
+    assert_eq!(run(&["ratina.yaml", "-x", "cobol"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_config() {
+    let dir = temp_dir("config");
+    std::fs::create_dir_all(dir.join("schemas")).unwrap();
+    std::fs::write(
+        dir.join("schemas/ratina.yaml"),
+        "$url: \"http://ratina.org\"\n$as: \"RatinaClient\"\nall: {}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("schemas/ahcro.yaml"),
+        "$url: \"http://ahcro.org\"\nall: {}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("xsrc.toml"),
+        "\
+[defaults]
+lang = \"ruby\"
+out-dir = \"generated\"
+
+[[target]]
+schema = \"schemas/ratina.yaml\"
+
+[[target]]
+schema = \"schemas/ahcro.yaml\"
+lang = \"php\"
+output = \"clients/ahcro.php\"
+",
+    )
+    .unwrap();
+    std::fs::create_dir_all(dir.join("clients")).unwrap();
+    // Found from a subdirectory, and builds every target without arguments
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .current_dir(dir.join("schemas"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(dir.join("generated/ratina_client.rb").exists());
+    assert!(dir.join("clients/ahcro.php").exists());
+
+    // The command line takes precedence over the targets and the defaults
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .current_dir(&dir)
+        .arg("-x")
+        .arg("javascript")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(dir.join("generated/RatinaClient.js").exists());
+
+    // Schemas on the command line take only the defaults
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .current_dir(&dir)
+        .arg("schemas/ahcro.yaml")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(dir.join("generated/ahcro.rb").exists());
+
+    std::fs::write(dir.join("broken.toml"), "[defaults]\nquotes = \"single\"\n").unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .current_dir(&dir)
+        .arg("--config")
+        .arg("broken.toml")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr.contains("unknown field `quotes`"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_out_dir_stale_files() {
+    let dir = temp_dir("out-dir-stale");
+    let schema = dir.join("ratina.yaml");
+    let write_schema = |klsname: &str| {
+        std::fs::write(
+            &schema,
+            format!(
+                "\
+$url: \"http://ratina.org\"
+$as: \"{}\"
+~users:
+  $url: \"${{!super}}/users\"
+  all: {{}}
+",
+                klsname
+            ),
+        )
+        .unwrap()
+    };
+    let generate = || {
+        std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .arg(&schema)
+            .arg("-d")
+            .arg(dir.join("out"))
+            .output()
+            .unwrap()
+    };
+    write_schema("RatinaClient");
+    let output = generate();
+    assert!(output.status.success());
+    assert!(dir.join("out/RatinaClient.js").exists());
+    assert!(dir.join("out/.xsrc-manifest.json").exists());
+
+    // The file named after the old class goes, the new one takes its place
+    write_schema("Ratina");
+    let output = generate();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Stale file removed: "));
+    assert!(stdout.contains("RatinaClient.js"));
+    assert!(!dir.join("out/RatinaClient.js").exists());
+    assert!(dir.join("out/Ratina.js").exists());
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg(&schema)
+        .arg("-o")
+        .arg(dir.join("ratina.js"))
+        .arg("--output-dir")
+        .arg(dir.join("out"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_out_dir_split() {
+    let dir = temp_dir("out-dir-split");
+    let schema = dir.join("ratina.yaml");
+    std::fs::write(
+        &schema,
+        "\
+$url: \"http://ratina.org\"
+$as: \"RatinaClient\"
+~users:
+  $url: \"${!super}/users\"
+  all: {}
+~ahcros:
+  $url: \"${!super}/ahcros\"
+  all: {}
+",
+    )
+    .unwrap();
+    let generate = || {
+        std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .arg(&schema)
+            .arg("-d")
+            .arg(dir.join("out"))
+            .arg("--split")
+            .output()
+            .unwrap()
+    };
+    let output = generate();
+    assert!(output.status.success());
+    let root = std::fs::read_to_string(dir.join("out/RatinaClient.js")).unwrap();
+    assert!(root.contains("import ahcros from \"./RatinaClient/ahcros.js\";"));
+    assert!(dir.join("out/RatinaClient/users.js").exists());
+    assert!(dir.join("out/RatinaClient/ahcros.js").exists());
+
+    // The file of the removed APISet goes, the others stay
+    std::fs::write(
+        &schema,
+        "\
+$url: \"http://ratina.org\"
+$as: \"RatinaClient\"
+~users:
+  $url: \"${!super}/users\"
+  all: {}
+",
+    )
+    .unwrap();
+    let output = generate();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Stale file removed: "));
+    assert!(stdout.contains("ahcros.js"));
+    assert!(!dir.join("out/RatinaClient/ahcros.js").exists());
+    assert!(dir.join("out/RatinaClient/users.js").exists());
+    assert!(dir.join("out/RatinaClient.js").exists());
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg(&schema)
+        .arg("-o")
+        .arg(dir.join("ratina.js"))
+        .arg("-d")
+        .arg(dir.join("out"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_output_dir_alias() {
+    let dir = temp_dir("output-dir-alias");
+    let generate = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .arg("tests/fixtures/cases/sample/schema.yaml")
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    let out = dir.join("out");
+    let out = out.to_str().unwrap();
+    assert_eq!(generate(&["--output-dir", out, "--split"]), Some(0));
+    assert!(dir.join("out/XiaoSiClient.js").exists());
+    assert!(dir.join("out/XiaoSiClient/users.js").exists());
+    assert!(dir.join("out/.xsrc-manifest.json").exists());
+    let output = dir.join("ratina.js");
+    let output = output.to_str().unwrap();
+    assert_eq!(generate(&["-o", output, "--output-dir", out]), Some(2));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_default_output_file() {
+    let dir = temp_dir("default-output-file");
+    std::fs::write(
+        dir.join("ratina.yaml"),
+        "$url: \"http://ratina.org\"\n$as: \"RatinaClient\"\nall: {}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("ahcro.yaml"),
+        "$url: \"http://ahcro.org\"\nall: {}\n",
+    )
+    .unwrap();
+    let generate = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .current_dir(&dir)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+    };
+    // Generating one client after another keeps both
+    generate(&["ratina.yaml"]);
+    generate(&["ahcro.yaml"]);
+    assert!(dir.join("RatinaClient.js").exists());
+    assert!(dir.join("ahcro.js").exists());
+    generate(&["ratina.yaml", "-x", "ruby"]);
+    assert!(dir.join("ratina_client.rb").exists());
+    assert!(!dir.join("output.js").exists());
+    // No manifest outside of --out-dir
+    assert!(!dir.join(".xsrc-manifest.json").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_verbose() {
+    let dir = temp_dir("verbose");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg("tests/fixtures/cases/sample/schema.yaml")
+        .arg("-o")
+        .arg(dir.join("sample.js"))
+        .arg("-vv")
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    for stage in &[
+        "Reading schema tests/fixtures/cases/sample/schema.yaml",
+        "Schema parsed: 2 APISets, 5 APIs in ",
+        "Transform of XiaoSiClient completed in ",
+        "Check completed: 0 warnings in ",
+        "Codegen (javascript) produced ",
+        "File written: ",
+    ] {
+        assert!(stderr.contains(stage), "{} not in {}", stage, stderr);
+    }
+
+    // Quiet by default
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg("tests/fixtures/cases/sample/schema.yaml")
+        .arg("-o")
+        .arg(dir.join("sample.js"))
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_dry_run_and_diff() {
+    let dir = temp_dir("diff");
+    let schema = dir.join("ratina.yaml");
+    let output_file = dir.join("client.rb");
+    std::fs::write(&schema, "$url: \"http://ratina.org\"\nall: {}\n").unwrap();
+    let run = |flag: &str| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+            .arg(&schema)
+            .arg("-x")
+            .arg("ruby")
+            .arg("-o")
+            .arg(&output_file)
+            .arg(flag)
+            .output()
+            .unwrap()
+    };
+
+    let output = run("--dry-run");
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("def all"));
+    assert!(!output_file.exists());
+
+    // A missing output file differs from anything
+    let output = run("--diff");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.starts_with("--- /dev/null\n"));
+    assert!(stdout.contains("+  def all"));
+    assert!(!output_file.exists());
+
+    std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg(&schema)
+        .arg("-x")
+        .arg("ruby")
+        .arg("-o")
+        .arg(&output_file)
+        .output()
+        .unwrap();
+    let output = run("--diff");
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("Up to date: "));
+
+    std::fs::write(&schema, "$url: \"http://ratina.org\"\nall: {}\nget: {}\n").unwrap();
+    let output = run("--diff");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains(&format!("--- {}\n", output_file.display())));
+    assert!(stdout.contains("+  def get"));
+    assert!(!stdout.contains("-  def all"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Waits for up to 10 seconds until `f` holds
+fn wait_until<F: Fn() -> bool>(f: F) -> bool {
+    for _ in 0..100 {
+        if f() {
+            return true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    false
+}
+
 #[test]
-fn test_noname() {
-    let schema_str = include_str!("fixtures/sample_no_klsname_no_url.yaml");
-    println!("===== Schema str =====");
-    println!("{}", schema_str);
-    let root_schema = xsrc::schema::parse_str(schema_str).unwrap();
-    println!("===== Schema structure =====");
-    println!("{:?}", root_schema);
-    let root = xsrc::transformer::transform(root_schema).unwrap();
-    println!("===== Context-bounded root =====");
-    println!("{:?}", root);
-    let gen_ctx = Default::default();
-    let code = xsrc::rewriter::javascript::gen(&root, &gen_ctx);
-    println!("===== JavaScript Code =====");
-    println!("{}", code);
+fn test_watch() {
+    let dir = temp_dir("watch");
+    let schema = dir.join("ratina.yaml");
+    let output_file = dir.join("client.js");
+    std::fs::write(&schema, "$url: \"http://ratina.org\"\nall: {}\n").unwrap();
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_xsrc"))
+        .arg(&schema)
+        .arg("-o")
+        .arg(&output_file)
+        .arg("--watch")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let read = || std::fs::read_to_string(&output_file).unwrap_or_default();
+    let generated = wait_until(|| read().contains("async all("));
+    // Errors don't stop watching
+    std::fs::write(&schema, "all: [").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    std::fs::write(&schema, "$url: \"http://ratina.org\"\nall: {}\nahcro: {}\n").unwrap();
+    let regenerated = wait_until(|| read().contains("async ahcro("));
+    let still_running = child.try_wait().unwrap().is_none();
+    child.kill().unwrap();
+    child.wait().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(generated);
+    assert!(regenerated);
+    assert!(still_running);
 }
@@ -0,0 +1,68 @@
+//! The logs of the pipeline, captured by a logger of its own. The logger is
+//! global to the process, hence this test binary.
+#![cfg(feature = "backend-javascript")]
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::sync::Mutex;
+use xsrc::rewriter::{generate, Registry};
+use xsrc::schema::parse_str;
+use xsrc::transformer::transform;
+
+struct Capture(Mutex<Vec<(Level, String)>>);
+
+impl Log for Capture {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        if record.target().starts_with("xsrc") {
+            let message = record.args().to_string();
+            self.0.lock().unwrap().push((record.level(), message));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Capture = Capture(Mutex::new(Vec::new()));
+
+#[test]
+fn test_pipeline_logs() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(LevelFilter::Trace);
+    let schema = parse_str(include_str!("fixtures/cases/sample/schema.yaml")).unwrap();
+    let root = transform(schema).unwrap();
+    let registry = Registry::default();
+    generate(
+        registry.get("javascript").unwrap(),
+        &root,
+        &Default::default(),
+    )
+    .unwrap();
+    let logs = LOGGER.0.lock().unwrap();
+    let logged = |level: Level, prefix: &str| {
+        assert!(
+            logs.iter()
+                .any(|(l, message)| *l == level && message.starts_with(prefix)),
+            "No {} log starting with {:?} in {:#?}",
+            level,
+            prefix,
+            logs
+        );
+    };
+    logged(Level::Info, "Schema parsed: 2 APISets, 5 APIs in ");
+    logged(Level::Debug, "Top-level keys: users");
+    logged(
+        Level::Trace,
+        "Parsed API users.get (GET ${!super}/<id:number>)",
+    );
+    logged(Level::Trace, "Context XiaoSiClient.users.budgets created");
+    logged(Level::Trace, "$url of XiaoSiClient parsed: 0 params");
+    logged(
+        Level::Trace,
+        "$url of XiaoSiClient.users.get parsed: 1 params",
+    );
+    logged(Level::Info, "Transform of XiaoSiClient completed in ");
+    logged(Level::Debug, "Rendering XiaoSiClient: ");
+    logged(Level::Info, "Codegen (javascript) produced ");
+}
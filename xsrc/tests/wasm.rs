@@ -0,0 +1,35 @@
+//! `xsrc::wasm` in a headless browser:
+//!
+//! ```sh
+//! wasm-pack test --headless --firefox xsrc -- --no-default-features --features wasm --test wasm
+//! ```
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::*;
+use xsrc::wasm::generate_js;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn property(error: &JsValue, key: &str) -> JsValue {
+    js_sys::Reflect::get(error, &JsValue::from_str(key)).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_generate_js() {
+    let schema = "$url: \"http://ratina.org\"\n$as: \"RatinaClient\"\nall: {}\n";
+    let code = generate_js(schema, "").unwrap();
+    assert!(code.contains("export default class RatinaClient {"));
+}
+
+#[wasm_bindgen_test]
+fn test_generate_js_parse_error() {
+    let error = generate_js("$url: [\n", "{\"param-case\": \"camel\"}").unwrap_err();
+    assert!(error.is_instance_of::<js_sys::Error>());
+    assert_eq!(property(&error, "kind"), JsValue::from_str("parse"));
+    assert_eq!(property(&error, "line"), JsValue::from_f64(2.0));
+    assert!(property(&error, "message")
+        .as_string()
+        .unwrap()
+        .starts_with("Parser error: "));
+}